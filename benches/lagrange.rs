@@ -0,0 +1,47 @@
+// Compares computing the Lagrange coefficients of 256 signers one by one with the subproduct tree.
+// Run with `cargo bench`.
+
+use coconut::lagrange::{LagrangeCoefficients, LAGRANGE_FFT_CUTOFF};
+use std::collections::BTreeSet;
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 10;
+
+fn time<F: FnMut()>(mut f: F) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn main() {
+    let threshold = 256;
+    assert!(threshold > LAGRANGE_FFT_CUTOFF);
+    let ids = (1..=threshold).collect::<BTreeSet<usize>>();
+
+    let naive_coeffs = LagrangeCoefficients::with_cutoff(&ids, usize::max_value());
+    let tree_coeffs = LagrangeCoefficients::with_cutoff(&ids, 0);
+    for id in &ids {
+        assert_eq!(naive_coeffs[*id], tree_coeffs[*id]);
+    }
+
+    let naive = time(|| {
+        LagrangeCoefficients::with_cutoff(&ids, usize::max_value());
+    });
+    let tree = time(|| {
+        LagrangeCoefficients::with_cutoff(&ids, 0);
+    });
+    println!(
+        "threshold {}: naive: {:?}, subproduct tree: {:?}, speedup: {:.2}x",
+        threshold,
+        naive,
+        tree,
+        naive.as_secs_f64() / tree.as_secs_f64()
+    );
+    // The tree is used above `LAGRANGE_FFT_CUTOFF` so it must be the faster way here
+    assert!(
+        tree < naive,
+        "subproduct tree is not faster than the naive way above LAGRANGE_FFT_CUTOFF"
+    );
+}
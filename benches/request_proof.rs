@@ -0,0 +1,48 @@
+// Compares verifying the proofs of the ciphertexts of a signature request with 32 hidden messages
+// together and one by one. Run with `cargo bench`.
+
+#[macro_use]
+extern crate coconut;
+
+use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
+use coconut::signature::{Params, SignatureRequest, SignatureRequestPoK};
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 20;
+
+fn time<F: FnMut()>(mut f: F) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn main() {
+    let count_hidden = 32;
+    let params = Params::new(count_hidden, "bench".as_bytes());
+    let msgs = FieldElementVector::random(count_hidden);
+    let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+    let (sig_req, randomness) =
+        SignatureRequest::new(&msgs, count_hidden, &elg_pk, &params).unwrap();
+    let pok = SignatureRequestPoK::init(&sig_req, &elg_pk, &params);
+    let challenge = FieldElement::from_msg_hash(&pok.to_bytes());
+    let proof = pok
+        .gen_proof(&msgs, randomness, &elg_sk, &challenge)
+        .unwrap();
+
+    let batched = time(|| {
+        assert!(proof
+            .verify(&sig_req, &elg_pk, &challenge, &params)
+            .unwrap())
+    });
+    let unbatched = time(|| {
+        assert!(proof
+            .verify_unbatched(&sig_req, &elg_pk, &challenge, &params)
+            .unwrap())
+    });
+    println!(
+        "count_hidden {}: verify: {:?}, verify_unbatched: {:?}",
+        count_hidden, batched, unbatched
+    );
+}
@@ -0,0 +1,51 @@
+// Compares the single attribute fast path of verification with the general path. Run with
+// `cargo bench`.
+
+#[macro_use]
+extern crate coconut;
+
+use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
+use coconut::keygen::trusted_party_SSS_keygen;
+use coconut::signature::{BlindSignature, Params, Signature, SignatureRequest, Verkey};
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 100;
+
+fn time<F: FnMut()>(mut f: F) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn main() {
+    let threshold = 3;
+    let total = 5;
+    let params = Params::new(1, "bench".as_bytes());
+    let (_, _, signers) = trusted_party_SSS_keygen(threshold, total, &params);
+    let keys = signers
+        .iter()
+        .map(|s| (s.id, &s.verkey))
+        .collect::<Vec<(usize, &Verkey)>>();
+
+    let msgs = FieldElementVector::random(1);
+    let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+    let (sig_req, _) = SignatureRequest::new(&msgs, 1, &elg_pk, &params).unwrap();
+    let sigs = (0..threshold)
+        .map(|i| {
+            (
+                signers[i].id,
+                BlindSignature::new(&sig_req, &signers[i].sigkey)
+                    .unwrap()
+                    .unblind(&elg_sk),
+            )
+        })
+        .collect::<Vec<(usize, Signature)>>();
+    let sig = Signature::aggregate(threshold, sigs).unwrap();
+    let vk = Verkey::aggregate(threshold, keys).unwrap();
+
+    let general = time(|| assert!(sig.verify(msgs.as_slice(), &vk, &params).unwrap()));
+    let single = time(|| assert!(sig.verify_single(&msgs[0], &vk, &params)));
+    println!("verify: {:?}, verify_single: {:?}", general, single);
+}
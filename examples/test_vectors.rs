@@ -0,0 +1,10 @@
+// Prints the issuance transcript of `coconut::test_vectors` in the format of its `VECTORS_FILE`.
+// After an intended change of the protocol or serialization, regenerate the expected transcript
+// with `cargo run --example test_vectors --features test_vectors > test_vectors/transcript.txt`
+// and review the diff before committing it.
+
+use coconut::test_vectors::transcript_text;
+
+fn main() {
+    print!("{}", transcript_text().unwrap());
+}
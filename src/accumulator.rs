@@ -0,0 +1,300 @@
+// Pairing based accumulator for revocation. One hidden attribute of the credential is used as the
+// revocation handle and is accumulated by the revocation authority. While showing the credential,
+// the holder proves that its handle is a member of the current accumulator without revealing the
+// handle. The membership proof uses the same blinding for the handle as used in the proof of
+// knowledge of the signature so the verifier can check both proofs are about the same handle.
+
+use crate::errors::{CoconutError, CoconutErrorKind};
+use crate::signature::{
+    Params, ProofSignatureGroup, ProverCommittedSignatureGroup, ProverCommittingSignatureGroup,
+};
+use crate::{ate_2_pairing, OtherGroup, SignatureGroup, SignatureGroupVec};
+use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
+use amcl_wrapper::group_elem::{GroupElement, GroupElementVector};
+
+/// Secret key of the revocation authority managing the accumulator
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AccumulatorSecretKey(pub FieldElement);
+
+/// Public key of the revocation authority, g_tilde^s
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AccumulatorPublicKey(pub OtherGroup);
+
+pub fn accumulator_keygen(params: &Params) -> (AccumulatorSecretKey, AccumulatorPublicKey) {
+    let s = FieldElement::random();
+    let Q = &params.g_tilde * &s;
+    (AccumulatorSecretKey(s), AccumulatorPublicKey(Q))
+}
+
+/// Accumulator over elements e_1, e_2, ... e_n has value g^((s+e_1)(s+e_2)...(s+e_n))
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Accumulator {
+    pub value: SignatureGroup,
+}
+
+/// Witness for element e is the accumulator value with e removed, i.e. w^(s+e) = accumulator
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MembershipWitness {
+    pub w: SignatureGroup,
+}
+
+impl Accumulator {
+    /// Empty accumulator
+    pub fn new(params: &Params) -> Self {
+        Self {
+            value: params.g.clone(),
+        }
+    }
+
+    pub fn add(&mut self, elem: &FieldElement, sk: &AccumulatorSecretKey) {
+        self.value = &self.value * &(&sk.0 + elem);
+    }
+
+    /// Remove element from accumulator. Witnesses of all other elements need to be updated by
+    /// getting new witnesses from the revocation authority.
+    pub fn remove(
+        &mut self,
+        elem: &FieldElement,
+        sk: &AccumulatorSecretKey,
+    ) -> Result<(), CoconutError> {
+        let d = &sk.0 + elem;
+        if d.is_zero() {
+            return Err(CoconutErrorKind::GeneralError {
+                msg: String::from("Element cannot be accumulated"),
+            }
+            .into());
+        }
+        self.value = &self.value * &d.inverse();
+        Ok(())
+    }
+
+    /// Created by the revocation authority for a member element
+    pub fn witness(
+        &self,
+        elem: &FieldElement,
+        sk: &AccumulatorSecretKey,
+    ) -> Result<MembershipWitness, CoconutError> {
+        let d = &sk.0 + elem;
+        if d.is_zero() {
+            return Err(CoconutErrorKind::GeneralError {
+                msg: String::from("Element cannot be accumulated"),
+            }
+            .into());
+        }
+        Ok(MembershipWitness {
+            w: &self.value * &d.inverse(),
+        })
+    }
+}
+
+impl MembershipWitness {
+    /// Check e(w, Q.g_tilde^e) == e(accumulator, g_tilde)
+    pub fn verify(
+        &self,
+        elem: &FieldElement,
+        accumulator: &Accumulator,
+        pk: &AccumulatorPublicKey,
+        params: &Params,
+    ) -> bool {
+        let Q_e = &pk.0 + &(&params.g_tilde * elem);
+        let e = ate_2_pairing(
+            &self.w,
+            &Q_e,
+            &accumulator.value.negation(),
+            &params.g_tilde,
+        );
+        e.is_one()
+    }
+}
+
+/// Commitment phase of the proof of knowledge of a witness for a hidden element.
+/// The witness w is randomized as W' = w^r. Since e(W', Q) = e(acc, g_tilde)^r.e(W', g_tilde)^-e,
+/// knowledge of r and e is proven with bases `acc` and `W'^-1` in SignatureGroup and the
+/// relation is checked by the verifier with a pairing.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PoKOfMembership {
+    pub W_prime: SignatureGroup,
+    pub r: FieldElement,
+    pub elem: FieldElement,
+    pub pok_vc: ProverCommittedSignatureGroup,
+}
+
+/// Response phase of the proof of knowledge of a witness for a hidden element
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MembershipProof {
+    pub W_prime: SignatureGroup,
+    pub proof_vc: ProofSignatureGroup,
+}
+
+impl PoKOfMembership {
+    /// `elem_blinding` should be the same blinding used for the revocation handle in the proof of
+    /// knowledge of signature.
+    pub fn init(
+        witness: &MembershipWitness,
+        elem: &FieldElement,
+        elem_blinding: &FieldElement,
+        accumulator: &Accumulator,
+    ) -> Self {
+        let r = FieldElement::random();
+        let W_prime = &witness.w * &r;
+        let mut committing = ProverCommittingSignatureGroup::new();
+        committing.commit(&accumulator.value, None);
+        committing.commit(&W_prime.negation(), Some(elem_blinding));
+        Self {
+            W_prime,
+            r,
+            elem: elem.clone(),
+            pok_vc: committing.finish(),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.W_prime.to_bytes();
+        bytes.append(&mut self.pok_vc.to_bytes());
+        bytes
+    }
+
+    pub fn gen_proof(self, challenge: &FieldElement) -> Result<MembershipProof, CoconutError> {
+        let proof_vc = self.pok_vc.gen_proof(challenge, &[self.r, self.elem])?;
+        Ok(MembershipProof {
+            W_prime: self.W_prime,
+            proof_vc,
+        })
+    }
+}
+
+impl MembershipProof {
+    /// Response for the hidden element. Should be equal to the response for the revocation handle
+    /// in the proof of knowledge of signature.
+    pub fn elem_response(&self) -> &FieldElement {
+        &self.proof_vc.responses[1]
+    }
+
+    /// Check e(acc^s_r.W'^-s_e.A^-1, g_tilde).e(W'^c, Q) == 1 where A is the commitment and s_r, s_e
+    /// are the responses.
+    pub fn verify(
+        &self,
+        accumulator: &Accumulator,
+        pk: &AccumulatorPublicKey,
+        params: &Params,
+        challenge: &FieldElement,
+    ) -> Result<bool, CoconutError> {
+        if self.W_prime.is_identity() || self.proof_vc.responses.len() != 2 {
+            return Ok(false);
+        }
+        let mut bases = SignatureGroupVec::with_capacity(2);
+        bases.push(accumulator.value.clone());
+        bases.push(self.W_prime.negation());
+        let responses: FieldElementVector = self.proof_vc.responses.clone();
+        let lhs = &bases.multi_scalar_mul_var_time(&responses).unwrap() - &self.proof_vc.commitment;
+        let e = ate_2_pairing(&lhs, &params.g_tilde, &(&self.W_prime * challenge), &pk.0);
+        Ok(e.is_one())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signature::{
+        transform_to_PS_params, transform_to_PS_sig, transform_to_PS_verkey, SignatureRequest,
+    };
+    use crate::test_utils::{sign_request, signers_with_verkey};
+    use ps_sig::pok_sig::PoKOfSignature;
+    use std::collections::{HashMap, HashSet};
+
+    #[test]
+    fn test_accumulator_witness() {
+        let params = Params::new(2, "test".as_bytes());
+        let (sk, pk) = accumulator_keygen(&params);
+        let mut acc = Accumulator::new(&params);
+        let elems = FieldElementVector::random(5);
+        for e in elems.iter() {
+            acc.add(e, &sk);
+        }
+        for e in elems.iter() {
+            let witness = acc.witness(e, &sk).unwrap();
+            assert!(witness.verify(e, &acc, &pk, &params));
+        }
+        let witness = acc.witness(&elems[1], &sk).unwrap();
+        acc.remove(&elems[1], &sk).unwrap();
+        assert!(!witness.verify(&elems[1], &acc, &pk, &params));
+    }
+
+    #[test]
+    fn test_revocation_with_credential() {
+        // Last attribute is the revocation handle
+        let threshold = 3;
+        let total = 5;
+        let msg_count = 3;
+        let handle_idx = 2;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (signers, vk) = signers_with_verkey(threshold, total, &params);
+
+        let msgs = FieldElementVector::random(msg_count);
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        let (sig_req, _) = SignatureRequest::new(&msgs, 0, &elg_pk, &params).unwrap();
+        let sig = sign_request(&sig_req, &elg_sk, &signers, threshold, &params);
+        assert!(sig.verify(msgs.as_slice(), &vk, &params).unwrap());
+
+        // Revocation authority accumulates the handle along with other handles
+        let (acc_sk, acc_pk) = accumulator_keygen(&params);
+        let mut acc = Accumulator::new(&params);
+        acc.add(&msgs[handle_idx], &acc_sk);
+        for e in FieldElementVector::random(3).iter() {
+            acc.add(e, &acc_sk);
+        }
+        let witness = acc.witness(&msgs[handle_idx], &acc_sk).unwrap();
+
+        // Reveal first attribute only, the handle is hidden. Same blinding for the handle in both
+        // proofs of knowledge.
+        let mut revealed_msg_indices = HashSet::new();
+        revealed_msg_indices.insert(0);
+        let blindings = FieldElementVector::random(msg_count - 1);
+        let handle_blinding = &blindings[handle_idx - 1];
+
+        let ps_params = transform_to_PS_params(&params);
+        let ps_verkey = transform_to_PS_verkey(&vk);
+        let ps_sig = transform_to_PS_sig(&sig);
+
+        let prove = |acc: &Accumulator| {
+            let pok_sig = PoKOfSignature::init(
+                &ps_sig,
+                &ps_verkey,
+                &ps_params,
+                msgs.as_slice(),
+                Some(blindings.as_slice()),
+                revealed_msg_indices.clone(),
+            )
+            .unwrap();
+            let pok_mem = PoKOfMembership::init(&witness, &msgs[handle_idx], handle_blinding, acc);
+            let mut chal_bytes = pok_sig.to_bytes();
+            chal_bytes.append(&mut pok_mem.to_bytes());
+            let chal = FieldElement::from_msg_hash(&chal_bytes);
+            (
+                pok_sig.gen_proof(&chal).unwrap(),
+                pok_mem.gen_proof(&chal).unwrap(),
+                chal,
+            )
+        };
+
+        let mut revealed_msgs = HashMap::new();
+        revealed_msgs.insert(0, msgs[0].clone());
+
+        let (proof_sig, proof_mem, chal) = prove(&acc);
+        assert!(proof_sig
+            .verify(&ps_verkey, &ps_params, revealed_msgs.clone(), &chal)
+            .unwrap());
+        assert!(proof_mem.verify(&acc, &acc_pk, &params, &chal).unwrap());
+        // Response for handle is at index 2 since index 0 is for the randomness and index 1 is for
+        // the 1st hidden message
+        assert_eq!(proof_sig.proof_vc.responses[2], *proof_mem.elem_response());
+
+        // Handle removed, membership proof fails
+        acc.remove(&msgs[handle_idx], &acc_sk).unwrap();
+        let (proof_sig, proof_mem, chal) = prove(&acc);
+        assert!(proof_sig
+            .verify(&ps_verkey, &ps_params, revealed_msgs.clone(), &chal)
+            .unwrap());
+        assert!(!proof_mem.verify(&acc, &acc_pk, &params, &chal).unwrap());
+    }
+}
@@ -0,0 +1,259 @@
+// Hash functions for deriving Fiat-Shamir challenges. By default challenges are
+// `FieldElement::from_msg_hash` of the transcript. Systems interoperating with others that derive
+// challenges with SHA-256 can pass `Sha256ChallengeHasher` to the `_with_hasher` functions
+// instead, and with features `challenge_sha512` and `challenge_blake2b`, SHA-512 or Blake2b. These
+// are hash_to_field of RFC 9380 with expand_message_xmd, each with its own domain separation tag:
+// the transcript is expanded to 48 bytes, 128 bits more than the group order, which are reduced
+// modulo the group order so that the challenge is statistically close to uniform.
+
+use amcl_wrapper::field_elem::FieldElement;
+#[cfg(feature = "challenge_blake2b")]
+use blake2::Blake2b;
+use sha2::digest::generic_array::typenum::Unsigned;
+use sha2::digest::BlockInput;
+#[cfg(feature = "challenge_sha512")]
+use sha2::Sha512;
+use sha2::{Digest, Sha256};
+
+/// Domain separation tag of `Sha256ChallengeHasher`
+pub const SHA256_CHALLENGE_DST: &[u8] = b"COCONUT-V01-CS01-with-BLS12381SCALAR_XMD:SHA-256";
+/// Domain separation tag of `Sha512ChallengeHasher`
+#[cfg(feature = "challenge_sha512")]
+pub const SHA512_CHALLENGE_DST: &[u8] = b"COCONUT-V01-CS01-with-BLS12381SCALAR_XMD:SHA-512";
+/// Domain separation tag of `Blake2bChallengeHasher`
+#[cfg(feature = "challenge_blake2b")]
+pub const BLAKE2B_CHALLENGE_DST: &[u8] = b"COCONUT-V01-CS01-with-BLS12381SCALAR_XMD:BLAKE2b";
+
+/// Bytes expanded to for a challenge, L of RFC 9380 for the 255 bit group order and 128 bit
+/// security, ceil((255 + 128) / 8)
+const CHALLENGE_EXPAND_SIZE: usize = 48;
+
+/// Hashes a transcript to a challenge
+pub trait ChallengeHasher {
+    fn hash_to_field(&self, msg: &[u8]) -> FieldElement;
+}
+
+/// `FieldElement::from_msg_hash`, used when no hasher is given
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultChallengeHasher;
+
+/// hash_to_field of RFC 9380 with expand_message_xmd using SHA-256 and `SHA256_CHALLENGE_DST`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sha256ChallengeHasher;
+
+/// hash_to_field of RFC 9380 with expand_message_xmd using SHA-512 and `SHA512_CHALLENGE_DST`
+#[cfg(feature = "challenge_sha512")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sha512ChallengeHasher;
+
+/// hash_to_field of RFC 9380 with expand_message_xmd using Blake2b with 64 byte output and
+/// `BLAKE2B_CHALLENGE_DST`
+#[cfg(feature = "challenge_blake2b")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Blake2bChallengeHasher;
+
+impl ChallengeHasher for DefaultChallengeHasher {
+    fn hash_to_field(&self, msg: &[u8]) -> FieldElement {
+        FieldElement::from_msg_hash(msg)
+    }
+}
+
+impl ChallengeHasher for Sha256ChallengeHasher {
+    fn hash_to_field(&self, msg: &[u8]) -> FieldElement {
+        hash_to_field_xmd::<Sha256>(msg, SHA256_CHALLENGE_DST)
+    }
+}
+
+#[cfg(feature = "challenge_sha512")]
+impl ChallengeHasher for Sha512ChallengeHasher {
+    fn hash_to_field(&self, msg: &[u8]) -> FieldElement {
+        hash_to_field_xmd::<Sha512>(msg, SHA512_CHALLENGE_DST)
+    }
+}
+
+#[cfg(feature = "challenge_blake2b")]
+impl ChallengeHasher for Blake2bChallengeHasher {
+    fn hash_to_field(&self, msg: &[u8]) -> FieldElement {
+        hash_to_field_xmd::<Blake2b>(msg, BLAKE2B_CHALLENGE_DST)
+    }
+}
+
+/// hash_to_field of RFC 9380 for a single element of the scalar field
+fn hash_to_field_xmd<D: Digest + BlockInput>(msg: &[u8], dst: &[u8]) -> FieldElement {
+    reduce_be_bytes(&expand_message_xmd::<D>(msg, dst, CHALLENGE_EXPAND_SIZE))
+}
+
+/// expand_message_xmd of RFC 9380, section 5.3.1. Panics if `len_in_bytes` needs more than 255
+/// hash outputs or is more than 65535, or `dst` is longer than 255 bytes, which are never the case
+/// for the constants it is called with.
+fn expand_message_xmd<D: Digest + BlockInput>(
+    msg: &[u8],
+    dst: &[u8],
+    len_in_bytes: usize,
+) -> Vec<u8> {
+    let b_in_bytes = D::OutputSize::to_usize();
+    let s_in_bytes = D::BlockSize::to_usize();
+    let ell = (len_in_bytes + b_in_bytes - 1) / b_in_bytes;
+    assert!(ell <= 255 && len_in_bytes <= 65535 && dst.len() <= 255);
+    let dst_len = [dst.len() as u8];
+    let len_bytes = [(len_in_bytes >> 8) as u8, len_in_bytes as u8];
+
+    let mut hasher = D::new();
+    hasher.input(&vec![0u8; s_in_bytes]);
+    hasher.input(msg);
+    hasher.input(&len_bytes);
+    hasher.input(&[0u8]);
+    hasher.input(dst);
+    hasher.input(&dst_len);
+    let b_0 = hasher.result();
+
+    let mut uniform_bytes = Vec::with_capacity(ell * b_in_bytes);
+    let mut b_i = vec![0u8; b_in_bytes];
+    for i in 1..=ell {
+        // b_1 = H(b_0 || 1 || DST'), b_i = H((b_0 xor b_(i-1)) || i || DST')
+        let xored = b_0
+            .iter()
+            .zip(b_i.iter())
+            .map(|(x, y)| x ^ y)
+            .collect::<Vec<u8>>();
+        let mut hasher = D::new();
+        hasher.input(&xored);
+        hasher.input(&[i as u8]);
+        hasher.input(dst);
+        hasher.input(&dst_len);
+        b_i = hasher.result().to_vec();
+        uniform_bytes.extend_from_slice(&b_i);
+    }
+    uniform_bytes.truncate(len_in_bytes);
+    uniform_bytes
+}
+
+/// The big-endian integer `bytes` modulo the group order, of any length
+pub fn reduce_be_bytes(bytes: &[u8]) -> FieldElement {
+    // Horner's method over 8 byte words, the 1st word being shorter if the length is not a
+    // multiple of 8
+    let base = &FieldElement::from(u64::max_value()) + &FieldElement::one();
+    let head = bytes.len() % 8;
+    let mut acc = FieldElement::zero();
+    for word in Some(&bytes[..head])
+        .into_iter()
+        .filter(|w| !w.is_empty())
+        .chain(bytes[head..].chunks(8))
+    {
+        let w = word.iter().fold(0u64, |w, b| (w << 8) | u64::from(*b));
+        acc = &(&acc * &base) + &FieldElement::from(w);
+    }
+    acc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{bytes_to_hex, hex_to_bytes};
+
+    /// hash_to_field of `msg` is `expected`, the hex of the big-endian challenge
+    fn check_kat(hasher: &dyn ChallengeHasher, msg: &[u8], expected: &str) {
+        assert_eq!(
+            hasher.hash_to_field(msg),
+            reduce_be_bytes(&hex_to_bytes(expected).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_expand_message_xmd() {
+        // Vectors of RFC 9380, appendix K.1
+        let dst = "QUUX-V01-CS02-with-expander-SHA256-128".as_bytes();
+        assert_eq!(
+            bytes_to_hex(&expand_message_xmd::<Sha256>(&[], dst, 0x20)),
+            "68a985b87eb6b46952128911f2a4412bbc302a9d759667f87f7a21d803f07235"
+        );
+        assert_eq!(
+            bytes_to_hex(&expand_message_xmd::<Sha256>("abc".as_bytes(), dst, 0x20)),
+            "d8ccab23b5985ccea865c6c97b6e5b8350e794e603b4b97902f53a8a0d605615"
+        );
+        assert_eq!(
+            bytes_to_hex(&expand_message_xmd::<Sha256>("abc".as_bytes(), dst, 0x80)),
+            "abba86a6129e366fc877aab32fc4ffc70120d8996c88aee2fe4b32d6c7b6437a\
+             647e6c3163d40b76a73cf6a5674ef1d890f95b664ee0afa5359a5c4e07985635\
+             bbecbac65d747d3d2da7ec2b8221b17b0ca9dc8a1ac1c07ea6a1e60583e2cb00\
+             058e77b7b72a298425cd1b941ad4ec65e8afc50303a22c0f99b0509b4c895f40"
+        );
+    }
+
+    #[test]
+    fn test_challenge_hashers() {
+        check_kat(
+            &Sha256ChallengeHasher,
+            &[],
+            "6c730e820a6ffc110a2c0652b0198e2c4f2316aee0ac3fd729fd5728a19e84de",
+        );
+        check_kat(
+            &Sha256ChallengeHasher,
+            "transcript".as_bytes(),
+            "456b7555e7b7d2518be31487299a90c3ae276a5b3fb7a1f4be754e9b45c86bcd",
+        );
+        let msg = "transcript".as_bytes();
+        assert_ne!(
+            Sha256ChallengeHasher.hash_to_field(msg),
+            DefaultChallengeHasher.hash_to_field(msg)
+        );
+        assert_eq!(
+            DefaultChallengeHasher.hash_to_field(msg),
+            FieldElement::from_msg_hash(msg)
+        );
+    }
+
+    #[cfg(feature = "challenge_sha512")]
+    #[test]
+    fn test_sha512_challenge_hasher() {
+        check_kat(
+            &Sha512ChallengeHasher,
+            &[],
+            "247c5c1eb2db18b7dbb07176efe57513fdfb8562218f062f36afbc5e37e84d33",
+        );
+        check_kat(
+            &Sha512ChallengeHasher,
+            "transcript".as_bytes(),
+            "71ee986afaf868931bdeeb4edc2b656ec3e9b26532f75339082a09d7ea95121c",
+        );
+    }
+
+    #[cfg(feature = "challenge_blake2b")]
+    #[test]
+    fn test_blake2b_challenge_hasher() {
+        check_kat(
+            &Blake2bChallengeHasher,
+            &[],
+            "5a1c4267f8ba64f0b355ec2e5a9933f57484a77633247d59b53e5d2097dfee6b",
+        );
+        check_kat(
+            &Blake2bChallengeHasher,
+            "transcript".as_bytes(),
+            "5edb5893b9d63ec2b9e930d5cb96f9d614cda6eea97ea3e8cf9ec97b01ea6078",
+        );
+    }
+
+    #[test]
+    fn test_reduce_be_bytes() {
+        assert_eq!(reduce_be_bytes(&[]), FieldElement::zero());
+        assert_eq!(reduce_be_bytes(&[1, 2]), FieldElement::from(258u64));
+        let mut bytes = vec![0; 20];
+        bytes.push(5);
+        assert_eq!(reduce_be_bytes(&bytes), FieldElement::from(5u64));
+
+        // order - 1 padded to 64 bytes
+        let minus_one = -FieldElement::one();
+        let mut bytes = vec![0; 64 - minus_one.to_bytes().len()];
+        bytes.append(&mut minus_one.to_bytes());
+        assert_eq!(reduce_be_bytes(&bytes), minus_one);
+
+        // 2^(8*48) + order - 1 is reduced to 2^(8*48) - 1
+        bytes[64 - minus_one.to_bytes().len() - 1] = 1;
+        let two_64 = &FieldElement::from(u64::max_value()) + &FieldElement::one();
+        let mut expected = FieldElement::one();
+        for _ in 0..minus_one.to_bytes().len() / 8 {
+            expected = &expected * &two_64;
+        }
+        assert_eq!(reduce_be_bytes(&bytes), &expected - &FieldElement::one());
+    }
+}
@@ -0,0 +1,172 @@
+// Flattening of signature verification into field elements for verifying Coconut signatures inside
+// a SNARK. Coordinates of group elements are over the base field which is larger than the scalar
+// field, so each coordinate is split into limbs of `CIRCUIT_LIMB_BYTES` bytes, most significant limb
+// first. A point in G1 has coordinates x, y and a point in G2 has x.a, x.b, y.a, y.b.
+
+use crate::errors::{CoconutError, CoconutErrorKind};
+use crate::utils::group_elem_from_bytes;
+use crate::{OtherGroup, SignatureGroup, FIELD_ELEMENT_SIZE, OTHER_GROUP_SIZE, SIGNATURE_GROUP_SIZE};
+use amcl_wrapper::constants::MODBYTES;
+use amcl_wrapper::field_elem::FieldElement;
+use amcl_wrapper::group_elem::GroupElement;
+
+/// Bytes in each limb. Small enough for every limb to be less than the scalar field order.
+pub const CIRCUIT_LIMB_BYTES: usize = 16;
+
+/// Limbs for each coordinate of a point
+pub const LIMBS_PER_COORDINATE: usize = MODBYTES / CIRCUIT_LIMB_BYTES;
+
+// Prefix byte of the uncompressed encoding of points in G1
+const UNCOMPRESSED_PREFIX: u8 = 0x04;
+
+/// Points of the pairing check e(sigma_1, kappa) == e(sigma_2, g_tilde) as limbs of their
+/// coordinates, where kappa = X_tilde.Y_tilde_1^m_1...Y_tilde_n^m_n
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CircuitInputs {
+    pub sigma_1: Vec<FieldElement>,
+    pub sigma_2: Vec<FieldElement>,
+    pub kappa: Vec<FieldElement>,
+    pub g_tilde: Vec<FieldElement>,
+}
+
+impl CircuitInputs {
+    pub fn new(
+        sigma_1: &SignatureGroup,
+        sigma_2: &SignatureGroup,
+        kappa: &OtherGroup,
+        g_tilde: &OtherGroup,
+    ) -> Self {
+        Self {
+            sigma_1: point_to_limbs(sigma_1),
+            sigma_2: point_to_limbs(sigma_2),
+            kappa: point_to_limbs(kappa),
+            g_tilde: point_to_limbs(g_tilde),
+        }
+    }
+
+    /// Recombine the limbs into (sigma_1, sigma_2, kappa, g_tilde)
+    pub fn to_points(
+        &self,
+    ) -> Result<(SignatureGroup, SignatureGroup, OtherGroup, OtherGroup), CoconutError> {
+        Ok((
+            point_from_limbs(&self.sigma_1, SIGNATURE_GROUP_SIZE)?,
+            point_from_limbs(&self.sigma_2, SIGNATURE_GROUP_SIZE)?,
+            point_from_limbs(&self.kappa, OTHER_GROUP_SIZE)?,
+            point_from_limbs(&self.g_tilde, OTHER_GROUP_SIZE)?,
+        ))
+    }
+}
+
+/// Limbs of the coordinates of the point
+pub fn point_to_limbs<G: GroupElement>(p: &G) -> Vec<FieldElement> {
+    let bytes = p.to_bytes();
+    let coordinates = bytes.len() / MODBYTES;
+    let mut limbs = Vec::with_capacity(coordinates * LIMBS_PER_COORDINATE);
+    for limb in bytes[bytes.len() - coordinates * MODBYTES..].chunks(CIRCUIT_LIMB_BYTES) {
+        let mut fe_bytes = vec![0u8; FIELD_ELEMENT_SIZE - CIRCUIT_LIMB_BYTES];
+        fe_bytes.extend_from_slice(limb);
+        // A limb is always less than the field order
+        limbs.push(FieldElement::from_bytes(&fe_bytes).unwrap());
+    }
+    limbs
+}
+
+/// Point from the limbs of its coordinates. `size` is the byte size of the point's encoding,
+/// `SIGNATURE_GROUP_SIZE` or `OTHER_GROUP_SIZE`.
+pub fn point_from_limbs<G: GroupElement>(
+    limbs: &[FieldElement],
+    size: usize,
+) -> Result<G, CoconutError> {
+    let coordinates = size / MODBYTES;
+    if limbs.len() != coordinates * LIMBS_PER_COORDINATE {
+        return Err(CoconutErrorKind::GeneralError {
+            msg: format!(
+                "Expected {} limbs but given {}",
+                coordinates * LIMBS_PER_COORDINATE,
+                limbs.len()
+            ),
+        }
+        .into());
+    }
+    let mut bytes = Vec::with_capacity(size);
+    if size > coordinates * MODBYTES {
+        bytes.push(UNCOMPRESSED_PREFIX);
+    }
+    for limb in limbs {
+        let fe_bytes = limb.to_bytes();
+        let (high, low) = fe_bytes.split_at(fe_bytes.len() - CIRCUIT_LIMB_BYTES);
+        if high.iter().any(|b| *b != 0) {
+            return Err(CoconutErrorKind::GeneralError {
+                msg: format!("Limb larger than {} bytes", CIRCUIT_LIMB_BYTES),
+            }
+            .into());
+        }
+        bytes.extend_from_slice(low);
+    }
+    group_elem_from_bytes(&bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ate_2_pairing;
+    use crate::keygen::trusted_party_SSS_keygen;
+    use crate::signature::{BlindSignature, Params, SignatureRequest};
+    use amcl_wrapper::field_elem::FieldElementVector;
+
+    #[test]
+    fn test_circuit_inputs() {
+        let msg_count = 3;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (_, _, signers) = trusted_party_SSS_keygen(3, 5, &params);
+        let vk = &signers[0].verkey;
+        let msgs = FieldElementVector::random(msg_count);
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        let (sig_req, _) = SignatureRequest::new(&msgs, 1, &elg_pk, &params).unwrap();
+        let sig = BlindSignature::new(&sig_req, &signers[0].sigkey)
+            .unwrap()
+            .unblind(&elg_sk);
+
+        let inputs = sig.to_circuit_inputs(msgs.as_slice(), vk, &params).unwrap();
+        let sig_coords = SIGNATURE_GROUP_SIZE / MODBYTES;
+        let other_coords = OTHER_GROUP_SIZE / MODBYTES;
+        assert_eq!(inputs.sigma_1.len(), sig_coords * LIMBS_PER_COORDINATE);
+        assert_eq!(inputs.sigma_2.len(), sig_coords * LIMBS_PER_COORDINATE);
+        assert_eq!(inputs.kappa.len(), other_coords * LIMBS_PER_COORDINATE);
+        assert_eq!(inputs.g_tilde.len(), other_coords * LIMBS_PER_COORDINATE);
+
+        // Recombined points reproduce the pairing check
+        let (sigma_1, sigma_2, kappa, g_tilde) = inputs.to_points().unwrap();
+        assert_eq!(sigma_1, sig.sigma_1);
+        assert_eq!(sigma_2, sig.sigma_2);
+        assert_eq!(g_tilde, params.g_tilde);
+        let expected_kappa = vk
+            .partial_kappa(
+                &(0..msg_count)
+                    .map(|i| (i, msgs[i].clone()))
+                    .collect::<Vec<(usize, FieldElement)>>(),
+                &params,
+            )
+            .unwrap();
+        assert_eq!(kappa, expected_kappa);
+        let e = ate_2_pairing(&sigma_1, &kappa, &sigma_2.negation(), &g_tilde);
+        let expected = ate_2_pairing(
+            &sig.sigma_1,
+            &expected_kappa,
+            &sig.sigma_2.negation(),
+            &params.g_tilde,
+        );
+        assert_eq!(e, expected);
+        assert!(e.is_one());
+
+        // Wrong messages give a kappa failing the pairing check
+        let wrong = FieldElementVector::random(msg_count);
+        let inputs = sig.to_circuit_inputs(wrong.as_slice(), vk, &params).unwrap();
+        let (sigma_1, sigma_2, kappa, g_tilde) = inputs.to_points().unwrap();
+        assert!(!ate_2_pairing(&sigma_1, &kappa, &sigma_2.negation(), &g_tilde).is_one());
+
+        assert!(sig
+            .to_circuit_inputs(&msgs.as_slice()[1..], vk, &params)
+            .is_err());
+    }
+}
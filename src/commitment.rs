@@ -0,0 +1,291 @@
+// Pedersen commitment to several messages, C = b_1^m_1.b_2^m_2...b_n^m_n.b_{n+1}^r, along with a
+// Schnorr proof of knowledge of its opening. The commitment of a signature request is such a
+// commitment to the hidden messages with the bases h_1, h_2, ... of the params and g for r. Other
+// protocols can prove that their messages are those in a commitment by using the same blindings
+// for the messages in both proofs under the same challenge, since the responses are then equal.
+
+use crate::errors::{CoconutError, CoconutErrorKind};
+use crate::signature::{
+    ProofSignatureGroup, ProverCommittedSignatureGroup, ProverCommittingSignatureGroup,
+    SchnorrCommitment,
+};
+use crate::{SignatureGroup, SignatureGroupVec};
+use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
+use amcl_wrapper::group_elem::{GroupElement, GroupElementVector};
+
+/// Proof of knowledge of the opening of a commitment. The responses are for the messages in order
+/// and then for the blinding.
+pub type CommitmentOpeningProof = ProofSignatureGroup;
+
+/// Commitment to messages along with the commitment phase of the proof of knowledge of its opening
+#[derive(Clone, Debug)]
+pub struct Commitment {
+    pub value: SignatureGroup,
+    /// The messages and then the blinding
+    secrets: Vec<FieldElement>,
+    pok_vc: ProverCommittedSignatureGroup,
+}
+
+impl Commitment {
+    /// Commit to `messages` with `blinding`. `bases` has a base for each message and then the base
+    /// for the blinding. Errors if the number of bases is not 1 more than the number of messages.
+    pub fn new(
+        messages: &[FieldElement],
+        blinding: &FieldElement,
+        bases: &[SignatureGroup],
+    ) -> Result<Self, CoconutError> {
+        let message_blindings = FieldElementVector::random(messages.len());
+        Self::new_with_blindings(messages, blinding, bases, message_blindings.as_slice())
+    }
+
+    /// Same as `new` but the blindings for the messages in the proof of knowledge of the opening
+    /// are given by the caller, 1 for each message in order
+    pub fn new_with_blindings(
+        messages: &[FieldElement],
+        blinding: &FieldElement,
+        bases: &[SignatureGroup],
+        message_blindings: &[FieldElement],
+    ) -> Result<Self, CoconutError> {
+        let value = Self::compute(messages, blinding, bases)?;
+        if message_blindings.len() != messages.len() {
+            return Err(CoconutErrorKind::GeneralError {
+                msg: format!(
+                    "Need {} blindings for messages but given {}",
+                    messages.len(),
+                    message_blindings.len()
+                ),
+            }
+            .into());
+        }
+        let mut secrets = messages.to_vec();
+        secrets.push(blinding.clone());
+        let pok_vc = commit_opening(bases, message_blindings, &FieldElement::random());
+        Ok(Self {
+            value,
+            secrets,
+            pok_vc,
+        })
+    }
+
+    /// Only the value b_1^m_1.b_2^m_2...b_n^m_n.b_{n+1}^blinding of the commitment to `messages`,
+    /// without a proof of knowledge of the opening. Errors like `new`.
+    pub fn compute(
+        messages: &[FieldElement],
+        blinding: &FieldElement,
+        bases: &[SignatureGroup],
+    ) -> Result<SignatureGroup, CoconutError> {
+        if bases.len() != messages.len() + 1 {
+            return Err(CoconutErrorKind::UnsupportedNoOfMessages {
+                expected: bases.len().saturating_sub(1),
+                given: messages.len(),
+            }
+            .into());
+        }
+        let mut b = SignatureGroupVec::with_capacity(bases.len());
+        let mut exps = FieldElementVector::with_capacity(bases.len());
+        for (base, m) in bases.iter().zip(messages.iter().chain(Some(blinding))) {
+            b.push(base.clone());
+            exps.push(m.clone());
+        }
+        Ok(b.multi_scalar_mul_const_time(&exps)?)
+    }
+
+    /// Commitment with the message at `index` changed from `old_value` to `new_value`, where
+    /// `bases` are as for `new`. Computed as C.b_index^(new_value - old_value) rather than
+    /// recomputing all the terms. The commitment phase of the proof of knowledge of the opening is
+    /// redone with fresh blindings so that proofs of the opening of both commitments don't share
+    /// blindings, which would reveal the unchanged messages. Errors if the number of bases is
+    /// wrong, or if there is no message at `index` or it is not `old_value`.
+    pub fn update(
+        &self,
+        index: usize,
+        old_value: &FieldElement,
+        new_value: &FieldElement,
+        bases: &[SignatureGroup],
+    ) -> Result<Self, CoconutError> {
+        // The last secret is the blinding
+        let msg_count = self.secrets.len() - 1;
+        if bases.len() != self.secrets.len() {
+            return Err(CoconutErrorKind::UnsupportedNoOfMessages {
+                expected: bases.len().saturating_sub(1),
+                given: msg_count,
+            }
+            .into());
+        }
+        if index >= msg_count {
+            return Err(CoconutErrorKind::InvalidAttributeIndex {
+                idx: index,
+                msg_count,
+            }
+            .into());
+        }
+        if self.secrets[index] != *old_value {
+            return Err(CoconutErrorKind::GeneralError {
+                msg: format!("Message {} of the commitment is not the old value", index),
+            }
+            .into());
+        }
+        let value = &self.value + &(&bases[index] * &(new_value - old_value));
+        let mut secrets = self.secrets.clone();
+        secrets[index] = new_value.clone();
+        let message_blindings = FieldElementVector::random(msg_count);
+        let pok_vc = commit_opening(bases, message_blindings.as_slice(), &FieldElement::random());
+        Ok(Self {
+            value,
+            secrets,
+            pok_vc,
+        })
+    }
+
+    /// The commitment and the commitment of the proof of knowledge of the opening, to be hashed
+    /// in the challenge
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.value.to_bytes();
+        bytes.append(&mut self.pok_vc.to_bytes());
+        bytes
+    }
+
+    /// Schnorr commitment of the proof of knowledge of the opening
+    pub fn opening_commitment(&self) -> SignatureGroup {
+        self.pok_vc.schnorr_commitment()
+    }
+
+    pub fn prove_opening(
+        self,
+        challenge: &FieldElement,
+    ) -> Result<CommitmentOpeningProof, CoconutError> {
+        Ok(self.pok_vc.gen_proof(challenge, &self.secrets)?)
+    }
+
+    /// Verify the proof of knowledge of the opening of commitment `value` with `bases`, given as
+    /// for `new`
+    pub fn verify_opening(
+        value: &SignatureGroup,
+        bases: &[SignatureGroup],
+        proof: &CommitmentOpeningProof,
+        challenge: &FieldElement,
+    ) -> Result<bool, CoconutError> {
+        if proof.responses.len() != bases.len() {
+            return Ok(false);
+        }
+        Ok(proof.verify(bases, value, challenge)?)
+    }
+}
+
+/// Commitment phase of the proof of knowledge of the opening of a commitment with `bases`, using
+/// `message_blindings` for the messages and `blinding_blinding` for the blinding
+pub(crate) fn commit_opening(
+    bases: &[SignatureGroup],
+    message_blindings: &[FieldElement],
+    blinding_blinding: &FieldElement,
+) -> ProverCommittedSignatureGroup {
+    let mut committing = ProverCommittingSignatureGroup::new();
+    for (b, r) in bases.iter().zip(message_blindings.iter()) {
+        committing.commit(b, Some(r));
+    }
+    committing.commit(&bases[message_blindings.len()], Some(blinding_blinding));
+    committing.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signature::Params;
+
+    #[test]
+    fn test_commitment_opening() {
+        let msg_count = 3;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let mut bases = params.h.iter().cloned().collect::<Vec<SignatureGroup>>();
+        bases.push(params.g.clone());
+        let msgs = FieldElementVector::random(msg_count);
+        let blinding = FieldElement::random();
+
+        let comm = Commitment::new(msgs.as_slice(), &blinding, &bases).unwrap();
+        let value = comm.value.clone();
+        let challenge = FieldElement::from_msg_hash(&comm.to_bytes());
+        let proof = comm.prove_opening(&challenge).unwrap();
+        assert!(Commitment::verify_opening(&value, &bases, &proof, &challenge).unwrap());
+
+        // Different challenge, commitment or bases
+        assert!(
+            !Commitment::verify_opening(&value, &bases, &proof, &FieldElement::random()).unwrap()
+        );
+        let other_value = SignatureGroup::random();
+        assert!(!Commitment::verify_opening(&other_value, &bases, &proof, &challenge).unwrap());
+        assert!(!Commitment::verify_opening(&value, &bases[1..], &proof, &challenge).unwrap());
+
+        // Same message blindings in 2 commitments give equal responses for the messages
+        let msg_blindings = FieldElementVector::random(msg_count);
+        let comm_1 = Commitment::new_with_blindings(
+            msgs.as_slice(),
+            &blinding,
+            &bases,
+            msg_blindings.as_slice(),
+        )
+        .unwrap();
+        let comm_2 = Commitment::new_with_blindings(
+            msgs.as_slice(),
+            &FieldElement::random(),
+            &bases,
+            msg_blindings.as_slice(),
+        )
+        .unwrap();
+        let proof_1 = comm_1.prove_opening(&challenge).unwrap();
+        let proof_2 = comm_2.prove_opening(&challenge).unwrap();
+        for i in 0..msg_count {
+            assert_eq!(proof_1.responses[i], proof_2.responses[i]);
+        }
+
+        // Updating a message gives the commitment built with the new message
+        let mut new_msgs = msgs.clone();
+        new_msgs[1] = FieldElement::random();
+        let comm = Commitment::new(msgs.as_slice(), &blinding, &bases).unwrap();
+        let updated = comm.update(1, &msgs[1], &new_msgs[1], &bases).unwrap();
+        let fresh = Commitment::new(new_msgs.as_slice(), &blinding, &bases).unwrap();
+        assert_eq!(updated.value, fresh.value);
+        let challenge = FieldElement::from_msg_hash(&updated.to_bytes());
+        let value = updated.value.clone();
+        let proof = updated.prove_opening(&challenge).unwrap();
+        assert!(Commitment::verify_opening(&value, &bases, &proof, &challenge).unwrap());
+
+        // The proofs of both commitments use distinct blindings, so under the same challenge the
+        // responses for the unchanged messages and the blinding differ
+        let updated = comm.update(1, &msgs[1], &new_msgs[1], &bases).unwrap();
+        let old_value = comm.value.clone();
+        let new_value = updated.value.clone();
+        let proof_old = comm.clone().prove_opening(&challenge).unwrap();
+        let proof_new = updated.prove_opening(&challenge).unwrap();
+        assert!(Commitment::verify_opening(&old_value, &bases, &proof_old, &challenge).unwrap());
+        assert!(Commitment::verify_opening(&new_value, &bases, &proof_new, &challenge).unwrap());
+        assert_ne!(proof_old.commitment, proof_new.commitment);
+        for i in (0..=msg_count).filter(|i| *i != 1) {
+            assert_ne!(proof_old.responses[i], proof_new.responses[i]);
+        }
+
+        // Wrong old value, bases or index
+        assert!(comm.update(1, &new_msgs[1], &msgs[1], &bases).is_err());
+        assert!(comm.update(1, &msgs[1], &new_msgs[1], &bases[1..]).is_err());
+        match comm.update(msg_count, &blinding, &msgs[0], &bases) {
+            Err(e) => match e.kind() {
+                CoconutErrorKind::InvalidAttributeIndex { idx, msg_count: n } => {
+                    assert_eq!(idx, msg_count);
+                    assert_eq!(n, msg_count);
+                }
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(_) => panic!("updated the blinding as a message"),
+        }
+
+        match Commitment::new(msgs.as_slice(), &blinding, &bases[1..]) {
+            Err(e) => match e.kind() {
+                CoconutErrorKind::UnsupportedNoOfMessages { expected, given } => {
+                    assert_eq!(expected, msg_count - 1);
+                    assert_eq!(given, msg_count);
+                }
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(_) => panic!("committed with fewer bases than messages"),
+        }
+    }
+}
@@ -0,0 +1,944 @@
+// Issuance over attributes already committed to by an external system. The external system gives
+// the holder a Pedersen commitment C = g^m.h^s to an attribute m. The holder requests a blind
+// signature with m as a hidden attribute and proves knowledge of the opening of C using the same
+// blinding for m as in the proof of knowledge of the signature request. The signer checks that the
+// responses for m are equal in both proofs and thus signs the committed attribute without learning it.
+//
+// When the holder knows m, it creates the request with `SignatureRequest::new` and the proof with
+// `CommittedAttributesPoK`. When only the external system knows the opening, the holder creates
+// the request with `CommittedSignatureRequest` from the `CommittedAttribute` points the external
+// system gives it, and the external system takes part in the proof as a co-prover for m with
+// `CommitmentOpening::init_proof`. The holder then never learns the scalar m, though like for any
+// hidden attribute it can decrypt h^m from the ciphertext it created. Both give the same
+// `SignatureRequestProof` and `CommittedAttributesProof`, verified the same way, and the request is
+// signed with `BlindSignature::new` as any other.
+
+use crate::errors::{CoconutError, CoconutErrorKind};
+use crate::signature::{
+    challenge_v1_transcript, ChallengeBuilder, Params, ProofSignatureGroup,
+    ProverCommittedSignatureGroup, ProverCommittingSignatureGroup, SchnorrCommitment,
+    SignatureRequest, SignatureRequestProof,
+};
+use crate::utils::{append_u32, hash_to_subgroup};
+use crate::{SignatureGroup, SIGNATURE_GROUP_COFACTOR};
+use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
+use amcl_wrapper::group_elem::GroupElement;
+use std::collections::{BTreeMap, HashSet};
+
+/// Generators of the external commitments, C = g^m.h^s
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CommitmentKey {
+    pub g: SignatureGroup,
+    pub h: SignatureGroup,
+}
+
+impl CommitmentKey {
+    pub fn new(label: &[u8]) -> Self {
+        Self {
+            g: hash_to_subgroup(
+                &[label, " : g".as_bytes()].concat(),
+                SIGNATURE_GROUP_COFACTOR,
+            ),
+            h: hash_to_subgroup(
+                &[label, " : h".as_bytes()].concat(),
+                SIGNATURE_GROUP_COFACTOR,
+            ),
+        }
+    }
+
+    pub fn commit(&self, m: &FieldElement, s: &FieldElement) -> SignatureGroup {
+        &(&self.g * m) + &(&self.h * s)
+    }
+
+    /// g || h
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.g.to_bytes();
+        bytes.append(&mut self.h.to_bytes());
+        bytes
+    }
+}
+
+/// Commitment phase of the proof of knowledge of openings of external commitments to hidden
+/// attributes. Each item is the index of the hidden attribute, the external commitment and the
+/// Schnorr commitment.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CommittedAttributesPoK {
+    pub comm_key: CommitmentKey,
+    pub pok_vcs: Vec<(usize, SignatureGroup, ProverCommittedSignatureGroup)>,
+}
+
+/// Response phase of the proof of knowledge of openings of external commitments to hidden attributes
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CommittedAttributesProof {
+    pub proofs: Vec<(usize, ProofSignatureGroup)>,
+}
+
+impl CommittedAttributesPoK {
+    /// `hidden_msg_indices` are the indices of the hidden attributes that are committed externally
+    /// and `commitments` their external commitments in the same order. `blindings` must be the
+    /// blindings for all hidden attributes passed to `SignatureRequestPoK::init_with_blindings`.
+    pub fn init(
+        hidden_msg_indices: &[usize],
+        commitments: &[SignatureGroup],
+        comm_key: &CommitmentKey,
+        blindings: &[FieldElement],
+    ) -> Result<Self, CoconutError> {
+        if commitments.len() != hidden_msg_indices.len() {
+            return Err(CoconutErrorKind::UnsupportedNoOfMessages {
+                expected: hidden_msg_indices.len(),
+                given: commitments.len(),
+            }
+            .into());
+        }
+        let mut pok_vcs = Vec::with_capacity(hidden_msg_indices.len());
+        for (idx, comm) in hidden_msg_indices.iter().zip(commitments.iter()) {
+            if *idx >= blindings.len() {
+                return Err(CoconutErrorKind::InvalidAttributeIndex {
+                    idx: *idx,
+                    msg_count: blindings.len(),
+                }
+                .into());
+            }
+            let mut committing = ProverCommittingSignatureGroup::new();
+            committing.commit(&comm_key.g, Some(&blindings[*idx]));
+            committing.commit(&comm_key.h, None);
+            pok_vcs.push((*idx, comm.clone(), committing.finish()));
+        }
+        Ok(Self {
+            comm_key: comm_key.clone(),
+            pok_vcs,
+        })
+    }
+
+    /// Bytes to include in the challenge, the same as `CommittedAttributesProof::challenge_bytes`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        attribute_proof_bytes(
+            &self.comm_key,
+            self.pok_vcs
+                .iter()
+                .map(|(idx, comm, pok_vc)| (*idx, comm, pok_vc.schnorr_commitment()))
+                .collect(),
+        )
+    }
+
+    /// `openings` are the (attribute, randomness) of each external commitment in the same order
+    /// as the indices given to `init`.
+    pub fn gen_proof(
+        self,
+        openings: &[(FieldElement, FieldElement)],
+        challenge: &FieldElement,
+    ) -> Result<CommittedAttributesProof, CoconutError> {
+        if openings.len() != self.pok_vcs.len() {
+            return Err(CoconutErrorKind::UnsupportedNoOfMessages {
+                expected: self.pok_vcs.len(),
+                given: openings.len(),
+            }
+            .into());
+        }
+        let mut proofs = Vec::with_capacity(openings.len());
+        for ((idx, _, pok_vc), (m, s)) in self.pok_vcs.into_iter().zip(openings.iter()) {
+            let proof = pok_vc.gen_proof(challenge, &[m.clone(), s.clone()])?;
+            proofs.push((idx, proof));
+        }
+        Ok(CommittedAttributesProof { proofs })
+    }
+}
+
+impl CommittedAttributesProof {
+    /// Verify the proofs of knowledge of openings of `commitments`, in the same order as the proofs,
+    /// and that the committed attributes are the hidden attributes of the signature request proven
+    /// by `sig_req_proof` with the same challenge. `sig_req_proof` should be verified separately.
+    pub fn verify(
+        &self,
+        commitments: &[SignatureGroup],
+        comm_key: &CommitmentKey,
+        sig_req_proof: &SignatureRequestProof,
+        challenge: &FieldElement,
+    ) -> Result<bool, CoconutError> {
+        if commitments.len() != self.proofs.len() {
+            return Ok(false);
+        }
+        let bases = [comm_key.g.clone(), comm_key.h.clone()];
+        // Last response in proof of the commitment in signature request is for its randomness. A
+        // proof without it is malformed.
+        let hidden_count = match sig_req_proof.proof_commitment.responses.len().checked_sub(1) {
+            Some(n) => n,
+            None => return Ok(false),
+        };
+        for ((idx, proof), comm) in self.proofs.iter().zip(commitments.iter()) {
+            if *idx >= hidden_count || proof.responses.len() != 2 {
+                return Ok(false);
+            }
+            // Same response for the attribute in both proofs
+            if proof.responses[0] != sig_req_proof.proof_commitment.responses[*idx] {
+                return Ok(false);
+            }
+            if !proof.verify(&bases, comm, challenge)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// The bytes of `CommittedAttributesPoK::to_bytes` for `commitments`, in the same order as the
+    /// proofs, so that a signer can recompute the challenge. None if the count of commitments is
+    /// not that of the proofs.
+    pub fn challenge_bytes(
+        &self,
+        commitments: &[SignatureGroup],
+        comm_key: &CommitmentKey,
+    ) -> Option<Vec<u8>> {
+        if commitments.len() != self.proofs.len() {
+            return None;
+        }
+        Some(attribute_proof_bytes(
+            comm_key,
+            self.proofs
+                .iter()
+                .zip(commitments.iter())
+                .map(|((idx, proof), comm)| (*idx, comm, proof.commitment.clone()))
+                .collect(),
+        ))
+    }
+}
+
+/// Challenge for a signature request proof together with the proof of its externally committed
+/// attributes. `request_commitments` are the Schnorr commitments of the signature request proof,
+/// from `SignatureRequestPoK::commitments` for the holder and `SignatureRequestProof::commitments`
+/// for the signer, and `attribute_bytes` are from `CommittedAttributesPoK::to_bytes`,
+/// `CommittedSignatureRequestPoK::attribute_bytes` or `CommittedAttributesProof::challenge_bytes`.
+pub fn committed_request_challenge(
+    sig_req: &SignatureRequest,
+    elgamal_pk: &SignatureGroup,
+    request_commitments: &[SignatureGroup],
+    attribute_bytes: &[u8],
+    params: &Params,
+) -> FieldElement {
+    ChallengeBuilder::new()
+        .bytes(&challenge_v1_transcript(
+            params,
+            elgamal_pk,
+            sig_req,
+            request_commitments,
+        ))
+        .bytes(attribute_bytes)
+        .finish()
+}
+
+/// Count of proofs followed by, for each, the attribute index as a 4 byte big-endian integer, the
+/// commitment key, the external commitment and the Schnorr commitment
+fn attribute_proof_bytes(
+    comm_key: &CommitmentKey,
+    items: Vec<(usize, &SignatureGroup, SignatureGroup)>,
+) -> Vec<u8> {
+    let mut bytes = vec![];
+    append_u32(&mut bytes, items.len());
+    for (idx, comm, t) in items {
+        append_u32(&mut bytes, idx);
+        bytes.append(&mut comm_key.to_bytes());
+        bytes.append(&mut comm.to_bytes());
+        bytes.append(&mut t.to_bytes());
+    }
+    bytes
+}
+
+/// Opening (m, s) of an external commitment C = g^m.h^s to a hidden attribute, kept by the external
+/// system
+#[derive(Clone, Debug)]
+pub struct CommitmentOpening {
+    pub m: FieldElement,
+    pub s: FieldElement,
+}
+
+/// Given by the external system to the holder for the hidden attribute at index `idx`: the
+/// external commitment and `request_part`, the attribute's part h_idx^m of the commitment of the
+/// signature request for the base h_idx of the params
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CommittedAttribute {
+    pub idx: usize,
+    pub commitment: SignatureGroup,
+    pub request_part: SignatureGroup,
+}
+
+/// Commitment phase of the external system as co-prover for the committed attribute. Only
+/// `commitment` is given to the holder.
+#[derive(Clone, Debug)]
+pub struct CoProverPoK {
+    pub commitment: CoProverCommitment,
+    blinding_m: FieldElement,
+    blinding_s: FieldElement,
+}
+
+/// Given by the external system to the holder for the hidden attribute at index `idx` after
+/// getting the generator h of the request: the plaintext h^m of the attribute's ciphertext and the
+/// Schnorr commitments for m in the proofs of the request commitment, of the ciphertext and of the
+/// opening of the external commitment
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CoProverCommitment {
+    pub idx: usize,
+    pub h_m: SignatureGroup,
+    pub t_request: SignatureGroup,
+    pub t_ciphertext: SignatureGroup,
+    pub t_opening: SignatureGroup,
+}
+
+/// Responses of the external system for the attribute at index `idx` and for the randomness of the
+/// external commitment
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CoProverResponse {
+    pub idx: usize,
+    pub m: FieldElement,
+    pub s: FieldElement,
+}
+
+impl CommitmentOpening {
+    pub fn new(m: FieldElement, s: FieldElement) -> Self {
+        Self { m, s }
+    }
+
+    /// The points for the hidden attribute at index `idx` to give the holder. Errors if `idx` is
+    /// not an index of `params`.
+    pub fn committed_attribute(
+        &self,
+        idx: usize,
+        comm_key: &CommitmentKey,
+        params: &Params,
+    ) -> Result<CommittedAttribute, CoconutError> {
+        if idx >= params.msg_count() {
+            return Err(CoconutErrorKind::InvalidAttributeIndex {
+                idx,
+                msg_count: params.msg_count(),
+            }
+            .into());
+        }
+        Ok(CommittedAttribute {
+            idx,
+            commitment: comm_key.commit(&self.m, &self.s),
+            request_part: &params.h[idx] * &self.m,
+        })
+    }
+
+    /// Commitment phase as co-prover for the attribute at index `idx` of the request with
+    /// generator `h`, see `CommittedSignatureRequest::h`. Errors if `idx` is not an index of
+    /// `params`.
+    pub fn init_proof(
+        &self,
+        idx: usize,
+        h: &SignatureGroup,
+        comm_key: &CommitmentKey,
+        params: &Params,
+    ) -> Result<CoProverPoK, CoconutError> {
+        if idx >= params.msg_count() {
+            return Err(CoconutErrorKind::InvalidAttributeIndex {
+                idx,
+                msg_count: params.msg_count(),
+            }
+            .into());
+        }
+        let blinding_m = FieldElement::random();
+        let blinding_s = FieldElement::random();
+        Ok(CoProverPoK {
+            commitment: CoProverCommitment {
+                idx,
+                h_m: h * &self.m,
+                t_request: &params.h[idx] * &blinding_m,
+                t_ciphertext: h * &blinding_m,
+                t_opening: comm_key.commit(&blinding_m, &blinding_s),
+            },
+            blinding_m,
+            blinding_s,
+        })
+    }
+}
+
+impl CoProverPoK {
+    /// Responses for `opening` for the challenge of the holder, see
+    /// `CommittedSignatureRequestPoK::challenge`
+    pub fn gen_proof(
+        self,
+        opening: &CommitmentOpening,
+        challenge: &FieldElement,
+    ) -> CoProverResponse {
+        CoProverResponse {
+            idx: self.commitment.idx,
+            m: &self.blinding_m - &(challenge * &opening.m),
+            s: &self.blinding_s - &(challenge * &opening.s),
+        }
+    }
+}
+
+/// Holder side of a signature request over hidden attributes of which some are committed by an
+/// external system that keeps their openings. Created before the ciphertexts since the plaintexts
+/// of the committed attributes are given by the external system for the generator `h` of the
+/// request.
+#[derive(Clone, Debug)]
+pub struct CommittedSignatureRequest {
+    /// Hidden attributes in order of index, None for those committed externally
+    hidden: Vec<Option<FieldElement>>,
+    committed: Vec<CommittedAttribute>,
+    known_messages: Vec<FieldElement>,
+    randomness: FieldElement,
+    commitment: SignatureGroup,
+    h: SignatureGroup,
+}
+
+/// Commitment phase of the holder's proof of knowledge of the request created by
+/// `CommittedSignatureRequest::init`
+#[derive(Clone, Debug)]
+pub struct CommittedSignatureRequestPoK {
+    pub sig_req: SignatureRequest,
+    hidden: Vec<Option<FieldElement>>,
+    committed: Vec<CommittedAttribute>,
+    comm_key: CommitmentKey,
+    /// Commitment randomness followed by the Elgamal randomness of each hidden attribute
+    randomness: FieldElementVector,
+    /// Blindings for the Elgamal secret key, the commitment randomness, the Elgamal randomness of
+    /// each hidden attribute and the hidden attributes known to the holder, by index
+    blinding_sk: FieldElement,
+    blinding_r: FieldElement,
+    blindings_k: Vec<FieldElement>,
+    blindings_m: BTreeMap<usize, FieldElement>,
+    /// Schnorr commitments in the order of `SignatureRequestPoK::commitments`
+    commitments: Vec<SignatureGroup>,
+    /// Schnorr commitments of the external system for the openings, in the order of `committed`
+    opening_commitments: Vec<SignatureGroup>,
+}
+
+impl CommittedSignatureRequest {
+    /// `messages` are the attributes known to the holder in order of index, that is all except
+    /// those of `committed`, and `count_hidden` the count of hidden attributes including those of
+    /// `committed`, which must be hidden. Errors if the counts don't match `params` or an index of
+    /// `committed` is repeated or not of a hidden attribute.
+    pub fn new(
+        messages: &FieldElementVector,
+        count_hidden: usize,
+        committed: &[CommittedAttribute],
+        params: &Params,
+    ) -> Result<Self, CoconutError> {
+        if messages.len() + committed.len() != params.msg_count() {
+            return Err(CoconutErrorKind::UnsupportedNoOfMessages {
+                expected: params.msg_count(),
+                given: messages.len() + committed.len(),
+            }
+            .into());
+        }
+        if count_hidden > params.msg_count() {
+            return Err(CoconutErrorKind::GeneralError {
+                msg: format!(
+                    "{} hidden attributes but {} attributes",
+                    count_hidden,
+                    params.msg_count()
+                ),
+            }
+            .into());
+        }
+        let mut indices = HashSet::new();
+        for attr in committed {
+            if attr.idx >= count_hidden {
+                return Err(CoconutErrorKind::InvalidAttributeIndex {
+                    idx: attr.idx,
+                    msg_count: count_hidden,
+                }
+                .into());
+            }
+            if !indices.insert(attr.idx) {
+                return Err(CoconutErrorKind::GeneralError {
+                    msg: format!("Attribute {} committed more than once", attr.idx),
+                }
+                .into());
+            }
+        }
+
+        let mut own = messages.iter();
+        let hidden = (0..count_hidden)
+            .map(|i| {
+                if indices.contains(&i) {
+                    None
+                } else {
+                    own.next().cloned()
+                }
+            })
+            .collect::<Vec<Option<FieldElement>>>();
+        let known_messages = own.cloned().collect::<Vec<FieldElement>>();
+
+        // commitment = h_1^m_1.h_2^m_2...h_count_hidden^m_count_hidden.g^r with the parts of the
+        // committed attributes given by the external system
+        let randomness = FieldElement::random();
+        let mut commitment = &params.g * &randomness;
+        for (i, m) in hidden.iter().enumerate() {
+            if let Some(m) = m {
+                commitment = &commitment + &(&params.h[i] * m);
+            }
+        }
+        for attr in committed {
+            commitment = &commitment + &attr.request_part;
+        }
+        let h = SignatureRequest::compute_h(&commitment, &known_messages);
+        Ok(Self {
+            hidden,
+            committed: committed.to_vec(),
+            known_messages,
+            randomness,
+            commitment,
+            h,
+        })
+    }
+
+    /// Generator of the request, given to the external system for `CommitmentOpening::init_proof`
+    pub fn h(&self) -> &SignatureGroup {
+        &self.h
+    }
+
+    /// Create the request with the hidden attributes encrypted under `elgamal_pk` and the
+    /// commitment phase of its proof of knowledge, combining the holder's Schnorr commitments with
+    /// `co_prover_commitments`, 1 for each committed attribute. Errors if a committed attribute
+    /// has no commitment from the external system.
+    pub fn init(
+        self,
+        co_prover_commitments: &[CoProverCommitment],
+        elgamal_pk: &SignatureGroup,
+        comm_key: &CommitmentKey,
+        params: &Params,
+    ) -> Result<CommittedSignatureRequestPoK, CoconutError> {
+        let mut co_provers = BTreeMap::new();
+        for c in co_prover_commitments {
+            co_provers.insert(c.idx, c);
+        }
+        if co_provers.len() != self.committed.len()
+            || self
+                .committed
+                .iter()
+                .any(|a| !co_provers.contains_key(&a.idx))
+        {
+            return Err(CoconutErrorKind::GeneralError {
+                msg: String::from("Need 1 co-prover commitment for each committed attribute"),
+            }
+            .into());
+        }
+
+        let mut randomness = FieldElementVector::with_capacity(self.hidden.len() + 1);
+        randomness.push(self.randomness.clone());
+        let blinding_sk = FieldElement::random();
+        let blinding_r = FieldElement::random();
+        let mut blindings_k = Vec::with_capacity(self.hidden.len());
+        let mut blindings_m = BTreeMap::new();
+        let mut ciphertexts = Vec::with_capacity(self.hidden.len());
+        let mut t_commitment = &params.g * &blinding_r;
+        let mut t_ciphertexts = Vec::with_capacity(self.hidden.len());
+        for (i, m) in self.hidden.iter().enumerate() {
+            let k = FieldElement::random();
+            let blinding_k = FieldElement::random();
+            // Plaintext h^m and the Schnorr commitments for m
+            let (h_m, t_request, t_ciphertext) = match m {
+                Some(m) => {
+                    let blinding_m = FieldElement::random();
+                    let parts = (
+                        &self.h * m,
+                        &params.h[i] * &blinding_m,
+                        &self.h * &blinding_m,
+                    );
+                    blindings_m.insert(i, blinding_m);
+                    parts
+                }
+                None => {
+                    let c = co_provers[&i];
+                    (c.h_m.clone(), c.t_request.clone(), c.t_ciphertext.clone())
+                }
+            };
+            ciphertexts.push((&params.g * &k, &(elgamal_pk * &k) + &h_m));
+            t_commitment = &t_commitment + &t_request;
+            t_ciphertexts.push((
+                &params.g * &blinding_k,
+                &(elgamal_pk * &blinding_k) + &t_ciphertext,
+            ));
+            randomness.push(k);
+            blindings_k.push(blinding_k);
+        }
+
+        let mut commitments = vec![&params.g * &blinding_sk, t_commitment];
+        for (t_1, t_2) in t_ciphertexts {
+            commitments.push(t_1);
+            commitments.push(t_2);
+        }
+        let opening_commitments = self
+            .committed
+            .iter()
+            .map(|a| co_provers[&a.idx].t_opening.clone())
+            .collect::<Vec<SignatureGroup>>();
+        Ok(CommittedSignatureRequestPoK {
+            sig_req: SignatureRequest {
+                known_messages: self.known_messages.into(),
+                commitment: self.commitment,
+                ciphertexts,
+                signer_ciphertexts: BTreeMap::new(),
+            },
+            hidden: self.hidden,
+            committed: self.committed,
+            comm_key: comm_key.clone(),
+            randomness,
+            blinding_sk,
+            blinding_r,
+            blindings_k,
+            blindings_m,
+            commitments,
+            opening_commitments,
+        })
+    }
+}
+
+impl CommittedSignatureRequestPoK {
+    /// Schnorr commitments in the order of `SignatureRequestPoK::commitments`
+    pub fn commitments(&self) -> &[SignatureGroup] {
+        &self.commitments
+    }
+
+    /// Bytes of the proof of the openings of the external commitments to include in the challenge,
+    /// the same as `CommittedAttributesProof::challenge_bytes`
+    pub fn attribute_bytes(&self) -> Vec<u8> {
+        attribute_proof_bytes(
+            &self.comm_key,
+            self.committed
+                .iter()
+                .zip(self.opening_commitments.iter())
+                .map(|(a, t)| (a.idx, &a.commitment, t.clone()))
+                .collect(),
+        )
+    }
+
+    /// Challenge for the request and the proof of the openings, see `committed_request_challenge`.
+    /// It is given to the external system for `CoProverPoK::gen_proof`.
+    pub fn challenge(&self, elgamal_pk: &SignatureGroup, params: &Params) -> FieldElement {
+        committed_request_challenge(
+            &self.sig_req,
+            elgamal_pk,
+            &self.commitments,
+            &self.attribute_bytes(),
+            params,
+        )
+    }
+
+    /// Proofs of the request and of the openings of the external commitments, in the order of the
+    /// committed attributes given to `CommittedSignatureRequest::new`, combining the holder's
+    /// responses with `co_prover_responses`, 1 for each committed attribute. Errors if a committed
+    /// attribute has no response from the external system.
+    pub fn gen_proof(
+        self,
+        co_prover_responses: &[CoProverResponse],
+        elgamal_sk: &FieldElement,
+        challenge: &FieldElement,
+    ) -> Result<(SignatureRequestProof, CommittedAttributesProof), CoconutError> {
+        let mut co_provers = BTreeMap::new();
+        for r in co_prover_responses {
+            co_provers.insert(r.idx, r);
+        }
+        if co_provers.len() != self.committed.len()
+            || self
+                .committed
+                .iter()
+                .any(|a| !co_provers.contains_key(&a.idx))
+        {
+            return Err(CoconutErrorKind::GeneralError {
+                msg: String::from("Need 1 co-prover response for each committed attribute"),
+            }
+            .into());
+        }
+        let respond =
+            |blinding: &FieldElement, secret: &FieldElement| blinding - &(challenge * secret);
+
+        // Responses for the hidden attributes, then for the commitment randomness
+        let mut responses_commitment = FieldElementVector::with_capacity(self.hidden.len() + 1);
+        for (i, m) in self.hidden.iter().enumerate() {
+            match m {
+                Some(m) => responses_commitment.push(respond(&self.blindings_m[&i], m)),
+                None => responses_commitment.push(co_provers[&i].m.clone()),
+            }
+        }
+        responses_commitment.push(respond(&self.blinding_r, &self.randomness[0]));
+
+        let proof_ciphertexts = self
+            .blindings_k
+            .iter()
+            .enumerate()
+            .map(|(i, blinding_k)| {
+                let response_k = respond(blinding_k, &self.randomness[i + 1]);
+                let mut responses_1 = FieldElementVector::with_capacity(1);
+                responses_1.push(response_k.clone());
+                let mut responses_2 = FieldElementVector::with_capacity(2);
+                responses_2.push(response_k);
+                responses_2.push(responses_commitment[i].clone());
+                (
+                    ProofSignatureGroup {
+                        commitment: self.commitments[2 + 2 * i].clone(),
+                        responses: responses_1,
+                    },
+                    ProofSignatureGroup {
+                        commitment: self.commitments[3 + 2 * i].clone(),
+                        responses: responses_2,
+                    },
+                )
+            })
+            .collect::<Vec<(ProofSignatureGroup, ProofSignatureGroup)>>();
+
+        let mut responses_sk = FieldElementVector::with_capacity(1);
+        responses_sk.push(respond(&self.blinding_sk, elgamal_sk));
+        let sig_req_proof = SignatureRequestProof {
+            proof_elgamal_sk: ProofSignatureGroup {
+                commitment: self.commitments[0].clone(),
+                responses: responses_sk,
+            },
+            proof_commitment: ProofSignatureGroup {
+                commitment: self.commitments[1].clone(),
+                responses: responses_commitment,
+            },
+            proof_ciphertexts,
+        };
+
+        let proofs = self
+            .committed
+            .iter()
+            .zip(self.opening_commitments.into_iter())
+            .map(|(a, t)| {
+                let response = co_provers[&a.idx];
+                let mut responses = FieldElementVector::with_capacity(2);
+                responses.push(response.m.clone());
+                responses.push(response.s.clone());
+                (
+                    a.idx,
+                    ProofSignatureGroup {
+                        commitment: t,
+                        responses,
+                    },
+                )
+            })
+            .collect::<Vec<(usize, ProofSignatureGroup)>>();
+        Ok((sig_req_proof, CommittedAttributesProof { proofs }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keygen::trusted_party_SSS_keygen;
+    use crate::signature::{BlindSignature, SignatureRequestPoK};
+    use crate::test_utils::{sign_request, signers_with_verkey};
+
+    #[test]
+    fn test_sign_externally_committed_attribute() {
+        let msg_count = 4;
+        let count_hidden = 2;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (_, _, signers) = trusted_party_SSS_keygen(3, 5, &params);
+        let comm_key = CommitmentKey::new("external".as_bytes());
+
+        // External system commits to the 2nd attribute
+        let msgs = FieldElementVector::random(msg_count);
+        let s = FieldElement::random();
+        let comm = comm_key.commit(&msgs[1], &s);
+
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        let (sig_req, randomness) =
+            SignatureRequest::new(&msgs, count_hidden, &elg_pk, &params).unwrap();
+
+        let prove = |openings: &[(FieldElement, FieldElement)]| {
+            let blindings = FieldElementVector::random(count_hidden);
+            let sig_req_pok = SignatureRequestPoK::init_with_blindings(
+                &sig_req,
+                &elg_pk,
+                &params,
+                blindings.as_slice(),
+            )
+            .unwrap();
+            let attr_pok = CommittedAttributesPoK::init(
+                &[1],
+                &[comm.clone()],
+                &comm_key,
+                blindings.as_slice(),
+            )
+            .unwrap();
+            let challenge = committed_request_challenge(
+                &sig_req,
+                &elg_pk,
+                &sig_req_pok.commitments(),
+                &attr_pok.to_bytes(),
+                &params,
+            );
+
+            let hidden_msgs: FieldElementVector = msgs
+                .iter()
+                .take(count_hidden)
+                .map(|m| m.clone())
+                .collect::<Vec<FieldElement>>()
+                .into();
+            let sig_req_proof = sig_req_pok
+                .gen_proof(&hidden_msgs, randomness.clone(), &elg_sk, &challenge)
+                .unwrap();
+            let attr_proof = attr_pok.gen_proof(openings, &challenge).unwrap();
+            (sig_req_proof, attr_proof, challenge)
+        };
+
+        let (sig_req_proof, attr_proof, challenge) = prove(&[(msgs[1].clone(), s.clone())]);
+        assert!(sig_req_proof
+            .verify(&sig_req, &elg_pk, &challenge, &params)
+            .unwrap());
+
+        // Signer recomputes the challenge, which is bound to the external commitment and its key
+        let attr_bytes = attr_proof
+            .challenge_bytes(&[comm.clone()], &comm_key)
+            .unwrap();
+        assert_eq!(
+            committed_request_challenge(
+                &sig_req,
+                &elg_pk,
+                &sig_req_proof.commitments(),
+                &attr_bytes,
+                &params
+            ),
+            challenge
+        );
+        let other_comm = comm_key.commit(&msgs[1], &FieldElement::random());
+        let other_key = CommitmentKey::new("other".as_bytes());
+        for (c, k) in &[(&other_comm, &comm_key), (&comm, &other_key)] {
+            let bytes = attr_proof.challenge_bytes(&[(*c).clone()], k).unwrap();
+            assert_ne!(bytes, attr_bytes);
+        }
+        assert!(attr_proof.challenge_bytes(&[], &comm_key).is_none());
+        assert!(attr_proof
+            .verify(&[comm.clone()], &comm_key, &sig_req_proof, &challenge)
+            .unwrap());
+
+        // Signer signs and the credential verifies over the committed attribute
+        let sig = BlindSignature::new(&sig_req, &signers[0].sigkey)
+            .unwrap()
+            .unblind(&elg_sk);
+        assert!(sig
+            .verify(msgs.as_slice(), &signers[0].verkey, &params)
+            .unwrap());
+
+        // Committed attribute is not the 1st hidden attribute
+        let mut wrong_idx = attr_proof.clone();
+        wrong_idx.proofs[0].0 = 0;
+        assert!(!wrong_idx
+            .verify(&[comm.clone()], &comm_key, &sig_req_proof, &challenge)
+            .unwrap());
+
+        // Signature request proof with no responses is rejected rather than panicking
+        let mut empty_proof = sig_req_proof.clone();
+        empty_proof.proof_commitment.responses = FieldElementVector::new(0);
+        assert!(!attr_proof
+            .verify(&[comm.clone()], &comm_key, &empty_proof, &challenge)
+            .unwrap());
+
+        // Holder proves knowledge of an opening to a different attribute
+        let (sig_req_proof, attr_proof, challenge) = prove(&[(msgs[0].clone(), s.clone())]);
+        assert!(!attr_proof
+            .verify(&[comm.clone()], &comm_key, &sig_req_proof, &challenge)
+            .unwrap());
+    }
+
+    #[test]
+    fn test_sign_attribute_committed_as_point() {
+        let msg_count = 4;
+        let count_hidden = 3;
+        let threshold = 3;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (signers, vk) = signers_with_verkey(threshold, 5, &params);
+        let comm_key = CommitmentKey::new("external".as_bytes());
+        let msgs = FieldElementVector::random(msg_count);
+
+        // External system commits to the 2nd attribute and keeps the opening. The holder gets
+        // only the points.
+        let opening = CommitmentOpening::new(msgs[1].clone(), FieldElement::random());
+        let attr = opening.committed_attribute(1, &comm_key, &params).unwrap();
+        assert_eq!(attr.commitment, comm_key.commit(&opening.m, &opening.s));
+        let holder_msgs: FieldElementVector =
+            vec![msgs[0].clone(), msgs[2].clone(), msgs[3].clone()].into();
+
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        let issue = |opening: &CommitmentOpening| {
+            let request = CommittedSignatureRequest::new(
+                &holder_msgs,
+                count_hidden,
+                &[attr.clone()],
+                &params,
+            )
+            .unwrap();
+            let co_prover = opening
+                .init_proof(attr.idx, request.h(), &comm_key, &params)
+                .unwrap();
+            let pok = request
+                .init(&[co_prover.commitment.clone()], &elg_pk, &comm_key, &params)
+                .unwrap();
+            let challenge = pok.challenge(&elg_pk, &params);
+            let response = co_prover.gen_proof(opening, &challenge);
+            let sig_req = pok.sig_req.clone();
+            let (sig_req_proof, attr_proof) =
+                pok.gen_proof(&[response], &elg_sk, &challenge).unwrap();
+            (sig_req, sig_req_proof, attr_proof)
+        };
+
+        // Signer recomputes the challenge and verifies both proofs
+        let (sig_req, sig_req_proof, attr_proof) = issue(&opening);
+        let challenge = committed_request_challenge(
+            &sig_req,
+            &elg_pk,
+            &sig_req_proof.commitments(),
+            &attr_proof
+                .challenge_bytes(&[attr.commitment.clone()], &comm_key)
+                .unwrap(),
+            &params,
+        );
+        assert!(sig_req_proof
+            .verify(&sig_req, &elg_pk, &challenge, &params)
+            .unwrap());
+        assert!(attr_proof
+            .verify(
+                &[attr.commitment.clone()],
+                &comm_key,
+                &sig_req_proof,
+                &challenge
+            )
+            .unwrap());
+
+        // Signers sign the request as any other and the credential is over the committed attribute
+        let sig = sign_request(&sig_req, &elg_sk, &signers, threshold, &params);
+        assert!(sig.verify(msgs.as_slice(), &vk, &params).unwrap());
+
+        // External system responding with an opening to another attribute fails
+        let wrong = CommitmentOpening::new(FieldElement::random(), opening.s.clone());
+        let (sig_req, sig_req_proof, attr_proof) = issue(&wrong);
+        let challenge = committed_request_challenge(
+            &sig_req,
+            &elg_pk,
+            &sig_req_proof.commitments(),
+            &attr_proof
+                .challenge_bytes(&[attr.commitment.clone()], &comm_key)
+                .unwrap(),
+            &params,
+        );
+        assert!(!sig_req_proof
+            .verify(&sig_req, &elg_pk, &challenge, &params)
+            .unwrap());
+
+        // Committed attribute that is not hidden, repeated or without a co-prover commitment
+        let request = CommittedSignatureRequest::new(&holder_msgs, 1, &[attr.clone()], &params);
+        match request.unwrap_err().kind() {
+            CoconutErrorKind::InvalidAttributeIndex { idx, msg_count } => {
+                assert_eq!(idx, 1);
+                assert_eq!(msg_count, 1);
+            }
+            k => panic!("unexpected error kind {:?}", k),
+        }
+        let two_msgs: FieldElementVector = vec![msgs[0].clone(), msgs[2].clone()].into();
+        assert!(CommittedSignatureRequest::new(
+            &two_msgs,
+            count_hidden,
+            &[attr.clone(), attr.clone()],
+            &params
+        )
+        .is_err());
+        let request =
+            CommittedSignatureRequest::new(&holder_msgs, count_hidden, &[attr.clone()], &params)
+                .unwrap();
+        assert!(request.init(&[], &elg_pk, &comm_key, &params).is_err());
+    }
+}
@@ -0,0 +1,344 @@
+// Continuity of hidden attributes across re-issuance. When an expiring credential is re-issued
+// with the same hidden attributes, the holder proves knowledge of the old credential and of the
+// hidden messages of the new signature request together. The hidden messages of the request are
+// the old credential's attributes at the same indices and get the same blindings in both proofs,
+// so under the same challenge their responses are equal. The signers learn that the new
+// credential carries the hidden attributes of a valid old credential without learning them. The
+// new request can be created with `SignatureRequest::new_with_randomness` and the randomness of
+// the old request, so that it has the same commitment and the signers can link both rounds, or
+// with fresh randomness like any other request, so that it can't be linked to the old request.
+
+use crate::errors::{CoconutError, CoconutErrorKind};
+use crate::pok_sig::hidden_attribute_response;
+use crate::signature::{
+    transform_to_PS_params, transform_to_PS_sig, transform_to_PS_verkey, Params, Signature,
+    SignatureRequest, SignatureRequestPoK, SignatureRequestProof, Verkey,
+};
+use crate::SignatureGroup;
+use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
+use ps_sig::pok_sig::{PoKOfSignature, PoKOfSignatureProof};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Commitment phase of the proof of continuity
+#[derive(Clone, Debug)]
+pub struct PoKOfContinuity {
+    pub pok_sig: PoKOfSignature,
+    pub pok_req: SignatureRequestPoK,
+    pub hidden_messages: FieldElementVector,
+    pub revealed_msgs: BTreeMap<usize, FieldElement>,
+}
+
+/// Response phase of the proof of continuity
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ContinuityProof {
+    pub proof_sig: PoKOfSignatureProof,
+    pub proof_req: SignatureRequestProof,
+    /// Revealed attributes of the old credential as index -> attribute
+    pub revealed_msgs: BTreeMap<usize, FieldElement>,
+}
+
+impl PoKOfContinuity {
+    /// `old_sig` is the old credential on `old_messages` under `old_vk`, of which the attributes
+    /// at `revealed_msg_indices` are revealed. `sig_req` is the request for the new credential
+    /// whose hidden messages are `old_messages` at the same indices, encrypted under
+    /// `elgamal_pk`. Errors if a hidden message of the request is revealed. The type attribute of
+    /// typed params is always revealed.
+    pub fn init(
+        old_sig: &Signature,
+        old_messages: &[FieldElement],
+        mut revealed_msg_indices: HashSet<usize>,
+        old_vk: &Verkey,
+        sig_req: &SignatureRequest,
+        elgamal_pk: &SignatureGroup,
+        params: &Params,
+    ) -> Result<Self, CoconutError> {
+        let msg_count = params.h.len();
+        if old_messages.len() != msg_count {
+            return Err(CoconutErrorKind::UnsupportedNoOfMessages {
+                expected: msg_count,
+                given: old_messages.len(),
+            }
+            .into());
+        }
+        let count_hidden = sig_req.ciphertexts.len();
+        if let Some(idx) = params.type_attribute_index() {
+            revealed_msg_indices.insert(idx);
+        }
+        for idx in &revealed_msg_indices {
+            if *idx >= msg_count {
+                return Err(CoconutErrorKind::InvalidAttributeIndex {
+                    idx: *idx,
+                    msg_count,
+                }
+                .into());
+            }
+            if *idx < count_hidden {
+                return Err(CoconutErrorKind::GeneralError {
+                    msg: format!("Hidden message {} of the request can't be revealed", idx),
+                }
+                .into());
+            }
+        }
+
+        let (pok_req, ctx) = SignatureRequestPoK::init_with_context(sig_req, elgamal_pk, params);
+        let blindings = ctx.presentation_blindings(msg_count, &revealed_msg_indices);
+        let revealed_msgs = revealed_msg_indices
+            .iter()
+            .map(|i| (*i, old_messages[*i].clone()))
+            .collect::<BTreeMap<usize, FieldElement>>();
+        let pok_sig = PoKOfSignature::init(
+            &transform_to_PS_sig(old_sig),
+            &transform_to_PS_verkey(old_vk),
+            &transform_to_PS_params(params),
+            old_messages,
+            Some(blindings.as_slice()),
+            revealed_msg_indices,
+        )?;
+        let hidden_messages = old_messages
+            .iter()
+            .take(count_hidden)
+            .cloned()
+            .collect::<Vec<FieldElement>>()
+            .into();
+        Ok(Self {
+            pok_sig,
+            pok_req,
+            hidden_messages,
+            revealed_msgs,
+        })
+    }
+
+    /// Bytes to derive the challenge from, along with the request and any verifier nonce
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.pok_sig.to_bytes();
+        bytes.append(&mut self.pok_req.to_bytes());
+        bytes
+    }
+
+    /// `randomness` and `elgamal_sk` are as for `SignatureRequestPoK::gen_proof`
+    pub fn gen_proof(
+        self,
+        randomness: FieldElementVector,
+        elgamal_sk: &FieldElement,
+        challenge: &FieldElement,
+    ) -> Result<ContinuityProof, CoconutError> {
+        let proof_sig = self.pok_sig.gen_proof(challenge)?;
+        let proof_req =
+            self.pok_req
+                .gen_proof(&self.hidden_messages, randomness, elgamal_sk, challenge)?;
+        Ok(ContinuityProof {
+            proof_sig,
+            proof_req,
+            revealed_msgs: self.revealed_msgs,
+        })
+    }
+}
+
+impl ContinuityProof {
+    /// Verify knowledge of a credential under `old_vk` with the revealed attributes and of the
+    /// hidden messages of `sig_req`, and that each hidden message of `sig_req` is the attribute
+    /// of the credential at the same index.
+    pub fn verify(
+        &self,
+        sig_req: &SignatureRequest,
+        elgamal_pk: &SignatureGroup,
+        old_vk: &Verkey,
+        challenge: &FieldElement,
+        params: &Params,
+    ) -> Result<bool, CoconutError> {
+        let count_hidden = sig_req.ciphertexts.len();
+        if (0..count_hidden).any(|i| self.revealed_msgs.contains_key(&i))
+            || !params.reveals_type_attribute(&self.revealed_msgs)
+        {
+            return Ok(false);
+        }
+        if self.proof_req.proof_commitment.responses.len() != count_hidden + 1 {
+            return Ok(false);
+        }
+        // The hidden messages of the request are the 1st hidden attributes of the credential
+        for i in 0..count_hidden {
+            let response = hidden_attribute_response(&self.proof_sig, self.revealed_msgs.keys(), i);
+            if response != Some(&self.proof_req.proof_commitment.responses[i]) {
+                return Ok(false);
+            }
+        }
+
+        if !self
+            .proof_req
+            .verify(sig_req, elgamal_pk, challenge, params)?
+        {
+            return Ok(false);
+        }
+        let revealed = self
+            .revealed_msgs
+            .iter()
+            .map(|(i, m)| (*i, m.clone()))
+            .collect::<HashMap<usize, FieldElement>>();
+        Ok(self.proof_sig.verify(
+            &transform_to_PS_verkey(old_vk),
+            &transform_to_PS_params(params),
+            revealed,
+            challenge,
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{sign_request, signers_with_verkey};
+
+    #[test]
+    fn test_reissue_with_continuity() {
+        // 2 hidden attributes carried over and the last attribute is the expiry
+        let threshold = 2;
+        let msg_count = 3;
+        let count_hidden = 2;
+        let params = Params::new_typed(msg_count, "test".as_bytes(), "membership".as_bytes());
+        let (signers, vk) = signers_with_verkey(threshold, 3, &params);
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        let issue = |sig_req: &SignatureRequest| {
+            sign_request(sig_req, &elg_sk, &signers, threshold, &params)
+        };
+
+        let old_msgs = params.typed_messages(FieldElementVector::random(msg_count).as_slice());
+        let (old_req, old_randomness) =
+            SignatureRequest::new(&old_msgs, count_hidden, &elg_pk, &params).unwrap();
+        let old_sig = issue(&old_req);
+        assert!(old_sig.verify(old_msgs.as_slice(), &vk, &params).unwrap());
+
+        // Same hidden attributes with a new expiry and fresh randomness
+        let mut new_msgs = old_msgs.clone();
+        new_msgs[2] = FieldElement::random();
+        let (new_req, new_randomness) =
+            SignatureRequest::new(&new_msgs, count_hidden, &elg_pk, &params).unwrap();
+        assert_ne!(new_req.commitment, old_req.commitment);
+        for (new_ct, old_ct) in new_req.ciphertexts.iter().zip(old_req.ciphertexts.iter()) {
+            assert_ne!(new_ct, old_ct);
+        }
+
+        // The old expiry is revealed
+        let mut revealed_msg_indices = HashSet::new();
+        revealed_msg_indices.insert(2);
+        let pok = PoKOfContinuity::init(
+            &old_sig,
+            old_msgs.as_slice(),
+            revealed_msg_indices.clone(),
+            &vk,
+            &new_req,
+            &elg_pk,
+            &params,
+        )
+        .unwrap();
+        let mut chal_bytes = pok.to_bytes();
+        chal_bytes.append(&mut new_req.to_bytes());
+        let challenge = FieldElement::from_msg_hash(&chal_bytes);
+        let proof = pok.gen_proof(new_randomness, &elg_sk, &challenge).unwrap();
+        assert!(proof
+            .verify(&new_req, &elg_pk, &vk, &challenge, &params)
+            .unwrap());
+        assert!(!proof
+            .verify(&new_req, &elg_pk, &vk, &FieldElement::random(), &params)
+            .unwrap());
+
+        let new_sig = issue(&new_req);
+        assert!(new_sig.verify(new_msgs.as_slice(), &vk, &params).unwrap());
+
+        // Re-issuing with the randomness of the old request gives the old commitment
+        let linked_req = SignatureRequest::new_with_randomness(
+            &new_msgs,
+            count_hidden,
+            &elg_pk,
+            &old_randomness,
+            &params,
+        )
+        .unwrap();
+        assert_eq!(linked_req.commitment, old_req.commitment);
+        assert_eq!(linked_req.ciphertexts.len(), count_hidden);
+        let pok = PoKOfContinuity::init(
+            &old_sig,
+            old_msgs.as_slice(),
+            revealed_msg_indices.clone(),
+            &vk,
+            &linked_req,
+            &elg_pk,
+            &params,
+        )
+        .unwrap();
+        let mut chal_bytes = pok.to_bytes();
+        chal_bytes.append(&mut linked_req.to_bytes());
+        let challenge = FieldElement::from_msg_hash(&chal_bytes);
+        let proof = pok
+            .gen_proof(old_randomness.clone(), &elg_sk, &challenge)
+            .unwrap();
+        assert!(proof
+            .verify(&linked_req, &elg_pk, &vk, &challenge, &params)
+            .unwrap());
+        let linked_sig = issue(&linked_req);
+        assert!(linked_sig
+            .verify(new_msgs.as_slice(), &vk, &params)
+            .unwrap());
+
+        // Caller supplied randomness of the wrong length or with a repeated value
+        let short = FieldElementVector::random(count_hidden);
+        assert!(SignatureRequest::new_with_randomness(
+            &new_msgs,
+            count_hidden,
+            &elg_pk,
+            &short,
+            &params
+        )
+        .is_err());
+        let mut repeated = old_randomness.clone();
+        repeated[2] = repeated[1].clone();
+        match SignatureRequest::new_with_randomness(
+            &new_msgs,
+            count_hidden,
+            &elg_pk,
+            &repeated,
+            &params,
+        )
+        .unwrap_err()
+        .kind()
+        {
+            CoconutErrorKind::RandomnessReuse { .. } => (),
+            k => panic!("unexpected error kind {:?}", k),
+        }
+
+        // A request for other hidden attributes can't be linked to the old credential
+        let other_msgs = params.typed_messages(FieldElementVector::random(msg_count).as_slice());
+        let (other_req, other_randomness) =
+            SignatureRequest::new(&other_msgs, count_hidden, &elg_pk, &params).unwrap();
+        let pok = PoKOfContinuity::init(
+            &old_sig,
+            old_msgs.as_slice(),
+            revealed_msg_indices,
+            &vk,
+            &other_req,
+            &elg_pk,
+            &params,
+        )
+        .unwrap();
+        let challenge = FieldElement::from_msg_hash(&pok.to_bytes());
+        let proof = pok
+            .gen_proof(other_randomness, &elg_sk, &challenge)
+            .unwrap();
+        assert!(!proof
+            .verify(&other_req, &elg_pk, &vk, &challenge, &params)
+            .unwrap());
+
+        // A hidden message of the request can't be revealed
+        let mut revealed_hidden = HashSet::new();
+        revealed_hidden.insert(0);
+        assert!(PoKOfContinuity::init(
+            &old_sig,
+            old_msgs.as_slice(),
+            revealed_hidden,
+            &vk,
+            &new_req,
+            &elg_pk,
+            &params,
+        )
+        .is_err());
+    }
+}
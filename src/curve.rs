@@ -0,0 +1,171 @@
+// The pairing curve the crate works over. Rather than naming the groups of a curve directly, the
+// crate level aliases `SignatureGroup`, `OtherGroup`, etc., the byte sizes, cofactors and
+// pairings are all taken from the `CoconutCurve` impl bound to `DefaultCurve`. Experimenting with
+// a different pairing curve needs an impl of `CoconutCurve` for it and changing `DefaultCurve`,
+// and the rest of the crate and its public API stay as they are.
+
+use amcl_wrapper::extension_field_gt::GT;
+use amcl_wrapper::group_elem::{GroupElement, GroupElementVector};
+use std::fmt::Debug;
+
+/// A pairing curve with the group the signatures are in, `SignatureGroup`, and the group the
+/// verkeys are in, `OtherGroup`.
+pub trait CoconutCurve {
+    type SignatureGroup: GroupElement + Clone + Debug + PartialEq;
+    type OtherGroup: GroupElement + Clone + Debug + PartialEq;
+    type SignatureGroupVec: GroupElementVector<Self::SignatureGroup>;
+    type OtherGroupVec: GroupElementVector<Self::OtherGroup>;
+
+    /// Byte sizes of serialized field elements and elements of the groups
+    const FIELD_ELEMENT_SIZE: usize;
+    const SIGNATURE_GROUP_SIZE: usize;
+    const OTHER_GROUP_SIZE: usize;
+
+    /// Cofactors of the groups as big-endian bytes
+    const SIGNATURE_GROUP_COFACTOR: &'static [u8];
+    const OTHER_GROUP_COFACTOR: &'static [u8];
+
+    fn ate_pairing(a: &Self::SignatureGroup, b: &Self::OtherGroup) -> GT;
+
+    fn ate_multi_pairing(pairs: &[(Self::SignatureGroup, Self::OtherGroup)]) -> GT;
+
+    /// e(a, b) * e(c, d)
+    fn ate_2_pairing(
+        a: &Self::SignatureGroup,
+        b: &Self::OtherGroup,
+        c: &Self::SignatureGroup,
+        d: &Self::OtherGroup,
+    ) -> GT;
+}
+
+/// BLS12-381 with signatures in G1 or G2 depending on the feature `SignatureG1` or `SignatureG2`
+#[derive(Clone, Copy, Debug)]
+pub struct Bls12_381;
+
+/// The curve the crate level aliases and functions are bound to
+pub type DefaultCurve = Bls12_381;
+
+// Cofactors of the BLS12-381 G1 and G2 curves as big-endian bytes
+const G1_COFACTOR: [u8; 16] = [
+    0x39, 0x6c, 0x8c, 0x00, 0x55, 0x55, 0xe1, 0x56, 0x8c, 0x00, 0xaa, 0xab, 0x00, 0x00, 0xaa, 0xab,
+];
+const G2_COFACTOR: [u8; 64] = [
+    0x05, 0xd5, 0x43, 0xa9, 0x54, 0x14, 0xe7, 0xf1, 0x09, 0x1d, 0x50, 0x79, 0x28, 0x76, 0xa2, 0x02,
+    0xcd, 0x91, 0xde, 0x45, 0x47, 0x08, 0x5a, 0xba, 0xa6, 0x8a, 0x20, 0x5b, 0x2e, 0x5a, 0x7d, 0xdf,
+    0xa6, 0x28, 0xf1, 0xcb, 0x4d, 0x9e, 0x82, 0xef, 0x21, 0x53, 0x7e, 0x29, 0x3a, 0x66, 0x91, 0xae,
+    0x16, 0x16, 0xec, 0x6e, 0x78, 0x6f, 0x0c, 0x70, 0xcf, 0x1c, 0x38, 0xe3, 0x1c, 0x72, 0x38, 0xe5,
+];
+
+#[cfg(feature = "SignatureG1")]
+impl CoconutCurve for Bls12_381 {
+    type SignatureGroup = amcl_wrapper::group_elem_g1::G1;
+    type OtherGroup = amcl_wrapper::group_elem_g2::G2;
+    type SignatureGroupVec = amcl_wrapper::group_elem_g1::G1Vector;
+    type OtherGroupVec = amcl_wrapper::group_elem_g2::G2Vector;
+
+    const FIELD_ELEMENT_SIZE: usize = amcl_wrapper::constants::MODBYTES;
+    const SIGNATURE_GROUP_SIZE: usize = amcl_wrapper::constants::GroupG1_SIZE;
+    const OTHER_GROUP_SIZE: usize = amcl_wrapper::constants::GroupG2_SIZE;
+
+    const SIGNATURE_GROUP_COFACTOR: &'static [u8] = &G1_COFACTOR;
+    const OTHER_GROUP_COFACTOR: &'static [u8] = &G2_COFACTOR;
+
+    fn ate_pairing(a: &Self::SignatureGroup, b: &Self::OtherGroup) -> GT {
+        GT::ate_pairing(a, b)
+    }
+
+    fn ate_multi_pairing(pairs: &[(Self::SignatureGroup, Self::OtherGroup)]) -> GT {
+        GT::ate_multi_pairing(pairs.iter().map(|(a, b)| (a, b)).collect())
+    }
+
+    fn ate_2_pairing(
+        a: &Self::SignatureGroup,
+        b: &Self::OtherGroup,
+        c: &Self::SignatureGroup,
+        d: &Self::OtherGroup,
+    ) -> GT {
+        ps_sig::ate_2_pairing(a, b, c, d)
+    }
+}
+
+#[cfg(feature = "SignatureG2")]
+impl CoconutCurve for Bls12_381 {
+    type SignatureGroup = amcl_wrapper::group_elem_g2::G2;
+    type OtherGroup = amcl_wrapper::group_elem_g1::G1;
+    type SignatureGroupVec = amcl_wrapper::group_elem_g2::G2Vector;
+    type OtherGroupVec = amcl_wrapper::group_elem_g1::G1Vector;
+
+    const FIELD_ELEMENT_SIZE: usize = amcl_wrapper::constants::MODBYTES;
+    const SIGNATURE_GROUP_SIZE: usize = amcl_wrapper::constants::GroupG2_SIZE;
+    const OTHER_GROUP_SIZE: usize = amcl_wrapper::constants::GroupG1_SIZE;
+
+    const SIGNATURE_GROUP_COFACTOR: &'static [u8] = &G2_COFACTOR;
+    const OTHER_GROUP_COFACTOR: &'static [u8] = &G1_COFACTOR;
+
+    fn ate_pairing(a: &Self::SignatureGroup, b: &Self::OtherGroup) -> GT {
+        GT::ate_pairing(b, a)
+    }
+
+    fn ate_multi_pairing(pairs: &[(Self::SignatureGroup, Self::OtherGroup)]) -> GT {
+        GT::ate_multi_pairing(pairs.iter().map(|(a, b)| (b, a)).collect())
+    }
+
+    fn ate_2_pairing(
+        a: &Self::SignatureGroup,
+        b: &Self::OtherGroup,
+        c: &Self::SignatureGroup,
+        d: &Self::OtherGroup,
+    ) -> GT {
+        ps_sig::ate_2_pairing(a, b, c, d)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signature::{Params, SignatureRequest};
+    use crate::test_utils::{sign_request, signers_with_verkey};
+    use crate::{SignatureGroup, OTHER_GROUP_SIZE, SIGNATURE_GROUP_SIZE};
+    use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
+
+    // Bilinearity, written only against the trait
+    fn check_bilinear<C: CoconutCurve>() -> bool {
+        let a = C::SignatureGroup::random();
+        let b = C::OtherGroup::random();
+        let x = FieldElement::random();
+        let lhs = C::ate_pairing(&(&a * &x), &b);
+        let rhs = C::ate_pairing(&a, &(&b * &x));
+        let neg_a = -(&a * &x);
+        lhs == rhs
+            && C::ate_multi_pairing(&[(a.clone(), &b * &x), (neg_a.clone(), b.clone())]).is_one()
+            && C::ate_2_pairing(&a, &(&b * &x), &neg_a, &b).is_one()
+    }
+
+    #[test]
+    fn test_default_curve() {
+        assert!(check_bilinear::<DefaultCurve>());
+        assert_eq!(
+            SignatureGroup::random().to_bytes().len(),
+            <DefaultCurve as CoconutCurve>::SIGNATURE_GROUP_SIZE
+        );
+        assert_eq!(
+            SIGNATURE_GROUP_SIZE,
+            <DefaultCurve as CoconutCurve>::SIGNATURE_GROUP_SIZE
+        );
+        assert_eq!(
+            OTHER_GROUP_SIZE,
+            <DefaultCurve as CoconutCurve>::OTHER_GROUP_SIZE
+        );
+
+        // The issuance and verification flow under the default curve binding
+        let threshold = 2;
+        let msg_count = 3;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (signers, vk) = signers_with_verkey(threshold, 3, &params);
+        let msgs = FieldElementVector::random(msg_count);
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        let (sig_req, _) = SignatureRequest::new(&msgs, 1, &elg_pk, &params).unwrap();
+        let sig = sign_request(&sig_req, &elg_sk, &signers, threshold, &params);
+        assert!(sig.verify(msgs.as_slice(), &vk, &params).unwrap());
+    }
+}
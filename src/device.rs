@@ -0,0 +1,349 @@
+// Credentials bound to a holder device to prevent sharing them. The device has a secret key d with
+// public key D = g^d and d never leaves the device. The credential is issued with the device
+// attribute, a hash of D, as a known attribute at `device_attribute_index`
+// (`SignatureRequest::new_device_bound`) and the device proves knowledge of d to the signer. While
+// showing, the holder reveals the device attribute and the device proves knowledge of d in D with
+// the challenge of the proof of knowledge of the signature, so the credential can only be shown
+// with the device. `CredentialProofBuilder::with_device_binding` does this, with the commitment of
+// the device's proof in the challenge so the device proof can't be taken from another showing.
+// D is revealed at issuance and in every showing so all showings of credentials bound to the same
+// device are linkable to each other and to the issuance. Use pseudonyms rather than device binding
+// where showings must be unlinkable.
+
+use crate::errors::CoconutError;
+use crate::signature::{
+    Params, ProofSignatureGroup, ProverCommittedSignatureGroup, ProverCommittingSignatureGroup,
+    SchnorrCommitment,
+};
+use crate::SignatureGroup;
+use amcl_wrapper::field_elem::FieldElement;
+use amcl_wrapper::group_elem::GroupElement;
+use std::collections::HashMap;
+
+/// Domain separation tag for the device attribute derived from the device public key
+pub const DEVICE_ATTRIBUTE_TAG: &[u8] = b"coconut/device/attribute";
+/// Domain separation tag for the challenge of the proof of the device key given at issuance
+pub const DEVICE_KEY_CHALLENGE_TAG: &[u8] = b"coconut/device/key-challenge";
+/// Domain separation tag for the bytes of the device proof in the challenge of a showing
+pub const DEVICE_SHOW_CHALLENGE_TAG: &[u8] = b"coconut/device/show-challenge";
+
+/// Device secret key and public key, g^secret. Done on the device, only the public key is exported.
+pub fn device_keygen(params: &Params) -> (FieldElement, SignatureGroup) {
+    let secret = FieldElement::random();
+    let pk = &params.g * &secret;
+    (secret, pk)
+}
+
+/// Index of the device attribute in a device bound credential, the last attribute before the type
+/// attribute.
+pub fn device_attribute_index(params: &Params) -> usize {
+    params.attribute_count() - 1
+}
+
+/// The device attribute of credentials bound to the device with public key `device_pk`
+pub fn device_attribute(device_pk: &SignatureGroup) -> FieldElement {
+    let mut bytes = DEVICE_ATTRIBUTE_TAG.to_vec();
+    bytes.append(&mut device_pk.to_bytes());
+    FieldElement::from_msg_hash(&bytes)
+}
+
+/// Commitment phase of the proof of knowledge of the device secret. Created on the device, the
+/// holder only gets the bytes for the challenge and the proof.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PoKOfDeviceBinding {
+    pub secret: FieldElement,
+    pub pok_vc: ProverCommittedSignatureGroup,
+}
+
+/// Response phase of the proof of knowledge of the device secret
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DeviceBindingProof {
+    pub proof_vc: ProofSignatureGroup,
+}
+
+impl PoKOfDeviceBinding {
+    pub fn init(secret: &FieldElement, params: &Params) -> Self {
+        let mut committing = ProverCommittingSignatureGroup::new();
+        committing.commit(&params.g, None);
+        Self {
+            secret: secret.clone(),
+            pok_vc: committing.finish(),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.pok_vc.to_bytes()
+    }
+
+    pub fn gen_proof(self, challenge: &FieldElement) -> Result<DeviceBindingProof, CoconutError> {
+        let proof_vc = self.pok_vc.gen_proof(challenge, &[self.secret])?;
+        Ok(DeviceBindingProof { proof_vc })
+    }
+}
+
+impl DeviceBindingProof {
+    /// Proof of the device key given to the signer when requesting a device bound credential. The
+    /// challenge is derived from the public key and `context`, which should be a nonce of the
+    /// signer. Created on the device.
+    pub fn for_issuance(
+        secret: &FieldElement,
+        context: &[u8],
+        params: &Params,
+    ) -> Result<Self, CoconutError> {
+        let device_pk = &params.g * secret;
+        let pok = PoKOfDeviceBinding::init(secret, params);
+        let challenge = Self::issuance_challenge(
+            &device_pk,
+            &pok.pok_vc.schnorr_commitment().to_bytes(),
+            context,
+            params,
+        );
+        pok.gen_proof(&challenge)
+    }
+
+    /// Verify a proof created with `for_issuance` for `device_pk` and `context`
+    pub fn verify_for_issuance(
+        &self,
+        device_pk: &SignatureGroup,
+        context: &[u8],
+        params: &Params,
+    ) -> Result<bool, CoconutError> {
+        let challenge = Self::issuance_challenge(
+            device_pk,
+            &self.proof_vc.commitment.to_bytes(),
+            context,
+            params,
+        );
+        self.verify_knowledge(device_pk, &challenge, params)
+    }
+
+    /// Verify knowledge of the secret key of `device_pk` and that the credential is bound to it,
+    /// i.e. `revealed_msgs` of the proof of knowledge of signature created with the same
+    /// challenge reveal the device attribute for `device_pk`. The challenge must cover the
+    /// commitment of this proof, like that of `CredentialProof::verify_device_bound`, otherwise a
+    /// proof of the device taken from another showing with the same challenge would verify.
+    pub fn verify(
+        &self,
+        device_pk: &SignatureGroup,
+        revealed_msgs: &HashMap<usize, FieldElement>,
+        challenge: &FieldElement,
+        params: &Params,
+    ) -> Result<bool, CoconutError> {
+        let attribute = revealed_msgs.get(&device_attribute_index(params));
+        if attribute != Some(&device_attribute(device_pk)) {
+            return Ok(false);
+        }
+        self.verify_knowledge(device_pk, challenge, params)
+    }
+
+    fn verify_knowledge(
+        &self,
+        device_pk: &SignatureGroup,
+        challenge: &FieldElement,
+        params: &Params,
+    ) -> Result<bool, CoconutError> {
+        if device_pk.is_identity() || self.proof_vc.responses.len() != 1 {
+            return Ok(false);
+        }
+        self.proof_vc
+            .verify(&[params.g.clone()], device_pk, challenge)
+    }
+
+    /// Bytes of `device_pk` and the commitment of the device's proof that go in the challenge of
+    /// a showing
+    pub(crate) fn show_challenge_bytes(device_pk: &SignatureGroup, commitment: &[u8]) -> Vec<u8> {
+        let mut bytes = DEVICE_SHOW_CHALLENGE_TAG.to_vec();
+        bytes.append(&mut device_pk.to_bytes());
+        bytes.extend_from_slice(commitment);
+        bytes
+    }
+
+    fn issuance_challenge(
+        device_pk: &SignatureGroup,
+        commitment: &[u8],
+        context: &[u8],
+        params: &Params,
+    ) -> FieldElement {
+        let mut bytes = DEVICE_KEY_CHALLENGE_TAG.to_vec();
+        bytes.append(&mut params.g.to_bytes());
+        bytes.append(&mut device_pk.to_bytes());
+        bytes.extend_from_slice(commitment);
+        bytes.extend_from_slice(context);
+        FieldElement::from_msg_hash(&bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pok_sig::CredentialProofBuilder;
+    use crate::signature::{
+        transform_to_PS_params, transform_to_PS_sig, transform_to_PS_verkey, SignatureRequest,
+    };
+    use crate::test_utils::{sign_request, signers_with_verkey};
+    use amcl_wrapper::field_elem::FieldElementVector;
+    use ps_sig::pok_sig::PoKOfSignature;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_device_bound_credential() {
+        let threshold = 2;
+        let msg_count = 3;
+        let params = Params::new(msg_count + 1, "test".as_bytes());
+        let (signers, vk) = signers_with_verkey(threshold, 3, &params);
+
+        // Only the device public key and proofs leave the device
+        let (device_secret, device_pk) = device_keygen(&params);
+        let nonce = b"signer nonce";
+        let key_proof = DeviceBindingProof::for_issuance(&device_secret, nonce, &params).unwrap();
+        assert!(key_proof
+            .verify_for_issuance(&device_pk, nonce, &params)
+            .unwrap());
+        assert!(!key_proof
+            .verify_for_issuance(&device_pk, b"other nonce", &params)
+            .unwrap());
+
+        let msgs = FieldElementVector::random(msg_count);
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        let (sig_req, _) = SignatureRequest::new_device_bound(
+            &msgs, &device_pk, &key_proof, nonce, 1, &elg_pk, &params,
+        )
+        .unwrap();
+        assert_eq!(sig_req.ciphertexts.len(), 1);
+        assert!(sig_req
+            .check_device_binding(&device_pk, &key_proof, nonce, &params)
+            .unwrap());
+        let (other_secret, other_pk) = device_keygen(&params);
+        let other_proof = DeviceBindingProof::for_issuance(&other_secret, nonce, &params).unwrap();
+        assert!(!sig_req
+            .check_device_binding(&other_pk, &other_proof, nonce, &params)
+            .unwrap());
+        // A proof for another device or params for a different number of messages are rejected
+        assert!(SignatureRequest::new_device_bound(
+            &msgs,
+            &device_pk,
+            &other_proof,
+            nonce,
+            1,
+            &elg_pk,
+            &params
+        )
+        .is_err());
+        assert!(SignatureRequest::new_device_bound(
+            &msgs,
+            &device_pk,
+            &key_proof,
+            nonce,
+            1,
+            &elg_pk,
+            &Params::new(msg_count, "test".as_bytes())
+        )
+        .is_err());
+
+        let sig = sign_request(&sig_req, &elg_sk, &signers, threshold, &params);
+        let mut all_msgs = msgs.clone();
+        all_msgs.push(device_attribute(&device_pk));
+        assert_eq!(device_attribute_index(&params), msg_count);
+        assert!(sig.verify(all_msgs.as_slice(), &vk, &params).unwrap());
+
+        // Show revealing the device attribute
+        let mut revealed_msg_indices = HashSet::new();
+        revealed_msg_indices.insert(device_attribute_index(&params));
+        let mut revealed_msgs = HashMap::new();
+        revealed_msgs.insert(msg_count, all_msgs[msg_count].clone());
+        let ps_params = transform_to_PS_params(&params);
+        let ps_verkey = transform_to_PS_verkey(&vk);
+        let ps_sig = transform_to_PS_sig(&sig);
+
+        let show = |device_secret: &FieldElement| {
+            let pok_sig = PoKOfSignature::init(
+                &ps_sig,
+                &ps_verkey,
+                &ps_params,
+                all_msgs.as_slice(),
+                None,
+                revealed_msg_indices.clone(),
+            )
+            .unwrap();
+            let pok_device = PoKOfDeviceBinding::init(device_secret, &params);
+            let mut chal_bytes = pok_sig.to_bytes();
+            chal_bytes.append(&mut pok_device.to_bytes());
+            let chal = FieldElement::from_msg_hash(&chal_bytes);
+            (
+                pok_sig.gen_proof(&chal).unwrap(),
+                pok_device.gen_proof(&chal).unwrap(),
+                chal,
+            )
+        };
+
+        let (proof_sig, proof_device, chal) = show(&device_secret);
+        assert!(proof_sig
+            .verify(&ps_verkey, &ps_params, revealed_msgs.clone(), &chal)
+            .unwrap());
+        assert!(proof_device
+            .verify(&device_pk, &revealed_msgs, &chal, &params)
+            .unwrap());
+        // Hiding the device attribute fails
+        assert!(!proof_device
+            .verify(&device_pk, &HashMap::new(), &chal, &params)
+            .unwrap());
+
+        // Shown with another device: its proof is valid for its own key but not for the credential
+        let (proof_sig, proof_device, chal) = show(&other_secret);
+        assert!(proof_sig
+            .verify(&ps_verkey, &ps_params, revealed_msgs.clone(), &chal)
+            .unwrap());
+        assert!(!proof_device
+            .verify(&other_pk, &revealed_msgs, &chal, &params)
+            .unwrap());
+        assert!(!proof_device
+            .verify(&device_pk, &revealed_msgs, &chal, &params)
+            .unwrap());
+
+        // Shown with CredentialProofBuilder, which puts the commitment of the device's proof in
+        // the challenge
+        let nonce = b"verifier nonce";
+        let show_bound = |device_secret: &FieldElement, device_pk: &SignatureGroup| {
+            CredentialProofBuilder::new(&sig, all_msgs.as_slice(), &vk, &params)
+                .verifier_nonce(nonce)
+                .with_device_binding(PoKOfDeviceBinding::init(device_secret, &params), device_pk)
+                .finish_device_bound()
+                .unwrap()
+        };
+        let (proof, device_proof) = show_bound(&device_secret, &device_pk);
+        assert_eq!(proof.revealed_msgs[&msg_count], all_msgs[msg_count]);
+        assert!(proof
+            .verify_device_bound(nonce, &device_proof, &device_pk, &vk, &params)
+            .unwrap());
+        assert!(!proof
+            .verify_device_bound(b"other nonce", &device_proof, &device_pk, &vk, &params)
+            .unwrap());
+        assert!(!proof.verify(nonce, &vk, &params).unwrap());
+        assert!(
+            CredentialProofBuilder::new(&sig, all_msgs.as_slice(), &vk, &params)
+                .with_device_binding(
+                    PoKOfDeviceBinding::init(&device_secret, &params),
+                    &device_pk
+                )
+                .finish()
+                .is_err()
+        );
+
+        // A holder without the device secret can't show the credential, neither with another
+        // device's secret for the credential's device nor as the other device
+        let (proof, other_device_proof) = show_bound(&other_secret, &device_pk);
+        assert!(!proof
+            .verify_device_bound(nonce, &other_device_proof, &device_pk, &vk, &params)
+            .unwrap());
+        let (proof, other_device_proof) = show_bound(&other_secret, &other_pk);
+        assert!(!proof
+            .verify_device_bound(nonce, &other_device_proof, &other_pk, &vk, &params)
+            .unwrap());
+
+        // Nor with the device's proof from another showing
+        let (other_showing, _) = show_bound(&device_secret, &device_pk);
+        assert!(!other_showing
+            .verify_device_bound(nonce, &device_proof, &device_pk, &vk, &params)
+            .unwrap());
+    }
+}
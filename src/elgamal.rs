@@ -6,17 +6,30 @@ macro_rules! elgamal_keygen {
         let pk = $base * &sk;
         (sk, pk)
     }};
+    ( $base:expr, $rng:expr ) => {{
+        // Same as above but sk is sampled from the given RNG
+        let sk = FieldElement::random_using_rng($rng);
+        let pk = $base * &sk;
+        (sk, pk)
+    }};
 }
 
 #[macro_export]
 macro_rules! elgamal_encrypt {
-( $base:expr, $pk:expr, $msg: expr ) => {{
+    ( $base:expr, $pk:expr, $msg: expr ) => {{
         // Return ($base^k, $pk^k * $msg, k). k is needed when knowledge of k needs to be proven in the ciphertext
         let k = FieldElement::random();
         let c1 = $base * &k;
         let c2 = &($pk * &k) + $msg;
         (c1, c2, k)
-    }}
+    }};
+    ( $base:expr, $pk:expr, $msg: expr, $rng:expr ) => {{
+        // Same as above but k is sampled from the given RNG
+        let k = FieldElement::random_using_rng($rng);
+        let c1 = $base * &k;
+        let c2 = &($pk * &k) + $msg;
+        (c1, c2, k)
+    }};
 }
 
 #[macro_export]
@@ -27,6 +40,48 @@ macro_rules! elgamal_decrypt {
     }};
 }
 
+use crate::SignatureGroup;
+use amcl_wrapper::field_elem::FieldElement;
+
+/// Elgamal secret key and public key g^sk of the user requesting a signature. Using this rather
+/// than the loose key from `elgamal_keygen` lets the APIs taking the user's key pair, like
+/// `SignatureRequest::new_with_keypair` and `BlindSignature::unblind_with_keypair`, pick the
+/// right key themselves.
+///
+/// ```compile_fail
+/// # use coconut::elgamal::ElgamalKeypair;
+/// # use coconut::signature::BlindSignature;
+/// fn unblind(blind_sig: BlindSignature, keypair: &ElgamalKeypair) {
+///     // The public key is not accepted where the key pair is expected
+///     blind_sig.unblind_with_keypair(&keypair.pk);
+/// }
+/// ```
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ElgamalKeypair {
+    pub sk: FieldElement,
+    pub pk: SignatureGroup,
+}
+
+impl ElgamalKeypair {
+    pub fn generate(g: &SignatureGroup) -> Self {
+        let (sk, pk) = elgamal_keygen!(g);
+        Self { sk, pk }
+    }
+
+    /// Returns (g^k, pk^k * msg, k)
+    pub fn encrypt(
+        &self,
+        g: &SignatureGroup,
+        msg: &SignatureGroup,
+    ) -> (SignatureGroup, SignatureGroup, FieldElement) {
+        elgamal_encrypt!(g, &self.pk, msg)
+    }
+
+    pub fn decrypt(&self, c1: &SignatureGroup, c2: &SignatureGroup) -> SignatureGroup {
+        elgamal_decrypt!(c1, c2, &self.sk)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use amcl_wrapper::field_elem::FieldElement;
@@ -61,4 +116,18 @@ mod tests {
 
         assert_eq!(msg, decrypted)
     }
+
+    #[test]
+    fn test_elgamal_keypair() {
+        let g = SignatureGroup::random();
+        let keypair = ElgamalKeypair::generate(&g);
+        assert_eq!(&g * &keypair.sk, keypair.pk);
+
+        let msg = SignatureGroup::random();
+        let (c1, c2, k) = keypair.encrypt(&g, &msg);
+        assert_eq!(c1, &g * &k);
+        assert_eq!(keypair.decrypt(&c1, &c2), msg);
+        // Same as the macros
+        assert_eq!(elgamal_decrypt!(&c1, &c2, &keypair.sk), msg);
+    }
 }
@@ -1,11 +1,14 @@
+use amcl_wrapper::errors::{SerzDeserzError, ValueError};
 use failure::{Backtrace, Context, Error, Fail};
 use ps_sig::errors::PSError;
+use std::error::Error as StdError;
 use std::fmt;
+use std::io;
 
 #[derive(Clone, Eq, PartialEq, Debug, Fail)]
 pub enum CoconutErrorKind {
     #[fail(
-        display = "Verkey valid for {} messages but given {} messages",
+        display = "Attribute count mismatch: expected {} attributes but given {}",
         expected, given
     )]
     UnsupportedNoOfMessages { expected: usize, given: usize },
@@ -16,16 +19,117 @@ pub enum CoconutErrorKind {
     )]
     UnequalNoOfBasesExponents { bases: usize, exponents: usize },
 
-    #[fail(display = "Error from PS sig crate {:?}", msg)]
+    #[fail(
+        display = "Multi-scalar multiplication given {} bases but {} exponents",
+        bases, exponents
+    )]
+    ScalarMulMismatch { bases: usize, exponents: usize },
+
+    #[fail(
+        display = "Need at least {} shares but given {} shares",
+        expected, given
+    )]
+    InsufficientShares { expected: usize, given: usize },
+
+    #[fail(display = "Signature from signer {} has a different h (sigma_1)", id)]
+    InconsistentH { id: usize },
+
+    #[fail(display = "Blind signature's h (sigma_1) is not the h of the signature request")]
+    HMismatch,
+
+    #[fail(display = "Signature has the identity as sigma_1 or sigma_2")]
+    SigmaIsIdentity,
+
+    #[fail(
+        display = "Verkey is for {} attributes but given {} attributes",
+        expected, given
+    )]
+    AttributeCountMismatch { expected: usize, given: usize },
+
+    #[fail(display = "Signature is not valid on the messages under the verkey")]
+    PairingCheckFailed,
+
+    #[fail(display = "Verkey does not have the expected fingerprint")]
+    VerkeyFingerprintMismatch,
+
+    #[fail(
+        display = "Disclosed attribute {} is not the required value or is not disclosed",
+        idx
+    )]
+    DisclosedAttributeMismatch { idx: usize },
+
+    #[fail(display = "Attribute {} is not the credential type of the params", idx)]
+    TypeAttributeMismatch { idx: usize },
+
+    #[fail(display = "Attribute {} is not encrypted to the required auditor", idx)]
+    AuditableAttributeMissing { idx: usize },
+
+    #[fail(
+        display = "Attribute {} is not proven to open the required commitment",
+        idx
+    )]
+    CommittedAttributeMissing { idx: usize },
+
+    #[fail(
+        display = "Threshold {} invalid for {} signers, must be at least 1 and at most the signers",
+        threshold, total
+    )]
+    InvalidThreshold { threshold: usize, total: usize },
+
+    #[fail(display = "Signer id {} given more than once", id)]
+    DuplicateSignerId { id: usize },
+
+    #[fail(display = "Signer id {} is not one of the known signers", id)]
+    UnknownSignerId { id: usize },
+
+    #[fail(
+        display = "Attribute index {} invalid for {} attributes",
+        idx, msg_count
+    )]
+    InvalidAttributeIndex { idx: usize, msg_count: usize },
+
+    #[fail(display = "No attribute named {:?} in schema", name)]
+    UnknownAttribute { name: String },
+
+    #[fail(display = "Invalid hex string: {}", msg)]
+    InvalidHex { msg: String },
+
+    #[fail(display = "Expected {} bytes but given {} bytes", expected, given)]
+    InvalidByteLength { expected: usize, given: usize },
+
+    #[fail(display = "Invalid group element: {}", msg)]
+    InvalidPoint { msg: String },
+
+    #[fail(display = "Failed to decode group element: {}", msg)]
+    PointDecodeFailed { msg: String },
+
+    #[fail(display = "Elgamal randomness at index {} reused at index {}", first, second)]
+    RandomnessReuse { first: usize, second: usize },
+
+    #[fail(display = "Proof failed verification: {}", msg)]
+    InvalidProof { msg: String },
+
+    #[fail(display = "Pairing failed: {}", msg)]
+    PairingFailed { msg: String },
+
+    #[fail(display = "Error while deserializing: {}", msg)]
+    SerzDeserzError { msg: String },
+
+    #[fail(display = "Error from PS sig crate: {}", msg)]
     PSError { msg: String },
 
-    #[fail(display = "Error with message {:?}", msg)]
+    #[fail(display = "IO error: {}", msg)]
+    IOError { msg: String },
+
+    #[fail(display = "{}", msg)]
     GeneralError { msg: String },
 }
 
 #[derive(Debug)]
 pub struct CoconutError {
     inner: Context<CoconutErrorKind>,
+    /// Error of a dependency this error was created from, returned by `source`
+    source: Option<Box<dyn StdError + Send + Sync + 'static>>,
 }
 
 impl CoconutError {
@@ -37,6 +141,7 @@ impl CoconutError {
     pub fn from_kind(kind: CoconutErrorKind) -> Self {
         Self {
             inner: Context::new("").context(kind),
+            source: None,
         }
     }
 }
@@ -45,23 +150,39 @@ impl From<CoconutErrorKind> for CoconutError {
     fn from(kind: CoconutErrorKind) -> Self {
         Self {
             inner: Context::new(kind),
+            source: None,
         }
     }
 }
 
 impl From<Context<CoconutErrorKind>> for CoconutError {
     fn from(inner: Context<CoconutErrorKind>) -> Self {
-        Self { inner }
+        Self {
+            inner,
+            source: None,
+        }
     }
 }
 
-impl Fail for CoconutError {
-    fn cause(&self) -> Option<&dyn Fail> {
-        self.inner.cause()
+impl CoconutError {
+    pub fn backtrace(&self) -> Option<&Backtrace> {
+        self.inner.backtrace()
     }
 
-    fn backtrace(&self) -> Option<&Backtrace> {
-        self.inner.backtrace()
+    fn with_source<E: StdError + Send + Sync + 'static>(mut self, source: E) -> Self {
+        self.source = Some(Box::new(source));
+        self
+    }
+}
+
+// `Fail` comes from the blanket implementation of failure for `std::error::Error` so the error can
+// be used with either. The source is the error of a dependency, like an `io::Error` or a
+// `PSError`, the error was created from.
+impl StdError for CoconutError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.source
+            .as_ref()
+            .map(|e| e.as_ref() as &(dyn StdError + 'static))
     }
 }
 
@@ -73,13 +194,104 @@ impl fmt::Display for CoconutError {
 
 impl From<PSError> for CoconutError {
     fn from(err: PSError) -> Self {
-        let message = format!(
-            "PSError: {}",
-            Fail::iter_causes(&err)
-                .map(|e| e.to_string())
-                .collect::<String>()
+        let message = Fail::iter_chain(&err)
+            .map(|e| e.to_string())
+            .collect::<Vec<String>>()
+            .join(": ");
+
+        CoconutError::from(CoconutErrorKind::PSError { msg: message }).with_source(err.compat())
+    }
+}
+
+impl From<SerzDeserzError> for CoconutError {
+    fn from(err: SerzDeserzError) -> Self {
+        CoconutErrorKind::SerzDeserzError {
+            msg: format!("{:?}", err),
+        }
+        .into()
+    }
+}
+
+impl From<io::Error> for CoconutError {
+    fn from(err: io::Error) -> Self {
+        CoconutError::from(CoconutErrorKind::IOError {
+            msg: err.to_string(),
+        })
+        .with_source(err)
+    }
+}
+
+impl From<ValueError> for CoconutError {
+    fn from(err: ValueError) -> Self {
+        match err {
+            ValueError::UnequalSizeVectors(bases, exponents) => {
+                CoconutErrorKind::ScalarMulMismatch { bases, exponents }.into()
+            }
+            _ => CoconutErrorKind::GeneralError {
+                msg: format!("{:?}", err),
+            }
+            .into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_display() {
+        let e: CoconutError = CoconutErrorKind::UnsupportedNoOfMessages {
+            expected: 6,
+            given: 5,
+        }
+        .into();
+        assert_eq!(
+            e.to_string(),
+            "Attribute count mismatch: expected 6 attributes but given 5"
         );
+        let e = CoconutError::from_kind(CoconutErrorKind::InsufficientShares {
+            expected: 3,
+            given: 2,
+        });
+        assert_eq!(e.to_string(), "Need at least 3 shares but given 2 shares");
+
+        // Messages are not quoted
+        let e: CoconutError = CoconutErrorKind::GeneralError {
+            msg: String::from("Weight of a signer cannot be 0"),
+        }
+        .into();
+        assert_eq!(e.to_string(), "Weight of a signer cannot be 0");
 
-        CoconutErrorKind::PSError { msg: message }.into()
+        // Usable as a std error and as a failure error
+        let std_err: Box<dyn StdError> = Box::new(e);
+        assert_eq!(std_err.to_string(), "Weight of a signer cannot be 0");
+        let e: CoconutError = CoconutErrorKind::InvalidAttributeIndex {
+            idx: 4,
+            msg_count: 3,
+        }
+        .into();
+        let failure_err = Error::from(e);
+        assert_eq!(
+            failure_err.to_string(),
+            "Attribute index 4 invalid for 3 attributes"
+        );
+    }
+
+    #[test]
+    fn test_error_source() {
+        // The io error is the source of the error created from it
+        let e = CoconutError::from(io::Error::new(io::ErrorKind::UnexpectedEof, "short read"));
+        let source = e.source().unwrap();
+        assert_eq!(source.to_string(), "short read");
+        let io_err = source.downcast_ref::<io::Error>().unwrap();
+        assert_eq!(io_err.kind(), io::ErrorKind::UnexpectedEof);
+
+        // Errors of this crate have no source
+        let e: CoconutError = CoconutErrorKind::GeneralError {
+            msg: String::from("no source"),
+        }
+        .into();
+        assert!(e.source().is_none());
     }
 }
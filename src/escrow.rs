@@ -0,0 +1,519 @@
+// Escrow of the hidden messages of a signature request to auditors, like the signers, so that a
+// threshold of them can jointly decrypt a hidden message for audit. The hidden messages are
+// encrypted as h^m under the auditors' threshold Elgamal key, next to their encryption under the
+// holder's key in the request, with h the h of the request. Knowledge of the messages in the
+// escrow is proven with the blindings of the hidden messages in the proof of knowledge of the
+// request (`SignatureRequestPoK::init_with_blindings`) so under the same challenge the responses
+// are equal, which shows the escrow is of the messages in the request's commitment. Each auditor's
+// decryption share carries a proof of equality of discrete logs with its share public key so a
+// bad share is found rather than giving a wrong decryption.
+
+use crate::errors::{CoconutError, CoconutErrorKind};
+use crate::signature::{
+    combine_decryption_shares, threshold_decrypt, Params, ProofSignatureGroup,
+    ProverCommittedSignatureGroup, ProverCommittingSignatureGroup, SchnorrCommitment,
+    SignatureRequest, SignatureRequestProof,
+};
+use crate::utils::{append_u32, ensure_valid_point};
+use crate::SignatureGroup;
+use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
+use amcl_wrapper::group_elem::GroupElement;
+use std::collections::BTreeMap;
+
+/// Domain separation tag for the challenge of the proof of a decryption share
+pub const ESCROW_SHARE_CHALLENGE_TAG: &[u8] = b"coconut/escrow/decryption-share";
+
+/// Threshold Elgamal key of the auditors. The secret key sk is shared among the auditors with
+/// `threshold` of them needed to decrypt, `pk` is g^sk and `share_pks` are g^sk_i for the share
+/// sk_i of each auditor by share id.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AuditorKey {
+    pub threshold: usize,
+    pub pk: SignatureGroup,
+    pub share_pks: BTreeMap<usize, SignatureGroup>,
+}
+
+/// Hidden messages of a signature request encrypted under the auditors' key
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HiddenMessageEscrow {
+    pub auditor_pk: SignatureGroup,
+    /// (g^k, pk^k.h^m) for each hidden message m
+    pub ciphertexts: Vec<(SignatureGroup, SignatureGroup)>,
+}
+
+/// Commitment phase of the proof of knowledge of the messages in an escrow
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PoKOfEscrow {
+    escrow_bytes: Vec<u8>,
+    /// k and m for each ciphertext
+    secrets: Vec<[FieldElement; 2]>,
+    pok_vc: Vec<(ProverCommittedSignatureGroup, ProverCommittedSignatureGroup)>,
+}
+
+/// Response phase of the proof of knowledge of the messages in an escrow
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EscrowProof {
+    /// Response for k for c_1 and for k and m for c_2 of each ciphertext
+    pub proof_ciphertexts: Vec<(ProofSignatureGroup, ProofSignatureGroup)>,
+}
+
+/// Share c_1^sk_i of an auditor of decrypting the escrowed message at `idx`, with a proof that it
+/// has the discrete log of the auditor's share public key
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EscrowDecryptionShare {
+    pub id: usize,
+    pub idx: usize,
+    pub share: SignatureGroup,
+    /// Response for sk_i with base g
+    pub proof_pk: ProofSignatureGroup,
+    /// Response for sk_i with base c_1
+    pub proof_share: ProofSignatureGroup,
+}
+
+impl AuditorKey {
+    /// Key of auditors with `share_pks`, g^sk_i by share id. The public key is interpolated from
+    /// the first `threshold` of them. Errors if less than `threshold` are given or one is invalid.
+    pub fn new(
+        threshold: usize,
+        share_pks: BTreeMap<usize, SignatureGroup>,
+    ) -> Result<Self, CoconutError> {
+        let pk = combine_decryption_shares(
+            threshold,
+            share_pks.iter().map(|(id, pk)| (*id, pk.clone())).collect(),
+        )?;
+        Ok(Self {
+            threshold,
+            pk,
+            share_pks,
+        })
+    }
+}
+
+impl HiddenMessageEscrow {
+    /// Encrypt the `hidden_messages` of `sig_req` under `auditor_pk`. Returns the escrow and the
+    /// randomness k of each ciphertext. Errors if the number of messages is not the number of
+    /// hidden messages of the request or `auditor_pk` is invalid.
+    pub fn new(
+        sig_req: &SignatureRequest,
+        hidden_messages: &FieldElementVector,
+        auditor_pk: &SignatureGroup,
+        params: &Params,
+    ) -> Result<(Self, FieldElementVector), CoconutError> {
+        if hidden_messages.len() != sig_req.ciphertexts.len() {
+            return Err(CoconutErrorKind::UnsupportedNoOfMessages {
+                expected: sig_req.ciphertexts.len(),
+                given: hidden_messages.len(),
+            }
+            .into());
+        }
+        ensure_valid_point(auditor_pk)?;
+        let h = SignatureRequest::compute_h(&sig_req.commitment, sig_req.known_messages.as_slice());
+        let mut randomness = FieldElementVector::with_capacity(hidden_messages.len());
+        let mut ciphertexts = Vec::with_capacity(hidden_messages.len());
+        for m in hidden_messages.iter() {
+            let (c1, c2, k) = elgamal_encrypt!(&params.g, auditor_pk, &(&h * m));
+            ciphertexts.push((c1, c2));
+            randomness.push(k);
+        }
+        Ok((
+            Self {
+                auditor_pk: auditor_pk.clone(),
+                ciphertexts,
+            },
+            randomness,
+        ))
+    }
+
+    /// Created by the auditor with share id `id` and secret key share `sk_share`. Its share of
+    /// decrypting the message at index `idx` with the proof of the share. Errors if there is no
+    /// message at `idx`.
+    pub fn decryption_share(
+        &self,
+        idx: usize,
+        id: usize,
+        sk_share: &FieldElement,
+        params: &Params,
+    ) -> Result<EscrowDecryptionShare, CoconutError> {
+        let c1 = &self.ciphertext(idx)?.0;
+        let share_pk = &params.g * sk_share;
+        let share = c1 * sk_share;
+
+        let blinding = FieldElement::random();
+        let mut committing = ProverCommittingSignatureGroup::new();
+        committing.commit(&params.g, Some(&blinding));
+        let pok_pk = committing.finish();
+        let mut committing = ProverCommittingSignatureGroup::new();
+        committing.commit(c1, Some(&blinding));
+        let pok_share = committing.finish();
+
+        let mut commitments = pok_pk.schnorr_commitment().to_bytes();
+        commitments.append(&mut pok_share.schnorr_commitment().to_bytes());
+        let challenge = share_challenge(id, idx, &share_pk, c1, &share, &commitments, params);
+        let secret = [sk_share.clone()];
+        Ok(EscrowDecryptionShare {
+            id,
+            idx,
+            share,
+            proof_pk: pok_pk.gen_proof(&challenge, &secret)?,
+            proof_share: pok_share.gen_proof(&challenge, &secret)?,
+        })
+    }
+
+    /// Jointly decrypt the message at index `idx` with the decryption shares of at least the
+    /// threshold of auditors of `auditor_key`. Decryption gives h^m for the h of `sig_req`, the
+    /// request of the escrow, so the message m is found among the `candidates`, like the values an
+    /// auditor suspects. None if m is not a candidate. Errors with `InvalidProof` naming the
+    /// auditor of a share whose proof fails, if `auditor_key` is not the key of the escrow or as
+    /// `threshold_decrypt`.
+    pub fn recover_hidden_message(
+        &self,
+        idx: usize,
+        shares: &[EscrowDecryptionShare],
+        auditor_key: &AuditorKey,
+        sig_req: &SignatureRequest,
+        candidates: &[FieldElement],
+        params: &Params,
+    ) -> Result<Option<FieldElement>, CoconutError> {
+        if auditor_key.pk != self.auditor_pk {
+            return Err(CoconutErrorKind::GeneralError {
+                msg: String::from("Auditor key is not the key of the escrow"),
+            }
+            .into());
+        }
+        let (c1, c2) = self.ciphertext(idx)?;
+        for s in shares {
+            let share_pk = auditor_key.share_pks.get(&s.id).ok_or_else(|| {
+                CoconutError::from(CoconutErrorKind::UnknownSignerId { id: s.id })
+            })?;
+            if s.idx != idx || !s.verify(share_pk, c1, params)? {
+                return Err(CoconutErrorKind::InvalidProof {
+                    msg: format!("decryption share of auditor {}", s.id),
+                }
+                .into());
+            }
+        }
+        let h_m = threshold_decrypt(
+            auditor_key.threshold,
+            c2,
+            shares.iter().map(|s| (s.id, s.share.clone())).collect(),
+        )?;
+        let h = SignatureRequest::compute_h(&sig_req.commitment, sig_req.known_messages.as_slice());
+        Ok(candidates.iter().find(|m| &h * *m == h_m).cloned())
+    }
+
+    /// Byte representation is auditor public key || count of ciphertexts || c1 || c2 for each
+    /// ciphertext. The count is a 4 byte big-endian integer.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.auditor_pk.to_bytes();
+        append_u32(&mut bytes, self.ciphertexts.len());
+        for (c1, c2) in &self.ciphertexts {
+            bytes.append(&mut c1.to_bytes());
+            bytes.append(&mut c2.to_bytes());
+        }
+        bytes
+    }
+
+    fn ciphertext(&self, idx: usize) -> Result<&(SignatureGroup, SignatureGroup), CoconutError> {
+        self.ciphertexts.get(idx).ok_or_else(|| {
+            CoconutErrorKind::InvalidAttributeIndex {
+                idx,
+                msg_count: self.ciphertexts.len(),
+            }
+            .into()
+        })
+    }
+}
+
+impl PoKOfEscrow {
+    /// `hidden_messages` and `randomness` are as given to and returned by
+    /// `HiddenMessageEscrow::new`. `blindings` are the blindings of the hidden messages given to
+    /// `SignatureRequestPoK::init_with_blindings` for the proof of knowledge of the request, which
+    /// must use the same challenge. Errors if the number of messages, randomness or blindings is
+    /// not the number of ciphertexts of the escrow.
+    pub fn init(
+        escrow: &HiddenMessageEscrow,
+        sig_req: &SignatureRequest,
+        hidden_messages: &FieldElementVector,
+        randomness: &FieldElementVector,
+        blindings: &[FieldElement],
+        params: &Params,
+    ) -> Result<Self, CoconutError> {
+        let count = escrow.ciphertexts.len();
+        if hidden_messages.len() != count || randomness.len() != count || blindings.len() != count {
+            return Err(CoconutErrorKind::GeneralError {
+                msg: format!(
+                    "Need {} messages, randomness and blindings for the escrow",
+                    count
+                ),
+            }
+            .into());
+        }
+        let h = SignatureRequest::compute_h(&sig_req.commitment, sig_req.known_messages.as_slice());
+        let mut secrets = Vec::with_capacity(count);
+        let mut pok_vc = Vec::with_capacity(count);
+        for i in 0..count {
+            let k_blinding = FieldElement::random();
+            let mut committing_1 = ProverCommittingSignatureGroup::new();
+            committing_1.commit(&params.g, Some(&k_blinding));
+            let mut committing_2 = ProverCommittingSignatureGroup::new();
+            committing_2.commit(&escrow.auditor_pk, Some(&k_blinding));
+            committing_2.commit(&h, Some(&blindings[i]));
+            pok_vc.push((committing_1.finish(), committing_2.finish()));
+            secrets.push([randomness[i].clone(), hidden_messages[i].clone()]);
+        }
+        Ok(Self {
+            escrow_bytes: escrow.to_bytes(),
+            secrets,
+            pok_vc,
+        })
+    }
+
+    /// The escrow and the commitments, to be hashed in the challenge along with
+    /// `SignatureRequestPoK::to_bytes`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.escrow_bytes.clone();
+        for (pok_vc_1, pok_vc_2) in &self.pok_vc {
+            bytes.append(&mut pok_vc_1.to_bytes());
+            bytes.append(&mut pok_vc_2.to_bytes());
+        }
+        bytes
+    }
+
+    pub fn gen_proof(self, challenge: &FieldElement) -> Result<EscrowProof, CoconutError> {
+        let mut proof_ciphertexts = Vec::with_capacity(self.pok_vc.len());
+        for ((pok_vc_1, pok_vc_2), secrets) in self.pok_vc.into_iter().zip(self.secrets.iter()) {
+            proof_ciphertexts.push((
+                pok_vc_1.gen_proof(challenge, &secrets[..1])?,
+                pok_vc_2.gen_proof(challenge, secrets)?,
+            ));
+        }
+        Ok(EscrowProof { proof_ciphertexts })
+    }
+}
+
+impl EscrowProof {
+    /// Verify that `escrow` is of the hidden messages in the commitment of `sig_req`.
+    /// `sig_req_proof` is the proof of knowledge of the request created with the same challenge
+    /// and must be verified separately.
+    pub fn verify(
+        &self,
+        escrow: &HiddenMessageEscrow,
+        sig_req: &SignatureRequest,
+        sig_req_proof: &SignatureRequestProof,
+        challenge: &FieldElement,
+        params: &Params,
+    ) -> Result<bool, CoconutError> {
+        let count = sig_req.ciphertexts.len();
+        if escrow.ciphertexts.len() != count
+            || self.proof_ciphertexts.len() != count
+            || sig_req_proof.proof_commitment.responses.len() != count + 1
+            || escrow.auditor_pk.is_identity()
+        {
+            return Ok(false);
+        }
+        let h = SignatureRequest::compute_h(&sig_req.commitment, sig_req.known_messages.as_slice());
+        let bases = [escrow.auditor_pk.clone(), h];
+        for (i, (proof_1, proof_2)) in self.proof_ciphertexts.iter().enumerate() {
+            // The response for the message is that of the message in the request's commitment
+            if proof_1.responses.len() != 1
+                || proof_2.responses.len() != 2
+                || proof_1.responses[0] != proof_2.responses[0]
+                || proof_2.responses[1] != sig_req_proof.proof_commitment.responses[i]
+            {
+                return Ok(false);
+            }
+            let (c1, c2) = &escrow.ciphertexts[i];
+            if !proof_1.verify(&[params.g.clone()], c1, challenge)?
+                || !proof_2.verify(&bases, c2, challenge)?
+            {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+impl EscrowDecryptionShare {
+    /// Verify that the share has the discrete log of `share_pk` with base `c1`
+    fn verify(
+        &self,
+        share_pk: &SignatureGroup,
+        c1: &SignatureGroup,
+        params: &Params,
+    ) -> Result<bool, CoconutError> {
+        if self.proof_pk.responses.len() != 1
+            || self.proof_share.responses.len() != 1
+            || self.proof_pk.responses[0] != self.proof_share.responses[0]
+        {
+            return Ok(false);
+        }
+        let mut commitments = self.proof_pk.commitment.to_bytes();
+        commitments.append(&mut self.proof_share.commitment.to_bytes());
+        let challenge = share_challenge(
+            self.id,
+            self.idx,
+            share_pk,
+            c1,
+            &self.share,
+            &commitments,
+            params,
+        );
+        Ok(self
+            .proof_pk
+            .verify(&[params.g.clone()], share_pk, &challenge)?
+            && self
+                .proof_share
+                .verify(&[c1.clone()], &self.share, &challenge)?)
+    }
+}
+
+fn share_challenge(
+    id: usize,
+    idx: usize,
+    share_pk: &SignatureGroup,
+    c1: &SignatureGroup,
+    share: &SignatureGroup,
+    commitments: &[u8],
+    params: &Params,
+) -> FieldElement {
+    let mut bytes = ESCROW_SHARE_CHALLENGE_TAG.to_vec();
+    append_u32(&mut bytes, id);
+    append_u32(&mut bytes, idx);
+    bytes.append(&mut params.g.to_bytes());
+    bytes.append(&mut share_pk.to_bytes());
+    bytes.append(&mut c1.to_bytes());
+    bytes.append(&mut share.to_bytes());
+    bytes.extend_from_slice(commitments);
+    FieldElement::from_msg_hash(&bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signature::SignatureRequestPoK;
+    use secret_sharing::shamir_secret_sharing::get_shared_secret;
+
+    #[test]
+    fn test_hidden_message_escrow() {
+        // Auditor key shared 2-of-3, separate from the holder's key
+        let threshold = 2;
+        let msg_count = 4;
+        let count_hidden = 2;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (_, sk_shares) = get_shared_secret(threshold, 3);
+        let auditor_key = AuditorKey::new(
+            threshold,
+            sk_shares
+                .iter()
+                .map(|(id, sk)| (*id, &params.g * sk))
+                .collect(),
+        )
+        .unwrap();
+
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        let msgs = FieldElementVector::random(msg_count);
+        let (sig_req, req_randomness) =
+            SignatureRequest::new(&msgs, count_hidden, &elg_pk, &params).unwrap();
+        let mut hidden_msgs = FieldElementVector::with_capacity(count_hidden);
+        for i in 0..count_hidden {
+            hidden_msgs.push(msgs[i].clone());
+        }
+        let (escrow, randomness) =
+            HiddenMessageEscrow::new(&sig_req, &hidden_msgs, &auditor_key.pk, &params).unwrap();
+
+        // Prove the escrow and the request with the same blindings and challenge
+        let prove = |escrowed_msgs: &FieldElementVector| {
+            let blindings = FieldElementVector::random(count_hidden);
+            let pok_req = SignatureRequestPoK::init_with_blindings(
+                &sig_req,
+                &elg_pk,
+                &params,
+                blindings.as_slice(),
+            )
+            .unwrap();
+            let pok_escrow = PoKOfEscrow::init(
+                &escrow,
+                &sig_req,
+                escrowed_msgs,
+                &randomness,
+                blindings.as_slice(),
+                &params,
+            )
+            .unwrap();
+            let mut chal_bytes = pok_req.to_bytes();
+            chal_bytes.append(&mut pok_escrow.to_bytes());
+            let chal = FieldElement::from_msg_hash(&chal_bytes);
+            let proof_req = pok_req
+                .gen_proof(&hidden_msgs, req_randomness.clone(), &elg_sk, &chal)
+                .unwrap();
+            (proof_req, pok_escrow.gen_proof(&chal).unwrap(), chal)
+        };
+
+        let (proof_req, proof_escrow, chal) = prove(&hidden_msgs);
+        assert!(proof_req.verify(&sig_req, &elg_pk, &chal, &params).unwrap());
+        assert!(proof_escrow
+            .verify(&escrow, &sig_req, &proof_req, &chal, &params)
+            .unwrap());
+
+        // Claiming the escrow is of other messages than the request's fails
+        let other_msgs = FieldElementVector::random(count_hidden);
+        let (proof_req, proof_escrow, chal) = prove(&other_msgs);
+        assert!(!proof_escrow
+            .verify(&escrow, &sig_req, &proof_req, &chal, &params)
+            .unwrap());
+
+        // Auditors 1 and 3 jointly recover the 2nd hidden message
+        let candidates = vec![
+            FieldElement::random(),
+            msgs[1].clone(),
+            FieldElement::random(),
+        ];
+        let shares = [1, 3]
+            .iter()
+            .map(|id| {
+                escrow
+                    .decryption_share(1, *id, &sk_shares[id], &params)
+                    .unwrap()
+            })
+            .collect::<Vec<EscrowDecryptionShare>>();
+        let recover = |idx: usize,
+                       shares: &[EscrowDecryptionShare],
+                       candidates: &[FieldElement]| {
+            escrow.recover_hidden_message(idx, shares, &auditor_key, &sig_req, candidates, &params)
+        };
+        assert_eq!(
+            recover(1, &shares, &candidates).unwrap(),
+            Some(msgs[1].clone())
+        );
+        // Not among the candidates
+        assert_eq!(recover(1, &shares, &candidates[2..]).unwrap(), None);
+
+        // A bad share is found rather than giving a wrong decryption
+        let mut tampered = shares.clone();
+        tampered[1].share = &tampered[1].share + &params.g;
+        match recover(1, &tampered, &candidates).unwrap_err().kind() {
+            CoconutErrorKind::InvalidProof { msg } => {
+                assert_eq!(msg, "decryption share of auditor 3")
+            }
+            k => panic!("unexpected error kind {:?}", k),
+        }
+        // Shares for the 2nd hidden message can't decrypt the 1st
+        match recover(0, &shares, &candidates).unwrap_err().kind() {
+            CoconutErrorKind::InvalidProof { .. } => (),
+            k => panic!("unexpected error kind {:?}", k),
+        }
+        match recover(1, &shares[..1], &candidates).unwrap_err().kind() {
+            CoconutErrorKind::InsufficientShares { .. } => (),
+            k => panic!("unexpected error kind {:?}", k),
+        }
+        let mut unknown = shares.clone();
+        unknown[1].id = 4;
+        match recover(1, &unknown, &candidates).unwrap_err().kind() {
+            CoconutErrorKind::UnknownSignerId { id } => assert_eq!(id, 4),
+            k => panic!("unexpected error kind {:?}", k),
+        }
+        assert!(escrow
+            .decryption_share(count_hidden, 1, &sk_shares[&1], &params)
+            .is_err());
+    }
+}
@@ -0,0 +1,426 @@
+// Issuing credentials where a designated subset of the attributes is given to the signers in
+// OtherGroup, as W_i = Y_tilde_i^m_i with the base Y_tilde_i of the aggregate verkey, rather than
+// as scalars or Elgamal ciphertexts. Useful when the attribute already exists in OtherGroup, like
+// a key, or later proofs about it are in OtherGroup. Such an attribute takes the place of a known
+// message of the request with value 0. The holder also gives h^m_i for the h of the request and
+// proves knowledge of m_i in W_i. The signer checks e(h^m_i, Y_tilde_i) == e(h, W_i), so both have
+// the same m_i, and multiplies the 2nd component of its blind signature with (h^m_i)^y_i. The
+// unblinded and aggregated signature is the usual signature over all m_i, so nothing depending on
+// y_i other than the signature is created for the holder and shows hide the attribute as usual.
+
+use crate::errors::{CoconutError, CoconutErrorKind};
+use crate::signature::{
+    BlindSignature, Params, SchnorrCommitment, Sigkey, Signature, SignatureRequest, Verkey,
+};
+use crate::utils::{append_u32, ensure_valid_point};
+use crate::{ate_2_pairing, OtherGroup, SignatureGroup};
+use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
+use amcl_wrapper::group_elem::GroupElement;
+use ps_sig::pok_sig::{ProofOtherGroup, ProverCommittingOtherGroup};
+use std::collections::HashSet;
+
+/// Domain separation tag prefixed to the bytes hashed for the challenge of `G2Attribute`
+pub const G2_ATTRIBUTE_CHALLENGE_TAG: &[u8] = b"coconut/g2-attribute/challenge";
+
+/// Attribute at `idx` given to the signers in OtherGroup as `value` = Y_tilde_idx^m along with
+/// `h_term` = h^m for the h of the request and the proof of knowledge of m in `value`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct G2Attribute {
+    pub idx: usize,
+    pub value: OtherGroup,
+    pub h_term: SignatureGroup,
+    pub proof: ProofOtherGroup,
+}
+
+impl G2Attribute {
+    /// Created by the holder for the attribute `m` at index `idx` of the request `sig_req`.
+    /// Errors if `vk` has no attribute at `idx`.
+    pub fn new(
+        idx: usize,
+        m: &FieldElement,
+        sig_req: &SignatureRequest,
+        vk: &Verkey,
+        params: &Params,
+    ) -> Result<Self, CoconutError> {
+        let y_tilde = y_tilde(idx, vk)?;
+        let h = SignatureRequest::compute_h(&sig_req.commitment, sig_req.known_messages.as_slice());
+        let value = y_tilde * m;
+        let h_term = &h * m;
+
+        let mut committing = ProverCommittingOtherGroup::new();
+        committing.commit(y_tilde, None);
+        let pok = committing.finish();
+        let challenge = Self::challenge(
+            idx,
+            &value,
+            &h_term,
+            &pok.schnorr_commitment(),
+            &h,
+            vk,
+            params,
+        );
+        let proof = pok.gen_proof(&challenge, &[m.clone()])?;
+        Ok(Self {
+            idx,
+            value,
+            h_term,
+            proof,
+        })
+    }
+
+    /// Verified by the signer. Checks e(h_term, Y_tilde_idx) == e(h, value) for the h of
+    /// `sig_req` and the proof of knowledge of m in `value`. Errors if `vk` has no attribute at
+    /// `idx` or a point is invalid.
+    pub fn verify(
+        &self,
+        sig_req: &SignatureRequest,
+        vk: &Verkey,
+        params: &Params,
+    ) -> Result<bool, CoconutError> {
+        let y_tilde = y_tilde(self.idx, vk)?;
+        ensure_valid_point(&self.value)?;
+        ensure_valid_point(&self.h_term)?;
+        let h = SignatureRequest::compute_h(&sig_req.commitment, sig_req.known_messages.as_slice());
+        // e(h_term, Y_tilde_idx) * e(-h, value) == 1
+        if !ate_2_pairing(&self.h_term, y_tilde, &h.negation(), &self.value).is_one() {
+            return Ok(false);
+        }
+        let challenge = Self::challenge(
+            self.idx,
+            &self.value,
+            &self.h_term,
+            &self.proof.commitment,
+            &h,
+            vk,
+            params,
+        );
+        Ok(self
+            .proof
+            .verify(&[y_tilde.clone()], &self.value, &challenge)?)
+    }
+
+    fn challenge(
+        idx: usize,
+        value: &OtherGroup,
+        h_term: &SignatureGroup,
+        commitment: &OtherGroup,
+        h: &SignatureGroup,
+        vk: &Verkey,
+        params: &Params,
+    ) -> FieldElement {
+        let mut bytes = G2_ATTRIBUTE_CHALLENGE_TAG.to_vec();
+        append_u32(&mut bytes, idx);
+        bytes.append(&mut params.g_tilde.to_bytes());
+        bytes.append(&mut vk.to_bytes());
+        bytes.append(&mut h.to_bytes());
+        bytes.append(&mut value.to_bytes());
+        bytes.append(&mut h_term.to_bytes());
+        bytes.append(&mut commitment.to_bytes());
+        FieldElement::from_msg_hash(&bytes)
+    }
+}
+
+fn y_tilde(idx: usize, vk: &Verkey) -> Result<&OtherGroup, CoconutError> {
+    vk.Y_tilde.get(idx).ok_or_else(|| {
+        CoconutError::from(CoconutErrorKind::InvalidAttributeIndex {
+            idx,
+            msg_count: vk.Y_tilde.len(),
+        })
+    })
+}
+
+/// Check that each of `g2_indices` is of a known message, that is between `count_hidden` and the
+/// number of messages of `params`, is not the type attribute and is not repeated
+fn check_g2_indices(
+    g2_indices: &[usize],
+    count_hidden: usize,
+    params: &Params,
+) -> Result<(), CoconutError> {
+    let msg_count = params.msg_count();
+    let mut seen = HashSet::new();
+    for &idx in g2_indices {
+        if idx < count_hidden || idx >= msg_count || !seen.insert(idx) {
+            return Err(CoconutErrorKind::InvalidAttributeIndex { idx, msg_count }.into());
+        }
+        if params.type_attribute_index() == Some(idx) {
+            return Err(CoconutErrorKind::TypeAttributeMismatch { idx }.into());
+        }
+    }
+    Ok(())
+}
+
+/// Created by the holder. Request for `messages` like `SignatureRequest::new` but with the
+/// attributes at `g2_indices` given in OtherGroup. They must be of known messages, that is not
+/// among the 1st `count_hidden`, and are replaced in the request with 0. Returns the request, its
+/// randomness and the attributes in the order of `g2_indices`.
+pub fn request_with_g2_attributes(
+    messages: &FieldElementVector,
+    count_hidden: usize,
+    g2_indices: &[usize],
+    elgamal_pubkey: &SignatureGroup,
+    vk: &Verkey,
+    params: &Params,
+) -> Result<(SignatureRequest, FieldElementVector, Vec<G2Attribute>), CoconutError> {
+    if messages.len() != params.msg_count() {
+        return Err(CoconutErrorKind::UnsupportedNoOfMessages {
+            expected: params.msg_count(),
+            given: messages.len(),
+        }
+        .into());
+    }
+    check_g2_indices(g2_indices, count_hidden, params)?;
+    let mut request_messages = messages.clone();
+    for &idx in g2_indices {
+        request_messages[idx] = FieldElement::zero();
+    }
+    let (sig_req, randomness) =
+        SignatureRequest::new(&request_messages, count_hidden, elgamal_pubkey, params)?;
+    let mut g2_attributes = Vec::with_capacity(g2_indices.len());
+    for &idx in g2_indices {
+        g2_attributes.push(G2Attribute::new(idx, &messages[idx], &sig_req, vk, params)?);
+    }
+    Ok((sig_req, randomness, g2_attributes))
+}
+
+/// Created by the signer. Blind signature on `sig_req` like `BlindSignature::new_typed` with the
+/// attributes of `g2_attributes` in place of their known messages, which must be 0. The proof of
+/// knowledge of the request is verified separately as for any request. Errors if an attribute is
+/// not of a known message or is repeated and with `InvalidProof` if an attribute does not verify.
+pub fn blind_sign_with_g2_attributes(
+    sig_req: &SignatureRequest,
+    g2_attributes: &[G2Attribute],
+    sigkey: &Sigkey,
+    vk: &Verkey,
+    params: &Params,
+) -> Result<BlindSignature, CoconutError> {
+    let count_hidden = sig_req.ciphertexts.len();
+    check_g2_indices(
+        &g2_attributes.iter().map(|a| a.idx).collect::<Vec<usize>>(),
+        count_hidden,
+        params,
+    )?;
+    for attr in g2_attributes {
+        match sig_req.known_messages.as_slice().get(attr.idx - count_hidden) {
+            Some(m) if m.is_zero() => (),
+            _ => {
+                return Err(CoconutErrorKind::GeneralError {
+                    msg: format!(
+                        "Known message {} is not 0 for the attribute in OtherGroup",
+                        attr.idx
+                    ),
+                }
+                .into())
+            }
+        }
+        if !attr.verify(sig_req, vk, params)? {
+            return Err(CoconutErrorKind::InvalidProof {
+                msg: format!("Attribute {} in OtherGroup", attr.idx),
+            }
+            .into());
+        }
+    }
+
+    let mut blind_sig = BlindSignature::new_typed(sig_req, sigkey, params)?;
+    // c_tilde_2.(h^m_i)^y_i for each attribute i in OtherGroup
+    for attr in g2_attributes {
+        blind_sig.blinded.1 = &blind_sig.blinded.1 + &(&attr.h_term * &sigkey.y[attr.idx]);
+    }
+    Ok(blind_sig)
+}
+
+/// Verify `sig` over the attributes in `scalar_messages` given as (index, m_i) and the attributes
+/// in OtherGroup given as (index, Y_tilde_i^m_i), like the `value` of `G2Attribute`. The terms of
+/// the latter are used in kappa as they are, so the check is e(sigma_1, kappa) ==
+/// e(sigma_2, g_tilde) with kappa = X_tilde.prod(Y_tilde_j^m_j).prod(W_i). Every attribute of the
+/// verkey must be in exactly one of the two. Y_tilde_i^m_i is the same in every show, so this is
+/// for the holder or signers checking the issued credential and shows hide the attribute as usual.
+pub fn verify_with_g2_attributes(
+    sig: &Signature,
+    scalar_messages: &[(usize, FieldElement)],
+    g2_values: &[(usize, OtherGroup)],
+    vk: &Verkey,
+    params: &Params,
+) -> Result<bool, CoconutError> {
+    let msg_count = vk.Y_tilde.len();
+    if scalar_messages.len() + g2_values.len() != msg_count {
+        return Err(CoconutErrorKind::UnsupportedNoOfMessages {
+            expected: msg_count,
+            given: scalar_messages.len() + g2_values.len(),
+        }
+        .into());
+    }
+    let mut seen = HashSet::new();
+    for idx in scalar_messages
+        .iter()
+        .map(|(i, _)| *i)
+        .chain(g2_values.iter().map(|(i, _)| *i))
+    {
+        if idx >= msg_count || !seen.insert(idx) {
+            return Err(CoconutErrorKind::InvalidAttributeIndex { idx, msg_count }.into());
+        }
+    }
+    if !params.reveals_type_attribute(scalar_messages.iter().map(|(i, m)| (i, m))) {
+        return Ok(false);
+    }
+    let mut kappa = vk.partial_kappa(scalar_messages, params)?;
+    for (_, value) in g2_values {
+        ensure_valid_point(value)?;
+        kappa = &kappa + value;
+    }
+    Ok(sig.pairing_check(&kappa, params))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::signers_with_verkey;
+
+    #[test]
+    fn test_issue_g2_attribute_credential() {
+        // Attribute at index 1 is given to the signers in OtherGroup. Index 0 is hidden and index
+        // 3 is the type attribute.
+        let threshold = 2;
+        let msg_count = 3;
+        let g2_idx = 1;
+        let params = Params::new_typed(msg_count, "test".as_bytes(), "membership".as_bytes());
+        let (signers, vk) = signers_with_verkey(threshold, 3, &params);
+        let msgs = params.typed_messages(FieldElementVector::random(msg_count).as_slice());
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+
+        let (sig_req, _, g2_attrs) =
+            request_with_g2_attributes(&msgs, 1, &[g2_idx], &elg_pk, &vk, &params).unwrap();
+        assert!(sig_req.known_messages[0].is_zero());
+        assert_eq!(g2_attrs.len(), 1);
+        assert_eq!(g2_attrs[0].value, &vk.Y_tilde[g2_idx] * &msgs[g2_idx]);
+        assert!(g2_attrs[0].verify(&sig_req, &vk, &params).unwrap());
+
+        let sigs = signers[1..]
+            .iter()
+            .map(|s| {
+                let blind_sig =
+                    blind_sign_with_g2_attributes(&sig_req, &g2_attrs, &s.sigkey, &vk, &params)
+                        .unwrap();
+                (s.id, blind_sig.unblind(&elg_sk))
+            })
+            .collect::<Vec<(usize, Signature)>>();
+        let sig = Signature::aggregate(threshold, sigs).unwrap();
+
+        // The usual signature over all attributes, so it is shown like any other
+        assert!(sig.verify(msgs.as_slice(), &vk, &params).unwrap());
+        assert!(sig
+            .randomize()
+            .verify(msgs.as_slice(), &vk, &params)
+            .unwrap());
+
+        // And verifies with the attribute in OtherGroup
+        let scalar_msgs = vec![
+            (0, msgs[0].clone()),
+            (2, msgs[2].clone()),
+            (3, msgs[3].clone()),
+        ];
+        let g2_values = vec![(g2_idx, g2_attrs[0].value.clone())];
+        assert!(verify_with_g2_attributes(&sig, &scalar_msgs, &g2_values, &vk, &params).unwrap());
+        let shifted = vec![(
+            g2_idx,
+            &g2_attrs[0].value + &(&vk.Y_tilde[g2_idx] * &FieldElement::random()),
+        )];
+        assert!(!verify_with_g2_attributes(&sig, &scalar_msgs, &shifted, &vk, &params).unwrap());
+        let overlapping = vec![
+            (0, msgs[0].clone()),
+            (1, msgs[1].clone()),
+            (3, msgs[3].clone()),
+        ];
+        match verify_with_g2_attributes(&sig, &overlapping, &g2_values, &vk, &params) {
+            Err(e) => match e.kind() {
+                CoconutErrorKind::InvalidAttributeIndex { idx, .. } => assert_eq!(idx, g2_idx),
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(_) => panic!("verified with an attribute given twice"),
+        }
+
+        // Signing the request without the attribute in OtherGroup gives a signature with 0 for it
+        let blind_sig = BlindSignature::new_typed(&sig_req, &signers[0].sigkey, &params).unwrap();
+        let sig = blind_sig.unblind(&elg_sk);
+        assert!(!sig
+            .verify(msgs.as_slice(), &signers[0].verkey, &params)
+            .unwrap());
+    }
+
+    #[test]
+    fn test_g2_attribute_rejected() {
+        let threshold = 2;
+        let msg_count = 3;
+        let g2_idx = 2;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (signers, vk) = signers_with_verkey(threshold, 3, &params);
+        let sigkey = &signers[0].sigkey;
+        let msgs = FieldElementVector::random(msg_count);
+        let (_, elg_pk) = elgamal_keygen!(&params.g);
+        let (sig_req, _, g2_attrs) =
+            request_with_g2_attributes(&msgs, 1, &[g2_idx], &elg_pk, &vk, &params).unwrap();
+
+        let expect_invalid_proof = |attrs: &[G2Attribute]| match blind_sign_with_g2_attributes(
+            &sig_req, attrs, sigkey, &vk, &params,
+        ) {
+            Err(e) => match e.kind() {
+                CoconutErrorKind::InvalidProof { .. } => (),
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(_) => panic!("signed an invalid attribute in OtherGroup"),
+        };
+
+        // h_term with a different m than value
+        let mut mismatched = g2_attrs[0].clone();
+        let h = SignatureRequest::compute_h(&sig_req.commitment, sig_req.known_messages.as_slice());
+        mismatched.h_term = &h * &FieldElement::random();
+        assert!(!mismatched.verify(&sig_req, &vk, &params).unwrap());
+        expect_invalid_proof(&[mismatched]);
+
+        // Both shifted by the same delta pass the pairing check but not the proof of knowledge
+        let delta = FieldElement::random();
+        let mut shifted = g2_attrs[0].clone();
+        shifted.value = &shifted.value + &(&vk.Y_tilde[g2_idx] * &delta);
+        shifted.h_term = &shifted.h_term + &(&h * &delta);
+        assert!(!shifted.verify(&sig_req, &vk, &params).unwrap());
+        expect_invalid_proof(&[shifted]);
+
+        // Attribute for another request
+        let (other_req, _, _) =
+            request_with_g2_attributes(&msgs, 1, &[g2_idx], &elg_pk, &vk, &params).unwrap();
+        assert!(!g2_attrs[0].verify(&other_req, &vk, &params).unwrap());
+
+        // The known message in place of the attribute must be 0
+        let mut nonzero_req = sig_req.clone();
+        nonzero_req.known_messages[g2_idx - 1] = FieldElement::random();
+        assert!(
+            blind_sign_with_g2_attributes(&nonzero_req, &g2_attrs, sigkey, &vk, &params).is_err()
+        );
+
+        // Attribute given twice
+        let twice = vec![g2_attrs[0].clone(), g2_attrs[0].clone()];
+        assert!(blind_sign_with_g2_attributes(&sig_req, &twice, sigkey, &vk, &params).is_err());
+
+        // A hidden attribute or no attribute at the index
+        for idx in &[0, msg_count] {
+            match request_with_g2_attributes(&msgs, 1, &[*idx], &elg_pk, &vk, &params) {
+                Err(e) => match e.kind() {
+                    CoconutErrorKind::InvalidAttributeIndex { idx: i, .. } => assert_eq!(i, *idx),
+                    k => panic!("unexpected error kind {:?}", k),
+                },
+                Ok(_) => panic!("requested with invalid attribute index {}", idx),
+            }
+        }
+
+        // The type attribute of typed params can't be in OtherGroup
+        let params = Params::new_typed(msg_count, "test".as_bytes(), "membership".as_bytes());
+        let (_, vk) = signers_with_verkey(threshold, 3, &params);
+        let msgs = params.typed_messages(FieldElementVector::random(msg_count).as_slice());
+        match request_with_g2_attributes(&msgs, 1, &[msg_count], &elg_pk, &vk, &params) {
+            Err(e) => match e.kind() {
+                CoconutErrorKind::TypeAttributeMismatch { idx } => assert_eq!(idx, msg_count),
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(_) => panic!("requested with the type attribute in OtherGroup"),
+        }
+    }
+}
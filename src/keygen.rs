@@ -1,10 +1,19 @@
 use secret_sharing::shamir_secret_sharing::get_shared_secret;
 use secret_sharing::pedersen_vss::PedersenVSS;
+use secret_sharing::polynomial::Polynomial;
 
-use crate::signature::{Params, Sigkey, Verkey};
+use crate::errors::{CoconutError, CoconutErrorKind};
+use crate::signature::{
+    challenge_v1, seeded_y, BlindSignature, Params, Sigkey, SignatureRequest,
+    SignatureRequestProof, Verkey,
+};
+use crate::utils::{append_u32, append_with_len, ByteReader};
+use crate::{SignatureGroup, FIELD_ELEMENT_SIZE, OTHER_GROUP_SIZE};
 use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
+use amcl_wrapper::group_elem::GroupElement;
 use amcl_wrapper::group_elem_g1::G1;
-use std::collections::HashMap;
+use rand::{CryptoRng, RngCore};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use secret_sharing::pedersen_dvss::PedersenDVSSParticipant;
 
 pub struct Signer {
@@ -13,6 +22,307 @@ pub struct Signer {
     pub verkey: Verkey,
 }
 
+impl Signer {
+    /// Verify the proof of knowledge of a signature request and sign it. The challenge is
+    /// recomputed with `challenge_v1` from the request and the commitments in `proof` rather than
+    /// taken from the user, so the user must have created the proof with the same challenge. The
+    /// user's `SignatureRequestPoK` holds the blindings of the proof and is never sent to the
+    /// signer. Errors with `InvalidProof` if the proof does not verify.
+    pub fn verify_and_sign(
+        &self,
+        sig_req: &SignatureRequest,
+        proof: &SignatureRequestProof,
+        elgamal_pk: &SignatureGroup,
+        params: &Params,
+    ) -> Result<BlindSignature, CoconutError> {
+        let challenge = challenge_v1(params, elgamal_pk, sig_req, &proof.commitments());
+        if !proof.verify(sig_req, elgamal_pk, &challenge, params)? {
+            return Err(CoconutErrorKind::InvalidProof {
+                msg: String::from("proof of knowledge of signature request"),
+            }
+            .into());
+        }
+        BlindSignature::new_typed(sig_req, &self.sigkey, params)
+    }
+}
+
+/// Signer with weight w holding w shares of the signing key. Counts as w signers towards the
+/// threshold. Each share has its own id (evaluation point) distinct from the ids of all other shares.
+pub struct WeightedSigner {
+    pub id: usize,
+    pub shares: Vec<Signer>,
+}
+
+impl WeightedSigner {
+    pub fn weight(&self) -> usize {
+        self.shares.len()
+    }
+
+    /// (share id, verkey) for each share, as needed by `Verkey::aggregate`
+    pub fn verkeys(&self) -> Vec<(usize, &Verkey)> {
+        self.shares.iter().map(|s| (s.id, &s.verkey)).collect()
+    }
+
+    /// Create a blind signature with each share. Returns (share id, blind signature) pairs.
+    pub fn blind_sign(
+        &self,
+        sig_request: &SignatureRequest,
+        params: &Params,
+    ) -> Result<Vec<(usize, BlindSignature)>, CoconutError> {
+        let mut sigs = Vec::with_capacity(self.shares.len());
+        for s in &self.shares {
+            sigs.push((s.id, BlindSignature::new_typed(sig_request, &s.sigkey, params)?));
+        }
+        Ok(sigs)
+    }
+}
+
+/// Version of the byte representation of `KeyShareBundle`, its 1st byte
+pub const KEY_SHARE_BUNDLE_VERSION: u8 = 2;
+
+/// Domain separation tag prefixed to the challenge of the dealer's signature over a
+/// `KeyShareBundle`
+pub const KEY_SHARE_BUNDLE_SIG_TAG: &[u8] = b"coconut/key-share-bundle/signature";
+
+/// Size in bytes of the dealer's signature at the end of `KeyShareBundle::to_bytes`
+pub const KEY_SHARE_BUNDLE_SIG_SIZE: usize = 2 * FIELD_ELEMENT_SIZE;
+
+/// Key pair (sk, G^sk) of the dealer that signs `KeyShareBundle`s, where G is the generator of
+/// SignatureGroup. The generator is fixed rather than g of the params since the params are in the
+/// signed bundle and a forger could pick them.
+pub fn dealer_keygen() -> (FieldElement, SignatureGroup) {
+    elgamal_keygen!(&SignatureGroup::generator())
+}
+
+/// Key material for provisioning a signer node: the signer's id, signing key and verkey, the
+/// threshold, the verkeys of all signers, their aggregate verkey and the params. Serialized with
+/// a signature of the dealer that created the keys so that a node only accepts bundles from it.
+#[derive(Clone, Debug)]
+pub struct KeyShareBundle {
+    pub id: usize,
+    pub threshold: usize,
+    pub sigkey: Sigkey,
+    pub verkey: Verkey,
+    /// Verkeys of all the signers by id, including this signer's
+    pub signer_verkeys: BTreeMap<usize, Verkey>,
+    pub aggregate_verkey: Verkey,
+    pub params: Params,
+}
+
+impl KeyShareBundle {
+    /// Bundle for `signer` among the signers with `signer_verkeys`. The aggregate verkey is
+    /// computed from `signer_verkeys`. Errors like `Verkey::aggregate`.
+    pub fn new(
+        signer: &Signer,
+        threshold: usize,
+        signer_verkeys: Vec<(usize, &Verkey)>,
+        params: &Params,
+    ) -> Result<Self, CoconutError> {
+        let aggregate_verkey = Verkey::aggregate(threshold, signer_verkeys.clone())?;
+        Ok(Self {
+            id: signer.id,
+            threshold,
+            sigkey: signer.sigkey.clone(),
+            verkey: signer.verkey.clone(),
+            signer_verkeys: signer_verkeys
+                .into_iter()
+                .map(|(id, vk)| (id, vk.clone()))
+                .collect(),
+            aggregate_verkey,
+            params: params.clone(),
+        })
+    }
+
+    /// Check that the bundle's verkey is the verkey of its sigkey, (g_tilde^x, g_tilde^y_1, ...,
+    /// g_tilde^y_n), that the keys are for the messages of the params, that the id and
+    /// threshold are non zero, that the bundle's verkey is the verkey of its id among the signers'
+    /// verkeys and that the aggregate verkey is the aggregate of both the first and the last
+    /// `threshold` signers' verkeys. The latter fails if the signers' verkeys are not shares of
+    /// degree `threshold - 1` polynomials, since different sets of signers then give different
+    /// aggregates.
+    pub fn validate(&self) -> Result<(), CoconutError> {
+        if self.id == 0 || self.threshold == 0 {
+            return Err(CoconutErrorKind::GeneralError {
+                msg: String::from("Signer id and threshold must be non zero"),
+            }
+            .into());
+        }
+        let msg_count = self.params.msg_count();
+        for given in vec![
+            self.sigkey.y.len(),
+            self.verkey.Y_tilde.len(),
+            self.aggregate_verkey.Y_tilde.len(),
+        ] {
+            if given != msg_count {
+                return Err(CoconutErrorKind::UnsupportedNoOfMessages {
+                    expected: msg_count,
+                    given,
+                }
+                .into());
+            }
+        }
+        let g_tilde = &self.params.g_tilde;
+        let matches = self.verkey.X_tilde == g_tilde * &self.sigkey.x
+            && self
+                .sigkey
+                .y
+                .iter()
+                .zip(self.verkey.Y_tilde.iter())
+                .all(|(y, Y)| *Y == g_tilde * y);
+        if !matches {
+            return Err(CoconutErrorKind::GeneralError {
+                msg: String::from("Verkey of the bundle is not the verkey of its sigkey"),
+            }
+            .into());
+        }
+        if self.signer_verkeys.get(&self.id) != Some(&self.verkey) {
+            return Err(CoconutErrorKind::GeneralError {
+                msg: format!(
+                    "Verkey of signer {} is not the verkey of the bundle",
+                    self.id
+                ),
+            }
+            .into());
+        }
+        if self.signer_verkeys.len() < self.threshold {
+            return Err(CoconutErrorKind::InsufficientShares {
+                expected: self.threshold,
+                given: self.signer_verkeys.len(),
+            }
+            .into());
+        }
+        let keys = self
+            .signer_verkeys
+            .iter()
+            .map(|(id, vk)| (*id, vk))
+            .collect::<Vec<(usize, &Verkey)>>();
+        for subset in vec![
+            keys[..self.threshold].to_vec(),
+            keys[keys.len() - self.threshold..].to_vec(),
+        ] {
+            if Verkey::aggregate(self.threshold, subset)? != self.aggregate_verkey {
+                return Err(CoconutErrorKind::GeneralError {
+                    msg: String::from(
+                        "Aggregate verkey is not the aggregate of the signers' verkeys",
+                    ),
+                }
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Byte representation is version || id || threshold || params || sigkey || verkey ||
+    /// signer count || (id || verkey) for each signer || aggregate verkey || signature, where the
+    /// version is 1 byte, id, threshold and count are 4 byte big-endian integers, the signature is
+    /// the dealer's Schnorr signature (c, s) over all the preceding bytes with `dealer_sk` and the
+    /// rest are each prefixed with their length as a 4 byte big-endian integer. The sigkey is not
+    /// encrypted so the bytes must be as protected as the sigkey.
+    pub fn to_bytes(&self, dealer_sk: &FieldElement) -> Vec<u8> {
+        let mut bytes = vec![KEY_SHARE_BUNDLE_VERSION];
+        append_u32(&mut bytes, self.id);
+        append_u32(&mut bytes, self.threshold);
+        append_with_len(&mut bytes, &self.params.to_bytes());
+        append_with_len(&mut bytes, &self.sigkey.to_bytes());
+        append_with_len(&mut bytes, &self.verkey.to_bytes());
+        append_u32(&mut bytes, self.signer_verkeys.len());
+        for (id, vk) in &self.signer_verkeys {
+            append_u32(&mut bytes, *id);
+            append_with_len(&mut bytes, &vk.to_bytes());
+        }
+        append_with_len(&mut bytes, &self.aggregate_verkey.to_bytes());
+        let mut signature = dealer_sign(&bytes, dealer_sk);
+        bytes.append(&mut signature);
+        bytes
+    }
+
+    /// Errors with `InvalidProof` if the bytes are not signed by the dealer with public key
+    /// `dealer_pk` and on an unknown version. Does not `validate` the bundle.
+    pub fn from_bytes(bytes: &[u8], dealer_pk: &SignatureGroup) -> Result<Self, CoconutError> {
+        if bytes.len() < KEY_SHARE_BUNDLE_SIG_SIZE {
+            return Err(CoconutErrorKind::InvalidByteLength {
+                expected: KEY_SHARE_BUNDLE_SIG_SIZE,
+                given: bytes.len(),
+            }
+            .into());
+        }
+        let (body, signature) = bytes.split_at(bytes.len() - KEY_SHARE_BUNDLE_SIG_SIZE);
+        if !dealer_verify(body, signature, dealer_pk)? {
+            return Err(CoconutErrorKind::InvalidProof {
+                msg: String::from("dealer signature of key share bundle"),
+            }
+            .into());
+        }
+        let mut reader = ByteReader::new(body);
+        let version = reader.take(1)?[0];
+        if version != KEY_SHARE_BUNDLE_VERSION {
+            return Err(CoconutErrorKind::GeneralError {
+                msg: format!("Unknown key share bundle version {}", version),
+            }
+            .into());
+        }
+        let id = reader.read_u32()?;
+        let threshold = reader.read_u32()?;
+        let params = Params::from_bytes(reader.read_with_len()?)?;
+        let sigkey = Sigkey::from_bytes(reader.read_with_len()?)?;
+        let verkey = Verkey::from_bytes(reader.read_with_len()?)?;
+        let signer_count = reader.read_count(4 + 4 + OTHER_GROUP_SIZE)?;
+        let mut signer_verkeys = BTreeMap::new();
+        for _ in 0..signer_count {
+            let signer_id = reader.read_u32()?;
+            let vk = Verkey::from_bytes(reader.read_with_len()?)?;
+            if signer_verkeys.insert(signer_id, vk).is_some() {
+                return Err(CoconutErrorKind::DuplicateSignerId { id: signer_id }.into());
+            }
+        }
+        let aggregate_verkey = Verkey::from_bytes(reader.read_with_len()?)?;
+        reader.finish()?;
+        Ok(Self {
+            id,
+            threshold,
+            sigkey,
+            verkey,
+            signer_verkeys,
+            aggregate_verkey,
+            params,
+        })
+    }
+}
+
+/// Challenge of the dealer's signature with public key `pk` and commitment `r` over `bytes`
+fn dealer_challenge(pk: &SignatureGroup, r: &SignatureGroup, bytes: &[u8]) -> FieldElement {
+    let mut challenge_bytes = KEY_SHARE_BUNDLE_SIG_TAG.to_vec();
+    challenge_bytes.append(&mut pk.to_bytes());
+    challenge_bytes.append(&mut r.to_bytes());
+    challenge_bytes.extend_from_slice(bytes);
+    FieldElement::from_msg_hash(&challenge_bytes)
+}
+
+/// Schnorr signature c || s over `bytes` with R = G^k, c = H(pk || R || bytes) and s = k - c.sk
+fn dealer_sign(bytes: &[u8], dealer_sk: &FieldElement) -> Vec<u8> {
+    let g = SignatureGroup::generator();
+    let k = FieldElement::random();
+    let c = dealer_challenge(&(&g * dealer_sk), &(&g * &k), bytes);
+    let s = &k - &(&c * dealer_sk);
+    let mut signature = c.to_bytes();
+    signature.append(&mut s.to_bytes());
+    signature
+}
+
+/// Check a signature of `dealer_sign` by recomputing R = G^s.pk^c and then c
+fn dealer_verify(
+    bytes: &[u8],
+    signature: &[u8],
+    dealer_pk: &SignatureGroup,
+) -> Result<bool, CoconutError> {
+    let mut reader = ByteReader::new(signature);
+    let c = reader.read_field_element()?;
+    let s = reader.read_field_element()?;
+    reader.finish()?;
+    let r = &(&SignatureGroup::generator() * &s) + &(dealer_pk * &c);
+    Ok(dealer_challenge(dealer_pk, &r, bytes) == c)
+}
+
 /// Takes shares for x and y and generate signing and verification keys
 fn keygen_from_shares(
     num_signers: usize,
@@ -49,6 +359,8 @@ fn keygen_from_shares(
 /// aggregate secret keys and can create signatures.
 /// Outputs 3 items, first 2 are shared secrets and should be destroyed.
 /// The last vector contains the keys, 1 item corresponding to each signer.
+/// With threshold 1 every signer gets the aggregate keys, so threshold 1 of total 1 gives a
+/// standard non-threshold PS credential from a single issuer.
 /// "TTPKeyGen" from paper
 pub fn trusted_party_SSS_keygen(
     threshold: usize,
@@ -70,6 +382,204 @@ pub fn trusted_party_SSS_keygen(
     )
 }
 
+/// Same as `trusted_party_SSS_keygen` but the secrets and the polynomials sharing them are sampled
+/// from `rng`. A seeded `rng` gives the same keys every time. Errors with `InvalidThreshold` if
+/// `threshold` is 0 or more than `total`.
+pub fn trusted_party_SSS_keygen_with_rng<R: RngCore + CryptoRng>(
+    threshold: usize,
+    total: usize,
+    params: &Params,
+    rng: &mut R,
+) -> Result<(FieldElement, FieldElementVector, Vec<Signer>), CoconutError> {
+    if threshold == 0 || threshold > total {
+        return Err(CoconutErrorKind::InvalidThreshold { threshold, total }.into());
+    }
+    let x_poly = random_polynomial(threshold - 1, rng);
+    let y_polys = (0..params.msg_count())
+        .map(|_| random_polynomial(threshold - 1, rng))
+        .collect::<Vec<Polynomial>>();
+    // The secrets are the polynomials evaluated at 0
+    let zero = FieldElement::zero();
+    let secret_x = x_poly.eval(&zero);
+    let secret_y = y_polys
+        .iter()
+        .map(|p| p.eval(&zero))
+        .collect::<Vec<FieldElement>>()
+        .into();
+    let ids = (1..=total).collect::<Vec<usize>>();
+    let signers = trusted_party_keygen_from_polynomials(threshold, x_poly, y_polys, &ids, params)?;
+    Ok((secret_x, secret_y, signers))
+}
+
+/// Keys of a single signer with a random x and the y's derived from `y_seed` with `seeded_y` so
+/// that the verkey can be stored compressed, see `Verkey::compress`. The seed must be kept as
+/// secret as the signing key.
+pub fn keygen_with_seeded_y(y_seed: &[u8], params: &Params) -> (Sigkey, Verkey) {
+    let x = FieldElement::random();
+    let y = seeded_y(y_seed, params.msg_count());
+    let verkey = Verkey::from_secrets(&x, &y, params);
+    (Sigkey { x, y }, verkey)
+}
+
+/// Polynomial of degree `degree` with coefficients sampled from `rng`
+fn random_polynomial<R: RngCore + CryptoRng>(degree: usize, rng: &mut R) -> Polynomial {
+    let mut poly = Polynomial::new(degree);
+    for i in 0..=degree {
+        poly.coefficients_mut()[i] = FieldElement::random_using_rng(rng);
+    }
+    poly
+}
+
+/// Same as `PedersenVSS::deal` but the polynomials sharing the secret and its blinding are sampled
+/// from `rng`. Returns the secret, the blinding, the commitments g^a_i.h^b_i to the coefficients
+/// by index i and the shares of the secret and of the blinding by id 1 to `total`.
+fn pedersen_vss_deal_with_rng<R: RngCore + CryptoRng>(
+    threshold: usize,
+    total: usize,
+    g: &G1,
+    h: &G1,
+    rng: &mut R,
+) -> (
+    FieldElement,
+    FieldElement,
+    HashMap<usize, G1>,
+    HashMap<usize, FieldElement>,
+    HashMap<usize, FieldElement>,
+) {
+    assert!(threshold > 0 && threshold <= total);
+    let s_poly = random_polynomial(threshold - 1, rng);
+    let t_poly = random_polynomial(threshold - 1, rng);
+    let comm_coeffs = (0..threshold)
+        .map(|i| {
+            let a = &s_poly.coefficients()[i];
+            let b = &t_poly.coefficients()[i];
+            (i, &(g * a) + &(h * b))
+        })
+        .collect::<HashMap<usize, G1>>();
+    let mut s_shares = HashMap::with_capacity(total);
+    let mut t_shares = HashMap::with_capacity(total);
+    for id in 1..=total {
+        let x = FieldElement::from(id as u64);
+        s_shares.insert(id, s_poly.eval(&x));
+        t_shares.insert(id, t_poly.eval(&x));
+    }
+    let zero = FieldElement::zero();
+    (
+        s_poly.eval(&zero),
+        t_poly.eval(&zero),
+        comm_coeffs,
+        s_shares,
+        t_shares,
+    )
+}
+
+/// Keygen by trusted party from existing polynomials sharing x and each y, like when migrating from
+/// another threshold system. Signer with id i gets the polynomials evaluated at i. Errors if a
+/// polynomial is not of degree `threshold - 1`, there is not 1 polynomial for each message, there
+/// are less than `threshold` ids or an id is 0 or repeated.
+pub fn trusted_party_keygen_from_polynomials(
+    threshold: usize,
+    x_poly: Polynomial,
+    y_polys: Vec<Polynomial>,
+    ids: &[usize],
+    params: &Params,
+) -> Result<Vec<Signer>, CoconutError> {
+    if y_polys.len() != params.msg_count() {
+        return Err(CoconutErrorKind::UnsupportedNoOfMessages {
+            expected: params.msg_count(),
+            given: y_polys.len(),
+        }
+        .into());
+    }
+    if threshold == 0
+        || std::iter::once(&x_poly)
+            .chain(y_polys.iter())
+            .any(|p| p.degree() != threshold - 1)
+    {
+        return Err(CoconutErrorKind::GeneralError {
+            msg: format!(
+                "Polynomials must be of degree {}",
+                threshold.saturating_sub(1)
+            ),
+        }
+        .into());
+    }
+    if ids.len() < threshold {
+        return Err(CoconutErrorKind::InsufficientShares {
+            expected: threshold,
+            given: ids.len(),
+        }
+        .into());
+    }
+    let mut seen = HashSet::new();
+    for id in ids {
+        if *id == 0 {
+            // Evaluation at 0 is the secret
+            return Err(CoconutErrorKind::GeneralError {
+                msg: String::from("Signer id cannot be 0"),
+            }
+            .into());
+        }
+        if !seen.insert(*id) {
+            return Err(CoconutErrorKind::DuplicateSignerId { id: *id }.into());
+        }
+    }
+    let mut signers = Vec::with_capacity(ids.len());
+    for id in ids {
+        let i = FieldElement::from(*id as u64);
+        let x_i = x_poly.eval(&i);
+        let y_i = y_polys
+            .iter()
+            .map(|p| p.eval(&i))
+            .collect::<Vec<FieldElement>>();
+        let verkey = Verkey {
+            X_tilde: &params.g_tilde * &x_i,
+            Y_tilde: y_i.iter().map(|y| &params.g_tilde * y).collect(),
+        };
+        signers.push(Signer {
+            id: *id,
+            sigkey: Sigkey { x: x_i, y: y_i },
+            verkey,
+        });
+    }
+    Ok(signers)
+}
+
+/// Keygen done by trusted party using Shamir secret sharing where signer i gets `weights[i]`
+/// shares. Signers have ids 1, 2, ... and the shares have ids 1, 2, ... across signers in order.
+/// Errors if the total weight is less than the threshold since the key could never be used.
+pub fn trusted_party_SSS_keygen_weighted(
+    threshold: usize,
+    weights: &[usize],
+    params: &Params,
+) -> Result<(FieldElement, FieldElementVector, Vec<WeightedSigner>), CoconutError> {
+    let total: usize = weights.iter().sum();
+    if total < threshold {
+        return Err(CoconutErrorKind::InsufficientShares {
+            expected: threshold,
+            given: total,
+        }
+        .into());
+    }
+    if weights.contains(&0) {
+        return Err(CoconutErrorKind::GeneralError {
+            msg: String::from("Weight of a signer cannot be 0"),
+        }
+        .into());
+    }
+    let (secret_x, secret_y, mut shares) = trusted_party_SSS_keygen(threshold, total, params);
+    let mut signers = vec![];
+    for (i, w) in weights.iter().enumerate() {
+        let rest = shares.split_off(*w);
+        signers.push(WeightedSigner {
+            id: i + 1,
+            shares,
+        });
+        shares = rest;
+    }
+    Ok((secret_x, secret_y, signers))
+}
+
 /// Keygen done by trusted party using Pedersen verifiable secret sharing.
 pub fn trusted_party_PVSS_keygen(
     threshold: usize,
@@ -121,6 +631,59 @@ pub fn trusted_party_PVSS_keygen(
     )
 }
 
+/// Same as `trusted_party_PVSS_keygen` but the polynomials sharing the secrets and their blindings
+/// are sampled from `rng`. A seeded `rng` gives the same keys and commitments every time.
+pub fn trusted_party_PVSS_keygen_with_rng<R: RngCore + CryptoRng>(
+    threshold: usize,
+    total: usize,
+    params: &Params,
+    g: &G1,
+    h: &G1,
+    rng: &mut R,
+) -> (
+    FieldElement,       // shared secret for x
+    FieldElementVector, // shared secret for each y
+    Vec<Signer>,
+    FieldElement,       // blinding for x
+    HashMap<usize, G1>, // commitment to coefficients for polynomial for x
+    HashMap<usize, FieldElement>,
+    HashMap<usize, FieldElement>,
+    FieldElementVector,      // blindings for each y
+    Vec<HashMap<usize, G1>>, // commitment to coefficients for polynomial for each y
+    Vec<HashMap<usize, FieldElement>>,
+    Vec<HashMap<usize, FieldElement>>,
+) {
+    let (secret_x, secret_x_t, comm_coeff_x, x_shares, x_t_shares) =
+        pedersen_vss_deal_with_rng(threshold, total, g, h, rng);
+    let mut y = vec![];
+    let mut secret_y = FieldElementVector::with_capacity(params.msg_count());
+    let mut secret_y_t = FieldElementVector::with_capacity(params.msg_count());
+    let mut comm_coeff_y_vec = vec![];
+    let mut y_t = vec![];
+    for _ in 0..params.msg_count() {
+        let (sec_y, sec_y_t, comm_coeff_y, y_shares, y_t_shares) =
+            pedersen_vss_deal_with_rng(threshold, total, g, h, rng);
+        secret_y.push(sec_y);
+        secret_y_t.push(sec_y_t);
+        comm_coeff_y_vec.push(comm_coeff_y);
+        y.push(y_shares);
+        y_t.push(y_t_shares);
+    }
+    (
+        secret_x,
+        secret_y,
+        keygen_from_shares(total, x_shares.clone(), y.clone(), params),
+        secret_x_t,
+        comm_coeff_x,
+        x_shares,
+        x_t_shares,
+        secret_y_t,
+        comm_coeff_y_vec,
+        y,
+        y_t,
+    )
+}
+
 /// Create participants that take part in a decentralized secret sharing and perform the secret sharing.
 #[cfg(test)]
 pub fn share_secret_for_testing(
@@ -209,9 +772,13 @@ mod tests {
     use super::*;
     use secret_sharing::polynomial::Polynomial;
     use secret_sharing::shamir_secret_sharing::reconstruct_secret;
+    use crate::lagrange::LagrangeCoefficients;
+    use crate::signature::SignatureRequestPoK;
     use crate::OtherGroupVec;
     use amcl_wrapper::group_elem::GroupElementVector;
-    use std::collections::{HashMap, HashSet};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::collections::{BTreeSet, HashMap};
 
     #[test]
     fn test_keygen() {
@@ -264,10 +831,11 @@ mod tests {
             .iter()
             .take(threshold)
             .map(|s| s.id)
-            .collect::<HashSet<usize>>();
+            .collect::<BTreeSet<usize>>();
+        let coeffs = LagrangeCoefficients::new(&signer_ids);
 
         for signer in signers.into_iter().take(threshold) {
-            let l = Polynomial::lagrange_basis_at_0(signer_ids.clone(), signer.id);
+            let l = &coeffs[signer.id];
             recon_X_tilde_bases.push(signer.verkey.X_tilde.clone());
             recon_X_tilde_exps.push(l.clone());
 
@@ -354,6 +922,64 @@ mod tests {
         check_reconstructed_keys(threshold, msg_count, secret_x, secret_y, &signers, &params);
     }
 
+    #[test]
+    fn test_keygen_verifiable_secret_sharing_with_rng() {
+        let threshold = 3;
+        let total = 5;
+        let msg_count = 4;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (g, h) = PedersenVSS::gens("testPVSS".as_bytes());
+
+        let run = |seed: u64| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            trusted_party_PVSS_keygen_with_rng(threshold, total, &params, &g, &h, &mut rng)
+        };
+        let (
+            secret_x,
+            secret_y,
+            signers,
+            _,
+            comm_coeff_x,
+            x_shares,
+            x_t_shares,
+            _,
+            comm_coeff_y,
+            y_shares,
+            y_t_shares,
+        ) = run(7);
+        for i in 1..=total {
+            assert!(PedersenVSS::verify_share(
+                threshold,
+                i,
+                (&x_shares[&i], &x_t_shares[&i]),
+                &comm_coeff_x,
+                &g,
+                &h,
+            ));
+            for j in 0..msg_count {
+                assert!(PedersenVSS::verify_share(
+                    threshold,
+                    i,
+                    (&y_shares[j][&i], &y_t_shares[j][&i]),
+                    &comm_coeff_y[j],
+                    &g,
+                    &h,
+                ));
+            }
+        }
+
+        // Same seed gives the same keys and commitments, a different seed different ones
+        let again = run(7);
+        assert_eq!(again.0, secret_x);
+        assert_eq!(again.4, comm_coeff_x);
+        for (s_1, s_2) in signers.iter().zip(again.2.iter()) {
+            assert_eq!(s_1.verkey.to_bytes(), s_2.verkey.to_bytes());
+        }
+        assert_ne!(run(8).0, secret_x);
+
+        check_reconstructed_keys(threshold, msg_count, secret_x, secret_y, &signers, &params);
+    }
+
     #[test]
     fn test_keygen_reconstruction_decentralized_verifiable_secret_sharing() {
         let threshold = 3;
@@ -367,4 +993,259 @@ mod tests {
 
         check_reconstructed_keys(threshold, msg_count, secret_x, secret_y, &signers, &params);
     }
+
+    #[test]
+    fn test_verify_and_sign() {
+        let msg_count = 4;
+        let count_hidden = 2;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (_, _, signers) = trusted_party_SSS_keygen(2, 3, &params);
+        let msgs = FieldElementVector::random(msg_count);
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        let (sig_req, randomness) =
+            SignatureRequest::new(&msgs, count_hidden, &elg_pk, &params).unwrap();
+        let hidden_msgs: FieldElementVector = msgs
+            .iter()
+            .take(count_hidden)
+            .map(|m| m.clone())
+            .collect::<Vec<FieldElement>>()
+            .into();
+
+        let pok = SignatureRequestPoK::init(&sig_req, &elg_pk, &params);
+        let challenge = challenge_v1(&params, &elg_pk, &sig_req, &pok.commitments());
+        let proof = pok
+            .clone()
+            .gen_proof(&hidden_msgs, randomness.clone(), &elg_sk, &challenge)
+            .unwrap();
+        let sig = signers[0]
+            .verify_and_sign(&sig_req, &proof, &elg_pk, &params)
+            .unwrap()
+            .unblind(&elg_sk);
+        assert!(sig
+            .verify(msgs.as_slice(), &signers[0].verkey, &params)
+            .unwrap());
+
+        // Proof created with a challenge derived differently than `challenge_v1`
+        let pok = SignatureRequestPoK::init(&sig_req, &elg_pk, &params);
+        let challenge = FieldElement::from_msg_hash(&pok.to_bytes());
+        let proof = pok
+            .clone()
+            .gen_proof(&hidden_msgs, randomness, &elg_sk, &challenge)
+            .unwrap();
+        match signers[0].verify_and_sign(&sig_req, &proof, &elg_pk, &params) {
+            Err(e) => match e.kind() {
+                CoconutErrorKind::InvalidProof { .. } => (),
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(_) => panic!("signed request with proof for a different challenge"),
+        }
+    }
+
+    #[test]
+    fn test_key_share_bundle() {
+        let threshold = 2;
+        let msg_count = 3;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (_, _, signers) = trusted_party_SSS_keygen(threshold, 3, &params);
+        let verkeys = signers
+            .iter()
+            .map(|s| (s.id, &s.verkey))
+            .collect::<Vec<(usize, &Verkey)>>();
+        let vk = Verkey::aggregate(threshold, verkeys.clone()).unwrap();
+        let (dealer_sk, dealer_pk) = dealer_keygen();
+
+        let bundle = KeyShareBundle::new(&signers[1], threshold, verkeys.clone(), &params).unwrap();
+        bundle.validate().unwrap();
+        let bytes = bundle.to_bytes(&dealer_sk);
+        assert_eq!(bytes[0], KEY_SHARE_BUNDLE_VERSION);
+        let parsed = KeyShareBundle::from_bytes(&bytes, &dealer_pk).unwrap();
+        parsed.validate().unwrap();
+        assert_eq!(parsed.id, signers[1].id);
+        assert_eq!(parsed.threshold, threshold);
+        assert_eq!(parsed.sigkey.x, signers[1].sigkey.x);
+        assert_eq!(parsed.aggregate_verkey, vk);
+        assert_eq!(parsed.signer_verkeys.len(), signers.len());
+        assert_eq!(parsed.params, params);
+        assert_eq!(
+            parsed.to_bytes(&dealer_sk)[..bytes.len() - KEY_SHARE_BUNDLE_SIG_SIZE],
+            bytes[..bytes.len() - KEY_SHARE_BUNDLE_SIG_SIZE]
+        );
+
+        // Signed by another dealer or modified after signing
+        let (other_sk, other_pk) = dealer_keygen();
+        let expect_invalid_signature =
+            |bytes: &[u8], pk: &SignatureGroup| match KeyShareBundle::from_bytes(bytes, pk)
+                .unwrap_err()
+                .kind()
+            {
+                CoconutErrorKind::InvalidProof { .. } => (),
+                k => panic!("unexpected error kind {:?}", k),
+            };
+        expect_invalid_signature(&bytes, &other_pk);
+        expect_invalid_signature(&bundle.to_bytes(&other_sk), &dealer_pk);
+        let mut modified = bytes.clone();
+        modified[4] ^= 1;
+        expect_invalid_signature(&modified, &dealer_pk);
+
+        // Verkey of another signer
+        let mut mismatched = bundle.clone();
+        mismatched.verkey = signers[0].verkey.clone();
+        assert!(mismatched.validate().is_err());
+        assert!(
+            KeyShareBundle::from_bytes(&mismatched.to_bytes(&dealer_sk), &dealer_pk)
+                .unwrap()
+                .validate()
+                .is_err()
+        );
+        let mut mismatched = bundle.clone();
+        mismatched.sigkey.y[2] = FieldElement::random();
+        assert!(mismatched.validate().is_err());
+        let mut other_params = bundle.clone();
+        other_params.params = Params::new(msg_count + 1, "test".as_bytes());
+        match other_params.validate().unwrap_err().kind() {
+            CoconutErrorKind::UnsupportedNoOfMessages { .. } => (),
+            k => panic!("unexpected error kind {:?}", k),
+        }
+
+        // Aggregate verkey of other signers, verkey of another signer among the signers' verkeys
+        // and a threshold above the number of signers
+        let (_, _, other_signers) = trusted_party_SSS_keygen(threshold, 3, &params);
+        let mut other_aggregate = bundle.clone();
+        other_aggregate.aggregate_verkey = Verkey::aggregate(
+            threshold,
+            other_signers
+                .iter()
+                .map(|s| (s.id, &s.verkey))
+                .collect::<Vec<(usize, &Verkey)>>(),
+        )
+        .unwrap();
+        assert!(other_aggregate.validate().is_err());
+        let mut inconsistent = bundle.clone();
+        inconsistent
+            .signer_verkeys
+            .insert(signers[2].id, other_signers[2].verkey.clone());
+        assert!(inconsistent.validate().is_err());
+        let mut high_threshold = bundle.clone();
+        high_threshold.threshold = signers.len() + 1;
+        match high_threshold.validate().unwrap_err().kind() {
+            CoconutErrorKind::InsufficientShares { .. } => (),
+            k => panic!("unexpected error kind {:?}", k),
+        }
+
+        // Unknown version, truncated and extended bytes
+        let mut other_version = bundle.to_bytes(&dealer_sk);
+        other_version[0] = KEY_SHARE_BUNDLE_VERSION + 1;
+        assert!(KeyShareBundle::from_bytes(&other_version, &dealer_pk).is_err());
+        assert!(KeyShareBundle::from_bytes(&bytes[0..bytes.len() - 1], &dealer_pk).is_err());
+        let mut extended = bytes.clone();
+        extended.push(0);
+        assert!(KeyShareBundle::from_bytes(&extended, &dealer_pk).is_err());
+    }
+
+    #[test]
+    fn test_keygen_from_polynomials() {
+        let threshold = 3;
+        let msg_count = 3;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let x_poly = Polynomial::random(threshold - 1);
+        let y_polys = (0..msg_count)
+            .map(|_| Polynomial::random(threshold - 1))
+            .collect::<Vec<Polynomial>>();
+        let zero = FieldElement::zero();
+        let secret_x = x_poly.eval(&zero);
+        let secret_y = y_polys
+            .iter()
+            .map(|p| p.eval(&zero))
+            .collect::<Vec<FieldElement>>();
+
+        // Ids need not be contiguous
+        let ids = [2, 3, 7, 11];
+        let signers = trusted_party_keygen_from_polynomials(
+            threshold,
+            x_poly.clone(),
+            y_polys.clone(),
+            &ids,
+            &params,
+        )
+        .unwrap();
+        assert_eq!(
+            signers.iter().map(|s| s.id).collect::<Vec<usize>>(),
+            ids.to_vec()
+        );
+        let expected_vk = Verkey::from_secrets(&secret_x, &secret_y, &params);
+        let aggr_vk = Verkey::aggregate(
+            threshold,
+            signers
+                .iter()
+                .skip(1)
+                .map(|s| (s.id, &s.verkey))
+                .collect::<Vec<(usize, &Verkey)>>(),
+        )
+        .unwrap();
+        assert_eq!(aggr_vk.X_tilde, expected_vk.X_tilde);
+        assert_eq!(aggr_vk.Y_tilde, expected_vk.Y_tilde);
+
+        // Wrong degree
+        assert!(trusted_party_keygen_from_polynomials(
+            threshold + 1,
+            x_poly.clone(),
+            y_polys.clone(),
+            &ids,
+            &params
+        )
+        .is_err());
+
+        // Polynomials for a different number of messages
+        match trusted_party_keygen_from_polynomials(
+            threshold,
+            x_poly.clone(),
+            y_polys[1..].to_vec(),
+            &ids,
+            &params,
+        ) {
+            Err(e) => match e.kind() {
+                CoconutErrorKind::UnsupportedNoOfMessages { expected, given } => {
+                    assert_eq!(expected, msg_count);
+                    assert_eq!(given, msg_count - 1);
+                }
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(_) => panic!("keygen with too few polynomials"),
+        }
+
+        // Too few, repeated and 0 ids
+        for bad_ids in vec![vec![1, 2], vec![1, 2, 2], vec![0, 1, 2]] {
+            assert!(trusted_party_keygen_from_polynomials(
+                threshold,
+                x_poly.clone(),
+                y_polys.clone(),
+                &bad_ids,
+                &params
+            )
+            .is_err());
+        }
+    }
+
+    #[test]
+    fn test_keygen_with_rng_invalid_threshold() {
+        let params = Params::new(2, "test".as_bytes());
+        let mut rng = StdRng::seed_from_u64(7);
+        for &(threshold, total) in &[(0, 3), (4, 3), (0, 0)] {
+            match trusted_party_SSS_keygen_with_rng(threshold, total, &params, &mut rng) {
+                Err(e) => match e.kind() {
+                    CoconutErrorKind::InvalidThreshold {
+                        threshold: t,
+                        total: n,
+                    } => {
+                        assert_eq!(t, threshold);
+                        assert_eq!(n, total);
+                    }
+                    k => panic!("unexpected error kind {:?}", k),
+                },
+                Ok(_) => panic!("keygen with threshold {} of {}", threshold, total),
+            }
+        }
+        let (_, _, signers) = trusted_party_SSS_keygen_with_rng(3, 3, &params, &mut rng).unwrap();
+        assert_eq!(signers.len(), 3);
+    }
 }
@@ -0,0 +1,392 @@
+// Lagrange coefficients at 0 for aggregating the signatures, verkeys or keys of a set of signers.
+// The coefficient of signer i among the signers with ids x_1, ..., x_t is
+// l_i = prod_{j != i} x_j / (x_j - x_i), which computed for every signer on its own costs O(t^2).
+// With P(X) = (X - x_1)...(X - x_t), the denominator is (-1)^(t-1).P'(x_i) and the coefficient
+// is l_i = -P(0) / (x_i.P'(x_i)). For large thresholds P is built with a subproduct tree and P' is
+// evaluated at all x_i with a remainder tree, both using FFT multiplication over the roots of
+// unity of the scalar field, in O(t.log^2(t)).
+
+use amcl_wrapper::field_elem::FieldElement;
+use secret_sharing::polynomial::Polynomial;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Index;
+
+/// Thresholds above which `LagrangeCoefficients::new` uses the subproduct tree
+pub const LAGRANGE_FFT_CUTOFF: usize = 128;
+
+/// Polynomials with both factors longer than this are multiplied with FFT
+const SCHOOLBOOK_MAX_LEN: usize = 32;
+
+/// 2^TWO_ADICITY divides the order of the multiplicative group of the scalar field
+const TWO_ADICITY: usize = 32;
+
+/// Generator of the multiplicative group of the scalar field
+const MULTIPLICATIVE_GENERATOR: u64 = 7;
+
+/// Lagrange coefficients at 0 of all signers of a set, by signer id
+#[derive(Clone, Debug)]
+pub struct LagrangeCoefficients {
+    coeffs: BTreeMap<usize, FieldElement>,
+}
+
+impl LagrangeCoefficients {
+    /// Coefficients of the signers with ids `signer_ids`, with the subproduct tree when there are
+    /// more than `LAGRANGE_FFT_CUTOFF` signers
+    pub fn new(signer_ids: &BTreeSet<usize>) -> Self {
+        Self::with_cutoff(signer_ids, LAGRANGE_FFT_CUTOFF)
+    }
+
+    /// Same as `new` but with the subproduct tree when there are more than `cutoff` signers. Both
+    /// ways give the same coefficients. The subproduct tree is not used if an id is 0.
+    pub fn with_cutoff(signer_ids: &BTreeSet<usize>, cutoff: usize) -> Self {
+        let coeffs =
+            if signer_ids.len() > cutoff && signer_ids.len() > 1 && !signer_ids.contains(&0) {
+                subproduct_tree_coefficients(signer_ids)
+            } else {
+                signer_ids
+                    .iter()
+                    .map(|id| (*id, lagrange_basis_at_0(signer_ids, *id)))
+                    .collect()
+            };
+        Self { coeffs }
+    }
+
+    /// Coefficient of the signer with id `id`. None if not one of the signers.
+    pub fn get(&self, id: usize) -> Option<&FieldElement> {
+        self.coeffs.get(&id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.coeffs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.coeffs.is_empty()
+    }
+}
+
+/// Panics if `id` is not one of the signers
+impl Index<usize> for LagrangeCoefficients {
+    type Output = FieldElement;
+
+    fn index(&self, id: usize) -> &FieldElement {
+        &self.coeffs[&id]
+    }
+}
+
+/// Lagrange coefficient at 0 of signer `id` among `signer_ids`. The ids are kept ordered so that
+/// aggregation doesn't depend on the iteration order of a hash set.
+pub(crate) fn lagrange_basis_at_0(signer_ids: &BTreeSet<usize>, id: usize) -> FieldElement {
+    // The basis of a single signer is the empty product, 1, whatever its id
+    if signer_ids.len() == 1 {
+        return FieldElement::one();
+    }
+    Polynomial::lagrange_basis_at_0(signer_ids.iter().cloned().collect(), id)
+}
+
+/// l_i = -P(0) / (x_i.P'(x_i)) for all ids, which must be non zero and at least 2
+fn subproduct_tree_coefficients(signer_ids: &BTreeSet<usize>) -> BTreeMap<usize, FieldElement> {
+    let roots = RootsOfUnity::new();
+    let xs = signer_ids
+        .iter()
+        .map(|id| FieldElement::from(*id as u64))
+        .collect::<Vec<FieldElement>>();
+
+    // Leaves are X - x_i, each node is the product of its children. A node without a sibling is
+    // carried up to the next level as it is.
+    let mut tree = vec![xs
+        .iter()
+        .map(|x| vec![-x.clone(), FieldElement::one()])
+        .collect::<Vec<Vec<FieldElement>>>()];
+    while tree[tree.len() - 1].len() > 1 {
+        let level = tree[tree.len() - 1]
+            .chunks(2)
+            .map(|pair| match pair {
+                [a, b] => roots.mul(a, b),
+                _ => pair[0].clone(),
+            })
+            .collect();
+        tree.push(level);
+    }
+    let p = &tree[tree.len() - 1][0];
+    let p_at_0 = p[0].clone();
+
+    // P' mod each node going down, at the leaves that is P'(x_i)
+    let p_derivative = p
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(i, c)| c * &FieldElement::from(i as u64))
+        .collect::<Vec<FieldElement>>();
+    let mut rems = vec![p_derivative];
+    for level in tree.iter().rev().skip(1) {
+        rems = level
+            .iter()
+            .enumerate()
+            .map(|(i, node)| roots.rem(&rems[i / 2], node))
+            .collect();
+    }
+
+    // x_i.P'(x_i) inverted all at once
+    let denominators = xs
+        .iter()
+        .zip(rems.iter())
+        .map(|(x, r)| x * &r[0])
+        .collect::<Vec<FieldElement>>();
+    let minus_p_at_0 = -p_at_0;
+    signer_ids
+        .iter()
+        .zip(batch_inverse(&denominators).iter())
+        .map(|(id, inv)| (*id, &minus_p_at_0 * inv))
+        .collect()
+}
+
+/// Inverses of all of `elems`, which must be non zero, with a single inversion
+fn batch_inverse(elems: &[FieldElement]) -> Vec<FieldElement> {
+    // prefix[i] = elems[0]...elems[i-1]
+    let mut prefix = Vec::with_capacity(elems.len() + 1);
+    prefix.push(FieldElement::one());
+    for e in elems {
+        let last = &prefix[prefix.len() - 1] * e;
+        prefix.push(last);
+    }
+    // inv = (elems[0]...elems[i])^-1 going down from the last i
+    let mut inv = prefix[elems.len()].inverse();
+    let mut inverses = Vec::with_capacity(elems.len());
+    for (e, p) in elems.iter().zip(prefix[..elems.len()].iter()).rev() {
+        inverses.push(&inv * p);
+        inv = &inv * e;
+    }
+    inverses.reverse();
+    inverses
+}
+
+/// base^exp for exp as big-endian bytes
+fn pow_be_bytes(base: &FieldElement, exp: &[u8]) -> FieldElement {
+    let mut acc = FieldElement::one();
+    for byte in exp {
+        for i in (0..8).rev() {
+            acc = &acc * &acc;
+            if (byte >> i) & 1 == 1 {
+                acc = &acc * base;
+            }
+        }
+    }
+    acc
+}
+
+/// Primitive roots of unity of the scalar field of order 2^k for k up to `TWO_ADICITY`, for
+/// multiplying and dividing polynomials given by their coefficients in increasing order of degree
+struct RootsOfUnity {
+    /// roots[k] has order 2^k
+    roots: Vec<FieldElement>,
+}
+
+impl RootsOfUnity {
+    fn new() -> Self {
+        // The order of the multiplicative group is q - 1 = 2^TWO_ADICITY.T with T odd, so g^T for
+        // a generator g has order 2^TWO_ADICITY. The low bytes of q - 1 are 0.
+        let order = (-FieldElement::one()).to_bytes();
+        let t = &order[..order.len() - TWO_ADICITY / 8];
+        let mut root = pow_be_bytes(&FieldElement::from(MULTIPLICATIVE_GENERATOR), t);
+        let mut roots = Vec::with_capacity(TWO_ADICITY + 1);
+        for _ in 0..=TWO_ADICITY {
+            roots.push(root.clone());
+            root = &root * &root;
+        }
+        roots.reverse();
+        Self { roots }
+    }
+
+    fn mul(&self, a: &[FieldElement], b: &[FieldElement]) -> Vec<FieldElement> {
+        if a.is_empty() || b.is_empty() {
+            return vec![];
+        }
+        let out_len = a.len() + b.len() - 1;
+        if a.len().min(b.len()) <= SCHOOLBOOK_MAX_LEN {
+            let mut out = vec![FieldElement::zero(); out_len];
+            for (i, x) in a.iter().enumerate() {
+                for (j, y) in b.iter().enumerate() {
+                    out[i + j] += x * y;
+                }
+            }
+            return out;
+        }
+        let n = out_len.next_power_of_two();
+        let log_n = n.trailing_zeros() as usize;
+        assert!(log_n <= TWO_ADICITY);
+        let mut fa = a.to_vec();
+        fa.resize(n, FieldElement::zero());
+        let mut fb = b.to_vec();
+        fb.resize(n, FieldElement::zero());
+        let omega = &self.roots[log_n];
+        fft(&mut fa, omega);
+        fft(&mut fb, omega);
+        let mut prod = fa
+            .iter()
+            .zip(fb.iter())
+            .map(|(x, y)| x * y)
+            .collect::<Vec<FieldElement>>();
+        fft(&mut prod, &omega.inverse());
+        let n_inv = FieldElement::from(n as u64).inverse();
+        prod.truncate(out_len);
+        prod.iter().map(|c| c * &n_inv).collect()
+    }
+
+    /// g with f.g = 1 mod X^k, f[0] must be non zero. By Newton iteration g' = g.(2 - f.g) which
+    /// doubles the number of correct coefficients.
+    fn inverse_series(&self, f: &[FieldElement], k: usize) -> Vec<FieldElement> {
+        let mut g = vec![f[0].inverse()];
+        let mut len = 1;
+        while len < k {
+            len = (2 * len).min(k);
+            let mut e = self.mul(&f[..f.len().min(len)], &g);
+            e.truncate(len);
+            for c in e.iter_mut() {
+                *c = -c.clone();
+            }
+            e[0] += FieldElement::from(2u64);
+            g = self.mul(&g, &e);
+            g.truncate(len);
+        }
+        g
+    }
+
+    /// a mod b for monic b
+    fn rem(&self, a: &[FieldElement], b: &[FieldElement]) -> Vec<FieldElement> {
+        if a.len() < b.len() {
+            return a.to_vec();
+        }
+        // The quotient reversed is a reversed divided by b reversed as power series
+        let q_len = a.len() - b.len() + 1;
+        let a_rev = a
+            .iter()
+            .rev()
+            .take(q_len)
+            .cloned()
+            .collect::<Vec<FieldElement>>();
+        let b_rev = b.iter().rev().cloned().collect::<Vec<FieldElement>>();
+        let mut q = self.mul(&a_rev, &self.inverse_series(&b_rev, q_len));
+        q.truncate(q_len);
+        q.resize(q_len, FieldElement::zero());
+        q.reverse();
+        let qb = self.mul(&q, b);
+        a.iter()
+            .zip(qb.iter())
+            .take(b.len() - 1)
+            .map(|(x, y)| x - y)
+            .collect()
+    }
+}
+
+/// In place FFT of `a` whose length is a power of 2 with `omega` a primitive root of unity of
+/// that order, a[i] becomes the evaluation at omega^i
+fn fft(a: &mut [FieldElement], omega: &FieldElement) {
+    let n = a.len();
+    // Bit reversal permutation
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j ^= bit;
+        if i < j {
+            a.swap(i, j);
+        }
+    }
+    // omega^(n/len) for len = n, n/2, ..., 2
+    let mut step_roots = vec![omega.clone()];
+    let mut len = n;
+    while len > 2 {
+        let last = &step_roots[step_roots.len() - 1];
+        step_roots.push(last * last);
+        len >>= 1;
+    }
+    let mut len = 2;
+    while len <= n {
+        let w_len = &step_roots[(n / len).trailing_zeros() as usize];
+        for chunk in a.chunks_mut(len) {
+            let (lo, hi) = chunk.split_at_mut(len / 2);
+            let mut w = FieldElement::one();
+            for (x, y) in lo.iter_mut().zip(hi.iter_mut()) {
+                let v = &*y * &w;
+                let u = x.clone();
+                *x = &u + &v;
+                *y = &u - &v;
+                w = &w * w_len;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subproduct_tree_matches_naive() {
+        for ids in vec![
+            (1..3).collect::<BTreeSet<usize>>(),
+            (1..8).collect::<BTreeSet<usize>>(),
+            (1..100).step_by(3).collect::<BTreeSet<usize>>(),
+            vec![2, 5, 1000, 7, 40000]
+                .into_iter()
+                .collect::<BTreeSet<usize>>(),
+            (1..=200).collect::<BTreeSet<usize>>(),
+        ] {
+            let naive = LagrangeCoefficients::with_cutoff(&ids, usize::max_value());
+            let fast = LagrangeCoefficients::with_cutoff(&ids, 0);
+            assert_eq!(naive.len(), ids.len());
+            assert_eq!(fast.len(), ids.len());
+            let mut sum = FieldElement::zero();
+            for id in &ids {
+                assert_eq!(naive[*id], fast[*id]);
+                sum += fast[*id].clone();
+            }
+            // Interpolating the constant polynomial 1
+            assert_eq!(sum, FieldElement::one());
+        }
+
+        // An id of 0 always takes the naive way
+        let ids = vec![0, 1, 2].into_iter().collect::<BTreeSet<usize>>();
+        let coeffs = LagrangeCoefficients::with_cutoff(&ids, 0);
+        assert_eq!(coeffs[0], FieldElement::one());
+        assert!(coeffs[1].is_zero() && coeffs[2].is_zero());
+        assert!(coeffs.get(3).is_none());
+    }
+
+    #[test]
+    fn test_polynomial_arithmetic() {
+        let roots = RootsOfUnity::new();
+        // Root of order 2^TWO_ADICITY is primitive
+        let mut half = roots.roots[TWO_ADICITY].clone();
+        for _ in 0..TWO_ADICITY - 1 {
+            half = &half * &half;
+        }
+        assert_eq!(half, -FieldElement::one());
+
+        // FFT and schoolbook multiplication agree, a = q.b + r
+        let a = (0..150).map(|_| FieldElement::random()).collect::<Vec<_>>();
+        let mut b = (0..70).map(|_| FieldElement::random()).collect::<Vec<_>>();
+        b.push(FieldElement::one());
+        let prod = roots.mul(&a, &b);
+        let mut expected = vec![FieldElement::zero(); a.len() + b.len() - 1];
+        for (i, x) in a.iter().enumerate() {
+            for (j, y) in b.iter().enumerate() {
+                expected[i + j] += x * y;
+            }
+        }
+        assert_eq!(prod, expected);
+        let r = roots.rem(&a, &b);
+        assert_eq!(r.len(), b.len() - 1);
+        let mut a_minus_r = a.clone();
+        for (c, d) in a_minus_r.iter_mut().zip(r.iter()) {
+            *c = &*c - d;
+        }
+        assert!(roots.rem(&a_minus_r, &b).iter().all(|c| c.is_zero()));
+        assert!(!roots.rem(&a, &b).iter().all(|c| c.is_zero()));
+    }
+}
@@ -10,9 +10,15 @@ extern crate amcl_wrapper;
 extern crate ps_sig;
 
 use ps_sig::pok_vc;
-use ps_sig::{ate_2_pairing, OtherGroup, OtherGroupVec, SignatureGroup, SignatureGroupVec};
+
+use amcl_wrapper::extension_field_gt::GT;
+use amcl_wrapper::group_elem::GroupElement;
+
+use curve::{CoconutCurve, DefaultCurve};
 
 extern crate rand;
+#[cfg(feature = "test_vectors")]
+extern crate rand_chacha;
 
 #[macro_use]
 extern crate failure;
@@ -23,9 +29,95 @@ extern crate serde_derive;
 
 extern crate secret_sharing;
 
+#[cfg(feature = "challenge_blake2b")]
+extern crate blake2;
+extern crate sha2;
+
+#[cfg(feature = "parallel")]
+extern crate rayon;
+
+// Used by exported macros so that callers don't need to depend on or import amcl_wrapper
+#[doc(hidden)]
+pub use amcl_wrapper as __amcl_wrapper;
+
+// The groups, sizes, cofactors and pairings of the curve bound to `DefaultCurve`
+pub type SignatureGroup = <DefaultCurve as CoconutCurve>::SignatureGroup;
+pub type OtherGroup = <DefaultCurve as CoconutCurve>::OtherGroup;
+pub type SignatureGroupVec = <DefaultCurve as CoconutCurve>::SignatureGroupVec;
+pub type OtherGroupVec = <DefaultCurve as CoconutCurve>::OtherGroupVec;
+
+// Byte sizes of serialized field elements and elements of SignatureGroup and OtherGroup
+pub const FIELD_ELEMENT_SIZE: usize = <DefaultCurve as CoconutCurve>::FIELD_ELEMENT_SIZE;
+pub const SIGNATURE_GROUP_SIZE: usize = <DefaultCurve as CoconutCurve>::SIGNATURE_GROUP_SIZE;
+pub const OTHER_GROUP_SIZE: usize = <DefaultCurve as CoconutCurve>::OTHER_GROUP_SIZE;
+
+pub const SIGNATURE_GROUP_COFACTOR: &[u8] =
+    <DefaultCurve as CoconutCurve>::SIGNATURE_GROUP_COFACTOR;
+pub const OTHER_GROUP_COFACTOR: &[u8] = <DefaultCurve as CoconutCurve>::OTHER_GROUP_COFACTOR;
+
+/// Pairing of an element of SignatureGroup with an element of OtherGroup
+pub fn ate_pairing(a: &SignatureGroup, b: &OtherGroup) -> GT {
+    DefaultCurve::ate_pairing(a, b)
+}
+
+/// Product of the pairings of each element of SignatureGroup with its element of OtherGroup
+pub fn ate_multi_pairing(pairs: &[(SignatureGroup, OtherGroup)]) -> GT {
+    DefaultCurve::ate_multi_pairing(pairs)
+}
+
+/// Product of the pairings e(a, b) and e(c, d)
+pub fn ate_2_pairing(a: &SignatureGroup, b: &OtherGroup, c: &SignatureGroup, d: &OtherGroup) -> GT {
+    DefaultCurve::ate_2_pairing(a, b, c, d)
+}
+
+/// `ate_2_pairing` that errors with `PairingFailed` rather than giving a degenerate result when an
+/// input is the identity, like a maliciously constructed g_tilde. The pairing with the identity is
+/// 1 whatever the other input, so a check of the product being 1 would be meaningless.
+pub fn checked_ate_2_pairing(
+    a: &SignatureGroup,
+    b: &OtherGroup,
+    c: &SignatureGroup,
+    d: &OtherGroup,
+) -> Result<GT, errors::CoconutError> {
+    if a.is_identity() || c.is_identity() {
+        return Err(errors::CoconutErrorKind::PairingFailed {
+            msg: String::from("identity in SignatureGroup"),
+        }
+        .into());
+    }
+    if b.is_identity() || d.is_identity() {
+        return Err(errors::CoconutErrorKind::PairingFailed {
+            msg: String::from("identity in OtherGroup"),
+        }
+        .into());
+    }
+    Ok(ate_2_pairing(a, b, c, d))
+}
+
 pub mod errors;
+pub mod utils;
 #[macro_use]
 pub mod elgamal;
+pub mod accumulator;
+pub mod challenge;
+pub mod circuit;
+pub mod commitment;
+pub mod continuity;
+pub mod committed;
+pub mod curve;
+pub mod device;
+pub mod escrow;
+pub mod g2_attributes;
 pub mod keygen;
+pub mod lagrange;
 pub mod pok_sig;
+pub mod pseudonym;
+pub mod replay;
+pub mod schema;
+pub mod session;
 pub mod signature;
+pub mod stream;
+#[cfg(feature = "test_vectors")]
+pub mod test_vectors;
+#[cfg(test)]
+mod test_utils;
@@ -1,19 +1,2270 @@
 // Proof of knowledge of signature. Uses `PoKOfSignature` from PS sig crate.
 
-use crate::signature::{Params, Signature, Verkey};
+use crate::challenge::{ChallengeHasher, DefaultChallengeHasher};
+use crate::commitment::{Commitment, CommitmentOpeningProof};
+use crate::committed::CommitmentKey;
+use crate::device::{device_attribute_index, DeviceBindingProof, PoKOfDeviceBinding};
+use crate::errors::{CoconutError, CoconutErrorKind};
+use crate::utils::{append_u32, ensure_valid_point, ByteReader};
+use crate::signature::{
+    request_commitment_bytes, transform_to_PS_params, transform_to_PS_sig, transform_to_PS_verkey,
+    BlindingContext, Params, ProofSignatureGroup, ProverCommittedSignatureGroup,
+    ProverCommittingSignatureGroup, SchnorrCommitment, Signature, SignatureRequest,
+    SignatureRequestPoK, SignatureRequestProof, Verkey, VERKEY_FINGERPRINT_SIZE,
+};
 use crate::{OtherGroup, OtherGroupVec, SignatureGroup, SignatureGroupVec};
-use amcl_wrapper::group_elem::GroupElement;
+use crate::{FIELD_ELEMENT_SIZE, OTHER_GROUP_SIZE, SIGNATURE_GROUP_SIZE};
+use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
+use amcl_wrapper::group_elem::{GroupElement, GroupElementVector};
 use ps_sig::keys::Params as PSParams;
+use ps_sig::pok_sig::{PoKOfSignature, PoKOfSignatureProof, ProofOtherGroup};
+use ps_sig::signature::Signature as PSSignature;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// Proof of knowledge of a credential (aggregate signature) where some attributes are revealed and
+/// statements about the hidden attributes are proven. The proof is non-interactive, the
+/// challenge is computed from the proof's commitments and statements. "ProveCred" from paper.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CredentialProof {
+    pub proof: PoKOfSignatureProof,
+    /// Revealed attributes as index -> attribute
+    pub revealed_msgs: BTreeMap<usize, FieldElement>,
+    /// Pairs of indices of hidden attributes proven to be equal
+    pub equal_attributes: Vec<(usize, usize)>,
+    /// Hidden attributes proven to not be equal to a value
+    pub not_equal_attributes: Vec<AttributeInequalityProof>,
+    /// Linear relations proven among hidden attributes
+    pub linear_relations: Vec<LinearRelation>,
+    /// Hidden attributes encrypted to an auditor
+    pub auditable_attributes: Vec<AuditableAttributeProof>,
+    /// Hidden attributes proven to open earlier published commitments
+    #[serde(default)]
+    pub committed_attributes: Vec<CommittedAttributeProof>,
+}
+
+/// The statement sum of a_i.m_i = b over hidden attributes m_i with coefficients a_i and public
+/// value b. Proven by choosing the blindings r_i of the attributes such that sum of a_i.r_i = 0, so
+/// the responses s_i = r_i - c.m_i satisfy sum of a_i.s_i = -c.sum of a_i.m_i. The verifier checks
+/// that this is -c.b.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LinearRelation {
+    /// Pairs of attribute index and its coefficient
+    pub coeffs: Vec<(usize, FieldElement)>,
+    pub value: FieldElement,
+}
+
+/// Proof that the hidden attribute at `idx` is not `value`. With m the attribute and v the value, m
+/// is committed as C = g^m.h_1^r and knowledge of w = (m - v)^-1 and t = -r.w such that
+/// g = D^w.h_1^t for D = C.g^-v is proven. Such w and t only exist if m != v since otherwise
+/// D = h_1^r and g = h_1^(r.w + t), which would give the discrete log of g to base h_1. The proof of
+/// knowledge of the opening of C uses the attribute's blinding from the proof of knowledge of
+/// signature so the response for m is the attribute's response in the credential proof.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AttributeInequalityProof {
+    pub idx: usize,
+    pub value: FieldElement,
+    pub commitment: SignatureGroup,
+    /// Responses for m and r
+    pub proof_opening: ProofSignatureGroup,
+    /// Responses for w and t
+    pub proof_inverse: ProofSignatureGroup,
+}
+
+/// Verifiable encryption of the hidden attribute at `idx` to an auditor, so that the auditor can
+/// learn the attribute when entitled to, like under a court order, while the verifier learns
+/// nothing about it. With m the attribute, the ciphertext is Elgamal (g^k, pk^k.h_1^m) under the
+/// auditor's public key pk = g^sk. Knowledge of k and m is proven with the attribute's blinding
+/// from the proof of knowledge of signature so the response for m is the attribute's response in
+/// the credential proof, and with the same blinding for k in both parts of the ciphertext. See
+/// `open` for the auditor.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AuditableAttributeProof {
+    pub idx: usize,
+    pub auditor_pk: SignatureGroup,
+    pub ciphertext: (SignatureGroup, SignatureGroup),
+    /// Response for k
+    pub proof_randomness: ProofSignatureGroup,
+    /// Responses for k and m
+    pub proof_ciphertext: ProofSignatureGroup,
+}
+
+/// Proof that the hidden attribute at `idx` is the value committed to in `commitment`, published
+/// earlier like a deposit commitment C = g^m.h^s under `comm_key`. Knowledge of the opening of C is
+/// proven with the attribute's blinding from the proof of knowledge of signature so the response
+/// for m is the attribute's response in the credential proof. The verifier learns that the
+/// attribute is the committed value without learning it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CommittedAttributeProof {
+    pub idx: usize,
+    pub commitment: SignatureGroup,
+    pub comm_key: CommitmentKey,
+    /// Responses for m and s
+    pub proof_opening: CommitmentOpeningProof,
+}
+
+/// Requirements of a verifier on the attributes disclosed in a showing, enforced by
+/// `CredentialProof::verify_with_requirements`
+#[derive(Clone, Debug, Default)]
+pub struct ShowingRequirements {
+    disclosed_equals: BTreeMap<usize, FieldElement>,
+    auditable: BTreeMap<usize, SignatureGroup>,
+    committed: BTreeMap<usize, (SignatureGroup, CommitmentKey)>,
+}
+
+/// A presentation with its challenge and the device's proof of knowledge if the presentation is
+/// device bound, for the proofs created along with the presentation
+struct FinishedPresentation {
+    proof: CredentialProof,
+    challenge: FieldElement,
+    pok_device: Option<PoKOfDeviceBinding>,
+}
+
+/// Collects the statements to be proven about a credential before creating a `CredentialProof`
+pub struct CredentialProofBuilder<'a> {
+    sig: &'a Signature,
+    messages: &'a [FieldElement],
+    vk: &'a Verkey,
+    params: &'a Params,
+    revealed_msg_indices: HashSet<usize>,
+    equal_attributes: Vec<(usize, usize)>,
+    not_equal_attributes: Vec<(usize, FieldElement)>,
+    linear_relations: Vec<LinearRelation>,
+    auditable_attributes: Vec<(usize, SignatureGroup)>,
+    committed_attributes: Vec<(usize, FieldElement, CommitmentKey)>,
+    verifier_nonce: Vec<u8>,
+    hasher: &'a dyn ChallengeHasher,
+    blinding_context: Option<&'a BlindingContext>,
+    device_binding: Option<(PoKOfDeviceBinding, SignatureGroup)>,
+}
+
+impl<'a> CredentialProofBuilder<'a> {
+    pub fn new(
+        sig: &'a Signature,
+        messages: &'a [FieldElement],
+        vk: &'a Verkey,
+        params: &'a Params,
+    ) -> Self {
+        Self {
+            sig,
+            messages,
+            vk,
+            params,
+            revealed_msg_indices: HashSet::new(),
+            equal_attributes: vec![],
+            not_equal_attributes: vec![],
+            linear_relations: vec![],
+            auditable_attributes: vec![],
+            committed_attributes: vec![],
+            verifier_nonce: vec![],
+            hasher: &DefaultChallengeHasher,
+            blinding_context: None,
+            device_binding: None,
+        }
+    }
+
+    /// Nonce given by the verifier, folded into the challenge so that the proof only verifies
+    /// with the same nonce. Verifiers must give a fresh nonce for each presentation, otherwise a
+    /// proof shown to them earlier can be replayed.
+    pub fn verifier_nonce(mut self, nonce: &[u8]) -> Self {
+        self.verifier_nonce = nonce.to_vec();
+        self
+    }
+
+    /// Hash function for the challenge, `DefaultChallengeHasher` if not given. The proof then
+    /// needs to be verified with `CredentialProof::verify_with_hasher` and the same hasher.
+    pub fn challenge_hasher(mut self, hasher: &'a dyn ChallengeHasher) -> Self {
+        self.hasher = hasher;
+        self
+    }
+
+    /// Reveal the attribute at index `idx` to the verifier
+    pub fn reveal(mut self, idx: usize) -> Self {
+        self.revealed_msg_indices.insert(idx);
+        self
+    }
+
+    /// Prove that the hidden attributes at indices `i` and `j` are equal without revealing them.
+    /// Done by using the same blinding for both attributes so that their responses are equal.
+    pub fn prove_attributes_equal(mut self, i: usize, j: usize) -> Self {
+        self.equal_attributes.push((i, j));
+        self
+    }
+
+    /// Prove that the hidden attribute at index `idx` is not `value` without revealing it, like
+    /// for an attribute not being on a blocklist. See `AttributeInequalityProof`.
+    pub fn prove_attribute_not_equals(mut self, idx: usize, value: &FieldElement) -> Self {
+        self.not_equal_attributes.push((idx, value.clone()));
+        self
+    }
+
+    /// Prove that the hidden attributes satisfy sum of a_i.m_i = `public_value` without revealing
+    /// them, where `coeffs` are pairs of attribute index i and coefficient a_i. The verifier only
+    /// learns that the relation holds. See `LinearRelation`.
+    pub fn with_linear_relation(
+        mut self,
+        coeffs: &[(usize, FieldElement)],
+        public_value: &FieldElement,
+    ) -> Self {
+        self.linear_relations.push(LinearRelation {
+            coeffs: coeffs.to_vec(),
+            value: public_value.clone(),
+        });
+        self
+    }
+
+    /// Encrypt the hidden attribute at index `idx` to the auditor with Elgamal public key
+    /// `auditor_pk` and prove that the ciphertext holds the attribute. See
+    /// `AuditableAttributeProof`.
+    pub fn with_auditable_attribute(mut self, idx: usize, auditor_pk: &SignatureGroup) -> Self {
+        self.auditable_attributes.push((idx, auditor_pk.clone()));
+        self
+    }
+
+    /// Prove that the hidden attribute at index `idx` is the value committed to under `comm_key`
+    /// with randomness `comm_randomness`, like in a deposit commitment published before the
+    /// showing. See `CommittedAttributeProof`.
+    pub fn prove_attribute_committed(
+        mut self,
+        idx: usize,
+        comm_randomness: &FieldElement,
+        comm_key: &CommitmentKey,
+    ) -> Self {
+        self.committed_attributes
+            .push((idx, comm_randomness.clone(), comm_key.clone()));
+        self
+    }
+
+    /// Use the blindings of the hidden messages of the signature request the credential was
+    /// issued for, so that their responses in the presentation equal those in the request's
+    /// proof. The presentation must then be finished with `finish_linked`. An attribute in
+    /// `ctx` must not be the pivot of a linear relation, whose blinding is set by the relation.
+    pub fn with_blinding_context(mut self, ctx: &'a BlindingContext) -> Self {
+        self.blinding_context = Some(ctx);
+        self
+    }
+
+    /// Show a device bound credential, see `device`. `pok_device` is created on the device with
+    /// public key `device_pk`. The device attribute is revealed and the commitment of the
+    /// device's proof goes in the challenge. The presentation must then be finished with
+    /// `finish_device_bound`.
+    pub fn with_device_binding(
+        mut self,
+        pok_device: PoKOfDeviceBinding,
+        device_pk: &SignatureGroup,
+    ) -> Self {
+        self.revealed_msg_indices
+            .insert(device_attribute_index(self.params));
+        self.device_binding = Some((pok_device, device_pk.clone()));
+        self
+    }
+
+    /// The type attribute of typed params is always revealed.
+    pub fn finish(self) -> Result<CredentialProof, CoconutError> {
+        if self.blinding_context.is_some() {
+            return Err(CoconutErrorKind::GeneralError {
+                msg: String::from("A presentation with a blinding context needs finish_linked"),
+            }
+            .into());
+        }
+        if self.device_binding.is_some() {
+            return Err(CoconutErrorKind::GeneralError {
+                msg: String::from("A presentation with a device binding needs finish_device_bound"),
+            }
+            .into());
+        }
+        self.finish_with_challenge().map(|finished| finished.proof)
+    }
+
+    /// Finish a presentation created `with_device_binding` along with the device's proof, both
+    /// with the presentation's challenge. The pair is verified with
+    /// `CredentialProof::verify_device_bound`.
+    pub fn finish_device_bound(
+        self,
+    ) -> Result<(CredentialProof, DeviceBindingProof), CoconutError> {
+        if self.blinding_context.is_some() {
+            return Err(CoconutErrorKind::GeneralError {
+                msg: String::from("A presentation with a blinding context needs finish_linked"),
+            }
+            .into());
+        }
+        match self.finish_with_challenge()? {
+            FinishedPresentation {
+                proof,
+                challenge,
+                pok_device: Some(pok_device),
+            } => Ok((proof, pok_device.gen_proof(&challenge)?)),
+            _ => Err(CoconutErrorKind::GeneralError {
+                msg: String::from("A device bound presentation needs a device binding"),
+            }
+            .into()),
+        }
+    }
+
+    /// Finish a presentation created `with_blinding_context` along with the proof of knowledge
+    /// of the signature request `pok_req` the context came from, both with the presentation's
+    /// challenge. `hidden_messages`, `randomness` and `elgamal_sk` are as for
+    /// `SignatureRequestPoK::gen_proof`. The pair is verified with
+    /// `CredentialProof::verify_linked`.
+    pub fn finish_linked(
+        self,
+        pok_req: SignatureRequestPoK,
+        hidden_messages: &FieldElementVector,
+        randomness: FieldElementVector,
+        elgamal_sk: &FieldElement,
+    ) -> Result<(CredentialProof, SignatureRequestProof), CoconutError> {
+        if self.blinding_context.is_none() {
+            return Err(CoconutErrorKind::GeneralError {
+                msg: String::from("A linked presentation needs a blinding context"),
+            }
+            .into());
+        }
+        if self.device_binding.is_some() {
+            return Err(CoconutErrorKind::GeneralError {
+                msg: String::from("A presentation with a device binding needs finish_device_bound"),
+            }
+            .into());
+        }
+        let finished = self.finish_with_challenge()?;
+        let req_proof =
+            pok_req.gen_proof(hidden_messages, randomness, elgamal_sk, &finished.challenge)?;
+        Ok((finished.proof, req_proof))
+    }
+
+    fn finish_with_challenge(mut self) -> Result<FinishedPresentation, CoconutError> {
+        let msg_count = self.vk.Y_tilde.len();
+        if self.messages.len() != msg_count {
+            return Err(CoconutErrorKind::UnsupportedNoOfMessages {
+                expected: msg_count,
+                given: self.messages.len(),
+            }
+            .into());
+        }
+        if let Some(idx) = self.params.type_attribute_index() {
+            self.revealed_msg_indices.insert(idx);
+        }
+        for idx in &self.revealed_msg_indices {
+            check_attribute_index(*idx, msg_count)?;
+        }
+        for (i, j) in &self.equal_attributes {
+            check_attribute_index(*i, msg_count)?;
+            check_attribute_index(*j, msg_count)?;
+            if i == j
+                || self.revealed_msg_indices.contains(i)
+                || self.revealed_msg_indices.contains(j)
+            {
+                return Err(CoconutErrorKind::GeneralError {
+                    msg: format!(
+                        "Equality can only be proven among distinct hidden attributes, given {} and {}",
+                        i, j
+                    ),
+                }
+                .into());
+            }
+        }
+        for (idx, value) in &self.not_equal_attributes {
+            check_attribute_index(*idx, msg_count)?;
+            if self.revealed_msg_indices.contains(idx) {
+                return Err(CoconutErrorKind::GeneralError {
+                    msg: format!(
+                        "Inequality can only be proven for a hidden attribute, given {}",
+                        idx
+                    ),
+                }
+                .into());
+            }
+            if self.messages[*idx] == *value {
+                return Err(CoconutErrorKind::GeneralError {
+                    msg: format!("Attribute {} is equal to the value", idx),
+                }
+                .into());
+            }
+        }
+
+        for (idx, auditor_pk) in &self.auditable_attributes {
+            check_attribute_index(*idx, msg_count)?;
+            if self.revealed_msg_indices.contains(idx) {
+                return Err(CoconutErrorKind::GeneralError {
+                    msg: format!(
+                        "Only a hidden attribute can be encrypted to an auditor, given {}",
+                        idx
+                    ),
+                }
+                .into());
+            }
+            ensure_valid_point(auditor_pk)?;
+        }
+
+        for (idx, _, comm_key) in &self.committed_attributes {
+            check_attribute_index(*idx, msg_count)?;
+            if self.revealed_msg_indices.contains(idx) {
+                return Err(CoconutErrorKind::GeneralError {
+                    msg: format!("Committed attribute {} can't be revealed", idx),
+                }
+                .into());
+            }
+            ensure_valid_point(&comm_key.g)?;
+            ensure_valid_point(&comm_key.h)?;
+        }
+
+        for relation in &self.linear_relations {
+            let mut sum = FieldElement::zero();
+            for (idx, a) in &relation.coeffs {
+                check_attribute_index(*idx, msg_count)?;
+                if self.revealed_msg_indices.contains(idx) {
+                    return Err(CoconutErrorKind::GeneralError {
+                        msg: format!(
+                            "Linear relation can only be proven among hidden attributes, given {}",
+                            idx
+                        ),
+                    }
+                    .into());
+                }
+                sum = &sum + &(a * &self.messages[*idx]);
+            }
+            if sum != relation.value {
+                return Err(CoconutErrorKind::GeneralError {
+                    msg: String::from("Attributes do not satisfy the linear relation"),
+                }
+                .into());
+            }
+        }
+
+        let blindings = self.hidden_msg_blindings(msg_count)?;
+        let pok = PoKOfSignature::init(
+            &transform_to_PS_sig(self.sig),
+            &transform_to_PS_verkey(self.vk),
+            &transform_to_PS_params(self.params),
+            self.messages,
+            Some(blindings.as_slice()),
+            self.revealed_msg_indices.clone(),
+        )?;
+
+        let revealed_msgs = self
+            .revealed_msg_indices
+            .iter()
+            .map(|i| (*i, self.messages[*i].clone()))
+            .collect::<BTreeMap<usize, FieldElement>>();
+        // Blindings are for the hidden attributes in increasing order of index
+        let blinding = |idx: usize| {
+            &blindings[idx
+                - self
+                    .revealed_msg_indices
+                    .iter()
+                    .filter(|i| **i < idx)
+                    .count()]
+        };
+        let inequalities = self
+            .not_equal_attributes
+            .iter()
+            .map(|(idx, value)| {
+                PoKOfInequality::init(
+                    *idx,
+                    value,
+                    &self.messages[*idx],
+                    blinding(*idx),
+                    self.params,
+                )
+            })
+            .collect::<Vec<PoKOfInequality>>();
+        let encryptions = self
+            .auditable_attributes
+            .iter()
+            .map(|(idx, auditor_pk)| {
+                PoKOfAuditableAttribute::init(
+                    *idx,
+                    auditor_pk,
+                    &self.messages[*idx],
+                    blinding(*idx),
+                    self.params,
+                )
+            })
+            .collect::<Vec<PoKOfAuditableAttribute>>();
+        let mut openings = Vec::with_capacity(self.committed_attributes.len());
+        for (idx, comm_randomness, comm_key) in &self.committed_attributes {
+            openings.push(PoKOfCommittedAttribute::init(
+                *idx,
+                comm_key,
+                comm_randomness,
+                &self.messages[*idx],
+                blinding(*idx),
+            )?);
+        }
+        let mut statements = statement_bytes(&self.equal_attributes, &self.linear_relations);
+        for p in &inequalities {
+            p.append_challenge_bytes(&mut statements);
+        }
+        for p in &encryptions {
+            p.append_challenge_bytes(&mut statements);
+        }
+        for p in &openings {
+            p.append_challenge_bytes(&mut statements);
+        }
+        if let Some(ctx) = self.blinding_context {
+            statements.append(&mut ctx.challenge_bytes());
+        }
+        let pok_device = match self.device_binding.take() {
+            Some((pok_device, device_pk)) => {
+                statements.append(&mut DeviceBindingProof::show_challenge_bytes(
+                    &device_pk,
+                    &pok_device.pok_vc.schnorr_commitment().to_bytes(),
+                ));
+                Some(pok_device)
+            }
+            None => None,
+        };
+
+        let challenge = self.hasher.hash_to_field(&CredentialProof::challenge_bytes(
+            &pok.sig,
+            &pok.J,
+            &pok.pok_vc.schnorr_commitment().to_bytes(),
+            &revealed_msgs,
+            &statements,
+            &self.verifier_nonce,
+            &challenge_context(self.vk, self.params),
+        ));
+        let proof = pok.gen_proof(&challenge)?;
+        let mut not_equal_attributes = Vec::with_capacity(inequalities.len());
+        for p in inequalities {
+            not_equal_attributes.push(p.gen_proof(&challenge)?);
+        }
+        let mut auditable_attributes = Vec::with_capacity(encryptions.len());
+        for p in encryptions {
+            auditable_attributes.push(p.gen_proof(&challenge)?);
+        }
+        let mut committed_attributes = Vec::with_capacity(openings.len());
+        for p in openings {
+            committed_attributes.push(p.gen_proof(&challenge)?);
+        }
+        Ok(FinishedPresentation {
+            proof: CredentialProof {
+                proof,
+                revealed_msgs,
+                equal_attributes: self.equal_attributes,
+                not_equal_attributes,
+                linear_relations: self.linear_relations,
+                auditable_attributes,
+                committed_attributes,
+            },
+            challenge,
+            pok_device,
+        })
+    }
+
+    /// Blindings for the hidden attributes in increasing order of index. Attributes proven equal
+    /// get the same blinding, the one from the blinding context if any of them has one. For each
+    /// linear relation, the blinding of 1 group of attributes in it, the pivot, is set so that the
+    /// sum of the coefficients times the blindings is 0. The pivot must have a non-zero
+    /// coefficient and not be in any other relation so that setting its blinding doesn't change
+    /// the sums of other relations.
+    fn hidden_msg_blindings(&self, msg_count: usize) -> Result<Vec<FieldElement>, CoconutError> {
+        // Each attribute's group is represented by an attribute index. Attributes proven equal are
+        // put in the same group.
+        let mut group = (0..msg_count).collect::<Vec<usize>>();
+        for (i, j) in &self.equal_attributes {
+            let (gi, gj) = (group[*i], group[*j]);
+            if gi != gj {
+                for g in group.iter_mut() {
+                    if *g == gj {
+                        *g = gi;
+                    }
+                }
+            }
+        }
+        let mut group_blindings = HashMap::<usize, FieldElement>::new();
+        if let Some(ctx) = self.blinding_context {
+            for i in (0..msg_count).filter(|i| !self.revealed_msg_indices.contains(i)) {
+                if let Some(b) = ctx.blinding(i) {
+                    group_blindings.entry(group[i]).or_insert_with(|| b.clone());
+                }
+            }
+        }
+        for i in (0..msg_count).filter(|i| !self.revealed_msg_indices.contains(i)) {
+            group_blindings
+                .entry(group[i])
+                .or_insert_with(FieldElement::random);
+        }
+
+        // Coefficient of each group in each relation
+        let relation_coeffs = self
+            .linear_relations
+            .iter()
+            .map(|relation| {
+                let mut coeffs = BTreeMap::<usize, FieldElement>::new();
+                for (i, a) in &relation.coeffs {
+                    let c = coeffs.entry(group[*i]).or_insert_with(FieldElement::zero);
+                    *c = &*c + a;
+                }
+                coeffs
+            })
+            .collect::<Vec<BTreeMap<usize, FieldElement>>>();
+        for (k, coeffs) in relation_coeffs.iter().enumerate() {
+            let (pivot, pivot_coeff) = coeffs
+                .iter()
+                .find(|(g, a)| {
+                    !a.is_zero()
+                        && relation_coeffs
+                            .iter()
+                            .enumerate()
+                            .all(|(l, other)| l == k || !other.contains_key(*g))
+                })
+                .map(|(g, a)| (*g, a.clone()))
+                .ok_or_else(|| {
+                    CoconutError::from(CoconutErrorKind::GeneralError {
+                        msg: String::from(
+                            "Linear relation needs an attribute with a non-zero coefficient in \
+                             no other relation",
+                        ),
+                    })
+                })?;
+            let mut sum = FieldElement::zero();
+            for (g, a) in coeffs {
+                if *g != pivot {
+                    sum = &sum + &(a * &group_blindings[g]);
+                }
+            }
+            group_blindings.insert(pivot, -(&sum * &pivot_coeff.inverse()));
+        }
+
+        Ok((0..msg_count)
+            .filter(|i| !self.revealed_msg_indices.contains(i))
+            .map(|i| group_blindings[&group[i]].clone())
+            .collect())
+    }
+}
+
+impl ShowingRequirements {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Require the attribute at index `idx` to be disclosed and equal to `expected`, like the hash
+    /// of some public context (`FieldElement::from_msg_hash`) the verifier knows. A later
+    /// requirement for the same index replaces the earlier.
+    pub fn require_disclosed_equals(mut self, idx: usize, expected: &FieldElement) -> Self {
+        self.disclosed_equals.insert(idx, expected.clone());
+        self
+    }
+
+    /// Require the hidden attribute at index `idx` to be encrypted to the auditor with Elgamal
+    /// public key `auditor_pk`, see `CredentialProofBuilder::with_auditable_attribute`
+    pub fn require_auditable(mut self, idx: usize, auditor_pk: &SignatureGroup) -> Self {
+        self.auditable.insert(idx, auditor_pk.clone());
+        self
+    }
+
+    /// Require the hidden attribute at index `idx` to be proven to open `commitment` under
+    /// `comm_key`, see `CredentialProofBuilder::prove_attribute_committed`
+    pub fn require_committed(
+        mut self,
+        idx: usize,
+        commitment: &SignatureGroup,
+        comm_key: &CommitmentKey,
+    ) -> Self {
+        self.committed
+            .insert(idx, (commitment.clone(), comm_key.clone()));
+        self
+    }
+
+    /// Errors with `DisclosedAttributeMismatch` for the 1st required attribute that is not
+    /// disclosed with the expected value, with `AuditableAttributeMissing` for the 1st required
+    /// attribute that is not encrypted to the auditor and with `CommittedAttributeMissing` for the
+    /// 1st required attribute that is not proven to open the commitment
+    fn check(&self, proof: &CredentialProof) -> Result<(), CoconutError> {
+        for (idx, expected) in &self.disclosed_equals {
+            if proof.revealed_msgs.get(idx) != Some(expected) {
+                return Err(CoconutErrorKind::DisclosedAttributeMismatch { idx: *idx }.into());
+            }
+        }
+        for (idx, auditor_pk) in &self.auditable {
+            if !proof
+                .auditable_attributes
+                .iter()
+                .any(|a| a.idx == *idx && a.auditor_pk == *auditor_pk)
+            {
+                return Err(CoconutErrorKind::AuditableAttributeMissing { idx: *idx }.into());
+            }
+        }
+        for (idx, (commitment, comm_key)) in &self.committed {
+            if !proof.committed_attributes.iter().any(|a| {
+                a.idx == *idx
+                    && a.commitment == *commitment
+                    && a.comm_key.g == comm_key.g
+                    && a.comm_key.h == comm_key.h
+            }) {
+                return Err(CoconutErrorKind::CommittedAttributeMissing { idx: *idx }.into());
+            }
+        }
+        Ok(())
+    }
+}
+
+fn check_attribute_index(idx: usize, msg_count: usize) -> Result<(), CoconutError> {
+    if idx >= msg_count {
+        return Err(CoconutErrorKind::InvalidAttributeIndex { idx, msg_count }.into());
+    }
+    Ok(())
+}
+
+/// Commitment phase of `AttributeInequalityProof`
+struct PoKOfInequality {
+    idx: usize,
+    value: FieldElement,
+    commitment: SignatureGroup,
+    /// m and r
+    opening_secrets: [FieldElement; 2],
+    pok_opening: ProverCommittedSignatureGroup,
+    /// w and t
+    inverse_secrets: [FieldElement; 2],
+    pok_inverse: ProverCommittedSignatureGroup,
+}
+
+impl PoKOfInequality {
+    /// `attribute_blinding` is the blinding of the attribute in the proof of knowledge of
+    /// signature. The attribute must not be equal to `value`.
+    fn init(
+        idx: usize,
+        value: &FieldElement,
+        attribute: &FieldElement,
+        attribute_blinding: &FieldElement,
+        params: &Params,
+    ) -> Self {
+        let m = attribute.clone();
+        let v = value.clone();
+        let r = FieldElement::random();
+        let h = &params.h[0];
+        let commitment = &(&params.g * &m) + &(h * &r);
+        let d = &commitment - &(&params.g * &v);
+        let w = (&m - &v).inverse();
+        let t = -(&r * &w);
+
+        let mut committing = ProverCommittingSignatureGroup::new();
+        committing.commit(&params.g, Some(attribute_blinding));
+        committing.commit(h, None);
+        let pok_opening = committing.finish();
+        let mut committing = ProverCommittingSignatureGroup::new();
+        committing.commit(&d, None);
+        committing.commit(h, None);
+        let pok_inverse = committing.finish();
+        Self {
+            idx,
+            value: value.clone(),
+            commitment,
+            opening_secrets: [m, r],
+            pok_opening,
+            inverse_secrets: [w, t],
+            pok_inverse,
+        }
+    }
+
+    fn append_challenge_bytes(&self, bytes: &mut Vec<u8>) {
+        append_inequality_bytes(
+            bytes,
+            self.idx,
+            &self.value,
+            &self.commitment,
+            &self.pok_opening.schnorr_commitment().to_bytes(),
+            &self.pok_inverse.schnorr_commitment().to_bytes(),
+        );
+    }
+
+    fn gen_proof(self, challenge: &FieldElement) -> Result<AttributeInequalityProof, CoconutError> {
+        Ok(AttributeInequalityProof {
+            idx: self.idx,
+            value: self.value,
+            commitment: self.commitment,
+            proof_opening: self
+                .pok_opening
+                .gen_proof(challenge, &self.opening_secrets)?,
+            proof_inverse: self
+                .pok_inverse
+                .gen_proof(challenge, &self.inverse_secrets)?,
+        })
+    }
+}
+
+impl AttributeInequalityProof {
+    /// `attribute_response` is the response for the attribute in the proof of knowledge of
+    /// signature
+    fn verify(
+        &self,
+        attribute_response: &FieldElement,
+        challenge: &FieldElement,
+        params: &Params,
+    ) -> Result<bool, CoconutError> {
+        if self.proof_opening.responses.len() != 2
+            || self.proof_inverse.responses.len() != 2
+            || self.proof_opening.responses[0] != *attribute_response
+        {
+            return Ok(false);
+        }
+        let h = &params.h[0];
+        let d = &self.commitment - &(&params.g * &self.value);
+        if d.is_identity() {
+            return Ok(false);
+        }
+        Ok(self.proof_opening.verify(
+            &[params.g.clone(), h.clone()],
+            &self.commitment,
+            challenge,
+        )? && self
+            .proof_inverse
+            .verify(&[d, h.clone()], &params.g, challenge)?)
+    }
+
+    fn append_challenge_bytes(&self, bytes: &mut Vec<u8>) {
+        append_inequality_bytes(
+            bytes,
+            self.idx,
+            &self.value,
+            &self.commitment,
+            &self.proof_opening.commitment.to_bytes(),
+            &self.proof_inverse.commitment.to_bytes(),
+        );
+    }
+}
+
+/// Commitment phase of `AuditableAttributeProof`
+struct PoKOfAuditableAttribute {
+    idx: usize,
+    auditor_pk: SignatureGroup,
+    ciphertext: (SignatureGroup, SignatureGroup),
+    /// k and m
+    secrets: [FieldElement; 2],
+    pok_randomness: ProverCommittedSignatureGroup,
+    pok_ciphertext: ProverCommittedSignatureGroup,
+}
+
+impl PoKOfAuditableAttribute {
+    /// `attribute_blinding` is the blinding of the attribute in the proof of knowledge of
+    /// signature
+    fn init(
+        idx: usize,
+        auditor_pk: &SignatureGroup,
+        attribute: &FieldElement,
+        attribute_blinding: &FieldElement,
+        params: &Params,
+    ) -> Self {
+        let m = attribute.clone();
+        let h = &params.h[0];
+        let (c1, c2, k) = elgamal_encrypt!(&params.g, auditor_pk, &(h * &m));
+
+        let k_blinding = FieldElement::random();
+        let mut committing = ProverCommittingSignatureGroup::new();
+        committing.commit(&params.g, Some(&k_blinding));
+        let pok_randomness = committing.finish();
+        let mut committing = ProverCommittingSignatureGroup::new();
+        committing.commit(auditor_pk, Some(&k_blinding));
+        committing.commit(h, Some(attribute_blinding));
+        let pok_ciphertext = committing.finish();
+        Self {
+            idx,
+            auditor_pk: auditor_pk.clone(),
+            ciphertext: (c1, c2),
+            secrets: [k, m],
+            pok_randomness,
+            pok_ciphertext,
+        }
+    }
+
+    fn append_challenge_bytes(&self, bytes: &mut Vec<u8>) {
+        append_auditable_bytes(
+            bytes,
+            self.idx,
+            &self.auditor_pk,
+            &self.ciphertext,
+            &self.pok_randomness.schnorr_commitment().to_bytes(),
+            &self.pok_ciphertext.schnorr_commitment().to_bytes(),
+        );
+    }
+
+    fn gen_proof(self, challenge: &FieldElement) -> Result<AuditableAttributeProof, CoconutError> {
+        Ok(AuditableAttributeProof {
+            idx: self.idx,
+            auditor_pk: self.auditor_pk,
+            ciphertext: self.ciphertext,
+            proof_randomness: self
+                .pok_randomness
+                .gen_proof(challenge, &self.secrets[..1])?,
+            proof_ciphertext: self.pok_ciphertext.gen_proof(challenge, &self.secrets)?,
+        })
+    }
+}
+
+impl AuditableAttributeProof {
+    /// `attribute_response` is the response for the attribute in the proof of knowledge of
+    /// signature
+    fn verify(
+        &self,
+        attribute_response: &FieldElement,
+        challenge: &FieldElement,
+        params: &Params,
+    ) -> Result<bool, CoconutError> {
+        if self.proof_randomness.responses.len() != 1
+            || self.proof_ciphertext.responses.len() != 2
+            || self.proof_randomness.responses[0] != self.proof_ciphertext.responses[0]
+            || self.proof_ciphertext.responses[1] != *attribute_response
+        {
+            return Ok(false);
+        }
+        if self.auditor_pk.is_identity() {
+            return Ok(false);
+        }
+        Ok(self
+            .proof_randomness
+            .verify(&[params.g.clone()], &self.ciphertext.0, challenge)?
+            && self.proof_ciphertext.verify(
+                &[self.auditor_pk.clone(), params.h[0].clone()],
+                &self.ciphertext.1,
+                challenge,
+            )?)
+    }
+
+    /// Decrypt h_1^m for the attribute m using the auditor's Elgamal secret key. Only meaningful
+    /// for a proof that verified.
+    pub fn decrypt(&self, auditor_sk: &FieldElement) -> SignatureGroup {
+        elgamal_decrypt!(&self.ciphertext.0, &self.ciphertext.1, auditor_sk)
+    }
+
+    /// The attribute among `candidates`, like the attribute values issued by the issuer, that is
+    /// encrypted, found by comparing the decryption with h_1^m for each candidate m. None if no
+    /// candidate matches.
+    pub fn open(
+        &self,
+        auditor_sk: &FieldElement,
+        candidates: &[FieldElement],
+        params: &Params,
+    ) -> Option<FieldElement> {
+        let decrypted = self.decrypt(auditor_sk);
+        candidates
+            .iter()
+            .find(|m| &params.h[0] * *m == decrypted)
+            .cloned()
+    }
+
+    fn append_challenge_bytes(&self, bytes: &mut Vec<u8>) {
+        append_auditable_bytes(
+            bytes,
+            self.idx,
+            &self.auditor_pk,
+            &self.ciphertext,
+            &self.proof_randomness.commitment.to_bytes(),
+            &self.proof_ciphertext.commitment.to_bytes(),
+        );
+    }
+}
+
+fn append_auditable_bytes(
+    bytes: &mut Vec<u8>,
+    idx: usize,
+    auditor_pk: &SignatureGroup,
+    ciphertext: &(SignatureGroup, SignatureGroup),
+    randomness_commitment: &[u8],
+    ciphertext_commitment: &[u8],
+) {
+    append_u32(bytes, idx);
+    bytes.append(&mut auditor_pk.to_bytes());
+    bytes.append(&mut ciphertext.0.to_bytes());
+    bytes.append(&mut ciphertext.1.to_bytes());
+    bytes.extend_from_slice(randomness_commitment);
+    bytes.extend_from_slice(ciphertext_commitment);
+}
+
+/// Commitment phase of `CommittedAttributeProof`
+struct PoKOfCommittedAttribute {
+    idx: usize,
+    comm_key: CommitmentKey,
+    commitment: Commitment,
+}
+
+impl PoKOfCommittedAttribute {
+    /// `attribute_blinding` is the blinding of the attribute in the proof of knowledge of
+    /// signature
+    fn init(
+        idx: usize,
+        comm_key: &CommitmentKey,
+        comm_randomness: &FieldElement,
+        attribute: &FieldElement,
+        attribute_blinding: &FieldElement,
+    ) -> Result<Self, CoconutError> {
+        let commitment = Commitment::new_with_blindings(
+            &[attribute.clone()],
+            comm_randomness,
+            &[comm_key.g.clone(), comm_key.h.clone()],
+            &[attribute_blinding.clone()],
+        )?;
+        Ok(Self {
+            idx,
+            comm_key: comm_key.clone(),
+            commitment,
+        })
+    }
+
+    fn append_challenge_bytes(&self, bytes: &mut Vec<u8>) {
+        append_committed_bytes(
+            bytes,
+            self.idx,
+            &self.comm_key,
+            &self.commitment.value,
+            &self.commitment.opening_commitment().to_bytes(),
+        );
+    }
+
+    fn gen_proof(self, challenge: &FieldElement) -> Result<CommittedAttributeProof, CoconutError> {
+        Ok(CommittedAttributeProof {
+            idx: self.idx,
+            commitment: self.commitment.value.clone(),
+            comm_key: self.comm_key,
+            proof_opening: self.commitment.prove_opening(challenge)?,
+        })
+    }
+}
+
+impl CommittedAttributeProof {
+    /// `attribute_response` is the response for the attribute in the proof of knowledge of
+    /// signature
+    fn verify(
+        &self,
+        attribute_response: &FieldElement,
+        challenge: &FieldElement,
+    ) -> Result<bool, CoconutError> {
+        if self.proof_opening.responses.len() != 2
+            || self.proof_opening.responses[0] != *attribute_response
+        {
+            return Ok(false);
+        }
+        if self.comm_key.g.is_identity() || self.comm_key.h.is_identity() {
+            return Ok(false);
+        }
+        Commitment::verify_opening(
+            &self.commitment,
+            &[self.comm_key.g.clone(), self.comm_key.h.clone()],
+            &self.proof_opening,
+            challenge,
+        )
+    }
+
+    fn append_challenge_bytes(&self, bytes: &mut Vec<u8>) {
+        append_committed_bytes(
+            bytes,
+            self.idx,
+            &self.comm_key,
+            &self.commitment,
+            &self.proof_opening.commitment.to_bytes(),
+        );
+    }
+}
+
+fn append_committed_bytes(
+    bytes: &mut Vec<u8>,
+    idx: usize,
+    comm_key: &CommitmentKey,
+    commitment: &SignatureGroup,
+    opening_commitment: &[u8],
+) {
+    append_u32(bytes, idx);
+    bytes.append(&mut comm_key.g.to_bytes());
+    bytes.append(&mut comm_key.h.to_bytes());
+    bytes.append(&mut commitment.to_bytes());
+    bytes.extend_from_slice(opening_commitment);
+}
+
+fn append_inequality_bytes(
+    bytes: &mut Vec<u8>,
+    idx: usize,
+    value: &FieldElement,
+    commitment: &SignatureGroup,
+    opening_commitment: &[u8],
+    inverse_commitment: &[u8],
+) {
+    append_u32(bytes, idx);
+    bytes.append(&mut value.to_bytes());
+    bytes.append(&mut commitment.to_bytes());
+    bytes.extend_from_slice(opening_commitment);
+    bytes.extend_from_slice(inverse_commitment);
+}
+
+/// Position of the hidden attribute at index `idx` among the hidden attributes of a proof of
+/// knowledge of signature, which are in increasing order of index. None if `idx` is one of
+/// `revealed_indices`.
+pub fn hidden_attribute_position<'a, I>(idx: usize, revealed_indices: I) -> Option<usize>
+where
+    I: IntoIterator<Item = &'a usize>,
+{
+    let mut revealed_before = 0;
+    for i in revealed_indices {
+        if *i == idx {
+            return None;
+        }
+        if *i < idx {
+            revealed_before += 1;
+        }
+    }
+    Some(idx - revealed_before)
+}
+
+/// Schnorr response for the hidden attribute at index `idx` in `proof`, a proof of knowledge of
+/// signature revealing the attributes at `revealed_indices`. None if the attribute is revealed or
+/// `proof` has no response for it.
+pub fn hidden_attribute_response<'a, 'b, I>(
+    proof: &'a PoKOfSignatureProof,
+    revealed_indices: I,
+    idx: usize,
+) -> Option<&'a FieldElement>
+where
+    I: IntoIterator<Item = &'b usize>,
+{
+    // 1st response is for the randomness in J
+    let pos = 1 + hidden_attribute_position(idx, revealed_indices)?;
+    if pos < proof.proof_vc.responses.len() {
+        Some(&proof.proof_vc.responses[pos])
+    } else {
+        None
+    }
+}
+
+/// Commitment phase of the proof of knowledge of a hidden attribute m of a credential in B^m for a
+/// base B. Linked to the proof of knowledge of signature by using the same blinding for m in both
+/// proofs, so the responses for m are equal under the same challenge.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PoKOfLinkedAttribute {
+    pub attribute: FieldElement,
+    pub pok_vc: ProverCommittedSignatureGroup,
+}
+
+/// Response phase of the proof of knowledge of a hidden attribute m of a credential in B^m
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LinkedAttributeProof {
+    pub proof_vc: ProofSignatureGroup,
+}
+
+impl PoKOfLinkedAttribute {
+    /// `attribute_blinding` should be the blinding of the attribute in the proof of knowledge of
+    /// signature, see `hidden_attribute_position`.
+    pub fn init(
+        attribute: &FieldElement,
+        attribute_blinding: &FieldElement,
+        base: &SignatureGroup,
+    ) -> Self {
+        let mut committing = ProverCommittingSignatureGroup::new();
+        committing.commit(base, Some(attribute_blinding));
+        Self {
+            attribute: attribute.clone(),
+            pok_vc: committing.finish(),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.pok_vc.to_bytes()
+    }
+
+    pub fn gen_proof(self, challenge: &FieldElement) -> Result<LinkedAttributeProof, CoconutError> {
+        let proof_vc = self.pok_vc.gen_proof(challenge, &[self.attribute])?;
+        Ok(LinkedAttributeProof { proof_vc })
+    }
+}
+
+impl LinkedAttributeProof {
+    /// Verify that `value` is `base`^m for the hidden attribute m at index `idx` of `proof_sig`, a
+    /// proof of knowledge of signature revealing `revealed_msgs` created with the same challenge.
+    /// `proof_sig` must be verified separately.
+    pub fn verify(
+        &self,
+        base: &SignatureGroup,
+        value: &SignatureGroup,
+        idx: usize,
+        proof_sig: &PoKOfSignatureProof,
+        revealed_msgs: &HashMap<usize, FieldElement>,
+        challenge: &FieldElement,
+    ) -> Result<bool, CoconutError> {
+        if value.is_identity() || self.proof_vc.responses.len() != 1 {
+            return Ok(false);
+        }
+        match hidden_attribute_response(proof_sig, revealed_msgs.keys(), idx) {
+            Some(s) if *s == self.proof_vc.responses[0] => (),
+            _ => return Ok(false),
+        }
+        Ok(self.proof_vc.verify(&[base.clone()], value, challenge)?)
+    }
+}
+
+/// Bytes a hidden attribute adds to `CredentialProof::to_bytes`, its response
+pub const HIDDEN_ATTRIBUTE_SIZE: usize = FIELD_ELEMENT_SIZE;
+
+/// Bytes a revealed attribute adds to `CredentialProof::to_bytes`, its index and value
+pub const REVEALED_ATTRIBUTE_SIZE: usize = 4 + FIELD_ELEMENT_SIZE;
+
+/// Size in bytes of `CredentialProof::to_bytes` for a credential over `msg_count` attributes of
+/// which `hidden_count` are hidden and the rest revealed, without equalities, inequalities,
+/// linear relations, auditable or committed attributes. Errors if `hidden_count` exceeds
+/// `msg_count`.
+pub fn presentation_size(msg_count: usize, hidden_count: usize) -> Result<usize, CoconutError> {
+    let revealed_count = msg_count.checked_sub(hidden_count).ok_or_else(|| {
+        CoconutError::from(CoconutErrorKind::UnsupportedNoOfMessages {
+            expected: msg_count,
+            given: hidden_count,
+        })
+    })?;
+    Ok(2 * SIGNATURE_GROUP_SIZE
+        + 2 * OTHER_GROUP_SIZE
+        + 4
+        + FIELD_ELEMENT_SIZE
+        + hidden_count * HIDDEN_ATTRIBUTE_SIZE
+        + 4
+        + revealed_count * REVEALED_ATTRIBUTE_SIZE
+        + 4
+        + 4
+        + 4
+        + 4
+        + 4)
+}
+
+/// Which attributes of a credential to reveal in a presentation and the size of the proof, see
+/// `plan_disclosure`
+#[derive(Clone, Debug, PartialEq)]
+pub struct DisclosurePlan {
+    pub revealed: BTreeSet<usize>,
+    pub hidden: BTreeSet<usize>,
+    /// Size in bytes of `CredentialProof::to_bytes` of the proof revealing `revealed`
+    pub size: usize,
+}
+
+impl DisclosurePlan {
+    /// The indices to reveal, as taken by `CredentialProof::new`
+    pub fn revealed_msg_indices(&self) -> HashSet<usize> {
+        self.revealed.iter().cloned().collect()
+    }
+}
+
+/// Plan the smallest presentation of a credential over `msg_count` attributes that reveals the
+/// attributes at `required` and none at `must_hide`. A hidden attribute costs fewer bytes than a
+/// revealed one and reveals nothing, so the smallest proof reveals exactly `required`. Errors if
+/// an index is invalid or both required and to be hidden.
+pub fn plan_disclosure(
+    msg_count: usize,
+    required: &[usize],
+    must_hide: &[usize],
+) -> Result<DisclosurePlan, CoconutError> {
+    for idx in required.iter().chain(must_hide.iter()) {
+        check_attribute_index(*idx, msg_count)?;
+    }
+    if let Some(idx) = required.iter().find(|i| must_hide.contains(i)) {
+        return Err(CoconutErrorKind::GeneralError {
+            msg: format!(
+                "Attribute {} is both required to be revealed and hidden",
+                idx
+            ),
+        }
+        .into());
+    }
+    let revealed = required.iter().cloned().collect::<BTreeSet<usize>>();
+    let hidden = (0..msg_count)
+        .filter(|i| !revealed.contains(i))
+        .collect::<BTreeSet<usize>>();
+    let size = presentation_size(msg_count, hidden.len())?;
+    Ok(DisclosurePlan {
+        revealed,
+        hidden,
+        size,
+    })
+}
+
+impl CredentialProof {
+    /// Prove knowledge of credential `sig` over `messages` revealing the messages at
+    /// `revealed_msg_indices`. The proof only verifies with `verifier_nonce`, which verifiers
+    /// must choose fresh for each presentation so that the proof can't be replayed to them. Use
+    /// `CredentialProofBuilder` to prove more statements.
+    pub fn new(
+        sig: &Signature,
+        messages: &[FieldElement],
+        revealed_msg_indices: HashSet<usize>,
+        verifier_nonce: &[u8],
+        vk: &Verkey,
+        params: &Params,
+    ) -> Result<Self, CoconutError> {
+        let mut builder =
+            CredentialProofBuilder::new(sig, messages, vk, params).verifier_nonce(verifier_nonce);
+        builder.revealed_msg_indices = revealed_msg_indices;
+        builder.finish()
+    }
+
+    /// `verifier_nonce` is the nonce the verifier gave for this presentation
+    pub fn verify(
+        &self,
+        verifier_nonce: &[u8],
+        vk: &Verkey,
+        params: &Params,
+    ) -> Result<bool, CoconutError> {
+        self.verify_with_hasher(verifier_nonce, vk, params, &DefaultChallengeHasher)
+    }
+
+    /// Same as `verify` but first checks that `vk` is the verkey with `fingerprint`, like a
+    /// fingerprint of the expected issuer's verkey cached by an offline verifier. Errors with
+    /// `VerkeyFingerprintMismatch` if not, before any pairing.
+    pub fn verify_with_fingerprint(
+        &self,
+        verifier_nonce: &[u8],
+        fingerprint: &[u8; VERKEY_FINGERPRINT_SIZE],
+        vk: &Verkey,
+        params: &Params,
+    ) -> Result<bool, CoconutError> {
+        if vk.fingerprint() != *fingerprint {
+            return Err(CoconutErrorKind::VerkeyFingerprintMismatch.into());
+        }
+        self.verify(verifier_nonce, vk, params)
+    }
+
+    /// Same as `verify` but first checks that the disclosed attributes meet `requirements`. Errors
+    /// with `DisclosedAttributeMismatch` if a required attribute is not disclosed or differs from
+    /// the expected value, before any pairing.
+    pub fn verify_with_requirements(
+        &self,
+        verifier_nonce: &[u8],
+        requirements: &ShowingRequirements,
+        vk: &Verkey,
+        params: &Params,
+    ) -> Result<bool, CoconutError> {
+        requirements.check(self)?;
+        self.verify(verifier_nonce, vk, params)
+    }
+
+    /// Same as `verify` for a proof whose challenge was derived with `hasher`, see
+    /// `CredentialProofBuilder::challenge_hasher`
+    pub fn verify_with_hasher(
+        &self,
+        verifier_nonce: &[u8],
+        vk: &Verkey,
+        params: &Params,
+        hasher: &dyn ChallengeHasher,
+    ) -> Result<bool, CoconutError> {
+        let challenge = self.challenge_with_hasher(verifier_nonce, vk, params, hasher);
+        self.verify_with_challenge(&challenge, vk, params)
+    }
+
+    /// Verify a presentation and the proof of knowledge of the signature request `sig_req`, with
+    /// Elgamal public key `elgamal_pk`, created together by
+    /// `CredentialProofBuilder::finish_linked`.
+    /// Both must verify with the presentation's challenge and each hidden message of the request
+    /// must have the same response in both, so the credential shown carries the messages hidden
+    /// in the request.
+    pub fn verify_linked(
+        &self,
+        verifier_nonce: &[u8],
+        sig_req: &SignatureRequest,
+        req_proof: &SignatureRequestProof,
+        elgamal_pk: &SignatureGroup,
+        vk: &Verkey,
+        params: &Params,
+    ) -> Result<bool, CoconutError> {
+        let request_bytes = request_commitment_bytes(&req_proof.commitments());
+        let challenge = self.challenge_for(
+            verifier_nonce,
+            &request_bytes,
+            vk,
+            params,
+            &DefaultChallengeHasher,
+        );
+        let count_hidden = sig_req.ciphertexts.len();
+        if req_proof.proof_commitment.responses.len() <= count_hidden {
+            return Ok(false);
+        }
+        for i in 0..count_hidden {
+            match self.hidden_msg_response(i) {
+                Some(r) if *r == req_proof.proof_commitment.responses[i] => (),
+                _ => return Ok(false),
+            }
+        }
+        Ok(req_proof.verify(sig_req, elgamal_pk, &challenge, params)?
+            && self.verify_with_challenge(&challenge, vk, params)?)
+    }
+
+    /// Verify a presentation of a device bound credential and the device's proof, created
+    /// together by `CredentialProofBuilder::finish_device_bound`. The presentation must reveal
+    /// the device attribute of `device_pk` and the device's proof must be of the secret key of
+    /// `device_pk`, both with the presentation's challenge.
+    pub fn verify_device_bound(
+        &self,
+        verifier_nonce: &[u8],
+        device_proof: &DeviceBindingProof,
+        device_pk: &SignatureGroup,
+        vk: &Verkey,
+        params: &Params,
+    ) -> Result<bool, CoconutError> {
+        let device_bytes = DeviceBindingProof::show_challenge_bytes(
+            device_pk,
+            &device_proof.proof_vc.commitment.to_bytes(),
+        );
+        let challenge = self.challenge_for(
+            verifier_nonce,
+            &device_bytes,
+            vk,
+            params,
+            &DefaultChallengeHasher,
+        );
+        let revealed_msgs = self
+            .revealed_msgs
+            .iter()
+            .map(|(i, m)| (*i, m.clone()))
+            .collect::<HashMap<usize, FieldElement>>();
+        Ok(
+            device_proof.verify(device_pk, &revealed_msgs, &challenge, params)?
+                && self.verify_with_challenge(&challenge, vk, params)?,
+        )
+    }
+
+    fn verify_with_challenge(
+        &self,
+        challenge: &FieldElement,
+        vk: &Verkey,
+        params: &Params,
+    ) -> Result<bool, CoconutError> {
+        if !params.reveals_type_attribute(&self.revealed_msgs) {
+            return Ok(false);
+        }
+        for (i, j) in &self.equal_attributes {
+            match (self.hidden_msg_response(*i), self.hidden_msg_response(*j)) {
+                (Some(r_i), Some(r_j)) => {
+                    if r_i != r_j {
+                        return Ok(false);
+                    }
+                }
+                _ => return Ok(false),
+            }
+        }
+        for ineq in &self.not_equal_attributes {
+            match self.hidden_msg_response(ineq.idx) {
+                Some(r) => {
+                    if !ineq.verify(r, challenge, params)? {
+                        return Ok(false);
+                    }
+                }
+                None => return Ok(false),
+            }
+        }
+        for relation in &self.linear_relations {
+            if !self.verify_linear_relation(relation, challenge) {
+                return Ok(false);
+            }
+        }
+        for enc in &self.auditable_attributes {
+            match self.hidden_msg_response(enc.idx) {
+                Some(r) => {
+                    if !enc.verify(r, challenge, params)? {
+                        return Ok(false);
+                    }
+                }
+                None => return Ok(false),
+            }
+        }
+        for opening in &self.committed_attributes {
+            match self.hidden_msg_response(opening.idx) {
+                Some(r) => {
+                    if !opening.verify(r, challenge)? {
+                        return Ok(false);
+                    }
+                }
+                None => return Ok(false),
+            }
+        }
+
+        let revealed_msgs = self
+            .revealed_msgs
+            .iter()
+            .map(|(i, m)| (*i, m.clone()))
+            .collect::<HashMap<usize, FieldElement>>();
+        let res = self.proof.verify(
+            &transform_to_PS_verkey(vk),
+            &transform_to_PS_params(params),
+            revealed_msgs,
+            challenge,
+        )?;
+        Ok(res)
+    }
+
+    /// Check sum of a_i.s_i = -c.b over the responses s_i
+    fn verify_linear_relation(&self, relation: &LinearRelation, challenge: &FieldElement) -> bool {
+        let mut sum = FieldElement::zero();
+        for (i, a) in &relation.coeffs {
+            match self.hidden_msg_response(*i) {
+                Some(s) => sum = &sum + &(a * s),
+                None => return false,
+            }
+        }
+        sum == -(challenge * &relation.value)
+    }
+
+    /// Schnorr response for the hidden attribute at index `idx`. None if the attribute is revealed.
+    pub fn hidden_msg_response(&self, idx: usize) -> Option<&FieldElement> {
+        hidden_attribute_response(&self.proof, self.revealed_msgs.keys(), idx)
+    }
+
+    /// Byte representation is sigma_1 || sigma_2 || J || commitment || count of responses ||
+    /// responses || count of revealed attributes || (index || attribute) for each revealed attribute ||
+    /// count of equalities || (index || index) for each equality || count of inequalities ||
+    /// (index || value || C || commitment || 2 responses || commitment || 2 responses) for each
+    /// inequality || count of linear relations || (count of terms || (index || coefficient) for
+    /// each term || value) for each relation || count of auditable attributes || (index ||
+    /// auditor public key || ciphertext || commitment || response || commitment || 2 responses)
+    /// for each auditable attribute || count of committed attributes || (index || commitment key
+    /// || C || commitment || 2 responses) for each committed attribute. Counts and indices are 4
+    /// byte big-endian integers. The proof can be verified using only these bytes, the verkey and
+    /// params.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.proof.sig.sigma_1.to_bytes();
+        bytes.append(&mut self.proof.sig.sigma_2.to_bytes());
+        bytes.append(&mut self.proof.J.to_bytes());
+        bytes.append(&mut self.proof.proof_vc.commitment.to_bytes());
+        append_u32(&mut bytes, self.proof.proof_vc.responses.len());
+        for r in self.proof.proof_vc.responses.iter() {
+            bytes.append(&mut r.to_bytes());
+        }
+        append_u32(&mut bytes, self.revealed_msgs.len());
+        for (i, m) in &self.revealed_msgs {
+            append_u32(&mut bytes, *i);
+            bytes.append(&mut m.to_bytes());
+        }
+        append_u32(&mut bytes, self.equal_attributes.len());
+        for (i, j) in &self.equal_attributes {
+            append_u32(&mut bytes, *i);
+            append_u32(&mut bytes, *j);
+        }
+        append_u32(&mut bytes, self.not_equal_attributes.len());
+        for ineq in &self.not_equal_attributes {
+            append_u32(&mut bytes, ineq.idx);
+            bytes.append(&mut ineq.value.to_bytes());
+            bytes.append(&mut ineq.commitment.to_bytes());
+            for proof in &[&ineq.proof_opening, &ineq.proof_inverse] {
+                bytes.append(&mut proof.commitment.to_bytes());
+                for r in proof.responses.iter() {
+                    bytes.append(&mut r.to_bytes());
+                }
+            }
+        }
+        append_linear_relations(&mut bytes, &self.linear_relations);
+        append_u32(&mut bytes, self.auditable_attributes.len());
+        for enc in &self.auditable_attributes {
+            append_u32(&mut bytes, enc.idx);
+            bytes.append(&mut enc.auditor_pk.to_bytes());
+            bytes.append(&mut enc.ciphertext.0.to_bytes());
+            bytes.append(&mut enc.ciphertext.1.to_bytes());
+            for proof in &[&enc.proof_randomness, &enc.proof_ciphertext] {
+                bytes.append(&mut proof.commitment.to_bytes());
+                for r in proof.responses.iter() {
+                    bytes.append(&mut r.to_bytes());
+                }
+            }
+        }
+        append_u32(&mut bytes, self.committed_attributes.len());
+        for opening in &self.committed_attributes {
+            append_u32(&mut bytes, opening.idx);
+            bytes.append(&mut opening.comm_key.g.to_bytes());
+            bytes.append(&mut opening.comm_key.h.to_bytes());
+            bytes.append(&mut opening.commitment.to_bytes());
+            bytes.append(&mut opening.proof_opening.commitment.to_bytes());
+            for r in opening.proof_opening.responses.iter() {
+                bytes.append(&mut r.to_bytes());
+            }
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CoconutError> {
+        let mut reader = ByteReader::new(bytes);
+        let sigma_1 = reader.read_group_elem::<SignatureGroup>(SIGNATURE_GROUP_SIZE)?;
+        let sigma_2 = reader.read_group_elem::<SignatureGroup>(SIGNATURE_GROUP_SIZE)?;
+        let J = reader.read_group_elem::<OtherGroup>(OTHER_GROUP_SIZE)?;
+        let commitment = reader.read_group_elem::<OtherGroup>(OTHER_GROUP_SIZE)?;
+        let resp_count = reader.read_count(FIELD_ELEMENT_SIZE)?;
+        let mut responses = FieldElementVector::with_capacity(resp_count);
+        for _ in 0..resp_count {
+            responses.push(reader.read_field_element()?);
+        }
+        let revealed_count = reader.read_count(4 + FIELD_ELEMENT_SIZE)?;
+        let mut revealed_msgs = BTreeMap::new();
+        for _ in 0..revealed_count {
+            let i = reader.read_u32()?;
+            revealed_msgs.insert(i, reader.read_field_element()?);
+        }
+        let eq_count = reader.read_count(8)?;
+        let mut equal_attributes = Vec::with_capacity(eq_count);
+        for _ in 0..eq_count {
+            equal_attributes.push((reader.read_u32()?, reader.read_u32()?));
+        }
+        // Index, value, commitment and 2 Schnorr proofs of a commitment and 2 responses each
+        let neq_count = reader.read_count(4 + 5 * FIELD_ELEMENT_SIZE + 3 * SIGNATURE_GROUP_SIZE)?;
+        let mut not_equal_attributes = Vec::with_capacity(neq_count);
+        for _ in 0..neq_count {
+            let idx = reader.read_u32()?;
+            let value = reader.read_field_element()?;
+            let commitment = reader.read_group_elem::<SignatureGroup>(SIGNATURE_GROUP_SIZE)?;
+            let proof_opening = read_schnorr_proof(&mut reader, 2)?;
+            let proof_inverse = read_schnorr_proof(&mut reader, 2)?;
+            not_equal_attributes.push(AttributeInequalityProof {
+                idx,
+                value,
+                commitment,
+                proof_opening,
+                proof_inverse,
+            });
+        }
+        // Term count and value
+        let rel_count = reader.read_count(4 + FIELD_ELEMENT_SIZE)?;
+        let mut linear_relations = Vec::with_capacity(rel_count);
+        for _ in 0..rel_count {
+            let term_count = reader.read_count(4 + FIELD_ELEMENT_SIZE)?;
+            let mut coeffs = Vec::with_capacity(term_count);
+            for _ in 0..term_count {
+                coeffs.push((reader.read_u32()?, reader.read_field_element()?));
+            }
+            let value = reader.read_field_element()?;
+            linear_relations.push(LinearRelation { coeffs, value });
+        }
+        // Index, public key, ciphertext and Schnorr proofs of 1 and 2 responses
+        let aud_count = reader.read_count(4 + 5 * SIGNATURE_GROUP_SIZE + 3 * FIELD_ELEMENT_SIZE)?;
+        let mut auditable_attributes = Vec::with_capacity(aud_count);
+        for _ in 0..aud_count {
+            let idx = reader.read_u32()?;
+            let auditor_pk = reader.read_group_elem::<SignatureGroup>(SIGNATURE_GROUP_SIZE)?;
+            let c1 = reader.read_group_elem::<SignatureGroup>(SIGNATURE_GROUP_SIZE)?;
+            let c2 = reader.read_group_elem::<SignatureGroup>(SIGNATURE_GROUP_SIZE)?;
+            let proof_randomness = read_schnorr_proof(&mut reader, 1)?;
+            let proof_ciphertext = read_schnorr_proof(&mut reader, 2)?;
+            auditable_attributes.push(AuditableAttributeProof {
+                idx,
+                auditor_pk,
+                ciphertext: (c1, c2),
+                proof_randomness,
+                proof_ciphertext,
+            });
+        }
+        // Index, commitment key, commitment and a Schnorr proof of 2 responses
+        let comm_count =
+            reader.read_count(4 + 4 * SIGNATURE_GROUP_SIZE + 2 * FIELD_ELEMENT_SIZE)?;
+        let mut committed_attributes = Vec::with_capacity(comm_count);
+        for _ in 0..comm_count {
+            let idx = reader.read_u32()?;
+            let g = reader.read_group_elem::<SignatureGroup>(SIGNATURE_GROUP_SIZE)?;
+            let h = reader.read_group_elem::<SignatureGroup>(SIGNATURE_GROUP_SIZE)?;
+            let commitment = reader.read_group_elem::<SignatureGroup>(SIGNATURE_GROUP_SIZE)?;
+            let proof_opening = read_schnorr_proof(&mut reader, 2)?;
+            committed_attributes.push(CommittedAttributeProof {
+                idx,
+                commitment,
+                comm_key: CommitmentKey { g, h },
+                proof_opening,
+            });
+        }
+        reader.finish()?;
+        Ok(Self {
+            proof: PoKOfSignatureProof {
+                sig: PSSignature { sigma_1, sigma_2 },
+                J,
+                proof_vc: ProofOtherGroup {
+                    commitment,
+                    responses,
+                },
+            },
+            revealed_msgs,
+            equal_attributes,
+            not_equal_attributes,
+            linear_relations,
+            auditable_attributes,
+            committed_attributes,
+        })
+    }
+
+    /// Compact byte representation for constrained transports like QR codes. The commitment is
+    /// replaced by the challenge, which is smaller and from which the verifier recomputes the
+    /// commitment. The number of responses follows from the verkey and counts and indices are
+    /// single bytes. Group elements are uncompressed since amcl_wrapper does not decode
+    /// compressed points. Layout is sigma_1 || sigma_2 || J || challenge || count of revealed
+    /// attributes || (index || attribute) for each revealed attribute || count of equalities ||
+    /// (index || index) for each equality || responses. Errors if an index or count is over 255
+    /// or if the proof has inequalities of attributes, linear relations, auditable or committed
+    /// attributes, which the compact encoding does not carry.
+    pub fn to_compact_bytes(
+        &self,
+        verifier_nonce: &[u8],
+        vk: &Verkey,
+        params: &Params,
+    ) -> Result<Vec<u8>, CoconutError> {
+        self.compact_bytes(&self.challenge(verifier_nonce, vk, params))
+    }
+
+    fn compact_bytes(&self, challenge: &FieldElement) -> Result<Vec<u8>, CoconutError> {
+        if !self.not_equal_attributes.is_empty()
+            || !self.linear_relations.is_empty()
+            || !self.auditable_attributes.is_empty()
+            || !self.committed_attributes.is_empty()
+        {
+            return Err(CoconutErrorKind::GeneralError {
+                msg: String::from(
+                    "Inequalities of attributes, linear relations, auditable and committed \
+                     attributes have no compact encoding",
+                ),
+            }
+            .into());
+        }
+        let mut bytes = self.proof.sig.sigma_1.to_bytes();
+        bytes.append(&mut self.proof.sig.sigma_2.to_bytes());
+        bytes.append(&mut self.proof.J.to_bytes());
+        bytes.append(&mut challenge.to_bytes());
+        push_u8(&mut bytes, self.revealed_msgs.len())?;
+        for (i, m) in &self.revealed_msgs {
+            push_u8(&mut bytes, *i)?;
+            bytes.append(&mut m.to_bytes());
+        }
+        push_u8(&mut bytes, self.equal_attributes.len())?;
+        for (i, j) in &self.equal_attributes {
+            push_u8(&mut bytes, *i)?;
+            push_u8(&mut bytes, *j)?;
+        }
+        for r in self.proof.proof_vc.responses.iter() {
+            bytes.append(&mut r.to_bytes());
+        }
+        Ok(bytes)
+    }
+
+    /// Parse bytes created by `to_compact_bytes`. The commitment is recomputed from the challenge
+    /// and responses as g_tilde^s_0.Y_tilde_i^s_i...(J.X_tilde^-1.Y_tilde_j^-m_j...)^c over the
+    /// hidden attributes i and revealed attributes j, so the proof only verifies if the challenge
+    /// recomputed from this commitment is the encoded challenge.
+    pub fn from_compact_bytes(
+        bytes: &[u8],
+        vk: &Verkey,
+        params: &Params,
+    ) -> Result<Self, CoconutError> {
+        Ok(Self::from_compact_bytes_with_challenge(bytes, vk, params)?.0)
+    }
+
+    /// `from_compact_bytes` also returning the encoded challenge
+    fn from_compact_bytes_with_challenge(
+        bytes: &[u8],
+        vk: &Verkey,
+        params: &Params,
+    ) -> Result<(Self, FieldElement), CoconutError> {
+        let msg_count = vk.Y_tilde.len();
+        let mut reader = ByteReader::new(bytes);
+        let sigma_1 = reader.read_group_elem::<SignatureGroup>(SIGNATURE_GROUP_SIZE)?;
+        let sigma_2 = reader.read_group_elem::<SignatureGroup>(SIGNATURE_GROUP_SIZE)?;
+        let J = reader.read_group_elem::<OtherGroup>(OTHER_GROUP_SIZE)?;
+        let challenge = reader.read_field_element()?;
+        let revealed_count = reader.take(1)?[0] as usize;
+        let mut revealed_msgs = BTreeMap::new();
+        for _ in 0..revealed_count {
+            let i = reader.take(1)?[0] as usize;
+            check_attribute_index(i, msg_count)?;
+            if revealed_msgs
+                .insert(i, reader.read_field_element()?)
+                .is_some()
+            {
+                return Err(CoconutErrorKind::GeneralError {
+                    msg: format!("Attribute {} revealed more than once", i),
+                }
+                .into());
+            }
+        }
+        let eq_count = reader.take(1)?[0] as usize;
+        let mut equal_attributes = Vec::with_capacity(eq_count);
+        for _ in 0..eq_count {
+            let i = reader.take(1)?[0] as usize;
+            let j = reader.take(1)?[0] as usize;
+            equal_attributes.push((i, j));
+        }
+        // 1 response for the randomness in J and 1 for each hidden attribute
+        let resp_count = 1 + msg_count - revealed_msgs.len();
+        let mut responses = FieldElementVector::with_capacity(resp_count);
+        for _ in 0..resp_count {
+            responses.push(reader.read_field_element()?);
+        }
+        reader.finish()?;
+
+        let mut bases = OtherGroupVec::with_capacity(resp_count + 1);
+        bases.push(params.g_tilde.clone());
+        for i in 0..msg_count {
+            if !revealed_msgs.contains_key(&i) {
+                bases.push(vk.Y_tilde[i].clone());
+            }
+        }
+        let mut j = &J - &vk.X_tilde;
+        for (i, m) in &revealed_msgs {
+            j = &j - &(&vk.Y_tilde[*i] * m);
+        }
+        bases.push(j);
+        let mut exps = responses.clone();
+        exps.push(challenge.clone());
+        let commitment = bases.multi_scalar_mul_var_time(&exps)?;
+
+        let proof = Self {
+            proof: PoKOfSignatureProof {
+                sig: PSSignature { sigma_1, sigma_2 },
+                J,
+                proof_vc: ProofOtherGroup {
+                    commitment,
+                    responses,
+                },
+            },
+            revealed_msgs,
+            equal_attributes,
+            not_equal_attributes: vec![],
+            linear_relations: vec![],
+            auditable_attributes: vec![],
+            committed_attributes: vec![],
+        };
+        Ok((proof, challenge))
+    }
+
+    /// Size in bytes of `to_compact_bytes` for a verkey over `msg_count` attributes
+    pub fn compact_size(msg_count: usize, revealed_count: usize, eq_count: usize) -> usize {
+        2 * SIGNATURE_GROUP_SIZE
+            + OTHER_GROUP_SIZE
+            + FIELD_ELEMENT_SIZE
+            + 1
+            + revealed_count * (1 + FIELD_ELEMENT_SIZE)
+            + 1
+            + 2 * eq_count
+            + (1 + msg_count - revealed_count) * FIELD_ELEMENT_SIZE
+    }
+
+    /// Challenge of the proof for `verifier_nonce`, verkey `vk` and the schema digest of `params`
+    fn challenge(&self, verifier_nonce: &[u8], vk: &Verkey, params: &Params) -> FieldElement {
+        self.challenge_with_hasher(verifier_nonce, vk, params, &DefaultChallengeHasher)
+    }
+
+    fn challenge_with_hasher(
+        &self,
+        verifier_nonce: &[u8],
+        vk: &Verkey,
+        params: &Params,
+        hasher: &dyn ChallengeHasher,
+    ) -> FieldElement {
+        self.challenge_for(verifier_nonce, &[], vk, params, hasher)
+    }
+
+    /// `extra_statements` are the bytes of the commitments of the proofs created along with the
+    /// presentation, the request's proof of knowledge of a linked presentation (see
+    /// `BlindingContext`) or the device's proof of a device bound one, or empty
+    fn challenge_for(
+        &self,
+        verifier_nonce: &[u8],
+        extra_statements: &[u8],
+        vk: &Verkey,
+        params: &Params,
+        hasher: &dyn ChallengeHasher,
+    ) -> FieldElement {
+        let mut statements = statement_bytes(&self.equal_attributes, &self.linear_relations);
+        for ineq in &self.not_equal_attributes {
+            ineq.append_challenge_bytes(&mut statements);
+        }
+        for enc in &self.auditable_attributes {
+            enc.append_challenge_bytes(&mut statements);
+        }
+        for opening in &self.committed_attributes {
+            opening.append_challenge_bytes(&mut statements);
+        }
+        statements.extend_from_slice(extra_statements);
+        hasher.hash_to_field(&Self::challenge_bytes(
+            &self.proof.sig,
+            &self.proof.J,
+            &self.proof.proof_vc.commitment.to_bytes(),
+            &self.revealed_msgs,
+            &statements,
+            verifier_nonce,
+            &challenge_context(vk, params),
+        ))
+    }
+
+    /// Bytes hashed for the challenge. `statements` are the bytes of the statements proven about
+    /// the hidden attributes, see `statement_bytes`, and `context` is from `challenge_context`.
+    fn challenge_bytes(
+        sig: &PSSignature,
+        J: &OtherGroup,
+        commitment: &[u8],
+        revealed_msgs: &BTreeMap<usize, FieldElement>,
+        statements: &[u8],
+        verifier_nonce: &[u8],
+        context: &[u8],
+    ) -> Vec<u8> {
+        let mut bytes = context.to_vec();
+        append_pok_bytes(&mut bytes, sig, J, commitment, revealed_msgs);
+        bytes.extend_from_slice(statements);
+        append_u32(&mut bytes, verifier_nonce.len());
+        bytes.extend_from_slice(verifier_nonce);
+        bytes
+    }
+}
+
+/// Bytes of the verkey and of the schema digest of the params, if any, that go in the challenge
+/// of a `CredentialProof` and of a `MultiCredentialProof`. With the digest, a proof made under params for 1 schema doesn't verify
+/// under params for another, see `Params::with_schema_digest`.
+fn challenge_context(vk: &Verkey, params: &Params) -> Vec<u8> {
+    let mut bytes = vk.to_bytes();
+    if let Some(digest) = params.schema_digest() {
+        bytes.extend_from_slice(digest);
+    }
+    bytes
+}
+
+/// Bytes of the equalities and linear relations of a proof that go in the challenge. The bytes
+/// of each inequality and then of each auditable attribute follow them.
+fn statement_bytes(
+    equal_attributes: &[(usize, usize)],
+    linear_relations: &[LinearRelation],
+) -> Vec<u8> {
+    let mut bytes = vec![];
+    for (i, j) in equal_attributes {
+        bytes.extend_from_slice(&(*i as u32).to_be_bytes());
+        bytes.extend_from_slice(&(*j as u32).to_be_bytes());
+    }
+    append_linear_relations(&mut bytes, linear_relations);
+    bytes
+}
+
+fn append_linear_relations(bytes: &mut Vec<u8>, linear_relations: &[LinearRelation]) {
+    append_u32(bytes, linear_relations.len());
+    for relation in linear_relations {
+        append_u32(bytes, relation.coeffs.len());
+        for (i, a) in &relation.coeffs {
+            append_u32(bytes, *i);
+            bytes.append(&mut a.to_bytes());
+        }
+        bytes.append(&mut relation.value.to_bytes());
+    }
+}
+
+/// Schnorr proof of `response_count` responses as written in `CredentialProof::to_bytes`
+fn read_schnorr_proof(
+    reader: &mut ByteReader,
+    response_count: usize,
+) -> Result<ProofSignatureGroup, CoconutError> {
+    let commitment = reader.read_group_elem::<SignatureGroup>(SIGNATURE_GROUP_SIZE)?;
+    let mut responses = FieldElementVector::with_capacity(response_count);
+    for _ in 0..response_count {
+        responses.push(reader.read_field_element()?);
+    }
+    Ok(ProofSignatureGroup {
+        commitment,
+        responses,
+    })
+}
+
+fn push_u8(bytes: &mut Vec<u8>, n: usize) -> Result<(), CoconutError> {
+    if n > u8::max_value() as usize {
+        return Err(CoconutErrorKind::GeneralError {
+            msg: format!("{} does not fit in 1 byte", n),
+        }
+        .into());
+    }
+    bytes.push(n as u8);
+    Ok(())
+}
+
+/// Bytes of the proof of knowledge of 1 signature that go in the challenge
+fn append_pok_bytes(
+    bytes: &mut Vec<u8>,
+    sig: &PSSignature,
+    J: &OtherGroup,
+    commitment: &[u8],
+    revealed_msgs: &BTreeMap<usize, FieldElement>,
+) {
+    bytes.append(&mut sig.sigma_1.to_bytes());
+    bytes.append(&mut sig.sigma_2.to_bytes());
+    bytes.append(&mut J.to_bytes());
+    bytes.extend_from_slice(commitment);
+    for (i, m) in revealed_msgs {
+        bytes.extend_from_slice(&(*i as u32).to_be_bytes());
+        bytes.append(&mut m.to_bytes());
+    }
+}
+
+/// Presentation of a credential in as few bytes as possible, like for a QR code. A
+/// `CredentialProof` (containing the randomized signature and the revealed attributes) in its
+/// compact encoding along with the fingerprint of the verkey. The verifier looks up the verkey by
+/// the fingerprint out of band.
+#[derive(Clone, Debug)]
+pub struct PresentationToken {
+    pub fingerprint: [u8; VERKEY_FINGERPRINT_SIZE],
+    pub proof: CredentialProof,
+    /// Challenge of `proof`, kept for the compact encoding
+    challenge: FieldElement,
+}
+
+impl PresentationToken {
+    /// Verifiers must give a fresh `verifier_nonce` for each presentation, see
+    /// `CredentialProof::new`
+    pub fn new(
+        sig: &Signature,
+        messages: &[FieldElement],
+        revealed_msg_indices: HashSet<usize>,
+        verifier_nonce: &[u8],
+        vk: &Verkey,
+        params: &Params,
+    ) -> Result<Self, CoconutError> {
+        let proof = CredentialProof::new(
+            sig,
+            messages,
+            revealed_msg_indices,
+            verifier_nonce,
+            vk,
+            params,
+        )?;
+        let challenge = proof.challenge(verifier_nonce, vk, params);
+        Ok(Self {
+            fingerprint: vk.fingerprint(),
+            proof,
+            challenge,
+        })
+    }
+
+    /// Byte representation is fingerprint || compact bytes of the proof, see
+    /// `CredentialProof::to_compact_bytes`
+    pub fn to_bytes(&self) -> Result<Vec<u8>, CoconutError> {
+        let mut bytes = self.fingerprint.to_vec();
+        bytes.append(&mut self.proof.compact_bytes(&self.challenge)?);
+        Ok(bytes)
+    }
+
+    /// Fingerprint of the verkey to look up before calling `from_bytes`
+    pub fn fingerprint_from_bytes(
+        bytes: &[u8],
+    ) -> Result<[u8; VERKEY_FINGERPRINT_SIZE], CoconutError> {
+        let mut fingerprint = [0u8; VERKEY_FINGERPRINT_SIZE];
+        fingerprint.copy_from_slice(ByteReader::new(bytes).take(VERKEY_FINGERPRINT_SIZE)?);
+        Ok(fingerprint)
+    }
+
+    /// Errors with `VerkeyFingerprintMismatch` if the fingerprint is not of `vk`
+    pub fn from_bytes(bytes: &[u8], vk: &Verkey, params: &Params) -> Result<Self, CoconutError> {
+        let fingerprint = Self::fingerprint_from_bytes(bytes)?;
+        if fingerprint != vk.fingerprint() {
+            return Err(CoconutErrorKind::VerkeyFingerprintMismatch.into());
+        }
+        let (proof, challenge) = CredentialProof::from_compact_bytes_with_challenge(
+            &bytes[VERKEY_FINGERPRINT_SIZE..],
+            vk,
+            params,
+        )?;
+        Ok(Self {
+            fingerprint,
+            proof,
+            challenge,
+        })
+    }
+
+    pub fn verify(
+        &self,
+        verifier_nonce: &[u8],
+        vk: &Verkey,
+        params: &Params,
+    ) -> Result<bool, CoconutError> {
+        if self.fingerprint != vk.fingerprint() {
+            return Ok(false);
+        }
+        self.proof.verify(verifier_nonce, vk, params)
+    }
+
+    /// Revealed attributes as index -> attribute
+    pub fn revealed_msgs(&self) -> &BTreeMap<usize, FieldElement> {
+        &self.proof.revealed_msgs
+    }
+}
+
+/// Opening of 1 attribute of a credential, like for resolving a dispute about what was signed.
+/// A `CredentialProof` revealing only the attribute at `index` so it proves that `value` is the
+/// attribute signed at `index` without revealing the other attributes or the credential.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OpeningProof {
+    pub index: usize,
+    pub value: FieldElement,
+    pub proof: CredentialProof,
+}
+
+impl OpeningProof {
+    /// Open the attribute at `index` of credential `sig` over `messages`
+    pub fn new(
+        sig: &Signature,
+        index: usize,
+        messages: &[FieldElement],
+        vk: &Verkey,
+        params: &Params,
+    ) -> Result<Self, CoconutError> {
+        check_attribute_index(index, vk.Y_tilde.len())?;
+        let proof = CredentialProofBuilder::new(sig, messages, vk, params)
+            .reveal(index)
+            .finish()?;
+        Ok(Self {
+            index,
+            value: messages[index].clone(),
+            proof,
+        })
+    }
+
+    /// Check that the proof reveals exactly `value` at `index` and verifies with `vk`
+    pub fn verify(&self, vk: &Verkey, params: &Params) -> Result<bool, CoconutError> {
+        if self.proof.revealed_msgs.len() != 1
+            || self.proof.revealed_msgs.get(&self.index) != Some(&self.value)
+            || !self.proof.equal_attributes.is_empty()
+            || !self.proof.not_equal_attributes.is_empty()
+            || !self.proof.linear_relations.is_empty()
+            || !self.proof.auditable_attributes.is_empty()
+            || !self.proof.committed_attributes.is_empty()
+        {
+            return Ok(false);
+        }
+        // Openings are meant to be passed on to others, like an arbiter, so are not bound to a
+        // verifier nonce
+        self.proof.verify(&[], vk, params)
+    }
+}
+
+/// Proof of knowledge of several credentials from the same issuer, each revealing some of its
+/// attributes, with a single challenge over all of them. The verifier learns that all credentials
+/// were presented together, it does not learn any link between their hidden attributes.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MultiCredentialProof {
+    pub proofs: Vec<PoKOfSignatureProof>,
+    /// Revealed attributes of each credential as index -> attribute
+    pub revealed_msgs: Vec<BTreeMap<usize, FieldElement>>,
+}
+
+/// Prove knowledge of each credential in `creds` given as (signature, messages, indices of hidden
+/// messages) under a shared challenge. Messages not hidden are revealed. The type attribute of
+/// typed params can't be hidden. As with `CredentialProof::new`, the proof only verifies with
+/// `verifier_nonce`, which verifiers must choose fresh for each presentation.
+pub fn present_multi(
+    creds: &[(&Signature, &[FieldElement], HashSet<usize>)],
+    verifier_nonce: &[u8],
+    vk: &Verkey,
+    params: &Params,
+) -> Result<MultiCredentialProof, CoconutError> {
+    if creds.is_empty() {
+        return Err(CoconutErrorKind::GeneralError {
+            msg: String::from("Need at least 1 credential to present"),
+        }
+        .into());
+    }
+    let msg_count = vk.Y_tilde.len();
+    let ps_vk = transform_to_PS_verkey(vk);
+    let ps_params = transform_to_PS_params(params);
+    let mut poks = Vec::with_capacity(creds.len());
+    let mut revealed_msgs = Vec::with_capacity(creds.len());
+    for (sig, messages, hidden_msg_indices) in creds {
+        if messages.len() != msg_count {
+            return Err(CoconutErrorKind::UnsupportedNoOfMessages {
+                expected: msg_count,
+                given: messages.len(),
+            }
+            .into());
+        }
+        for idx in hidden_msg_indices {
+            check_attribute_index(*idx, msg_count)?;
+            if params.type_attribute_index() == Some(*idx) {
+                return Err(CoconutErrorKind::GeneralError {
+                    msg: format!("Type attribute {} can't be hidden", idx),
+                }
+                .into());
+            }
+        }
+        let revealed_msg_indices = (0..msg_count)
+            .filter(|i| !hidden_msg_indices.contains(i))
+            .collect::<HashSet<usize>>();
+        poks.push(PoKOfSignature::init(
+            &transform_to_PS_sig(sig),
+            &ps_vk,
+            &ps_params,
+            messages,
+            None,
+            revealed_msg_indices.clone(),
+        )?);
+        revealed_msgs.push(
+            revealed_msg_indices
+                .iter()
+                .map(|i| (*i, messages[*i].clone()))
+                .collect::<BTreeMap<usize, FieldElement>>(),
+        );
+    }
+
+    let mut bytes = challenge_context(vk, params);
+    for (pok, revealed) in poks.iter().zip(revealed_msgs.iter()) {
+        append_pok_bytes(
+            &mut bytes,
+            &pok.sig,
+            &pok.J,
+            &pok.pok_vc.schnorr_commitment().to_bytes(),
+            revealed,
+        );
+    }
+    append_u32(&mut bytes, verifier_nonce.len());
+    bytes.extend_from_slice(verifier_nonce);
+    let challenge = FieldElement::from_msg_hash(&bytes);
+    let mut proofs = Vec::with_capacity(poks.len());
+    for pok in poks {
+        proofs.push(pok.gen_proof(&challenge)?);
+    }
+    Ok(MultiCredentialProof {
+        proofs,
+        revealed_msgs,
+    })
+}
+
+impl MultiCredentialProof {
+    /// `verifier_nonce` is the nonce the verifier gave for this presentation
+    pub fn verify(
+        &self,
+        verifier_nonce: &[u8],
+        vk: &Verkey,
+        params: &Params,
+    ) -> Result<bool, CoconutError> {
+        if self.proofs.is_empty() || self.proofs.len() != self.revealed_msgs.len() {
+            return Ok(false);
+        }
+        let mut bytes = challenge_context(vk, params);
+        for (proof, revealed) in self.proofs.iter().zip(self.revealed_msgs.iter()) {
+            append_pok_bytes(
+                &mut bytes,
+                &proof.sig,
+                &proof.J,
+                &proof.proof_vc.commitment.to_bytes(),
+                revealed,
+            );
+        }
+        append_u32(&mut bytes, verifier_nonce.len());
+        bytes.extend_from_slice(verifier_nonce);
+        let challenge = FieldElement::from_msg_hash(&bytes);
+        let ps_vk = transform_to_PS_verkey(vk);
+        let ps_params = transform_to_PS_params(params);
+        for (proof, revealed) in self.proofs.iter().zip(self.revealed_msgs.iter()) {
+            if !params.reveals_type_attribute(revealed) {
+                return Ok(false);
+            }
+            let revealed = revealed
+                .iter()
+                .map(|(i, m)| (*i, m.clone()))
+                .collect::<HashMap<usize, FieldElement>>();
+            if !proof.verify(&ps_vk, &ps_params, revealed, &challenge)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::challenge::Sha256ChallengeHasher;
     use crate::keygen::trusted_party_SSS_keygen;
-    use crate::signature::{BlindSignature, SignatureRequest, SignatureRequestPoK, transform_to_PS_params, transform_to_PS_verkey, transform_to_PS_sig};
+    use crate::signature::{
+        BlindSignature, SignatureRequest, SignatureRequestPoK, transform_to_PS_params,
+        transform_to_PS_verkey, transform_to_PS_sig,
+    };
+    use crate::schema::SCHEMA_DIGEST_SIZE;
+    use crate::test_utils::{issue_credential, issue_credentials};
     use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
     use ps_sig::pok_sig::PoKOfSignature;
     use std::collections::{HashMap, HashSet};
 
+    const NONCE: &[u8] = b"verifier nonce";
+
     #[test]
     fn test_PoK_sig() {
         // Test proof of knowledge of signature and reveal some of the messages
@@ -26,7 +2277,8 @@ mod tests {
 
         let msgs = FieldElementVector::random(msg_count);
         let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
-        let (sig_req, randomness) = SignatureRequest::new(&msgs, count_hidden, &elg_pk, &params);
+        let (sig_req, randomness) =
+            SignatureRequest::new(&msgs, count_hidden, &elg_pk, &params).unwrap();
 
         // Initiate proof of knowledge of various items of Signature request
         let sig_req_pok = SignatureRequestPoK::init(&sig_req, &elg_pk, &params);
@@ -51,7 +2303,7 @@ mod tests {
             assert!(sig_req_proof
                 .verify(&sig_req, &elg_pk, &challenge, &params)
                 .unwrap());
-            blinded_sigs.push(BlindSignature::new(&sig_req, &signers[i].sigkey));
+            blinded_sigs.push(BlindSignature::new(&sig_req, &signers[i].sigkey).unwrap());
         }
 
         let mut unblinded_sigs = vec![];
@@ -60,7 +2312,7 @@ mod tests {
             unblinded_sigs.push((signers[i].id, unblinded_sig));
         }
 
-        let aggr_sig = Signature::aggregate(threshold, unblinded_sigs);
+        let aggr_sig = Signature::aggregate(threshold, unblinded_sigs).unwrap();
 
         let aggr_vk = Verkey::aggregate(
             threshold,
@@ -68,9 +2320,10 @@ mod tests {
                 .iter()
                 .map(|s| (s.id, &s.verkey))
                 .collect::<Vec<(usize, &Verkey)>>(),
-        );
+        )
+        .unwrap();
 
-        assert!(aggr_sig.verify(msgs.as_slice(), &aggr_vk, &params));
+        assert!(aggr_sig.verify(msgs.as_slice(), &aggr_vk, &params).unwrap());
 
         let ps_params = transform_to_PS_params(&params);
         let ps_verkey = transform_to_PS_verkey(&aggr_vk);
@@ -92,7 +2345,9 @@ mod tests {
         )
         .unwrap();
         let chal = FieldElement::from_msg_hash(&pok.to_bytes());
+        let t = pok.pok_vc.schnorr_commitment();
         let proof = pok.gen_proof(&chal).unwrap();
+        assert_eq!(t, proof.proof_vc.commitment);
 
         // The prover reveals these messages
         let mut revealed_msgs = HashMap::new();
@@ -104,4 +2359,879 @@ mod tests {
             .verify(&ps_verkey, &ps_params, revealed_msgs.clone(), &chal)
             .unwrap());
     }
+
+    #[test]
+    fn test_credential_proof() {
+        let msg_count = 5;
+        for params in vec![
+            Params::new(msg_count, "test".as_bytes()),
+            Params::new_typed(msg_count, "test".as_bytes(), "passport".as_bytes()),
+        ] {
+            let msgs = params.typed_messages(FieldElementVector::random(msg_count).as_slice());
+            let (sig, vk) = issue_credential(&msgs, &params);
+
+            let mut revealed_msg_indices = HashSet::new();
+            revealed_msg_indices.insert(1);
+            revealed_msg_indices.insert(4);
+            let proof = CredentialProof::new(
+                &sig,
+                msgs.as_slice(),
+                revealed_msg_indices,
+                NONCE,
+                &vk,
+                &params,
+            )
+            .unwrap();
+            assert!(proof.verify(NONCE, &vk, &params).unwrap());
+
+            // Tampered revealed attribute
+            let mut tampered = proof.clone();
+            tampered.revealed_msgs.insert(4, FieldElement::random());
+            assert!(!tampered.verify(NONCE, &vk, &params).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_credential_proof_verifier_nonce() {
+        let msg_count = 3;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let msgs = FieldElementVector::random(msg_count);
+        let (sig, vk) = issue_credential(&msgs, &params);
+
+        let revealed_msg_indices = vec![0].into_iter().collect::<HashSet<usize>>();
+        let proof = CredentialProof::new(
+            &sig,
+            msgs.as_slice(),
+            revealed_msg_indices.clone(),
+            b"nonce A",
+            &vk,
+            &params,
+        )
+        .unwrap();
+        assert!(proof.verify(b"nonce A", &vk, &params).unwrap());
+        // Replayed to a verifier with a different nonce
+        assert!(!proof.verify(b"nonce B", &vk, &params).unwrap());
+        assert!(!proof.verify(&[], &vk, &params).unwrap());
+
+        let token = PresentationToken::new(
+            &sig,
+            msgs.as_slice(),
+            revealed_msg_indices,
+            b"nonce A",
+            &vk,
+            &params,
+        )
+        .unwrap();
+        let parsed =
+            PresentationToken::from_bytes(&token.to_bytes().unwrap(), &vk, &params).unwrap();
+        assert!(parsed.verify(b"nonce A", &vk, &params).unwrap());
+        assert!(!parsed.verify(b"nonce B", &vk, &params).unwrap());
+    }
+
+    #[test]
+    fn test_hidden_attribute_position() {
+        let revealed = [1, 3].iter().cloned().collect::<HashSet<usize>>();
+        assert_eq!(hidden_attribute_position(0, &revealed), Some(0));
+        assert_eq!(hidden_attribute_position(1, &revealed), None);
+        assert_eq!(hidden_attribute_position(2, &revealed), Some(1));
+        assert_eq!(hidden_attribute_position(4, &revealed), Some(2));
+        assert_eq!(hidden_attribute_position(4, &HashSet::new()), Some(4));
+    }
+
+    #[test]
+    fn test_prove_attributes_equal() {
+        let msg_count = 5;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let mut msgs = FieldElementVector::random(msg_count);
+        // Billing country == shipping country
+        msgs[3] = msgs[1].clone();
+        let (sig, vk) = issue_credential(&msgs, &params);
+
+        let proof = CredentialProofBuilder::new(&sig, msgs.as_slice(), &vk, &params)
+            .verifier_nonce(NONCE)
+            .reveal(0)
+            .prove_attributes_equal(1, 3)
+            .finish()
+            .unwrap();
+        assert!(proof.verify(NONCE, &vk, &params).unwrap());
+        assert_eq!(proof.hidden_msg_response(1), proof.hidden_msg_response(3));
+
+        // Attributes 1 and 2 differ so equality proof fails
+        let proof = CredentialProofBuilder::new(&sig, msgs.as_slice(), &vk, &params)
+            .verifier_nonce(NONCE)
+            .reveal(0)
+            .prove_attributes_equal(1, 2)
+            .finish()
+            .unwrap();
+        assert!(!proof.verify(NONCE, &vk, &params).unwrap());
+
+        // Equality proof cannot be stripped from the proof
+        let mut stripped = CredentialProofBuilder::new(&sig, msgs.as_slice(), &vk, &params)
+            .verifier_nonce(NONCE)
+            .prove_attributes_equal(1, 3)
+            .finish()
+            .unwrap();
+        stripped.equal_attributes.clear();
+        assert!(!stripped.verify(NONCE, &vk, &params).unwrap());
+
+        // Revealed attribute cannot be used in equality
+        assert!(
+            CredentialProofBuilder::new(&sig, msgs.as_slice(), &vk, &params)
+                .verifier_nonce(NONCE)
+                .reveal(1)
+                .prove_attributes_equal(1, 3)
+                .finish()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_prove_attribute_not_equals() {
+        let msg_count = 4;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let msgs = FieldElementVector::random(msg_count);
+        let (sig, vk) = issue_credential(&msgs, &params);
+        // Blocklisted values of the attribute at index 2
+        let blocklist = vec![FieldElement::random(), FieldElement::random()];
+
+        let mut builder = CredentialProofBuilder::new(&sig, msgs.as_slice(), &vk, &params)
+            .verifier_nonce(NONCE)
+            .reveal(0);
+        for v in &blocklist {
+            builder = builder.prove_attribute_not_equals(2, v);
+        }
+        let proof = builder.finish().unwrap();
+        assert!(proof.verify(NONCE, &vk, &params).unwrap());
+        assert_eq!(
+            proof.not_equal_attributes[0].proof_opening.responses[0],
+            *proof.hidden_msg_response(2).unwrap()
+        );
+        let parsed = CredentialProof::from_bytes(&proof.to_bytes()).unwrap();
+        assert!(parsed.verify(NONCE, &vk, &params).unwrap());
+        assert!(proof.to_compact_bytes(NONCE, &vk, &params).is_err());
+
+        // Inequality proof cannot be stripped, moved to another attribute or to another value
+        let mut stripped = proof.clone();
+        stripped.not_equal_attributes.pop();
+        assert!(!stripped.verify(NONCE, &vk, &params).unwrap());
+        let mut moved = proof.clone();
+        moved.not_equal_attributes[0].idx = 3;
+        assert!(!moved.verify(NONCE, &vk, &params).unwrap());
+        let mut other_value = proof.clone();
+        other_value.not_equal_attributes[0].value = msgs[2].clone();
+        assert!(!other_value.verify(NONCE, &vk, &params).unwrap());
+
+        // The attribute equals the value so no inequality proof can be created
+        match CredentialProofBuilder::new(&sig, msgs.as_slice(), &vk, &params)
+            .verifier_nonce(NONCE)
+            .prove_attribute_not_equals(2, &msgs[2])
+            .finish()
+        {
+            Err(e) => match e.kind() {
+                CoconutErrorKind::GeneralError { .. } => (),
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(_) => panic!("proved inequality of equal attribute and value"),
+        }
+
+        // A proof for an attribute differing from the value doesn't verify for an attribute
+        // equal to it: the proof is for attribute 1 but claims attribute 2, equal to the value
+        let mut equal_msgs = msgs.clone();
+        equal_msgs[1] = blocklist[0].clone();
+        let (sig, vk) = issue_credential(&equal_msgs, &params);
+        let mut forged = CredentialProofBuilder::new(&sig, equal_msgs.as_slice(), &vk, &params)
+            .verifier_nonce(NONCE)
+            .prove_attribute_not_equals(2, &blocklist[0])
+            .finish()
+            .unwrap();
+        forged.not_equal_attributes[0].idx = 1;
+        assert!(!forged.verify(NONCE, &vk, &params).unwrap());
+
+        // Revealed attribute cannot be used in inequality
+        assert!(
+            CredentialProofBuilder::new(&sig, equal_msgs.as_slice(), &vk, &params)
+                .verifier_nonce(NONCE)
+                .reveal(2)
+                .prove_attribute_not_equals(2, &blocklist[0])
+                .finish()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_auditable_attribute() {
+        let msg_count = 4;
+        let params = Params::new_typed(msg_count, "test".as_bytes(), "account".as_bytes());
+        let msgs = params.typed_messages(FieldElementVector::random(msg_count).as_slice());
+        let (sig, vk) = issue_credential(&msgs, &params);
+        let (auditor_sk, auditor_pk) = elgamal_keygen!(&params.g);
+
+        // The attribute at index 1, like an account id, is encrypted to the auditor
+        let proof = CredentialProofBuilder::new(&sig, msgs.as_slice(), &vk, &params)
+            .verifier_nonce(NONCE)
+            .reveal(0)
+            .with_auditable_attribute(1, &auditor_pk)
+            .finish()
+            .unwrap();
+        assert!(proof.verify(NONCE, &vk, &params).unwrap());
+        let requirements = ShowingRequirements::new().require_auditable(1, &auditor_pk);
+        assert!(proof
+            .verify_with_requirements(NONCE, &requirements, &vk, &params)
+            .unwrap());
+        let parsed = CredentialProof::from_bytes(&proof.to_bytes()).unwrap();
+        assert!(parsed.verify(NONCE, &vk, &params).unwrap());
+        assert!(proof.to_compact_bytes(NONCE, &vk, &params).is_err());
+
+        // The verifier only has the ciphertext, which the auditor opens to the issued attribute
+        let enc = &proof.auditable_attributes[0];
+        assert!(!proof.revealed_msgs.contains_key(&1));
+        let issued = vec![FieldElement::random(), msgs[1].clone(), msgs[2].clone()];
+        assert_eq!(
+            enc.open(&auditor_sk, &issued, &params),
+            Some(msgs[1].clone())
+        );
+        assert_eq!(enc.open(&FieldElement::random(), &issued, &params), None);
+        assert_eq!(enc.open(&auditor_sk, &issued[2..], &params), None);
+
+        // Encrypting another value, moving the encryption to another attribute or to another
+        // auditor, or stripping it fails
+        let mut other_value = proof.clone();
+        let (c1, c2, _) = elgamal_encrypt!(&params.g, &auditor_pk, &params.h[0]);
+        other_value.auditable_attributes[0].ciphertext = (c1, c2);
+        assert!(!other_value.verify(NONCE, &vk, &params).unwrap());
+        let mut moved = proof.clone();
+        moved.auditable_attributes[0].idx = 2;
+        assert!(!moved.verify(NONCE, &vk, &params).unwrap());
+        let (_, other_pk) = elgamal_keygen!(&params.g);
+        let mut other_auditor = proof.clone();
+        other_auditor.auditable_attributes[0].auditor_pk = other_pk.clone();
+        assert!(!other_auditor.verify(NONCE, &vk, &params).unwrap());
+        let mut stripped = proof.clone();
+        stripped.auditable_attributes.pop();
+        assert!(!stripped.verify(NONCE, &vk, &params).unwrap());
+        match stripped
+            .verify_with_requirements(NONCE, &requirements, &vk, &params)
+            .unwrap_err()
+            .kind()
+        {
+            CoconutErrorKind::AuditableAttributeMissing { idx } => assert_eq!(idx, 1),
+            k => panic!("unexpected error kind {:?}", k),
+        }
+        let other_requirements = ShowingRequirements::new().require_auditable(1, &other_pk);
+        assert!(proof
+            .verify_with_requirements(NONCE, &other_requirements, &vk, &params)
+            .is_err());
+
+        // Revealed attribute cannot be encrypted
+        assert!(
+            CredentialProofBuilder::new(&sig, msgs.as_slice(), &vk, &params)
+                .verifier_nonce(NONCE)
+                .reveal(1)
+                .with_auditable_attribute(1, &auditor_pk)
+                .finish()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_committed_attribute() {
+        let msg_count = 4;
+        let params = Params::new_typed(msg_count, "test".as_bytes(), "payment".as_bytes());
+        let msgs = params.typed_messages(FieldElementVector::random(msg_count).as_slice());
+        let (sig, vk) = issue_credential(&msgs, &params);
+
+        // Deposit commitment to the hidden attribute at index 1 published before the showing
+        let comm_key = CommitmentKey::new("deposit".as_bytes());
+        let s = FieldElement::random();
+        let deposit = comm_key.commit(&msgs[1], &s);
+        let prove = |idx: usize, randomness: &FieldElement, comm_key: &CommitmentKey| {
+            CredentialProofBuilder::new(&sig, msgs.as_slice(), &vk, &params)
+                .verifier_nonce(NONCE)
+                .reveal(3)
+                .prove_attribute_committed(idx, randomness, comm_key)
+                .finish()
+                .unwrap()
+        };
+
+        let proof = prove(1, &s, &comm_key);
+        assert!(proof.verify(NONCE, &vk, &params).unwrap());
+        assert!(!proof.verify(b"other nonce", &vk, &params).unwrap());
+        assert_eq!(proof.committed_attributes[0].commitment, deposit);
+        assert_eq!(proof.revealed_msgs[&3], msgs[3]);
+        let requirements = ShowingRequirements::new().require_committed(1, &deposit, &comm_key);
+        assert!(proof
+            .verify_with_requirements(NONCE, &requirements, &vk, &params)
+            .unwrap());
+        let parsed = CredentialProof::from_bytes(&proof.to_bytes()).unwrap();
+        assert!(parsed
+            .verify_with_requirements(NONCE, &requirements, &vk, &params)
+            .unwrap());
+        assert!(proof.to_compact_bytes(NONCE, &vk, &params).is_err());
+
+        // Another commitment, another commitment key or another attribute is not the deposit
+        let other = comm_key.commit(&msgs[1], &FieldElement::random());
+        let other_requirements = ShowingRequirements::new().require_committed(1, &other, &comm_key);
+        match proof
+            .verify_with_requirements(NONCE, &other_requirements, &vk, &params)
+            .unwrap_err()
+            .kind()
+        {
+            CoconutErrorKind::CommittedAttributeMissing { idx } => assert_eq!(idx, 1),
+            k => panic!("unexpected error kind {:?}", k),
+        }
+        let other_key = CommitmentKey::new("other".as_bytes());
+        let proof_other_key = prove(1, &s, &other_key);
+        assert!(proof_other_key.verify(NONCE, &vk, &params).unwrap());
+        assert!(proof_other_key
+            .verify_with_requirements(NONCE, &requirements, &vk, &params)
+            .is_err());
+        let proof_0 = prove(0, &s, &comm_key);
+        assert!(proof_0.verify(NONCE, &vk, &params).unwrap());
+        assert!(proof_0
+            .verify_with_requirements(NONCE, &requirements, &vk, &params)
+            .is_err());
+
+        // Swapping in the deposit, moving the proof to another attribute or stripping it fails
+        let mut swapped = proof_0.clone();
+        swapped.committed_attributes[0].commitment = deposit.clone();
+        assert!(!swapped.verify(NONCE, &vk, &params).unwrap());
+        let mut moved = proof_0.clone();
+        moved.committed_attributes[0].idx = 1;
+        assert!(!moved.verify(NONCE, &vk, &params).unwrap());
+        let mut stripped = proof.clone();
+        stripped.committed_attributes.pop();
+        assert!(!stripped.verify(NONCE, &vk, &params).unwrap());
+
+        // The committed attribute can't be revealed
+        assert!(
+            CredentialProofBuilder::new(&sig, msgs.as_slice(), &vk, &params)
+                .reveal(1)
+                .prove_attribute_committed(1, &s, &comm_key)
+                .finish()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_linear_relation() {
+        let msg_count = 4;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let mut msgs = FieldElementVector::random(msg_count);
+        msgs[0] = FieldElement::from(30u64);
+        msgs[1] = FieldElement::from(12u64);
+        let (sig, vk) = issue_credential(&msgs, &params);
+        let one = FieldElement::one();
+        let sum = [(0, one.clone()), (1, one.clone())];
+
+        // attr0 + attr1 == 42
+        let proof = CredentialProofBuilder::new(&sig, msgs.as_slice(), &vk, &params)
+            .verifier_nonce(NONCE)
+            .reveal(3)
+            .with_linear_relation(&sum, &FieldElement::from(42u64))
+            .finish()
+            .unwrap();
+        assert!(proof.verify(NONCE, &vk, &params).unwrap());
+        let parsed = CredentialProof::from_bytes(&proof.to_bytes()).unwrap();
+        assert_eq!(parsed.linear_relations, proof.linear_relations);
+        assert!(parsed.verify(NONCE, &vk, &params).unwrap());
+
+        // The proof doesn't verify for another value or with the relation stripped
+        let mut other_value = proof.clone();
+        other_value.linear_relations[0].value = FieldElement::from(43u64);
+        assert!(!other_value.verify(NONCE, &vk, &params).unwrap());
+        let mut stripped = proof.clone();
+        stripped.linear_relations.clear();
+        assert!(!stripped.verify(NONCE, &vk, &params).unwrap());
+
+        // A false relation can't be proven
+        match CredentialProofBuilder::new(&sig, msgs.as_slice(), &vk, &params)
+            .verifier_nonce(NONCE)
+            .with_linear_relation(&sum, &FieldElement::from(41u64))
+            .finish()
+        {
+            Err(e) => match e.kind() {
+                CoconutErrorKind::GeneralError { .. } => (),
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(_) => panic!("proved a false linear relation"),
+        }
+
+        // 2 relations along with an equality: 2.attr0 - attr1 == 48 and attr1 + attr2 == the sum
+        // of them where attr3 == attr2
+        msgs[3] = msgs[2].clone();
+        let (sig, vk) = issue_credential(&msgs, &params);
+        let two = FieldElement::from(2u64);
+        let proof = CredentialProofBuilder::new(&sig, msgs.as_slice(), &vk, &params)
+            .verifier_nonce(NONCE)
+            .prove_attributes_equal(2, 3)
+            .with_linear_relation(&[(0, two), (1, -one.clone())], &FieldElement::from(48u64))
+            .with_linear_relation(
+                &[(1, one.clone()), (2, one.clone())],
+                &(&msgs[1] + &msgs[2]),
+            )
+            .finish()
+            .unwrap();
+        assert!(proof.verify(NONCE, &vk, &params).unwrap());
+
+        // Revealed attribute cannot be used in a relation
+        assert!(
+            CredentialProofBuilder::new(&sig, msgs.as_slice(), &vk, &params)
+                .verifier_nonce(NONCE)
+                .reveal(1)
+                .with_linear_relation(&sum, &FieldElement::from(42u64))
+                .finish()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_credential_proof_challenge_hasher() {
+        let msg_count = 3;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let msgs = FieldElementVector::random(msg_count);
+        let (sig, vk) = issue_credential(&msgs, &params);
+
+        let proof = CredentialProofBuilder::new(&sig, msgs.as_slice(), &vk, &params)
+            .verifier_nonce(NONCE)
+            .reveal(0)
+            .challenge_hasher(&Sha256ChallengeHasher)
+            .finish()
+            .unwrap();
+        assert!(proof
+            .verify_with_hasher(NONCE, &vk, &params, &Sha256ChallengeHasher)
+            .unwrap());
+        // The challenge is reproducible from the proof and differs from the default one
+        assert_eq!(
+            proof.challenge_with_hasher(NONCE, &vk, &params, &Sha256ChallengeHasher),
+            proof.challenge_with_hasher(NONCE, &vk, &params, &Sha256ChallengeHasher)
+        );
+        assert_ne!(
+            proof.challenge_with_hasher(NONCE, &vk, &params, &Sha256ChallengeHasher),
+            proof.challenge(NONCE, &vk, &params)
+        );
+        assert!(!proof.verify(NONCE, &vk, &params).unwrap());
+    }
+
+    #[test]
+    fn test_credential_proof_from_bytes() {
+        let msg_count = 6;
+        let params_bytes;
+        let vk_bytes;
+        let proof_bytes;
+        let revealed;
+        {
+            let params = Params::new(msg_count, "test".as_bytes());
+            let mut msgs = FieldElementVector::random(msg_count);
+            msgs[2] = msgs[5].clone();
+            let (sig, vk) = issue_credential(&msgs, &params);
+            let proof = CredentialProofBuilder::new(&sig, msgs.as_slice(), &vk, &params)
+                .verifier_nonce(NONCE)
+                .reveal(0)
+                .reveal(3)
+                .prove_attributes_equal(2, 5)
+                .finish()
+                .unwrap();
+            params_bytes = params.to_bytes();
+            vk_bytes = vk.to_bytes();
+            proof_bytes = proof.to_bytes();
+            revealed = (msgs[0].clone(), msgs[3].clone());
+        }
+
+        // Verify using only the bytes
+        let params = Params::from_bytes(&params_bytes).unwrap();
+        let vk = Verkey::from_bytes(&vk_bytes).unwrap();
+        let proof = CredentialProof::from_bytes(&proof_bytes).unwrap();
+        assert!(proof.verify(NONCE, &vk, &params).unwrap());
+        assert_eq!(proof.revealed_msgs[&0], revealed.0);
+        assert_eq!(proof.revealed_msgs[&3], revealed.1);
+        assert_eq!(proof.equal_attributes, vec![(2, 5)]);
+        assert_eq!(proof.to_bytes(), proof_bytes);
+
+        // Truncated or extended bytes fail to parse
+        assert!(CredentialProof::from_bytes(&proof_bytes[0..proof_bytes.len() - 1]).is_err());
+        let mut extended = proof_bytes.clone();
+        extended.push(0);
+        assert!(CredentialProof::from_bytes(&extended).is_err());
+
+        // A huge response count is an error rather than an allocation for it
+        let mut huge = proof_bytes[0..2 * SIGNATURE_GROUP_SIZE + 2 * OTHER_GROUP_SIZE].to_vec();
+        huge.extend_from_slice(&u32::max_value().to_be_bytes());
+        match CredentialProof::from_bytes(&huge).unwrap_err().kind() {
+            CoconutErrorKind::InvalidByteLength { .. } => (),
+            k => panic!("unexpected error kind {:?}", k),
+        }
+    }
+
+    #[test]
+    fn test_credential_proof_compact_bytes() {
+        let msg_count = 6;
+        for params in vec![
+            Params::new(msg_count, "test".as_bytes()),
+            Params::new_typed(msg_count, "test".as_bytes(), "passport".as_bytes()),
+        ] {
+            let mut msgs = params.typed_messages(FieldElementVector::random(msg_count).as_slice());
+            msgs[2] = msgs[5].clone();
+            let (sig, vk) = issue_credential(&msgs, &params);
+            let proof = CredentialProofBuilder::new(&sig, msgs.as_slice(), &vk, &params)
+                .verifier_nonce(NONCE)
+                .reveal(0)
+                .reveal(3)
+                .prove_attributes_equal(2, 5)
+                .finish()
+                .unwrap();
+
+            // The type attribute of typed params is revealed too
+            let revealed_count = proof.revealed_msgs.len();
+            assert_eq!(revealed_count, if params.is_typed() { 3 } else { 2 });
+            let compact = proof.to_compact_bytes(NONCE, &vk, &params).unwrap();
+            assert_eq!(
+                compact.len(),
+                CredentialProof::compact_size(params.msg_count(), revealed_count, 1)
+            );
+            // Smaller than the regular encoding by the commitment less the challenge, the count of
+            // responses and 3 bytes for each of the 2 counts, the revealed indices and 2 equality
+            // indices
+            assert_eq!(
+                proof.to_bytes().len() - compact.len(),
+                OTHER_GROUP_SIZE - FIELD_ELEMENT_SIZE + 4 + 3 * (4 + revealed_count)
+            );
+
+            let parsed = CredentialProof::from_compact_bytes(&compact, &vk, &params).unwrap();
+            assert!(parsed.verify(NONCE, &vk, &params).unwrap());
+            assert_eq!(parsed.revealed_msgs, proof.revealed_msgs);
+            assert_eq!(parsed.equal_attributes, proof.equal_attributes);
+            assert_eq!(
+                parsed.proof.proof_vc.commitment,
+                proof.proof.proof_vc.commitment
+            );
+            assert_eq!(
+                parsed.to_compact_bytes(NONCE, &vk, &params).unwrap(),
+                compact
+            );
+
+            // Tampered challenge gives a commitment whose challenge differs
+            let mut tampered = compact.clone();
+            let challenge_offset = 2 * SIGNATURE_GROUP_SIZE + OTHER_GROUP_SIZE;
+            tampered[challenge_offset + FIELD_ELEMENT_SIZE - 1] ^= 1;
+            let parsed = CredentialProof::from_compact_bytes(&tampered, &vk, &params).unwrap();
+            assert!(!parsed.verify(NONCE, &vk, &params).unwrap());
+
+            // Tampered revealed attribute
+            let mut tampered = compact.clone();
+            tampered[challenge_offset + FIELD_ELEMENT_SIZE + 2 + FIELD_ELEMENT_SIZE - 1] ^= 1;
+            let parsed = CredentialProof::from_compact_bytes(&tampered, &vk, &params).unwrap();
+            assert!(!parsed.verify(NONCE, &vk, &params).unwrap());
+
+            assert!(CredentialProof::from_compact_bytes(
+                &compact[0..compact.len() - 1],
+                &vk,
+                &params
+            )
+            .is_err());
+        }
+    }
+
+    #[test]
+    fn test_presentation_token() {
+        // Budget for a 4 attribute credential with 2 revealed attributes
+        const BUDGET: usize = 800;
+        let msg_count = 4;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let msgs = FieldElementVector::random(msg_count);
+        let (sig, vk) = issue_credential(&msgs, &params);
+        let mut revealed_msg_indices = HashSet::new();
+        revealed_msg_indices.insert(0);
+        revealed_msg_indices.insert(2);
+        let token = PresentationToken::new(
+            &sig,
+            msgs.as_slice(),
+            revealed_msg_indices,
+            NONCE,
+            &vk,
+            &params,
+        )
+        .unwrap();
+        assert!(token.verify(NONCE, &vk, &params).unwrap());
+        // Signature is randomized
+        assert_ne!(token.proof.proof.sig.sigma_1, sig.sigma_1);
+
+        let bytes = token.to_bytes().unwrap();
+        assert_eq!(
+            bytes.len(),
+            VERKEY_FINGERPRINT_SIZE + CredentialProof::compact_size(msg_count, 2, 0)
+        );
+        assert!(bytes.len() <= BUDGET);
+
+        // Verifier looks up the verkey by fingerprint
+        let (_, other_vk) = issue_credential(&msgs, &params);
+        let verkeys = vec![other_vk, vk];
+        let fingerprint = PresentationToken::fingerprint_from_bytes(&bytes).unwrap();
+        let vk = verkeys
+            .iter()
+            .find(|vk| vk.fingerprint() == fingerprint)
+            .unwrap();
+        let parsed = PresentationToken::from_bytes(&bytes, vk, &params).unwrap();
+        assert!(parsed.verify(NONCE, vk, &params).unwrap());
+        assert_eq!(parsed.revealed_msgs()[&0], msgs[0]);
+        assert_eq!(parsed.revealed_msgs()[&2], msgs[2]);
+        assert_eq!(parsed.to_bytes().unwrap(), bytes);
+
+        assert!(PresentationToken::from_bytes(&bytes, &verkeys[0], &params).is_err());
+        assert!(!parsed.verify(NONCE, &verkeys[0], &params).unwrap());
+
+        // Tampered revealed attribute, the last byte before the count of equalities and the 3
+        // responses
+        let mut tampered = bytes.clone();
+        let len = tampered.len();
+        tampered[len - 3 * FIELD_ELEMENT_SIZE - 2] ^= 1;
+        let parsed = PresentationToken::from_bytes(&tampered, vk, &params).unwrap();
+        assert!(!parsed.verify(NONCE, vk, &params).unwrap());
+    }
+
+    #[test]
+    fn test_verify_with_fingerprint() {
+        // Credential from issuer A, verifier has cached the fingerprint of A's verkey
+        let msg_count = 3;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let msgs = FieldElementVector::random(msg_count);
+        let (sig, vk_a) = issue_credential(&msgs, &params);
+        let (_, vk_b) = issue_credential(&msgs, &params);
+        let fingerprint_a = vk_a.fingerprint();
+        let proof = CredentialProofBuilder::new(&sig, msgs.as_slice(), &vk_a, &params)
+            .verifier_nonce(NONCE)
+            .reveal(1)
+            .finish()
+            .unwrap();
+        assert!(proof
+            .verify_with_fingerprint(NONCE, &fingerprint_a, &vk_a, &params)
+            .unwrap());
+
+        // Verifying against issuer B's verkey is caught by the fingerprint
+        match proof.verify_with_fingerprint(NONCE, &fingerprint_a, &vk_b, &params) {
+            Err(e) => match e.kind() {
+                CoconutErrorKind::VerkeyFingerprintMismatch => (),
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(_) => panic!("verified against the wrong verkey"),
+        }
+        assert!(proof
+            .verify_with_fingerprint(NONCE, &vk_b.fingerprint(), &vk_a, &params)
+            .is_err());
+        // A proof for A doesn't verify with B's verkey and fingerprint either
+        assert!(!proof
+            .verify_with_fingerprint(NONCE, &vk_b.fingerprint(), &vk_b, &params)
+            .unwrap());
+    }
+
+    #[test]
+    fn test_verify_with_requirements() {
+        // The verifier requires the disclosed email to be the hash of the one it knows
+        let msg_count = 3;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let email = FieldElement::from_msg_hash("alice@example.com".as_bytes());
+        let mut msgs = FieldElementVector::random(msg_count);
+        msgs[1] = email.clone();
+        let (sig, vk) = issue_credential(&msgs, &params);
+        let proof = CredentialProofBuilder::new(&sig, msgs.as_slice(), &vk, &params)
+            .verifier_nonce(NONCE)
+            .reveal(1)
+            .finish()
+            .unwrap();
+        let requirements = ShowingRequirements::new().require_disclosed_equals(1, &email);
+        assert!(proof
+            .verify_with_requirements(NONCE, &requirements, &vk, &params)
+            .unwrap());
+        assert!(proof
+            .verify_with_requirements(NONCE, &ShowingRequirements::new(), &vk, &params)
+            .unwrap());
+
+        // Another email
+        let other = FieldElement::from_msg_hash("bob@example.com".as_bytes());
+        let requirements = ShowingRequirements::new().require_disclosed_equals(1, &other);
+        match proof
+            .verify_with_requirements(NONCE, &requirements, &vk, &params)
+            .unwrap_err()
+            .kind()
+        {
+            CoconutErrorKind::DisclosedAttributeMismatch { idx } => assert_eq!(idx, 1),
+            k => panic!("unexpected error kind {:?}", k),
+        }
+
+        // A hidden attribute doesn't meet the requirement even if equal to the expected value
+        let requirements = ShowingRequirements::new().require_disclosed_equals(0, &msgs[0]);
+        match proof
+            .verify_with_requirements(NONCE, &requirements, &vk, &params)
+            .unwrap_err()
+            .kind()
+        {
+            CoconutErrorKind::DisclosedAttributeMismatch { idx } => assert_eq!(idx, 0),
+            k => panic!("unexpected error kind {:?}", k),
+        }
+    }
+
+    #[test]
+    fn test_present_multi() {
+        // 2 credentials from the same issuer, attributes revealed from both
+        let msg_count = 4;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let msgs_1 = FieldElementVector::random(msg_count);
+        let msgs_2 = FieldElementVector::random(msg_count);
+        let (sigs, vk) = issue_credentials(&[&msgs_1, &msgs_2], &params);
+
+        let hidden_1 = vec![0, 1, 2].into_iter().collect::<HashSet<usize>>();
+        let hidden_2 = vec![0, 3].into_iter().collect::<HashSet<usize>>();
+        let creds = vec![
+            (&sigs[0], msgs_1.as_slice(), hidden_1.clone()),
+            (&sigs[1], msgs_2.as_slice(), hidden_2.clone()),
+        ];
+        let proof = present_multi(&creds, NONCE, &vk, &params).unwrap();
+        assert!(proof.verify(NONCE, &vk, &params).unwrap());
+        // The proof doesn't verify with another nonce, so it can't be replayed to a verifier
+        assert!(!proof.verify(b"other nonce", &vk, &params).unwrap());
+        assert_eq!(proof.revealed_msgs[0].len(), 1);
+        assert_eq!(proof.revealed_msgs[0][&3], msgs_1[3]);
+        assert_eq!(proof.revealed_msgs[1][&1], msgs_2[1]);
+        assert_eq!(proof.revealed_msgs[1][&2], msgs_2[2]);
+
+        // Proofs of a multi presentation can't be separated
+        let mut separated = proof.clone();
+        separated.proofs.truncate(1);
+        separated.revealed_msgs.truncate(1);
+        assert!(!separated.verify(NONCE, &vk, &params).unwrap());
+
+        // Tampered revealed attribute
+        let mut tampered = proof.clone();
+        tampered.revealed_msgs[1].insert(1, FieldElement::random());
+        assert!(!tampered.verify(NONCE, &vk, &params).unwrap());
+
+        // Messages of the 2nd credential given with the 1st signature
+        let wrong_creds = vec![
+            (&sigs[0], msgs_1.as_slice(), hidden_1),
+            (&sigs[0], msgs_2.as_slice(), hidden_2),
+        ];
+        let proof = present_multi(&wrong_creds, NONCE, &vk, &params).unwrap();
+        assert!(!proof.verify(NONCE, &vk, &params).unwrap());
+
+        // The schema digest of the params is bound into the challenge. Untyped params have no
+        // type attribute, so the credentials themselves verify under either schema.
+        let params_a = params.clone().with_schema_digest([1; SCHEMA_DIGEST_SIZE]);
+        let params_b = params.with_schema_digest([2; SCHEMA_DIGEST_SIZE]);
+        let creds = vec![
+            (&sigs[0], msgs_1.as_slice(), HashSet::new()),
+            (&sigs[1], msgs_2.as_slice(), HashSet::new()),
+        ];
+        let proof = present_multi(&creds, NONCE, &vk, &params_a).unwrap();
+        assert!(proof.verify(NONCE, &vk, &params_a).unwrap());
+        assert!(!proof.verify(NONCE, &vk, &params_b).unwrap());
+    }
+
+    #[test]
+    fn test_open_attribute() {
+        let msg_count = 4;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let msgs = FieldElementVector::random(msg_count);
+        let (sigs, vk) = issue_credentials(&[&msgs], &params);
+
+        let opening = sigs[0]
+            .open_attribute(1, msgs.as_slice(), &vk, &params)
+            .unwrap();
+        assert_eq!(opening.index, 1);
+        assert_eq!(opening.value, msgs[1]);
+        assert!(opening.verify(&vk, &params).unwrap());
+
+        // Forged opening value
+        let mut forged = opening.clone();
+        forged.value = FieldElement::random();
+        assert!(!forged.verify(&vk, &params).unwrap());
+        forged.proof.revealed_msgs.insert(1, forged.value.clone());
+        assert!(!forged.verify(&vk, &params).unwrap());
+
+        // Opening claimed for a different index
+        let mut forged = opening.clone();
+        forged.index = 2;
+        assert!(!forged.verify(&vk, &params).unwrap());
+
+        // Opening of messages that were not signed
+        let other_msgs = FieldElementVector::random(msg_count);
+        let opening = sigs[0]
+            .open_attribute(1, other_msgs.as_slice(), &vk, &params)
+            .unwrap();
+        assert!(!opening.verify(&vk, &params).unwrap());
+
+        match sigs[0].open_attribute(msg_count, msgs.as_slice(), &vk, &params) {
+            Err(e) => match e.kind() {
+                CoconutErrorKind::InvalidAttributeIndex {
+                    idx,
+                    msg_count: count,
+                } => {
+                    assert_eq!(idx, msg_count);
+                    assert_eq!(count, msg_count);
+                }
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(_) => panic!("opened attribute with invalid index"),
+        }
+    }
+
+    #[test]
+    fn test_plan_disclosure() {
+        let msg_count = 5;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let msgs = FieldElementVector::random(msg_count);
+        let (sig, vk) = issue_credential(&msgs, &params);
+
+        // Revealing only the required attribute versus also revealing 2 that could be hidden
+        let minimal = plan_disclosure(msg_count, &[1], &[0]).unwrap();
+        assert_eq!(minimal.revealed, vec![1].into_iter().collect());
+        assert_eq!(minimal.hidden, vec![0, 2, 3, 4].into_iter().collect());
+        let larger = plan_disclosure(msg_count, &[1, 3, 4], &[0]).unwrap();
+        assert_eq!(
+            larger.size - minimal.size,
+            2 * (REVEALED_ATTRIBUTE_SIZE - HIDDEN_ATTRIBUTE_SIZE)
+        );
+        for plan in vec![minimal, larger] {
+            let proof = CredentialProof::new(
+                &sig,
+                msgs.as_slice(),
+                plan.revealed_msg_indices(),
+                NONCE,
+                &vk,
+                &params,
+            )
+            .unwrap();
+            assert_eq!(proof.to_bytes().len(), plan.size);
+            assert!(proof.verify(NONCE, &vk, &params).unwrap());
+        }
+
+        // Nothing required
+        let plan = plan_disclosure(msg_count, &[], &[]).unwrap();
+        assert!(plan.revealed.is_empty());
+        assert_eq!(plan.size, presentation_size(msg_count, msg_count).unwrap());
+
+        assert!(plan_disclosure(msg_count, &[1, 2], &[2]).is_err());
+        match plan_disclosure(msg_count, &[msg_count], &[])
+            .unwrap_err()
+            .kind()
+        {
+            CoconutErrorKind::InvalidAttributeIndex { idx, .. } => assert_eq!(idx, msg_count),
+            k => panic!("unexpected error kind {:?}", k),
+        }
+    }
+
+    #[test]
+    fn test_presentation_size() {
+        for &(msg_count, hidden_count) in &[(1, 0), (1, 1), (3, 1), (5, 5), (6, 2)] {
+            let params = Params::new(msg_count, "test".as_bytes());
+            let msgs = FieldElementVector::random(msg_count);
+            let (sig, vk) = issue_credential(&msgs, &params);
+            let revealed = (hidden_count..msg_count).collect::<HashSet<usize>>();
+            let proof =
+                CredentialProof::new(&sig, msgs.as_slice(), revealed, NONCE, &vk, &params).unwrap();
+            assert_eq!(
+                proof.to_bytes().len(),
+                presentation_size(msg_count, hidden_count).unwrap()
+            );
+        }
+        assert!(presentation_size(2, 3).is_err());
+    }
 }
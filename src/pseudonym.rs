@@ -0,0 +1,191 @@
+// Verifier specific pseudonyms of a holder. The holder has a secret s which is a hidden attribute
+// of its credential. For a verifier with id v, the pseudonym is H(v)^s where H hashes to
+// SignatureGroup, so the same holder always has the same pseudonym with a verifier but pseudonyms
+// with different verifiers can't be linked without s. While showing, the holder keeps s hidden in
+// the proof of knowledge of the signature and proves knowledge of s in the pseudonym linked to it,
+// see `PoKOfLinkedAttribute`, so the pseudonym is of the holder of the credential.
+
+use crate::errors::CoconutError;
+use crate::pok_sig::{LinkedAttributeProof, PoKOfLinkedAttribute};
+use crate::signature::Params;
+use crate::utils::hash_to_subgroup;
+use crate::{SignatureGroup, SIGNATURE_GROUP_COFACTOR};
+use amcl_wrapper::field_elem::FieldElement;
+use amcl_wrapper::group_elem::GroupElement;
+use ps_sig::pok_sig::PoKOfSignatureProof;
+use std::collections::HashMap;
+
+/// Domain separation tag prefixed to the bytes hashed in `pseudonym_base`
+pub const PSEUDONYM_TAG: &[u8] = b"coconut/pseudonym";
+
+/// H(v), the base of the pseudonyms with the verifier with id `verifier_id`. Hash of
+/// `PSEUDONYM_TAG || g || verifier_id` so that pseudonyms under different params differ.
+pub fn pseudonym_base(verifier_id: &[u8], params: &Params) -> SignatureGroup {
+    let mut bytes = PSEUDONYM_TAG.to_vec();
+    bytes.append(&mut params.g.to_bytes());
+    bytes.extend_from_slice(verifier_id);
+    hash_to_subgroup(&bytes, SIGNATURE_GROUP_COFACTOR)
+}
+
+/// Pseudonym of the holder with secret `holder_secret` with the verifier with id `verifier_id`
+pub fn pseudonym(
+    holder_secret: &FieldElement,
+    verifier_id: &[u8],
+    params: &Params,
+) -> SignatureGroup {
+    &pseudonym_base(verifier_id, params) * holder_secret
+}
+
+/// Commitment phase of the proof of knowledge of the holder secret of a pseudonym
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PoKOfPseudonym(pub PoKOfLinkedAttribute);
+
+/// Response phase of the proof of knowledge of the holder secret of a pseudonym
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PseudonymProof(pub LinkedAttributeProof);
+
+impl PoKOfPseudonym {
+    /// `secret_blinding` should be the same blinding used for the holder secret attribute in the
+    /// proof of knowledge of signature.
+    pub fn init(
+        holder_secret: &FieldElement,
+        secret_blinding: &FieldElement,
+        verifier_id: &[u8],
+        params: &Params,
+    ) -> Self {
+        let base = pseudonym_base(verifier_id, params);
+        Self(PoKOfLinkedAttribute::init(holder_secret, secret_blinding, &base))
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.to_bytes()
+    }
+
+    pub fn gen_proof(self, challenge: &FieldElement) -> Result<PseudonymProof, CoconutError> {
+        Ok(PseudonymProof(self.0.gen_proof(challenge)?))
+    }
+}
+
+impl PseudonymProof {
+    /// Verify that `pseudonym` is the holder's pseudonym with the verifier whose pseudonyms have
+    /// base `nym_base`, see `pseudonym_base`, for the holder secret, the hidden attribute at
+    /// `secret_idx` of `proof_sig`. `proof_sig` is the proof of knowledge of signature revealing
+    /// `revealed_msgs` created with the same challenge and must be verified separately.
+    pub fn verify(
+        &self,
+        pseudonym: &SignatureGroup,
+        nym_base: &SignatureGroup,
+        secret_idx: usize,
+        proof_sig: &PoKOfSignatureProof,
+        revealed_msgs: &HashMap<usize, FieldElement>,
+        challenge: &FieldElement,
+    ) -> Result<bool, CoconutError> {
+        self.0.verify(
+            nym_base,
+            pseudonym,
+            secret_idx,
+            proof_sig,
+            revealed_msgs,
+            challenge,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pok_sig::hidden_attribute_position;
+    use crate::signature::{
+        transform_to_PS_params, transform_to_PS_sig, transform_to_PS_verkey, SignatureRequest,
+    };
+    use crate::test_utils::{sign_request, signers_with_verkey};
+    use amcl_wrapper::field_elem::FieldElementVector;
+    use ps_sig::pok_sig::PoKOfSignature;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_pseudonym() {
+        // Holder secret is the 1st attribute
+        let threshold = 2;
+        let msg_count = 3;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (signers, vk) = signers_with_verkey(threshold, 3, &params);
+        let msgs = FieldElementVector::random(msg_count);
+        let holder_secret = msgs[0].clone();
+
+        // Stable with a verifier, different across verifiers and holders
+        let nym_a = pseudonym(&holder_secret, b"verifier A", &params);
+        assert_eq!(nym_a, pseudonym(&holder_secret, b"verifier A", &params));
+        let nym_b = pseudonym(&holder_secret, b"verifier B", &params);
+        let base_a = pseudonym_base(b"verifier A", &params);
+        let base_b = pseudonym_base(b"verifier B", &params);
+        assert_eq!(nym_a, &base_a * &holder_secret);
+        assert_ne!(nym_a, nym_b);
+        assert_ne!(
+            nym_a,
+            pseudonym(&FieldElement::random(), b"verifier A", &params)
+        );
+
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        let (sig_req, _) = SignatureRequest::new(&msgs, 1, &elg_pk, &params).unwrap();
+        let sig = sign_request(&sig_req, &elg_sk, &signers, threshold, &params);
+
+        // Show revealing the last attribute with the pseudonym for verifier A
+        let mut revealed_msg_indices = HashSet::new();
+        revealed_msg_indices.insert(2);
+        let mut revealed_msgs = HashMap::new();
+        revealed_msgs.insert(2, msgs[2].clone());
+        let ps_params = transform_to_PS_params(&params);
+        let ps_verkey = transform_to_PS_verkey(&vk);
+        let ps_sig = transform_to_PS_sig(&sig);
+        let secret_pos = hidden_attribute_position(0, &revealed_msg_indices).unwrap();
+        let show = |secret: &FieldElement| {
+            let blindings = FieldElementVector::random(msg_count - 1);
+            let pok_sig = PoKOfSignature::init(
+                &ps_sig,
+                &ps_verkey,
+                &ps_params,
+                msgs.as_slice(),
+                Some(blindings.as_slice()),
+                revealed_msg_indices.clone(),
+            )
+            .unwrap();
+            let pok_nym =
+                PoKOfPseudonym::init(secret, &blindings[secret_pos], b"verifier A", &params);
+            let mut chal_bytes = pok_sig.to_bytes();
+            chal_bytes.append(&mut pok_nym.to_bytes());
+            let chal = FieldElement::from_msg_hash(&chal_bytes);
+            (
+                pok_sig.gen_proof(&chal).unwrap(),
+                pok_nym.gen_proof(&chal).unwrap(),
+                chal,
+            )
+        };
+
+        let (proof_sig, proof_nym, chal) = show(&holder_secret);
+        assert!(proof_sig
+            .verify(&ps_verkey, &ps_params, revealed_msgs.clone(), &chal)
+            .unwrap());
+        assert!(proof_nym
+            .verify(&nym_a, &base_a, 0, &proof_sig, &revealed_msgs, &chal)
+            .unwrap());
+        // Not a proof for the pseudonym with another verifier or of another attribute
+        assert!(!proof_nym
+            .verify(&nym_b, &base_b, 0, &proof_sig, &revealed_msgs, &chal)
+            .unwrap());
+        assert!(!proof_nym
+            .verify(&nym_a, &base_a, 1, &proof_sig, &revealed_msgs, &chal)
+            .unwrap());
+        assert!(!proof_nym
+            .verify(&nym_a, &base_a, 2, &proof_sig, &revealed_msgs, &chal)
+            .unwrap());
+
+        // Pseudonym of another secret can't be passed off as the credential holder's
+        let other_secret = FieldElement::random();
+        let other_nym = pseudonym(&other_secret, b"verifier A", &params);
+        let (proof_sig, proof_nym, chal) = show(&other_secret);
+        assert!(!proof_nym
+            .verify(&other_nym, &base_a, 0, &proof_sig, &revealed_msgs, &chal)
+            .unwrap());
+    }
+}
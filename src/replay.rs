@@ -0,0 +1,197 @@
+// Replay guard for verifiers of proofs of knowledge. A verifier accepting proofs with challenges
+// chosen by the prover (like a `SignatureRequestProof` for a challenge agreed out of band) should
+// reject a challenge it has already seen so that a captured proof cannot be presented again.
+
+use crate::errors::CoconutError;
+use amcl_wrapper::field_elem::FieldElement;
+use std::collections::{HashSet, VecDeque};
+use std::sync::Mutex;
+
+/// Bounded set of the most recently seen challenges. Once `capacity` challenges are held, the
+/// least recently seen one is evicted, so a replay is only detected within the last `capacity`
+/// challenges. Can be shared across threads.
+pub struct ChallengeCache {
+    capacity: usize,
+    seen: Mutex<SeenChallenges>,
+}
+
+struct SeenChallenges {
+    set: HashSet<Vec<u8>>,
+    /// Challenges in order of being seen, least recent first
+    order: VecDeque<Vec<u8>>,
+}
+
+impl SeenChallenges {
+    /// Whether `bytes` is held, which then becomes the most recently seen
+    fn touch(&mut self, bytes: &[u8]) -> bool {
+        if !self.set.contains(bytes) {
+            return false;
+        }
+        if let Some(pos) = self.order.iter().position(|c| c.as_slice() == bytes) {
+            self.order.remove(pos);
+        }
+        self.order.push_back(bytes.to_vec());
+        true
+    }
+}
+
+impl ChallengeCache {
+    /// A `capacity` of 0 is taken as 1
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            capacity,
+            seen: Mutex::new(SeenChallenges {
+                set: HashSet::with_capacity(capacity),
+                order: VecDeque::with_capacity(capacity),
+            }),
+        }
+    }
+
+    /// Verify a proof with `verify` unless `challenge` was seen before and record the challenge
+    /// only if the proof verifies, so that a proof failing verification doesn't keep a valid proof
+    /// for the same challenge from being accepted. False for a replayed challenge, without calling
+    /// `verify`, and also if another proof with the same challenge was accepted while verifying.
+    pub fn check_and_verify<F: FnOnce() -> Result<bool, CoconutError>>(
+        &self,
+        challenge: &FieldElement,
+        verify: F,
+    ) -> Result<bool, CoconutError> {
+        if self.is_seen(challenge) || !verify()? {
+            return Ok(false);
+        }
+        Ok(self.insert(challenge))
+    }
+
+    /// Whether the challenge was seen before, which then becomes the most recently seen
+    pub fn is_seen(&self, challenge: &FieldElement) -> bool {
+        let bytes = challenge.to_bytes();
+        let mut seen = self.seen.lock().unwrap();
+        seen.touch(&bytes)
+    }
+
+    /// Record the challenge of a verified proof. Returns true if the challenge was not seen before
+    /// and false for a replayed challenge, which becomes the most recently seen and the proof
+    /// should be rejected.
+    pub fn insert(&self, challenge: &FieldElement) -> bool {
+        let bytes = challenge.to_bytes();
+        let mut seen = self.seen.lock().unwrap();
+        if seen.touch(&bytes) {
+            return false;
+        }
+        if seen.order.len() == self.capacity {
+            if let Some(oldest) = seen.order.pop_front() {
+                seen.set.remove(&oldest);
+            }
+        }
+        seen.set.insert(bytes.clone());
+        seen.order.push_back(bytes);
+        true
+    }
+
+    pub fn len(&self) -> usize {
+        self.seen.lock().unwrap().order.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::CoconutErrorKind;
+    use crate::signature::{Params, SignatureRequest, SignatureRequestPoK, SignatureRequestProof};
+    use amcl_wrapper::field_elem::FieldElementVector;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn test_challenge_cache() {
+        let cache = ChallengeCache::new(2);
+        let c1 = FieldElement::random();
+        let c2 = FieldElement::random();
+        let c3 = FieldElement::random();
+        assert!(!cache.is_seen(&c1));
+        assert!(cache.insert(&c1));
+        assert!(cache.is_seen(&c1));
+        assert!(!cache.insert(&c1));
+        assert!(cache.insert(&c2));
+        // c1 was seen more recently than c2 so c2 is evicted
+        assert!(cache.is_seen(&c1));
+        assert!(cache.insert(&c3));
+        assert_eq!(cache.len(), 2);
+        assert!(!cache.insert(&c1));
+        assert!(!cache.insert(&c3));
+        assert!(cache.insert(&c2));
+
+        // Capacity 0 holds 1 challenge rather than panicking
+        let cache = ChallengeCache::new(0);
+        assert!(cache.insert(&c1));
+        assert!(!cache.insert(&c1));
+        assert!(cache.insert(&c2));
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_replayed_signature_request_proof() {
+        let params = Params::new(3, "test".as_bytes());
+        let msgs = FieldElementVector::random(3);
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        let (sig_req, randomness) = SignatureRequest::new(&msgs, 1, &elg_pk, &params).unwrap();
+        let sig_req_pok = SignatureRequestPoK::init(&sig_req, &elg_pk, &params);
+        let challenge = FieldElement::from_msg_hash(&sig_req_pok.to_bytes());
+        let hidden_msgs: FieldElementVector = vec![msgs[0].clone()].into();
+        let proof = sig_req_pok
+            .gen_proof(&hidden_msgs, randomness, &elg_sk, &challenge)
+            .unwrap();
+
+        let cache = ChallengeCache::new(16);
+        let accept = |proof: &SignatureRequestProof| {
+            cache
+                .check_and_verify(&challenge, || {
+                    proof.verify(&sig_req, &elg_pk, &challenge, &params)
+                })
+                .unwrap()
+        };
+
+        // A forged proof for the challenge fails and doesn't block the valid proof
+        let mut forged = proof.clone();
+        forged.proof_elgamal_sk.responses[0] = FieldElement::random();
+        assert!(!accept(&forged));
+        assert!(!cache.is_seen(&challenge));
+        assert!(accept(&proof));
+        // Same proof presented again
+        assert!(!accept(&proof));
+
+        // An error from verification is returned and the challenge isn't recorded
+        let other = FieldElement::random();
+        assert!(cache
+            .check_and_verify(&other, || Err(CoconutErrorKind::HMismatch.into()))
+            .is_err());
+        assert!(!cache.is_seen(&other));
+    }
+
+    #[test]
+    fn test_challenge_cache_threads() {
+        let cache = Arc::new(ChallengeCache::new(100));
+        let challenges = (0..8).map(|_| FieldElement::random()).collect::<Vec<_>>();
+        // Every thread tries every challenge, each challenge is accepted exactly once
+        let handles = (0..4)
+            .map(|_| {
+                let cache = cache.clone();
+                let challenges = challenges.clone();
+                thread::spawn(move || {
+                    challenges
+                        .iter()
+                        .filter(|c| cache.check_and_verify(c, || Ok(true)).unwrap())
+                        .count()
+                })
+            })
+            .collect::<Vec<_>>();
+        let accepted: usize = handles.into_iter().map(|h| h.join().unwrap()).sum();
+        assert_eq!(accepted, challenges.len());
+        assert_eq!(cache.len(), challenges.len());
+    }
+}
@@ -0,0 +1,570 @@
+// Named attribute schema. Lets the issuer declare the attributes of a credential by name and
+// downstream code refer to attributes by name rather than by index.
+
+use crate::errors::{CoconutError, CoconutErrorKind};
+use crate::signature::{Params, Verkey};
+use crate::utils::append_with_len;
+use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+/// Size in bytes of `AttributeSchema::digest`
+pub const SCHEMA_DIGEST_SIZE: usize = 32;
+
+/// Domain separation tag prefixed to the schema in `AttributeSchema::digest`
+pub const SCHEMA_DIGEST_TAG: &[u8] = b"coconut/schema/digest";
+
+/// Maps attribute names to their indices in the messages of a credential
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AttributeSchema {
+    names: Vec<String>,
+    indices: HashMap<String, usize>,
+}
+
+impl AttributeSchema {
+    pub fn index(&self, name: &str) -> Result<usize, CoconutError> {
+        self.indices.get(name).cloned().ok_or_else(|| {
+            CoconutErrorKind::UnknownAttribute {
+                name: name.to_string(),
+            }
+            .into()
+        })
+    }
+
+    pub fn name(&self, idx: usize) -> Option<&str> {
+        self.names.get(idx).map(|n| n.as_str())
+    }
+
+    /// Attribute names in order of index
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// SHA-256 of `SCHEMA_DIGEST_TAG` || `name` || attribute names in order of index, each
+    /// prefixed by its length. Identifies the schema and its version, like "passport/v2", for
+    /// `Params::with_schema_digest`.
+    pub fn digest(&self, name: &str) -> [u8; SCHEMA_DIGEST_SIZE] {
+        let mut bytes = SCHEMA_DIGEST_TAG.to_vec();
+        append_with_len(&mut bytes, name.as_bytes());
+        for n in &self.names {
+            append_with_len(&mut bytes, n.as_bytes());
+        }
+        let mut digest = [0u8; SCHEMA_DIGEST_SIZE];
+        digest.copy_from_slice(&Sha256::digest(&bytes));
+        digest
+    }
+
+    /// Check that the params support exactly the attributes of this schema, besides the type
+    /// attribute of typed params
+    pub fn check_params(&self, params: &Params) -> Result<(), CoconutError> {
+        if params.attribute_count() != self.len() {
+            return Err(CoconutErrorKind::UnsupportedNoOfMessages {
+                expected: self.len(),
+                given: params.attribute_count(),
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Name of each attribute index of the verkey, as (index, name) pairs. The type attribute of
+    /// typed `params` has no name in the schema and is left out. Errors if the params or the
+    /// verkey are not for exactly the attributes of this schema.
+    pub fn label_indices(
+        &self,
+        vk: &Verkey,
+        params: &Params,
+    ) -> Result<Vec<(usize, &str)>, CoconutError> {
+        self.check_params(params)?;
+        if vk.Y_tilde.len() != params.msg_count() {
+            return Err(CoconutErrorKind::UnsupportedNoOfMessages {
+                expected: params.msg_count(),
+                given: vk.Y_tilde.len(),
+            }
+            .into());
+        }
+        let type_idx = params.type_attribute_index();
+        Ok(vk
+            .attribute_indices()
+            .filter(|i| Some(*i) != type_idx)
+            .map(|i| (i, self.names[i].as_str()))
+            .collect())
+    }
+
+    /// Arrange the given named attribute values in order of index. Every attribute of the schema
+    /// must be given exactly once.
+    pub fn messages(
+        &self,
+        values: &[(&str, FieldElement)],
+    ) -> Result<FieldElementVector, CoconutError> {
+        let mut msgs: Vec<Option<FieldElement>> = vec![None; self.len()];
+        for (name, value) in values {
+            let idx = self.index(name)?;
+            if msgs[idx].is_some() {
+                return Err(CoconutErrorKind::GeneralError {
+                    msg: format!("Attribute {} given more than once", name),
+                }
+                .into());
+            }
+            msgs[idx] = Some(value.clone());
+        }
+        let mut messages = FieldElementVector::with_capacity(self.len());
+        for (i, m) in msgs.into_iter().enumerate() {
+            match m {
+                Some(m) => messages.push(m),
+                None => {
+                    return Err(CoconutErrorKind::GeneralError {
+                        msg: format!("Attribute {} not given", self.names[i]),
+                    }
+                    .into())
+                }
+            }
+        }
+        Ok(messages)
+    }
+}
+
+/// Builds `Params` along with an `AttributeSchema` for a declared list of attribute names. Attributes
+/// get indices in the order they are added.
+pub struct ParamsBuilder {
+    label: Vec<u8>,
+    credential_type: Option<Vec<u8>>,
+    schema_name: Option<String>,
+    names: Vec<String>,
+}
+
+impl ParamsBuilder {
+    pub fn new(label: &[u8]) -> Self {
+        Self {
+            label: label.to_vec(),
+            credential_type: None,
+            schema_name: None,
+            names: vec![],
+        }
+    }
+
+    /// Build typed params, see `Params::new_typed`
+    pub fn credential_type(mut self, credential_type: &[u8]) -> Self {
+        self.credential_type = Some(credential_type.to_vec());
+        self
+    }
+
+    /// Carry the digest of the schema under `name` in the params, see `AttributeSchema::digest`.
+    /// The params are typed, with `name` as the credential type if none is given, so that the
+    /// digest is bound at issuance, see `Params::with_schema_digest`.
+    pub fn schema_name(mut self, name: &str) -> Self {
+        self.schema_name = Some(name.to_string());
+        self
+    }
+
+    pub fn add_attribute(mut self, name: &str) -> Self {
+        self.names.push(name.to_string());
+        self
+    }
+
+    pub fn build(self) -> Result<(Params, AttributeSchema), CoconutError> {
+        if self.names.is_empty() {
+            return Err(CoconutErrorKind::GeneralError {
+                msg: String::from("Schema needs at least 1 attribute"),
+            }
+            .into());
+        }
+        let mut indices = HashMap::new();
+        for (i, name) in self.names.iter().enumerate() {
+            if indices.insert(name.clone(), i).is_some() {
+                return Err(CoconutErrorKind::GeneralError {
+                    msg: format!("Attribute {} declared more than once", name),
+                }
+                .into());
+            }
+        }
+        let credential_type = self
+            .credential_type
+            .clone()
+            .or_else(|| self.schema_name.as_ref().map(|n| n.as_bytes().to_vec()));
+        let mut params = match &credential_type {
+            Some(t) => Params::new_typed(self.names.len(), &self.label, t),
+            None => Params::new(self.names.len(), &self.label),
+        };
+        let schema = AttributeSchema {
+            names: self.names,
+            indices,
+        };
+        if let Some(name) = &self.schema_name {
+            params = params.with_schema_digest(schema.digest(name));
+        }
+        Ok((params, schema))
+    }
+}
+
+/// A record whose fields are the attributes of a credential, converted to messages in a fixed
+/// order matching the `Params`.
+pub trait ToAttributes {
+    fn to_attributes(&self) -> FieldElementVector;
+}
+
+/// A record built back from the messages of a credential, in the same order as `ToAttributes`
+pub trait FromAttributes: Sized {
+    /// Errors if the number of messages is not the number of fields of the record
+    fn from_attributes(attributes: &[FieldElement]) -> Result<Self, CoconutError>;
+}
+
+/// Implement `ToAttributes` and `FromAttributes` for a struct. The fields are listed in order of
+/// their attribute index and each must be convertible to and from `FieldElement`.
+/// `impl_attributes!(Passport { name, date_of_birth, country });`
+#[macro_export]
+macro_rules! impl_attributes {
+    ( $name:ident { $( $field:ident ),+ $(,)* } ) => {
+        impl $crate::schema::ToAttributes for $name {
+            fn to_attributes(&self) -> $crate::__amcl_wrapper::field_elem::FieldElementVector {
+                let attributes: ::std::vec::Vec<$crate::__amcl_wrapper::field_elem::FieldElement> =
+                    ::std::vec![$(
+                        $crate::__amcl_wrapper::field_elem::FieldElement::from(
+                            ::std::clone::Clone::clone(&self.$field),
+                        )
+                    ),+];
+                $crate::__amcl_wrapper::field_elem::FieldElementVector::from(attributes)
+            }
+        }
+
+        impl $crate::schema::FromAttributes for $name {
+            fn from_attributes(
+                attributes: &[$crate::__amcl_wrapper::field_elem::FieldElement],
+            ) -> ::std::result::Result<Self, $crate::errors::CoconutError> {
+                let fields = [$( ::std::stringify!($field) ),+];
+                if attributes.len() != fields.len() {
+                    return ::std::result::Result::Err(::std::convert::Into::into(
+                        $crate::errors::CoconutErrorKind::UnsupportedNoOfMessages {
+                            expected: fields.len(),
+                            given: attributes.len(),
+                        },
+                    ));
+                }
+                let mut attributes = attributes.iter();
+                ::std::result::Result::Ok(Self {
+                    $(
+                        $field: ::std::convert::Into::into(
+                            ::std::clone::Clone::clone(
+                                ::std::iter::Iterator::next(&mut attributes).unwrap(),
+                            ),
+                        ),
+                    )+
+                })
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keygen::trusted_party_SSS_keygen;
+    use crate::pok_sig::CredentialProof;
+    use crate::signature::{BlindSignature, SignatureRequest};
+    use crate::test_utils::{issue_credential, sign_request, signers_with_verkey};
+    use crate::{FIELD_ELEMENT_SIZE, OTHER_GROUP_SIZE, SIGNATURE_GROUP_SIZE};
+    use amcl_wrapper::group_elem::GroupElementVector;
+    use std::collections::HashSet;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Passport {
+        name: FieldElement,
+        date_of_birth: FieldElement,
+        country: FieldElement,
+    }
+
+    impl_attributes!(Passport {
+        name,
+        date_of_birth,
+        country
+    });
+
+    // The macro only relies on paths it names itself, not on the caller's imports
+    mod without_imports {
+        #[derive(Clone, Debug, PartialEq)]
+        pub struct Ticket {
+            pub event: amcl_wrapper::field_elem::FieldElement,
+            pub seat: amcl_wrapper::field_elem::FieldElement,
+        }
+
+        impl_attributes!(Ticket { event, seat });
+    }
+
+    #[test]
+    fn test_params_builder() {
+        let (params, schema) = ParamsBuilder::new("test".as_bytes())
+            .add_attribute("name")
+            .add_attribute("date_of_birth")
+            .add_attribute("country")
+            .build()
+            .unwrap();
+        assert_eq!(params.msg_count(), 3);
+        assert!(schema.check_params(&params).is_ok());
+        assert!(schema
+            .check_params(&Params::new(4, "test".as_bytes()))
+            .is_err());
+
+        assert_eq!(schema.index("name").unwrap(), 0);
+        assert_eq!(schema.index("date_of_birth").unwrap(), 1);
+        assert_eq!(schema.index("country").unwrap(), 2);
+        assert_eq!(schema.name(2), Some("country"));
+        assert_eq!(schema.name(3), None);
+        match schema.index("email").unwrap_err().kind() {
+            CoconutErrorKind::UnknownAttribute { .. } => (),
+            k => panic!("unexpected error kind {:?}", k),
+        }
+
+        // Same generators as params created without the builder
+        assert_eq!(
+            params.h.as_slice(),
+            Params::new(3, "test".as_bytes()).h.as_slice()
+        );
+
+        let country = FieldElement::random();
+        let name = FieldElement::random();
+        let dob = FieldElement::random();
+        let msgs = schema
+            .messages(&[
+                ("country", country.clone()),
+                ("name", name.clone()),
+                ("date_of_birth", dob.clone()),
+            ])
+            .unwrap();
+        assert_eq!(msgs[0], name);
+        assert_eq!(msgs[1], dob);
+        assert_eq!(msgs[2], country);
+        assert!(schema.messages(&[("name", name.clone())]).is_err());
+
+        assert!(ParamsBuilder::new("test".as_bytes())
+            .add_attribute("name")
+            .add_attribute("name")
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn test_schema_digest() {
+        let build = |schema_name: &str| {
+            ParamsBuilder::new("test".as_bytes())
+                .credential_type("passport".as_bytes())
+                .schema_name(schema_name)
+                .add_attribute("name")
+                .add_attribute("date_of_birth")
+                .build()
+                .unwrap()
+        };
+        // Same generators but different schemas, so different tags
+        let (params_a, schema) = build("passport/v1");
+        let (params_b, _) = build("passport/v2");
+        assert_eq!(params_a.h.as_slice(), params_b.h.as_slice());
+        assert_eq!(
+            params_a.schema_digest(),
+            Some(&schema.digest("passport/v1"))
+        );
+        assert_ne!(params_a.schema_digest(), params_b.schema_digest());
+        assert_ne!(schema.digest("passport/v1"), schema.digest("passport/v2"));
+        assert_ne!(params_a.credential_type, params_b.credential_type);
+        let typed = Params::new_typed(2, "test".as_bytes(), "passport".as_bytes());
+        assert_ne!(params_a.credential_type, typed.credential_type);
+        let plain = Params::new(3, "test".as_bytes());
+        assert!(plain.schema_digest().is_none());
+        assert_ne!(params_a, plain);
+
+        // The schema name is the credential type if none is given
+        let (named, _) = ParamsBuilder::new("test".as_bytes())
+            .schema_name("ticket/v1")
+            .add_attribute("seat")
+            .build()
+            .unwrap();
+        assert!(named.is_typed());
+        assert_eq!(named.attribute_count(), 1);
+
+        // The digest survives serialization
+        assert_eq!(Params::from_bytes(&params_a.to_bytes()).unwrap(), params_a);
+        assert!(Params::from_bytes(&plain.to_bytes())
+            .unwrap()
+            .schema_digest()
+            .is_none());
+
+        // The flag tells whether a digest is present, whatever the count of h
+        let flag_offset = SIGNATURE_GROUP_SIZE + OTHER_GROUP_SIZE + FIELD_ELEMENT_SIZE;
+        let plain_bytes = plain.to_bytes();
+        assert_eq!(plain_bytes[flag_offset], 0);
+        let mut digest_bytes = params_a.to_bytes();
+        assert_eq!(digest_bytes[flag_offset], 1);
+        digest_bytes[flag_offset] = 2;
+        assert!(Params::from_bytes(&digest_bytes).is_err());
+        let mut flagged = plain_bytes[..=flag_offset].to_vec();
+        flagged[flag_offset] = 1;
+        flagged.extend_from_slice(&plain_bytes[flag_offset + 1..]);
+        assert!(Params::from_bytes(&flagged).is_err());
+
+        let threshold = 2;
+        let (signers, vk) = signers_with_verkey(threshold, 3, &params_a);
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params_a.g);
+        let attributes = FieldElementVector::random(2);
+        let msgs = params_a.typed_messages(attributes.as_slice());
+        let (sig_req, _) = SignatureRequest::new(&msgs, 1, &elg_pk, &params_a).unwrap();
+        // A signer for v2 doesn't sign a request for v1
+        assert!(BlindSignature::new_typed(&sig_req, &signers[0].sigkey, &params_b).is_err());
+        let sig = sign_request(&sig_req, &elg_sk, &signers, threshold, &params_a);
+        // The signature is bound to the schema it was issued for
+        assert!(sig.verify(msgs.as_slice(), &vk, &params_a).unwrap());
+        assert!(!sig.verify(msgs.as_slice(), &vk, &params_b).unwrap());
+        let msgs_b = params_b.typed_messages(attributes.as_slice());
+        assert!(!sig.verify(msgs_b.as_slice(), &vk, &params_b).unwrap());
+
+        let nonce = "verifier nonce".as_bytes();
+        let mut revealed = HashSet::new();
+        revealed.insert(1);
+        let proof = CredentialProof::new(
+            &sig,
+            msgs.as_slice(),
+            revealed.clone(),
+            nonce,
+            &vk,
+            &params_a,
+        )
+        .unwrap();
+        assert!(proof.verify(nonce, &vk, &params_a).unwrap());
+        assert!(!proof.verify(nonce, &vk, &params_b).unwrap());
+        assert!(!proof.verify(nonce, &vk, &plain).unwrap());
+
+        // The holder proves the credential afresh under the params for v2, with the type
+        // attribute of v1 or claiming the one of v2
+        for msgs in vec![msgs.as_slice(), msgs_b.as_slice()] {
+            let reproof =
+                CredentialProof::new(&sig, msgs, revealed.clone(), nonce, &vk, &params_b).unwrap();
+            assert!(!reproof.verify(nonce, &vk, &params_b).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_attribute_indices() {
+        let (params, schema) = ParamsBuilder::new("test".as_bytes())
+            .add_attribute("name")
+            .add_attribute("date_of_birth")
+            .add_attribute("country")
+            .build()
+            .unwrap();
+        assert_eq!(params.attribute_indices(), 0..3);
+        let (_, _, signers) = trusted_party_SSS_keygen(2, 3, &params);
+        let vk = &signers[0].verkey;
+        assert_eq!(vk.attribute_indices(), 0..3);
+        assert_eq!(vk.attribute_indices(), params.attribute_indices());
+
+        assert_eq!(
+            schema.label_indices(vk, &params).unwrap(),
+            vec![(0, "name"), (1, "date_of_birth"), (2, "country")]
+        );
+
+        let other_params = Params::new(4, "test".as_bytes());
+        assert_eq!(other_params.attribute_indices(), 0..4);
+        let (_, _, other_signers) = trusted_party_SSS_keygen(2, 3, &other_params);
+        match schema.label_indices(&other_signers[0].verkey, &params) {
+            Err(e) => match e.kind() {
+                CoconutErrorKind::UnsupportedNoOfMessages { expected, given } => {
+                    assert_eq!(expected, 3);
+                    assert_eq!(given, 4);
+                }
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(_) => panic!("labelled a verkey for a different number of attributes"),
+        }
+
+        // Typed params have the type attribute after the named attributes, which gets no label
+        let (typed_params, typed_schema) = ParamsBuilder::new("test".as_bytes())
+            .schema_name("person/v1")
+            .add_attribute("name")
+            .add_attribute("country")
+            .build()
+            .unwrap();
+        assert_eq!(typed_params.type_attribute_index(), Some(2));
+        let (_, typed_vk) = signers_with_verkey(2, 3, &typed_params);
+        assert_eq!(typed_vk.attribute_indices(), 0..3);
+        assert_eq!(
+            typed_schema
+                .label_indices(&typed_vk, &typed_params)
+                .unwrap(),
+            vec![(0, "name"), (1, "country")]
+        );
+        assert!(typed_schema
+            .label_indices(&other_signers[0].verkey, &typed_params)
+            .is_err());
+        assert!(schema.label_indices(&typed_vk, &typed_params).is_err());
+    }
+
+    #[test]
+    fn test_attribute_record() {
+        let (params, schema) = ParamsBuilder::new("test".as_bytes())
+            .add_attribute("name")
+            .add_attribute("date_of_birth")
+            .add_attribute("country")
+            .build()
+            .unwrap();
+        let passport = Passport {
+            name: FieldElement::random(),
+            date_of_birth: FieldElement::from(19900101u64),
+            country: FieldElement::random(),
+        };
+
+        // Same order as the schema
+        let msgs = passport.to_attributes();
+        assert_eq!(
+            msgs.as_slice(),
+            schema
+                .messages(&[
+                    ("country", passport.country.clone()),
+                    ("name", passport.name.clone()),
+                    ("date_of_birth", passport.date_of_birth.clone()),
+                ])
+                .unwrap()
+                .as_slice()
+        );
+        assert_eq!(
+            Passport::from_attributes(msgs.as_slice()).unwrap(),
+            passport
+        );
+        match Passport::from_attributes(&msgs.as_slice()[0..2]) {
+            Err(e) => match e.kind() {
+                CoconutErrorKind::UnsupportedNoOfMessages { expected, given } => {
+                    assert_eq!(expected, 3);
+                    assert_eq!(given, 2);
+                }
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(_) => panic!("built record from too few attributes"),
+        }
+
+        let ticket = without_imports::Ticket {
+            event: FieldElement::random(),
+            seat: FieldElement::from(12u64),
+        };
+        let ticket_msgs = ticket.to_attributes();
+        assert_eq!(ticket_msgs.len(), 2);
+        assert_eq!(
+            without_imports::Ticket::from_attributes(ticket_msgs.as_slice()).unwrap(),
+            ticket
+        );
+
+        // Credential issued over the record verifies over the record
+        let (sig, vk) = issue_credential(&msgs, &params);
+        assert!(sig
+            .verify(passport.to_attributes().as_slice(), &vk, &params)
+            .unwrap());
+
+        let mut other = passport.clone();
+        other.country = FieldElement::random();
+        assert!(!sig
+            .verify(other.to_attributes().as_slice(), &vk, &params)
+            .unwrap());
+    }
+}
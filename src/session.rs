@@ -0,0 +1,183 @@
+// Capture of an issuance session for debugging. A developer can serialize the messages of a
+// failing issuance, the params, request, proof of knowledge, challenge and blind signature, and
+// replay it later, like in a test, against the signer's key to get the same blind signature and
+// the same outcome of verifying the proof.
+
+use crate::errors::CoconutError;
+use crate::signature::{
+    BlindSignature, Params, Sigkey, SignatureRequest, SignatureRequestPoK, SignatureRequestProof,
+};
+use crate::utils::{append_with_len, ByteReader};
+use crate::{SignatureGroup, SIGNATURE_GROUP_SIZE};
+use amcl_wrapper::field_elem::FieldElement;
+use amcl_wrapper::group_elem::GroupElement;
+
+#[derive(Clone, Debug)]
+pub struct Session {
+    pub params: Params,
+    pub sig_req: SignatureRequest,
+    pub elgamal_pk: SignatureGroup,
+    /// `SignatureRequestPoK::to_bytes` of the proof of knowledge before the challenge
+    pub pok_bytes: Vec<u8>,
+    pub challenge: FieldElement,
+    pub proof: SignatureRequestProof,
+    pub blind_sig: BlindSignature,
+}
+
+impl Session {
+    /// `pok` is the proof of knowledge the `proof` was generated from, so its bytes need to be
+    /// taken before `SignatureRequestPoK::gen_proof`
+    pub fn new(
+        params: &Params,
+        sig_req: &SignatureRequest,
+        elgamal_pk: &SignatureGroup,
+        pok: &SignatureRequestPoK,
+        challenge: &FieldElement,
+        proof: &SignatureRequestProof,
+        blind_sig: &BlindSignature,
+    ) -> Self {
+        Self {
+            params: params.clone(),
+            sig_req: sig_req.clone(),
+            elgamal_pk: elgamal_pk.clone(),
+            pok_bytes: pok.to_bytes(),
+            challenge: challenge.clone(),
+            proof: proof.clone(),
+            blind_sig: blind_sig.clone(),
+        }
+    }
+
+    /// Verify the proof of the request and sign the request again with `sigkey`. Returns whether
+    /// the proof verified and the blind signature.
+    pub fn replay(&self, sigkey: &Sigkey) -> Result<(bool, BlindSignature), CoconutError> {
+        let verified = self.proof.verify(
+            &self.sig_req,
+            &self.elgamal_pk,
+            &self.challenge,
+            &self.params,
+        )?;
+        let blind_sig = BlindSignature::new(&self.sig_req, sigkey)?;
+        Ok((verified, blind_sig))
+    }
+
+    /// Byte representation is params || request || elgamal_pk || pok bytes || challenge || proof
+    /// || blind signature, where params, request, pok bytes, proof and blind signature are each
+    /// prefixed with their length as a 4 byte big-endian integer.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        append_with_len(&mut bytes, &self.params.to_bytes());
+        append_with_len(&mut bytes, &self.sig_req.to_bytes());
+        bytes.append(&mut self.elgamal_pk.to_bytes());
+        append_with_len(&mut bytes, &self.pok_bytes);
+        bytes.append(&mut self.challenge.to_bytes());
+        append_with_len(&mut bytes, &self.proof.to_bytes());
+        append_with_len(&mut bytes, &self.blind_sig.to_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CoconutError> {
+        let mut reader = ByteReader::new(bytes);
+        let params = Params::from_bytes(reader.read_with_len()?)?;
+        let sig_req = SignatureRequest::from_bytes(reader.read_with_len()?)?;
+        let elgamal_pk = reader.read_group_elem::<SignatureGroup>(SIGNATURE_GROUP_SIZE)?;
+        let pok_bytes = reader.read_with_len()?.to_vec();
+        let challenge = reader.read_field_element()?;
+        let proof = SignatureRequestProof::from_bytes(reader.read_with_len()?)?;
+        let blind_sig = BlindSignature::from_bytes(reader.read_with_len()?)?;
+        reader.finish()?;
+        Ok(Self {
+            params,
+            sig_req,
+            elgamal_pk,
+            pok_bytes,
+            challenge,
+            proof,
+            blind_sig,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keygen::trusted_party_SSS_keygen;
+    use amcl_wrapper::field_elem::FieldElementVector;
+
+    #[test]
+    fn test_session_replay() {
+        let msg_count = 4;
+        let count_hidden = 2;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (_, _, signers) = trusted_party_SSS_keygen(2, 3, &params);
+        let sigkey = &signers[0].sigkey;
+
+        let msgs = FieldElementVector::random(msg_count);
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        let (sig_req, randomness) =
+            SignatureRequest::new(&msgs, count_hidden, &elg_pk, &params).unwrap();
+        let pok = SignatureRequestPoK::init(&sig_req, &elg_pk, &params);
+        let pok_bytes = pok.to_bytes();
+        let challenge = FieldElement::from_msg_hash(&pok_bytes);
+        let hidden_msgs: FieldElementVector = msgs
+            .iter()
+            .take(count_hidden)
+            .cloned()
+            .collect::<Vec<FieldElement>>()
+            .into();
+        let session_pok = pok.clone();
+        let proof = pok
+            .gen_proof(&hidden_msgs, randomness, &elg_sk, &challenge)
+            .unwrap();
+        let blind_sig = BlindSignature::new(&sig_req, sigkey).unwrap();
+        let session = Session::new(
+            &params,
+            &sig_req,
+            &elg_pk,
+            &session_pok,
+            &challenge,
+            &proof,
+            &blind_sig,
+        );
+
+        let bytes = session.to_bytes();
+        let replayed = Session::from_bytes(&bytes).unwrap();
+        assert_eq!(replayed.to_bytes(), bytes);
+        assert_eq!(replayed.pok_bytes, pok_bytes);
+        let (verified, replayed_sig) = replayed.replay(sigkey).unwrap();
+        assert!(verified);
+        assert_eq!(replayed_sig.to_bytes(), blind_sig.to_bytes());
+
+        // A session captured with the wrong challenge replays to the same failed verification
+        let mut failing = session.clone();
+        failing.challenge = FieldElement::random();
+        let replayed = Session::from_bytes(&failing.to_bytes()).unwrap();
+        let (verified, replayed_sig) = replayed.replay(sigkey).unwrap();
+        assert!(!verified);
+        assert_eq!(replayed_sig.to_bytes(), blind_sig.to_bytes());
+
+        // A session with a truncated proof replays to a failed verification rather than a panic
+        let mut truncated = session.clone();
+        truncated.proof.proof_ciphertexts.pop();
+        let replayed = Session::from_bytes(&truncated.to_bytes()).unwrap();
+        let (verified, _) = replayed.replay(sigkey).unwrap();
+        assert!(!verified);
+        let mut truncated = session.clone();
+        truncated.proof.proof_commitment.responses = truncated
+            .proof
+            .proof_commitment
+            .responses
+            .iter()
+            .take(count_hidden)
+            .cloned()
+            .collect::<Vec<FieldElement>>()
+            .into();
+        let replayed = Session::from_bytes(&truncated.to_bytes()).unwrap();
+        let (verified, _) = replayed.replay(sigkey).unwrap();
+        assert!(!verified);
+
+        assert!(Session::from_bytes(&bytes[0..bytes.len() - 1]).is_err());
+        let mut extended = bytes.clone();
+        extended.push(0);
+        assert!(Session::from_bytes(&extended).is_err());
+    }
+}
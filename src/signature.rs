@@ -1,39 +1,375 @@
-use crate::errors::CoconutError;
-use secret_sharing::polynomial::Polynomial;
-use crate::{ate_2_pairing, OtherGroup, OtherGroupVec, SignatureGroup, SignatureGroupVec};
+use crate::challenge::{ChallengeHasher, DefaultChallengeHasher};
+use crate::circuit::CircuitInputs;
+use crate::commitment::{commit_opening, Commitment};
+use crate::device::{device_attribute, device_attribute_index, DeviceBindingProof};
+use crate::elgamal::ElgamalKeypair;
+use crate::errors::{CoconutError, CoconutErrorKind};
+use crate::keygen::WeightedSigner;
+use crate::lagrange::LagrangeCoefficients;
+use crate::pok_sig::OpeningProof;
+use crate::schema::SCHEMA_DIGEST_SIZE;
+use crate::utils::{
+    append_u32, bytes_to_hex, ensure_valid_point, group_elem_from_bytes, hash_to_subgroup,
+    hex_to_bytes, read_framed, write_framed, ByteReader, BytesVisitor,
+};
+use crate::{
+    ate_2_pairing, ate_multi_pairing, ate_pairing, checked_ate_2_pairing, OtherGroup,
+    OtherGroupVec, SignatureGroup, SignatureGroupVec,
+};
+use crate::{FIELD_ELEMENT_SIZE, OTHER_GROUP_SIZE, SIGNATURE_GROUP_SIZE};
+use crate::{OTHER_GROUP_COFACTOR, SIGNATURE_GROUP_COFACTOR};
 use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
 use amcl_wrapper::group_elem::{GroupElement, GroupElementVector};
 use ps_sig::errors::PSError;
 use ps_sig::keys::Params as PSParams;
 use ps_sig::keys::Verkey as PSVerkey;
+use ps_sig::pok_sig::ProverCommittedOtherGroup as PSProverCommittedOtherGroup;
 use ps_sig::signature::Signature as PSSignature;
-use std::collections::HashSet;
+use rand::{thread_rng, CryptoRng, RngCore};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use serde::de::Error as DError;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::convert::TryFrom;
+use std::hash::{Hash, Hasher};
+use std::io::{Read, Write};
+use std::ops::Range;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Params {
     pub g: SignatureGroup,
     pub g_tilde: OtherGroup,
     pub h: SignatureGroupVec,
+    /// Tag of the credential type (schema). Zero for untyped params. Typed params have 1 more
+    /// message than attributes, the type attribute, which is signed with this tag as value so
+    /// that signatures of one credential type don't verify as another type even under the same
+    /// verkey, see `Params::new_typed`.
+    pub credential_type: FieldElement,
+    #[serde(default)]
+    schema_digest: Option<[u8; SCHEMA_DIGEST_SIZE]>,
 }
 
 impl Params {
     /// Generate g1, g2 and 1 h for each message. These are shared by all signers and users.
-    /// "Setup" from paper.
+    /// All are hashed to the prime order subgroup. "Setup" from paper.
     pub fn new(msg_count: usize, label: &[u8]) -> Self {
-        let g = SignatureGroup::from_msg_hash(&[label, " : g".as_bytes()].concat());
-        let g_tilde = OtherGroup::from_msg_hash(&[label, " : g_tilde".as_bytes()].concat());
+        let g = hash_to_subgroup::<SignatureGroup>(
+            &[label, " : g".as_bytes()].concat(),
+            SIGNATURE_GROUP_COFACTOR,
+        );
+        let g_tilde = hash_to_subgroup::<OtherGroup>(
+            &[label, " : g_tilde".as_bytes()].concat(),
+            OTHER_GROUP_COFACTOR,
+        );
+        let h = Self::gen_h(msg_count, label);
+        Self {
+            g,
+            g_tilde,
+            h,
+            credential_type: FieldElement::zero(),
+            schema_digest: None,
+        }
+    }
+
+    /// Generate params for a specific credential type with `msg_count` attributes. g1 and g2 are
+    /// same as for `Params::new` so an issuer's verkey is usable across all credential types but
+    /// the `h`s and the tag are specific to the credential type. The params are for `msg_count + 1`
+    /// messages, the last is the type attribute whose value must be the tag. The signer checks
+    /// the type attribute as a known message of the request and every verification checks it, so
+    /// the messages of a signature can't be adjusted to verify as another type. Build the messages
+    /// with `typed_messages`. The verkey must be for `msg_count + 1` messages.
+    pub fn new_typed(msg_count: usize, label: &[u8], credential_type: &[u8]) -> Self {
+        let typed_label = [label, " : credential_type : ".as_bytes(), credential_type].concat();
+        let mut params = Self::new(msg_count + 1, label);
+        params.h = Self::gen_h(msg_count + 1, &typed_label);
+        params.credential_type = FieldElement::from_msg_hash(&typed_label);
+        params
+    }
+
+    /// Params from g1, g2 and the `h`s given by a setup ceremony rather than hashed to the curve,
+    /// with 1 h for each message. Points are on the curve as they are group elements, but errors
+    /// with `InvalidPoint` if any is the identity or not in the prime order subgroup, or if 2 of
+    /// g1 and the `h`s are equal. The params are untyped.
+    pub fn from_points(
+        g: SignatureGroup,
+        g_tilde: OtherGroup,
+        h: Vec<SignatureGroup>,
+    ) -> Result<Self, CoconutError> {
+        check_structure_point(&g, || String::from("g"))?;
+        if g_tilde.is_identity() || !g_tilde.has_correct_order() {
+            return Err(CoconutErrorKind::InvalidPoint {
+                msg: String::from("g_tilde"),
+            }
+            .into());
+        }
+        for (i, h_i) in h.iter().enumerate() {
+            check_structure_point(h_i, || format!("h[{}]", i))?;
+            if *h_i == g {
+                return Err(CoconutErrorKind::InvalidPoint {
+                    msg: format!("h[{}] is g", i),
+                }
+                .into());
+            }
+            if let Some(j) = h[..i].iter().position(|h_j| h_j == h_i) {
+                return Err(CoconutErrorKind::InvalidPoint {
+                    msg: format!("h[{}] is h[{}]", i, j),
+                }
+                .into());
+            }
+        }
+        Ok(Self {
+            g,
+            g_tilde,
+            h: h.into(),
+            credential_type: FieldElement::zero(),
+            schema_digest: None,
+        })
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    fn gen_h(msg_count: usize, label: &[u8]) -> SignatureGroupVec {
+        Self::gen_h_sequential(msg_count, label)
+    }
+
+    #[cfg(feature = "parallel")]
+    fn gen_h(msg_count: usize, label: &[u8]) -> SignatureGroupVec {
+        Self::gen_h_parallel(msg_count, label)
+    }
+
+    /// Only used by tests with the `parallel` feature.
+    #[cfg_attr(feature = "parallel", allow(dead_code))]
+    fn gen_h_sequential(msg_count: usize, label: &[u8]) -> SignatureGroupVec {
         let mut h = SignatureGroupVec::with_capacity(msg_count);
         for i in 0..msg_count {
-            h.push(SignatureGroup::from_msg_hash(
-                &[label, " : y".as_bytes(), i.to_string().as_bytes()].concat(),
-            ));
+            h.push(Self::gen_h_i(label, i));
+        }
+        h
+    }
+
+    /// Same as `gen_h_sequential` but each h is hashed on the rayon thread pool since they are
+    /// independent, which helps with many messages.
+    #[cfg(feature = "parallel")]
+    fn gen_h_parallel(msg_count: usize, label: &[u8]) -> SignatureGroupVec {
+        let elems = (0..msg_count)
+            .into_par_iter()
+            .map(|i| Self::gen_h_i(label, i))
+            .collect::<Vec<SignatureGroup>>();
+        let mut h = SignatureGroupVec::with_capacity(msg_count);
+        for e in elems {
+            h.push(e);
         }
-        Self { g, g_tilde, h }
+        h
+    }
+
+    fn gen_h_i(label: &[u8], i: usize) -> SignatureGroup {
+        hash_to_subgroup::<SignatureGroup>(
+            &[label, " : y".as_bytes(), i.to_string().as_bytes()].concat(),
+            SIGNATURE_GROUP_COFACTOR,
+        )
     }
 
     pub fn msg_count(&self) -> usize {
         self.h.len()
     }
+
+    /// Number of messages other than the type attribute of typed params
+    pub fn attribute_count(&self) -> usize {
+        self.msg_count() - self.type_attribute_index().map_or(0, |_| 1)
+    }
+
+    /// Indices of the messages these params support, 0 to `msg_count`
+    pub fn attribute_indices(&self) -> Range<usize> {
+        0..self.msg_count()
+    }
+
+    pub fn is_typed(&self) -> bool {
+        !self.credential_type.is_zero()
+    }
+
+    /// Carry `digest` of the attribute schema the params are for, see `AttributeSchema::digest`.
+    /// For typed params the tag, the value of the type attribute, is derived from the digest so
+    /// credentials are issued for the schema: neither a credential nor any presentation of it,
+    /// even one made afresh under params for another schema, verifies under those params. The
+    /// digest is also bound into the challenge of a `CredentialProof` and of a
+    /// `MultiCredentialProof`. Untyped params have no type attribute so only the challenge binds
+    /// the digest.
+    pub fn with_schema_digest(mut self, digest: [u8; SCHEMA_DIGEST_SIZE]) -> Self {
+        if self.is_typed() {
+            self.credential_type = FieldElement::from_msg_hash(
+                &[
+                    self.credential_type.to_bytes().as_slice(),
+                    " : schema : ".as_bytes(),
+                    &digest,
+                ]
+                .concat(),
+            );
+        }
+        self.schema_digest = Some(digest);
+        self
+    }
+
+    /// Digest of the attribute schema the params are for. None if the params carry no schema.
+    pub fn schema_digest(&self) -> Option<&[u8; SCHEMA_DIGEST_SIZE]> {
+        self.schema_digest.as_ref()
+    }
+
+    /// Index of the type attribute, the last message, for typed params. None for untyped params.
+    pub fn type_attribute_index(&self) -> Option<usize> {
+        if self.is_typed() {
+            Some(self.msg_count() - 1)
+        } else {
+            None
+        }
+    }
+
+    /// The messages of a credential with `attributes`, the attributes followed by the type
+    /// attribute for typed params. The attributes as they are for untyped params.
+    pub fn typed_messages(&self, attributes: &[FieldElement]) -> FieldElementVector {
+        let mut messages = FieldElementVector::from(attributes.to_vec());
+        if self.is_typed() {
+            messages.push(self.credential_type.clone());
+        }
+        messages
+    }
+
+    /// Whether `messages` end with the type attribute of the params. Always true for untyped
+    /// params.
+    pub fn has_type_attribute(&self, messages: &[FieldElement]) -> bool {
+        !self.is_typed() || messages.last() == Some(&self.credential_type)
+    }
+
+    /// Whether message `m` at index `idx` is the type attribute index with a value other than the
+    /// tag. Never for untyped params.
+    pub(crate) fn is_wrong_type_attribute(&self, idx: usize, m: &FieldElement) -> bool {
+        self.type_attribute_index() == Some(idx) && *m != self.credential_type
+    }
+
+    /// Whether `revealed` (index, message) pairs reveal the type attribute of the params. A proof
+    /// hiding the type attribute doesn't say which credential type it is for. Always true for
+    /// untyped params.
+    pub(crate) fn reveals_type_attribute<'a, I>(&self, revealed: I) -> bool
+    where
+        I: IntoIterator<Item = (&'a usize, &'a FieldElement)>,
+    {
+        match self.type_attribute_index() {
+            Some(idx) => revealed
+                .into_iter()
+                .any(|(i, m)| *i == idx && *m == self.credential_type),
+            None => true,
+        }
+    }
+
+    /// Bases of the commitment of a `SignatureRequest` with `count_hidden` hidden messages, h_1,
+    /// h_2, ... for the hidden messages and then g for the randomness
+    pub fn commitment_bases(&self, count_hidden: usize) -> Vec<SignatureGroup> {
+        let mut bases = self
+            .h
+            .iter()
+            .take(count_hidden)
+            .cloned()
+            .collect::<Vec<SignatureGroup>>();
+        bases.push(self.g.clone());
+        bases
+    }
+
+    /// Commitment h_1^m_1.h_2^m_2...h_k^m_k.g^r to the hidden messages m_1, ..., m_k with
+    /// randomness `r`, the commitment of a `SignatureRequest` created with the same hidden messages
+    /// and commitment randomness (1st element of the randomness returned by
+    /// `SignatureRequest::new`). Lets a client and a server compute the commitment on their own
+    /// to find where they disagree. Errors if there are more hidden messages than messages.
+    pub fn commit(
+        &self,
+        hidden_messages: &[FieldElement],
+        r: &FieldElement,
+    ) -> Result<SignatureGroup, CoconutError> {
+        if hidden_messages.len() > self.h.len() {
+            return Err(CoconutErrorKind::UnsupportedNoOfMessages {
+                expected: self.h.len(),
+                given: hidden_messages.len(),
+            }
+            .into());
+        }
+        Commitment::compute(
+            hidden_messages,
+            r,
+            &self.commitment_bases(hidden_messages.len()),
+        )
+    }
+
+    /// Byte representation is g || g_tilde || credential_type || schema digest flag || schema
+    /// digest, if any || count of h || h_1 || h_2 || ... h_n. The flag is 1 byte, 1 if a schema
+    /// digest follows and 0 if not. The count is a 4 byte big-endian integer.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.g.to_bytes();
+        bytes.append(&mut self.g_tilde.to_bytes());
+        bytes.append(&mut self.credential_type.to_bytes());
+        match &self.schema_digest {
+            Some(digest) => {
+                bytes.push(1);
+                bytes.extend_from_slice(digest);
+            }
+            None => bytes.push(0),
+        }
+        append_u32(&mut bytes, self.h.len());
+        for h in self.h.iter() {
+            bytes.append(&mut h.to_bytes());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CoconutError> {
+        let mut reader = ByteReader::new(bytes);
+        let g = reader.read_group_elem::<SignatureGroup>(SIGNATURE_GROUP_SIZE)?;
+        let g_tilde = reader.read_group_elem::<OtherGroup>(OTHER_GROUP_SIZE)?;
+        let credential_type = reader.read_field_element()?;
+        let schema_digest = match reader.take(1)?[0] {
+            0 => None,
+            1 => {
+                let mut digest = [0u8; SCHEMA_DIGEST_SIZE];
+                digest.copy_from_slice(reader.take(SCHEMA_DIGEST_SIZE)?);
+                Some(digest)
+            }
+            flag => {
+                return Err(CoconutErrorKind::GeneralError {
+                    msg: format!("Unknown schema digest flag {}", flag),
+                }
+                .into())
+            }
+        };
+        let msg_count = reader.read_count(SIGNATURE_GROUP_SIZE)?;
+        let mut h = SignatureGroupVec::with_capacity(msg_count);
+        for _ in 0..msg_count {
+            h.push(reader.read_group_elem::<SignatureGroup>(SIGNATURE_GROUP_SIZE)?);
+        }
+        reader.finish()?;
+        Ok(Self {
+            g,
+            g_tilde,
+            h,
+            credential_type,
+            schema_digest,
+        })
+    }
+
+    pub fn to_hex(&self) -> String {
+        bytes_to_hex(&self.to_bytes())
+    }
+
+    pub fn from_hex(hex: &str) -> Result<Self, CoconutError> {
+        Self::from_bytes(&hex_to_bytes(hex)?)
+    }
+
+    /// Write `to_bytes` to `writer` framed by its length, see `write_framed`
+    pub fn write_to<W: Write>(&self, writer: W) -> Result<(), CoconutError> {
+        write_framed(writer, &self.to_bytes())
+    }
+
+    /// Read params written by `write_to`
+    pub fn read_from<R: Read>(reader: R) -> Result<Self, CoconutError> {
+        Self::from_bytes(&read_framed(reader)?)
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -42,7 +378,9 @@ pub struct Sigkey {
     pub y: Vec<FieldElement>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+/// Serialized with serde as bytes (see `to_bytes`) so that decoding failures of group elements
+/// surface as serde errors rather than panics.
+#[derive(Clone, Debug)]
 pub struct Verkey {
     pub X_tilde: OtherGroup,
     pub Y_tilde: Vec<OtherGroup>,
@@ -54,6 +392,11 @@ pub struct SignatureRequest {
     pub known_messages: FieldElementVector,
     pub commitment: SignatureGroup,
     pub ciphertexts: Vec<(SignatureGroup, SignatureGroup)>,
+    /// Ciphertexts of the hidden messages under the Elgamal key for each signer, by signer id, for
+    /// a request created with `new_multi_issuer`. `ciphertexts` is empty then and each signer
+    /// signs the request given by `for_signer`.
+    #[serde(default)]
+    pub signer_ciphertexts: BTreeMap<usize, Vec<(SignatureGroup, SignatureGroup)>>,
 }
 
 /// Created by the signer
@@ -61,15 +404,129 @@ pub struct SignatureRequest {
 pub struct BlindSignature {
     pub h: SignatureGroup,
     pub blinded: (SignatureGroup, SignatureGroup),
+    /// a_i^y_i for each ciphertext (a_i, b_i) of the request. Only set by `new_selective`, see
+    /// there for why these can be given to the user.
+    #[serde(default)]
+    pub blinded_terms: Vec<SignatureGroup>,
 }
 
 /// Result of the unblinding of the blind signature. Is in the form of PS signature.
-#[derive(Clone, Debug, Serialize, Deserialize)]
+/// Serialized with serde as bytes (see `to_bytes`) so that decoding failures of group elements
+/// surface as serde errors rather than panics.
+#[derive(Clone, Debug)]
 pub struct Signature {
     pub sigma_1: SignatureGroup,
     pub sigma_2: SignatureGroup,
 }
 
+/// Ids of the signers whose signatures were aggregated, from `Signature::aggregate_with_metadata`
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AggregateMetadata {
+    pub signer_ids: BTreeSet<usize>,
+}
+
+/// Whether 2 aggregated signatures were created from the signatures of the same signers
+pub fn same_signer_set(a: &AggregateMetadata, b: &AggregateMetadata) -> bool {
+    a.signer_ids == b.signer_ids
+}
+
+/// Signatures sharing the same sigma_1 (like the signatures from different signers over the same
+/// signature request) stored with sigma_1 only once.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SignatureBatch {
+    pub sigma_1: SignatureGroup,
+    pub sigma_2s: Vec<SignatureGroup>,
+}
+
+/// Collects the blind signatures of the signers of a request as they arrive, in any order, like
+/// when the signers sign concurrently and their responses are awaited independently. Each blind
+/// signature is checked on arrival against the signer's verkey so that `aggregate` only gets
+/// signatures by the expected signers.
+#[derive(Clone, Debug)]
+pub struct PartialSignatureSet {
+    threshold: usize,
+    sig_req: SignatureRequest,
+    params: Params,
+    /// h of the request that all blind signatures must have
+    h: SignatureGroup,
+    sigs: BTreeMap<usize, BlindSignature>,
+}
+
+impl Serialize for Verkey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&self.to_bytes())
+    }
+}
+
+impl<'de> Deserialize<'de> for Verkey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = deserializer.deserialize_bytes(BytesVisitor)?;
+        Verkey::from_bytes(&bytes).map_err(D::Error::custom)
+    }
+}
+
+impl Serialize for Signature {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&self.to_bytes())
+    }
+}
+
+impl<'de> Deserialize<'de> for Signature {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = deserializer.deserialize_bytes(BytesVisitor)?;
+        Signature::from_bytes(&bytes).map_err(D::Error::custom)
+    }
+}
+
+// Equality compares the group elements, not in constant time, and hashing is over `to_bytes`,
+// which is the same for equal elements.
+
+impl PartialEq for Params {
+    fn eq(&self, other: &Self) -> bool {
+        self.g == other.g
+            && self.g_tilde == other.g_tilde
+            && self.h.as_slice() == other.h.as_slice()
+            && self.credential_type == other.credential_type
+            && self.schema_digest == other.schema_digest
+    }
+}
+
+impl Eq for Params {}
+
+impl Hash for Params {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.to_bytes().hash(state)
+    }
+}
+
+impl PartialEq for Verkey {
+    fn eq(&self, other: &Self) -> bool {
+        self.X_tilde == other.X_tilde && self.Y_tilde == other.Y_tilde
+    }
+}
+
+impl Eq for Verkey {}
+
+impl Hash for Verkey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.to_bytes().hash(state)
+    }
+}
+
+impl PartialEq for Signature {
+    fn eq(&self, other: &Self) -> bool {
+        self.sigma_1 == other.sigma_1 && self.sigma_2 == other.sigma_2
+    }
+}
+
+impl Eq for Signature {}
+
+impl Hash for Signature {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.to_bytes().hash(state)
+    }
+}
+
 impl_PoK_VC!(
     ProverCommittingSignatureGroup,
     ProverCommittedSignatureGroup,
@@ -89,17 +546,70 @@ pub fn transform_to_PS_params(params: &Params) -> PSParams {
 
 // Transform the verkey to Verkey struct of ps_sig crate
 pub fn transform_to_PS_verkey(vk: &Verkey) -> PSVerkey {
-    PSVerkey {
-        X_tilde: vk.X_tilde.clone(),
-        Y_tilde: vk.Y_tilde.clone(),
-    }
+    PSVerkey::from(vk.clone())
 }
 
 // Transform the signature to Signature struct of ps_sig crate
 pub fn transform_to_PS_sig(sig: &Signature) -> PSSignature {
-    PSSignature {
-        sigma_1: sig.sigma_1.clone(),
-        sigma_2: sig.sigma_2.clone(),
+    PSSignature::from(sig.clone())
+}
+
+// Conversions for migrating single signer credentials of the ps_sig crate. A ps_sig signature
+// and verkey are a Coconut signature and verkey of a single signer, but:
+// - ps_sig signs the messages as given, so a converted signature only verifies under untyped
+//   params (`Params::new`, `Params::from_points`) with the same g and g_tilde as the ps_sig params.
+// - ps_sig issuance is not blind, there is no `SignatureRequest` with Elgamal ciphertexts of
+//   hidden messages, and a ps_sig key is not a share of a threshold key, so a converted verkey
+//   can't be aggregated with the verkeys of Coconut signers. It is a 1 out of 1 issuer.
+// Conversions from ps_sig error with `InvalidPoint` if a point is the identity or outside the
+// prime order subgroup, like `Signature::is_valid_structure` and `Verkey::validate`.
+
+impl TryFrom<PSSignature> for Signature {
+    type Error = CoconutError;
+
+    fn try_from(sig: PSSignature) -> Result<Self, CoconutError> {
+        let sig = Self {
+            sigma_1: sig.sigma_1,
+            sigma_2: sig.sigma_2,
+        };
+        if !sig.is_valid_structure() {
+            return Err(CoconutErrorKind::InvalidPoint {
+                msg: String::from("sigma_1 or sigma_2 of ps_sig signature"),
+            }
+            .into());
+        }
+        Ok(sig)
+    }
+}
+
+impl From<Signature> for PSSignature {
+    fn from(sig: Signature) -> Self {
+        Self {
+            sigma_1: sig.sigma_1,
+            sigma_2: sig.sigma_2,
+        }
+    }
+}
+
+impl TryFrom<PSVerkey> for Verkey {
+    type Error = CoconutError;
+
+    fn try_from(vk: PSVerkey) -> Result<Self, CoconutError> {
+        let vk = Self {
+            X_tilde: vk.X_tilde,
+            Y_tilde: vk.Y_tilde,
+        };
+        vk.validate()?;
+        Ok(vk)
+    }
+}
+
+impl From<Verkey> for PSVerkey {
+    fn from(vk: Verkey) -> Self {
+        Self {
+            X_tilde: vk.X_tilde,
+            Y_tilde: vk.Y_tilde,
+        }
     }
 }
 
@@ -112,6 +622,61 @@ pub struct SignatureRequestPoK {
     pub pok_vc_ciphertext: Vec<(ProverCommittedSignatureGroup, ProverCommittedSignatureGroup)>,
 }
 
+/// Blindings of the hidden messages used in a `SignatureRequestPoK`, by message index, along with
+/// the Schnorr commitments of that proof of knowledge. Handed to the show protocol with
+/// `CredentialProofBuilder::with_blinding_context` so that a hidden message gets the same blinding
+/// and thus the same response in the request's proof and in the presentation, linking them without
+/// revealing the message. Both proofs are then created with the presentation's challenge, which
+/// covers the request's commitments, see `CredentialProofBuilder::finish_linked`. Responses to
+/// different challenges with the same blinding reveal the message, so the request's proof must
+/// not also be created with another challenge.
+#[derive(Clone, Debug)]
+pub struct BlindingContext {
+    blindings: BTreeMap<usize, FieldElement>,
+    request_commitments: Vec<SignatureGroup>,
+}
+
+impl BlindingContext {
+    /// Blinding of the hidden message at index `idx`
+    pub fn blinding(&self, idx: usize) -> Option<&FieldElement> {
+        self.blindings.get(&idx)
+    }
+
+    /// Blindings for the messages not in `revealed_msg_indices` in increasing order of index, as
+    /// taken by `PoKOfSignature::init`. Messages without a blinding in the context get a random
+    /// one.
+    pub fn presentation_blindings(
+        &self,
+        msg_count: usize,
+        revealed_msg_indices: &HashSet<usize>,
+    ) -> Vec<FieldElement> {
+        (0..msg_count)
+            .filter(|i| !revealed_msg_indices.contains(i))
+            .map(|i| match self.blindings.get(&i) {
+                Some(b) => b.clone(),
+                None => FieldElement::random(),
+            })
+            .collect()
+    }
+
+    /// Bytes of the request's Schnorr commitments that go in the challenge of a linked
+    /// presentation
+    pub(crate) fn challenge_bytes(&self) -> Vec<u8> {
+        request_commitment_bytes(&self.request_commitments)
+    }
+}
+
+/// Count of commitments || commitments, for the Schnorr commitments of a request's proof of
+/// knowledge in the order of `SignatureRequestPoK::commitments`
+pub(crate) fn request_commitment_bytes(commitments: &[SignatureGroup]) -> Vec<u8> {
+    let mut bytes = vec![];
+    append_u32(&mut bytes, commitments.len());
+    for c in commitments {
+        bytes.append(&mut c.to_bytes());
+    }
+    bytes
+}
+
 /// Created by entity requesting a signature to prove knowledge of hidden elements used in SignatureRequest.
 /// Represents the response phase of Schnoor protocol
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -129,34 +694,122 @@ impl SignatureRequest {
         count_hidden: usize,
         elgamal_pubkey: &SignatureGroup,
         params: &Params,
-    ) -> (Self, FieldElementVector) {
-        assert!(messages.len() >= count_hidden);
-        assert_eq!(messages.len(), params.h.len());
+    ) -> Result<(Self, FieldElementVector), CoconutError> {
+        Self::new_with_rng(
+            messages,
+            count_hidden,
+            elgamal_pubkey,
+            params,
+            &mut thread_rng(),
+        )
+    }
 
-        // Randomness for commitment and ciphertexts. Used to prove knowleddge later on
-        let mut randomness = FieldElementVector::with_capacity(count_hidden + 1);
+    /// Same as `new` but the hidden messages are encrypted under the public key of `keypair`
+    pub fn new_with_keypair(
+        messages: &FieldElementVector,
+        count_hidden: usize,
+        keypair: &ElgamalKeypair,
+        params: &Params,
+    ) -> Result<(Self, FieldElementVector), CoconutError> {
+        Self::new(messages, count_hidden, &keypair.pk, params)
+    }
 
-        // Commit to the hidden messages
-        let mut bases: SignatureGroupVec = params
-            .h
-            .iter()
-            .take(count_hidden)
-            .map(|g| g.clone())
-            .collect::<Vec<SignatureGroup>>()
-            .into();
-        bases.push(params.g.clone());
-        let mut exponents: FieldElementVector = messages
-            .iter()
-            .take(count_hidden)
-            .map(|f| f.clone())
+    /// Same as `new` but the commitment and Elgamal randomness is sampled from `rng`. A seeded
+    /// `rng` gives the same request every time, like for reproducing a bug.
+    pub fn new_with_rng<R: RngCore + CryptoRng>(
+        messages: &FieldElementVector,
+        count_hidden: usize,
+        elgamal_pubkey: &SignatureGroup,
+        params: &Params,
+        rng: &mut R,
+    ) -> Result<(Self, FieldElementVector), CoconutError> {
+        let (sig_req, randomness) =
+            Self::create(messages, count_hidden, elgamal_pubkey, params, rng)?;
+        debug_assert!(check_elgamal_randomness(&randomness).is_ok());
+        Ok((sig_req, randomness))
+    }
+
+    /// Same as `new_with_rng` but errors with `RandomnessReuse` if any 2 of the commitment and
+    /// Elgamal randomness are equal, as with a broken `rng`, rather than returning a request
+    /// whose encryption leaks relations between the hidden messages.
+    pub fn new_checked<R: RngCore + CryptoRng>(
+        messages: &FieldElementVector,
+        count_hidden: usize,
+        elgamal_pubkey: &SignatureGroup,
+        params: &Params,
+        rng: &mut R,
+    ) -> Result<(Self, FieldElementVector), CoconutError> {
+        let (sig_req, randomness) =
+            Self::create(messages, count_hidden, elgamal_pubkey, params, rng)?;
+        check_all_randomness(&randomness)?;
+        Ok((sig_req, randomness))
+    }
+
+    /// Same as `new` but with the caller's commitment randomness `r` and Elgamal randomness, given
+    /// as `randomness` laid out as returned by `new`, r followed by 1 for each hidden message. When
+    /// re-issuing a credential with the same hidden messages, passing the randomness of the earlier
+    /// request gives the same commitment, so the signers can link both rounds. Errors if
+    /// `randomness` is not of that length and with `RandomnessReuse` if any 2 of its values are
+    /// equal.
+    pub fn new_with_randomness(
+        messages: &FieldElementVector,
+        count_hidden: usize,
+        elgamal_pubkey: &SignatureGroup,
+        randomness: &FieldElementVector,
+        params: &Params,
+    ) -> Result<Self, CoconutError> {
+        if randomness.len() != count_hidden + 1 {
+            return Err(CoconutErrorKind::GeneralError {
+                msg: format!(
+                    "Need {} randomness for {} hidden messages but given {}",
+                    count_hidden + 1,
+                    count_hidden,
+                    randomness.len()
+                ),
+            }
+            .into());
+        }
+        check_all_randomness(randomness)?;
+        Self::create_with_randomness(messages, count_hidden, elgamal_pubkey, randomness, params)
+    }
+
+    /// Request and its randomness without any check of the randomness
+    fn create<R: RngCore + CryptoRng>(
+        messages: &FieldElementVector,
+        count_hidden: usize,
+        elgamal_pubkey: &SignatureGroup,
+        params: &Params,
+        rng: &mut R,
+    ) -> Result<(Self, FieldElementVector), CoconutError> {
+        // Randomness for commitment and ciphertexts. Used to prove knowleddge later on
+        let randomness: FieldElementVector = (0..count_hidden + 1)
+            .map(|_| FieldElement::random_using_rng(rng))
             .collect::<Vec<FieldElement>>()
             .into();
-        let r = FieldElement::random();
-        exponents.push(r.clone());
-        // commitment = h_1^m_1.h_2^m_2...h_count_hidden^m_count_hidden.g_1^r
-        let commitment = bases.multi_scalar_mul_const_time(&exponents).unwrap();
+        let sig_req = Self::create_with_randomness(
+            messages,
+            count_hidden,
+            elgamal_pubkey,
+            &randomness,
+            params,
+        )?;
+        Ok((sig_req, randomness))
+    }
+
+    /// Request with commitment randomness `randomness[0]` and Elgamal randomness `randomness[1..]`
+    fn create_with_randomness(
+        messages: &FieldElementVector,
+        count_hidden: usize,
+        elgamal_pubkey: &SignatureGroup,
+        randomness: &FieldElementVector,
+        params: &Params,
+    ) -> Result<Self, CoconutError> {
+        assert!(messages.len() >= count_hidden);
+        assert_eq!(messages.len(), params.h.len());
+        assert_eq!(randomness.len(), count_hidden + 1);
 
-        randomness.push(r);
+        // commitment = h_1^m_1.h_2^m_2...h_count_hidden^m_count_hidden.g_1^r
+        let commitment = params.commit(&messages.as_slice()[..count_hidden], &randomness[0])?;
 
         let known_messages = messages
             .iter()
@@ -164,16 +817,17 @@ impl SignatureRequest {
             .map(|f| f.clone())
             .collect::<Vec<FieldElement>>();
 
-        // Each element of `ciphertexts` is the elgamal ciphertext and the randomness used during encryption.
-        // The randomness is used for proof of knowledge
+        // Each element of `ciphertexts` is the elgamal ciphertext (g^k, pk^k.h^m) of a hidden
+        // message with k from `randomness`
         let ciphertexts = if count_hidden > 0 {
             let h = Self::compute_h(&commitment, &known_messages);
             messages
                 .iter()
                 .take(count_hidden)
-                .map(|m| {
-                    let (c1, c2, k) = elgamal_encrypt!(&params.g, elgamal_pubkey, &(&h * m));
-                    randomness.push(k);
+                .zip(randomness.iter().skip(1))
+                .map(|(m, k)| {
+                    let c1 = &params.g * k;
+                    let c2 = &(elgamal_pubkey * k) + &(&h * m);
                     (c1, c2)
                 })
                 .collect::<Vec<(SignatureGroup, SignatureGroup)>>()
@@ -181,32 +835,474 @@ impl SignatureRequest {
             vec![]
         };
 
+        Ok(Self {
+            known_messages: known_messages.into(),
+            commitment,
+            ciphertexts,
+            signer_ciphertexts: BTreeMap::new(),
+        })
+    }
+
+    /// Switch the Elgamal key the hidden messages are encrypted under. Each ciphertext is decrypted
+    /// with `old_sk` and the plaintext is encrypted again under `new_pk` with fresh randomness.
+    /// The commitment and known messages are unchanged so the same `h` applies. Returns the new
+    /// request and the new encryption randomness, 1 for each ciphertext. The proof of knowledge
+    /// has to be created again with the randomness for `gen_proof` being the commitment randomness
+    /// (1st element of the randomness returned by `new`) followed by the returned randomness.
+    /// The ciphertexts for each signer of a request created with `new_multi_issuer` are under the
+    /// signers' Elgamal keys, not `old_sk`, so they are carried over unchanged.
+    pub fn reencrypt(
+        &self,
+        old_sk: &FieldElement,
+        new_pk: &SignatureGroup,
+        params: &Params,
+    ) -> (Self, FieldElementVector) {
+        let mut randomness = FieldElementVector::with_capacity(self.ciphertexts.len());
+        let ciphertexts = self
+            .ciphertexts
+            .iter()
+            .map(|(c1, c2)| {
+                let m = elgamal_decrypt!(c1, c2, old_sk);
+                let (c1, c2, k) = elgamal_encrypt!(&params.g, new_pk, &m);
+                randomness.push(k);
+                (c1, c2)
+            })
+            .collect::<Vec<(SignatureGroup, SignatureGroup)>>();
         (
             Self {
-                known_messages: known_messages.into(),
-                commitment,
+                known_messages: self.known_messages.clone(),
+                commitment: self.commitment.clone(),
                 ciphertexts,
+                signer_ciphertexts: self.signer_ciphertexts.clone(),
             },
             randomness,
         )
     }
 
-    /// Compute a generator in SignatureGroup by hashing commitment to hidden messages and all known messages.
-    /// It is important that the for computing h, all messages in the signature are taken into account to
-    /// prevent malleability.
-    pub fn compute_h(
-        commitment: &SignatureGroup,
-        known_messages: &[FieldElement],
-    ) -> SignatureGroup {
-        let mut bytes = commitment.to_bytes();
-        for m in known_messages {
-            bytes.append(&mut m.to_bytes());
+    /// Request for a credential bound to the holder device with public key `device_pk`. The
+    /// credential has `messages`, of which the first `count_hidden` are hidden, followed by the
+    /// device attribute for `device_pk` as a known message, see `device::device_attribute`, and the
+    /// type attribute for typed params. `device_proof` is created on the device with
+    /// `DeviceBindingProof::for_issuance` for `context` so the device secret is never given to the
+    /// holder. Errors if `device_proof` is invalid or `params` aren't for 1 more attribute than
+    /// `messages`. The device public key is known to the signers, see `device` on linkability.
+    pub fn new_device_bound(
+        messages: &FieldElementVector,
+        device_pk: &SignatureGroup,
+        device_proof: &DeviceBindingProof,
+        context: &[u8],
+        count_hidden: usize,
+        elgamal_pubkey: &SignatureGroup,
+        params: &Params,
+    ) -> Result<(Self, FieldElementVector), CoconutError> {
+        if messages.len() + 1 != params.attribute_count() {
+            return Err(CoconutErrorKind::UnsupportedNoOfMessages {
+                expected: params.attribute_count(),
+                given: messages.len() + 1,
+            }
+            .into());
+        }
+        if !device_proof.verify_for_issuance(device_pk, context, params)? {
+            return Err(CoconutErrorKind::InvalidProof {
+                msg: String::from("proof of knowledge of device key"),
+            }
+            .into());
         }
-        SignatureGroup::from_msg_hash(&bytes)
+        let mut attributes = messages.as_slice().to_vec();
+        attributes.push(device_attribute(device_pk));
+        Self::new(
+            &params.typed_messages(&attributes),
+            count_hidden,
+            elgamal_pubkey,
+            params,
+        )
     }
-}
 
-impl SignatureRequestPoK {
+    /// Check by a signer that the request is for a credential bound to the device with public key
+    /// `device_pk`, i.e. `device_proof` created for `context` is valid and the known messages have
+    /// the device attribute for `device_pk`.
+    pub fn check_device_binding(
+        &self,
+        device_pk: &SignatureGroup,
+        device_proof: &DeviceBindingProof,
+        context: &[u8],
+        params: &Params,
+    ) -> Result<bool, CoconutError> {
+        // The known messages are the last ones
+        let count_known = self.known_messages.len();
+        let idx = device_attribute_index(params);
+        if count_known > params.msg_count() || idx + count_known < params.msg_count() {
+            return Ok(false);
+        }
+        if self.known_messages[idx + count_known - params.msg_count()]
+            != device_attribute(device_pk)
+        {
+            return Ok(false);
+        }
+        device_proof.verify_for_issuance(device_pk, context, params)
+    }
+
+    /// Same as `new` but the hidden messages are encrypted separately under the Elgamal key of
+    /// each signer, given as (signer id, Elgamal public key), rather than under 1 key for all
+    /// signers. Returns the request and, by signer id, the randomness as returned by `new` for the
+    /// request of that signer (see `for_signer`). The commitment randomness is the same for all.
+    /// Errors if no key is given or a signer id is repeated.
+    pub fn new_multi_issuer(
+        messages: &FieldElementVector,
+        count_hidden: usize,
+        elgamal_pubkeys: &[(usize, SignatureGroup)],
+        params: &Params,
+    ) -> Result<(Self, BTreeMap<usize, FieldElementVector>), CoconutError> {
+        check_share_ids(1, elgamal_pubkeys.iter().map(|(id, _)| *id))?;
+        let (first_id, first_pk) = &elgamal_pubkeys[0];
+        let (mut sig_req, first_randomness) = Self::new(messages, count_hidden, first_pk, params)?;
+        let h = Self::compute_h(&sig_req.commitment, sig_req.known_messages.as_slice());
+
+        let mut randomness = BTreeMap::new();
+        for (id, pk) in &elgamal_pubkeys[1..] {
+            let mut signer_randomness = FieldElementVector::with_capacity(count_hidden + 1);
+            signer_randomness.push(first_randomness[0].clone());
+            let ciphertexts = messages
+                .iter()
+                .take(count_hidden)
+                .map(|m| {
+                    let (c1, c2, k) = elgamal_encrypt!(&params.g, pk, &(&h * m));
+                    signer_randomness.push(k);
+                    (c1, c2)
+                })
+                .collect::<Vec<(SignatureGroup, SignatureGroup)>>();
+            debug_assert!(check_elgamal_randomness(&signer_randomness).is_ok());
+            sig_req.signer_ciphertexts.insert(*id, ciphertexts);
+            randomness.insert(*id, signer_randomness);
+        }
+        let first_ciphertexts = std::mem::replace(&mut sig_req.ciphertexts, vec![]);
+        sig_req
+            .signer_ciphertexts
+            .insert(*first_id, first_ciphertexts);
+        randomness.insert(*first_id, first_randomness);
+        Ok((sig_req, randomness))
+    }
+
+    /// The request to be signed by the signer with id `signer_id`, with the ciphertexts under that
+    /// signer's Elgamal key. The request itself if it was not created with `new_multi_issuer`.
+    /// Errors if the request has no ciphertexts for the signer.
+    pub fn for_signer(&self, signer_id: usize) -> Result<Self, CoconutError> {
+        if self.signer_ciphertexts.is_empty() {
+            return Ok(self.clone());
+        }
+        match self.signer_ciphertexts.get(&signer_id) {
+            Some(ciphertexts) => Ok(Self {
+                known_messages: self.known_messages.clone(),
+                commitment: self.commitment.clone(),
+                ciphertexts: ciphertexts.clone(),
+                signer_ciphertexts: BTreeMap::new(),
+            }),
+            None => Err(CoconutErrorKind::GeneralError {
+                msg: format!("No ciphertexts for signer {}", signer_id),
+            }
+            .into()),
+        }
+    }
+
+    /// Cheap check of the shape of the request before verifying its proof of knowledge, without
+    /// any pairing. The ciphertexts and known messages must add up to the messages of `params`,
+    /// for a request created with `new_multi_issuer` each signer must have 1 ciphertext for each
+    /// hidden message, and the commitment and ciphertexts must be non-identity points in the
+    /// prime order subgroup. Passing says nothing about the validity of the proof.
+    pub fn validate_structure(&self, params: &Params) -> Result<(), CoconutError> {
+        let msg_count = params.h.len();
+        if self.known_messages.len() > msg_count {
+            return Err(CoconutErrorKind::UnsupportedNoOfMessages {
+                expected: msg_count,
+                given: self.known_messages.len(),
+            }
+            .into());
+        }
+        let count_hidden = msg_count - self.known_messages.len();
+        if !self.signer_ciphertexts.is_empty() && !self.ciphertexts.is_empty() {
+            return Err(CoconutErrorKind::GeneralError {
+                msg: String::from("Request has both ciphertexts and ciphertexts for each signer"),
+            }
+            .into());
+        }
+        let all_ciphertexts = if self.signer_ciphertexts.is_empty() {
+            vec![&self.ciphertexts]
+        } else {
+            self.signer_ciphertexts.values().collect()
+        };
+        for ciphertexts in all_ciphertexts {
+            if ciphertexts.len() != count_hidden {
+                return Err(CoconutErrorKind::UnsupportedNoOfMessages {
+                    expected: msg_count,
+                    given: ciphertexts.len() + self.known_messages.len(),
+                }
+                .into());
+            }
+            for (i, (c1, c2)) in ciphertexts.iter().enumerate() {
+                check_structure_point(c1, || format!("c1 of ciphertext {}", i))?;
+                check_structure_point(c2, || format!("c2 of ciphertext {}", i))?;
+            }
+        }
+        check_structure_point(&self.commitment, || String::from("commitment"))
+    }
+
+    /// Compute a generator in SignatureGroup by hashing commitment to hidden messages and all known messages.
+    /// It is important that the for computing h, all messages in the signature are taken into account to
+    /// prevent malleability.
+    pub fn compute_h(
+        commitment: &SignatureGroup,
+        known_messages: &[FieldElement],
+    ) -> SignatureGroup {
+        let mut bytes = commitment.to_bytes();
+        for m in known_messages {
+            bytes.append(&mut m.to_bytes());
+        }
+        hash_to_subgroup::<SignatureGroup>(&bytes, SIGNATURE_GROUP_COFACTOR)
+    }
+
+    /// Bases of `commitment` as a `Commitment` to the hidden messages, h_1, h_2, ... for the
+    /// hidden messages and then g for the randomness
+    pub fn commitment_bases(&self, params: &Params) -> Vec<SignatureGroup> {
+        params.commitment_bases(self.ciphertexts.len())
+    }
+
+    /// Byte representation is commitment || ciphertexts || count of known messages || known
+    /// messages || count of signers || (signer id || ciphertexts) for each signer of
+    /// `signer_ciphertexts`, where ciphertexts are a count followed by c1 || c2 for each
+    /// ciphertext. Counts and ids are 4 byte big-endian integers.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.commitment.to_bytes();
+        append_ciphertexts(&mut bytes, &self.ciphertexts);
+        append_u32(&mut bytes, self.known_messages.len());
+        for m in self.known_messages.iter() {
+            bytes.append(&mut m.to_bytes());
+        }
+        append_u32(&mut bytes, self.signer_ciphertexts.len());
+        for (id, ciphertexts) in &self.signer_ciphertexts {
+            append_u32(&mut bytes, *id);
+            append_ciphertexts(&mut bytes, ciphertexts);
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CoconutError> {
+        let mut reader = ByteReader::new(bytes);
+        let commitment = reader.read_group_elem::<SignatureGroup>(SIGNATURE_GROUP_SIZE)?;
+        let ciphertexts = read_ciphertexts(&mut reader)?;
+        let known_count = reader.read_count(FIELD_ELEMENT_SIZE)?;
+        let mut known_messages = FieldElementVector::with_capacity(known_count);
+        for _ in 0..known_count {
+            known_messages.push(reader.read_field_element()?);
+        }
+        // Signer id and count of its ciphertexts
+        let signer_count = reader.read_count(8)?;
+        let mut signer_ciphertexts = BTreeMap::new();
+        for _ in 0..signer_count {
+            let id = reader.read_u32()?;
+            signer_ciphertexts.insert(id, read_ciphertexts(&mut reader)?);
+        }
+        reader.finish()?;
+        Ok(Self {
+            known_messages,
+            commitment,
+            ciphertexts,
+            signer_ciphertexts,
+        })
+    }
+}
+
+fn append_schnorr_proof(bytes: &mut Vec<u8>, proof: &ProofSignatureGroup) {
+    bytes.append(&mut proof.commitment.to_bytes());
+    append_u32(bytes, proof.responses.len());
+    for r in proof.responses.iter() {
+        bytes.append(&mut r.to_bytes());
+    }
+}
+
+fn read_schnorr_proof(reader: &mut ByteReader) -> Result<ProofSignatureGroup, CoconutError> {
+    let commitment = reader.read_group_elem::<SignatureGroup>(SIGNATURE_GROUP_SIZE)?;
+    let count = reader.read_count(FIELD_ELEMENT_SIZE)?;
+    let mut responses = FieldElementVector::with_capacity(count);
+    for _ in 0..count {
+        responses.push(reader.read_field_element()?);
+    }
+    Ok(ProofSignatureGroup {
+        commitment,
+        responses,
+    })
+}
+
+fn append_ciphertexts(bytes: &mut Vec<u8>, ciphertexts: &[(SignatureGroup, SignatureGroup)]) {
+    append_u32(bytes, ciphertexts.len());
+    for (c1, c2) in ciphertexts {
+        bytes.append(&mut c1.to_bytes());
+        bytes.append(&mut c2.to_bytes());
+    }
+}
+
+fn read_ciphertexts(
+    reader: &mut ByteReader,
+) -> Result<Vec<(SignatureGroup, SignatureGroup)>, CoconutError> {
+    let count = reader.read_count(2 * SIGNATURE_GROUP_SIZE)?;
+    let mut ciphertexts = Vec::with_capacity(count);
+    for _ in 0..count {
+        let c1 = reader.read_group_elem::<SignatureGroup>(SIGNATURE_GROUP_SIZE)?;
+        let c2 = reader.read_group_elem::<SignatureGroup>(SIGNATURE_GROUP_SIZE)?;
+        ciphertexts.push((c1, c2));
+    }
+    Ok(ciphertexts)
+}
+
+/// Size in bytes of `SignatureRequest::to_bytes` for a request created with `SignatureRequest::new`
+/// over `msg_count` messages of which `hidden_count` are hidden. Errors if `hidden_count` exceeds
+/// `msg_count`.
+pub fn signature_request_size(
+    msg_count: usize,
+    hidden_count: usize,
+) -> Result<usize, CoconutError> {
+    let known_count = msg_count.checked_sub(hidden_count).ok_or_else(|| {
+        CoconutError::from(CoconutErrorKind::UnsupportedNoOfMessages {
+            expected: msg_count,
+            given: hidden_count,
+        })
+    })?;
+    Ok(SIGNATURE_GROUP_SIZE
+        + 4
+        + hidden_count * 2 * SIGNATURE_GROUP_SIZE
+        + 4
+        + known_count * FIELD_ELEMENT_SIZE
+        + 4)
+}
+
+/// `InvalidPoint` naming the point with `what` if `p` is the identity or outside the prime order
+/// subgroup
+fn check_structure_point<F: Fn() -> String>(
+    p: &SignatureGroup,
+    what: F,
+) -> Result<(), CoconutError> {
+    if p.is_identity() || !p.has_correct_order() {
+        return Err(CoconutErrorKind::InvalidPoint { msg: what() }.into());
+    }
+    Ok(())
+}
+
+/// Check that the Elgamal randomness `randomness[1..]` (as returned by `SignatureRequest::new`)
+/// has no repeated value. Encrypting 2 messages with the same randomness leaks the relation between
+/// the plaintexts since c2/c2' = (h^m)/(h^m').
+pub fn check_elgamal_randomness(randomness: &FieldElementVector) -> Result<(), CoconutError> {
+    for i in 1..randomness.len() {
+        for j in i + 1..randomness.len() {
+            if randomness[i] == randomness[j] {
+                return Err(CoconutErrorKind::RandomnessReuse {
+                    first: i,
+                    second: j,
+                }
+                .into());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Same as `check_elgamal_randomness` but the commitment randomness `randomness[0]` must not
+/// repeat an Elgamal randomness either
+fn check_all_randomness(randomness: &FieldElementVector) -> Result<(), CoconutError> {
+    check_elgamal_randomness(randomness)?;
+    for i in 1..randomness.len() {
+        if randomness[0] == randomness[i] {
+            return Err(CoconutErrorKind::RandomnessReuse {
+                first: 0,
+                second: i,
+            }
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// Builds a `SignatureRequest` from attributes added in order of their index. Since the hidden
+/// messages of a request are its first messages, all hidden attributes must be added before any
+/// public attribute.
+#[derive(Clone, Debug, Default)]
+pub struct SignatureRequestBuilder {
+    /// Attribute values in order of index and whether each is hidden
+    attributes: Vec<(FieldElement, bool)>,
+    elgamal_pubkey: Option<SignatureGroup>,
+}
+
+impl SignatureRequestBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Attribute hidden from the signers
+    pub fn hidden_attribute(mut self, value: FieldElement) -> Self {
+        self.attributes.push((value, true));
+        self
+    }
+
+    /// Attribute known to the signers
+    pub fn public_attribute(mut self, value: FieldElement) -> Self {
+        self.attributes.push((value, false));
+        self
+    }
+
+    /// Elgamal public key to encrypt the hidden attributes with
+    pub fn elgamal_pubkey(mut self, pk: &SignatureGroup) -> Self {
+        self.elgamal_pubkey = Some(pk.clone());
+        self
+    }
+
+    /// Same as `SignatureRequest::new` but errors rather than panicking on a mismatch between the
+    /// attributes and `params`. Errors also if a hidden attribute is added after a public
+    /// attribute or there are hidden attributes but no Elgamal public key.
+    pub fn build(
+        self,
+        params: &Params,
+    ) -> Result<(SignatureRequest, FieldElementVector), CoconutError> {
+        if self.attributes.len() != params.msg_count() {
+            return Err(CoconutErrorKind::UnsupportedNoOfMessages {
+                expected: params.msg_count(),
+                given: self.attributes.len(),
+            }
+            .into());
+        }
+        let count_hidden = self.attributes.iter().take_while(|(_, h)| *h).count();
+        if let Some(i) = self
+            .attributes
+            .iter()
+            .skip(count_hidden)
+            .position(|(_, h)| *h)
+        {
+            return Err(CoconutErrorKind::GeneralError {
+                msg: format!(
+                    "Hidden attribute at index {} after a public attribute",
+                    count_hidden + i
+                ),
+            }
+            .into());
+        }
+        let elgamal_pubkey = match self.elgamal_pubkey {
+            Some(pk) => pk,
+            None if count_hidden == 0 => SignatureGroup::identity(),
+            None => {
+                return Err(CoconutErrorKind::GeneralError {
+                    msg: String::from("Elgamal public key needed for hidden attributes"),
+                }
+                .into())
+            }
+        };
+        let messages: FieldElementVector = self
+            .attributes
+            .into_iter()
+            .map(|(m, _)| m)
+            .collect::<Vec<FieldElement>>()
+            .into();
+        SignatureRequest::new(&messages, count_hidden, &elgamal_pubkey, params)
+    }
+}
+
+impl SignatureRequestPoK {
     // Proof of knowledge using Schnorr protocol. There are multiple proof of knowledge protocols being done.
     // 1 for knowledge of Elgamal secret key, 1 for knowledge of hidden messages and randomness in the
     // commitment and 2 for each ciphertext. The protocol is broken down in 2 steps, pre-challenge and post challenge
@@ -218,28 +1314,146 @@ impl SignatureRequestPoK {
         elgamal_pk: &SignatureGroup,
         params: &Params,
     ) -> SignatureRequestPoK {
+        Self::init_with_rng(sig_req, elgamal_pk, params, &mut thread_rng())
+    }
+
+    /// Same as `init` but also returns the blindings of the hidden messages, to be handed to the
+    /// presentation of the issued credential, see `BlindingContext`
+    pub fn init_with_context(
+        sig_req: &SignatureRequest,
+        elgamal_pk: &SignatureGroup,
+        params: &Params,
+    ) -> (SignatureRequestPoK, BlindingContext) {
+        let blindings = FieldElementVector::random(sig_req.ciphertexts.len());
+        let pok =
+            Self::init_with_blindings(sig_req, elgamal_pk, params, blindings.as_slice()).unwrap();
+        let ctx = BlindingContext {
+            blindings: blindings.iter().cloned().enumerate().collect(),
+            request_commitments: pok.commitments(),
+        };
+        (pok, ctx)
+    }
+
+    /// Same as `init` but all blindings are sampled from `rng`
+    pub fn init_with_rng<R: RngCore + CryptoRng>(
+        sig_req: &SignatureRequest,
+        elgamal_pk: &SignatureGroup,
+        params: &Params,
+        rng: &mut R,
+    ) -> SignatureRequestPoK {
+        let blindings = (0..sig_req.ciphertexts.len())
+            .map(|_| FieldElement::random_using_rng(rng))
+            .collect::<Vec<FieldElement>>();
+        Self::init_with_blindings_and_rng(sig_req, elgamal_pk, params, &blindings, rng).unwrap()
+    }
+
+    /// Same as `init` but the blindings for the hidden messages are given by the caller, 1 for each
+    /// hidden message in order. Using the same blinding for a hidden message in another proof of
+    /// knowledge (like of an external Pedersen commitment or of the issued signature when showing
+    /// it) under the same challenge proves that both proofs are about the same message since their
+    /// responses will be equal. The blindings must not be used again with another challenge, as
+    /// responses to 2 challenges with the same blinding reveal the message.
+    pub fn init_with_blindings(
+        sig_req: &SignatureRequest,
+        elgamal_pk: &SignatureGroup,
+        params: &Params,
+        blindings: &[FieldElement],
+    ) -> Result<SignatureRequestPoK, CoconutError> {
+        Self::init_with_blindings_and_rng(sig_req, elgamal_pk, params, blindings, &mut thread_rng())
+    }
+
+    /// Proof of knowledge for a request created with `new_multi_issuer`, 1 for each signer's
+    /// request (see `SignatureRequest::for_signer`) by signer id. All use the same blindings for
+    /// the hidden messages so that under the same challenge, the responses for the hidden messages
+    /// are equal which shows that every signer's ciphertexts are of the same messages. The
+    /// challenge should be over all of them, like with `ChallengeBuilder`. Errors if a signer of
+    /// the request has no key in `elgamal_pubkeys` or a key is of a signer not in the request.
+    pub fn init_multi_issuer(
+        sig_req: &SignatureRequest,
+        elgamal_pubkeys: &[(usize, SignatureGroup)],
+        params: &Params,
+    ) -> Result<BTreeMap<usize, SignatureRequestPoK>, CoconutError> {
+        check_signer_keys(sig_req, elgamal_pubkeys.iter().map(|(id, _)| *id))?;
+        let count_hidden = hidden_message_count(sig_req, params)?;
+        let blindings = FieldElementVector::random(count_hidden);
+        let mut poks = BTreeMap::new();
+        for (id, pk) in elgamal_pubkeys {
+            let pok = Self::init_with_blindings(
+                &sig_req.for_signer(*id)?,
+                pk,
+                params,
+                blindings.as_slice(),
+            )?;
+            poks.insert(*id, pok);
+        }
+        Ok(poks)
+    }
+
+    /// Proofs for the proofs of knowledge created by `init_multi_issuer` with the randomness
+    /// returned by `SignatureRequest::new_multi_issuer` and the Elgamal secret keys given as
+    /// (signer id, Elgamal secret key).
+    pub fn gen_proof_multi_issuer(
+        poks: BTreeMap<usize, SignatureRequestPoK>,
+        hidden_messages: &FieldElementVector,
+        mut randomness: BTreeMap<usize, FieldElementVector>,
+        elgamal_sks: &[(usize, FieldElement)],
+        challenge: &FieldElement,
+    ) -> Result<BTreeMap<usize, SignatureRequestProof>, CoconutError> {
+        let mut proofs = BTreeMap::new();
+        for (id, pok) in poks {
+            let signer_randomness = randomness.remove(&id);
+            let elgamal_sk = elgamal_sks.iter().find(|(i, _)| *i == id);
+            match (signer_randomness, elgamal_sk) {
+                (Some(rand), Some((_, sk))) => {
+                    proofs.insert(id, pok.gen_proof(hidden_messages, rand, sk, challenge)?);
+                }
+                _ => {
+                    return Err(CoconutErrorKind::GeneralError {
+                        msg: format!("No randomness or Elgamal secret key for signer {}", id),
+                    }
+                    .into())
+                }
+            }
+        }
+        Ok(proofs)
+    }
+
+    /// Blindings other than those for the hidden messages are sampled from `rng`
+    fn init_with_blindings_and_rng<R: RngCore + CryptoRng>(
+        sig_req: &SignatureRequest,
+        elgamal_pk: &SignatureGroup,
+        params: &Params,
+        blindings: &[FieldElement],
+        rng: &mut R,
+    ) -> Result<SignatureRequestPoK, CoconutError> {
         assert_eq!(
             sig_req.known_messages.len() + sig_req.ciphertexts.len(),
             params.h.len()
         );
+        if blindings.len() != sig_req.ciphertexts.len() {
+            return Err(CoconutErrorKind::GeneralError {
+                msg: format!(
+                    "Need {} blindings for hidden messages but given {}",
+                    sig_req.ciphertexts.len(),
+                    blindings.len()
+                ),
+            }
+            .into());
+        }
 
         // For knowledge of Elgamal secret key
         let mut committing_elgamal_sk = ProverCommittingSignatureGroup::new();
-        committing_elgamal_sk.commit(&params.g, None);
+        committing_elgamal_sk.commit(&params.g, Some(&FieldElement::random_using_rng(rng)));
         let committed_elgamal_sk = committing_elgamal_sk.finish();
 
         // For knowledge of hidden messages and randomness in the commitment
-        let mut committing_comm = ProverCommittingSignatureGroup::new();
         // Since the hidden messages are same inside this commitment and ciphertexts, same blinding needs to be used.
-        let mut hidden_msg_blindings = vec![];
-        for h in params.h.iter().take(sig_req.ciphertexts.len()) {
-            let b = FieldElement::random();
-            committing_comm.commit(h, Some(&b));
-            hidden_msg_blindings.push(b);
-        }
-        // For randomness
-        committing_comm.commit(&params.g, None);
-        let committed_comm = committing_comm.finish();
+        let hidden_msg_blindings = blindings;
+        let committed_comm = commit_opening(
+            &sig_req.commitment_bases(params),
+            hidden_msg_blindings,
+            &FieldElement::random_using_rng(rng),
+        );
 
         let ciphertext_commts = if sig_req.ciphertexts.len() > 0 {
             // XXX: This computation can be avoided if h is persisted from `new`
@@ -248,10 +1462,10 @@ impl SignatureRequestPoK {
             let mut ciphertext_commts = vec![];
             for i in 0..sig_req.ciphertexts.len() {
                 let mut committing_1 = ProverCommittingSignatureGroup::new();
-                committing_1.commit(&params.g, None);
+                committing_1.commit(&params.g, Some(&FieldElement::random_using_rng(rng)));
 
                 let mut committing_2 = ProverCommittingSignatureGroup::new();
-                committing_2.commit(elgamal_pk, None);
+                committing_2.commit(elgamal_pk, Some(&FieldElement::random_using_rng(rng)));
                 // Use the same blinding for the hidden message used in the commitment
                 committing_2.commit(&h, Some(&hidden_msg_blindings[i]));
                 ciphertext_commts.push((committing_1.finish(), committing_2.finish()));
@@ -261,11 +1475,11 @@ impl SignatureRequestPoK {
             vec![]
         };
 
-        SignatureRequestPoK {
+        Ok(SignatureRequestPoK {
             pok_vc_elgamal_sk: committed_elgamal_sk,
             pok_vc_commitment: committed_comm,
             pok_vc_ciphertext: ciphertext_commts,
-        }
+        })
     }
 
     pub fn to_bytes(&self) -> Vec<u8> {
@@ -279,6 +1493,20 @@ impl SignatureRequestPoK {
         bytes
     }
 
+    /// Schnorr commitments in the order Elgamal secret key, commitment, then c1 and c2 of each
+    /// ciphertext. These are the commitments hashed by `challenge_v1`.
+    pub fn commitments(&self) -> Vec<SignatureGroup> {
+        let mut commitments = vec![
+            self.pok_vc_elgamal_sk.schnorr_commitment(),
+            self.pok_vc_commitment.schnorr_commitment(),
+        ];
+        for (pok_vc_1, pok_vc_2) in &self.pok_vc_ciphertext {
+            commitments.push(pok_vc_1.schnorr_commitment());
+            commitments.push(pok_vc_2.schnorr_commitment());
+        }
+        commitments
+    }
+
     pub fn gen_proof(
         self,
         hidden_messages: &FieldElementVector,
@@ -288,6 +1516,10 @@ impl SignatureRequestPoK {
     ) -> Result<SignatureRequestProof, CoconutError> {
         assert_eq!(self.pok_vc_ciphertext.len(), hidden_messages.len());
         assert_eq!(self.pok_vc_ciphertext.len(), randomness.len() - 1);
+        #[cfg(feature = "strict")]
+        check_elgamal_randomness(&randomness)?;
+        #[cfg(not(feature = "strict"))]
+        debug_assert!(check_elgamal_randomness(&randomness).is_ok());
 
         // Proof of knowledge of Elgamal secret key.
         let proof_elgamal_sk = self
@@ -303,8 +1535,37 @@ impl SignatureRequestPoK {
             .pok_vc_commitment
             .gen_proof(challenge, &secrets_commitment)?;
 
+        #[cfg(feature = "parallel")]
+        let proof_ciphertexts = Self::gen_ciphertext_proofs_parallel(
+            self.pok_vc_ciphertext,
+            hidden_messages,
+            &randomness,
+            challenge,
+        )?;
+        #[cfg(not(feature = "parallel"))]
+        let proof_ciphertexts = Self::gen_ciphertext_proofs(
+            self.pok_vc_ciphertext,
+            hidden_messages,
+            &randomness,
+            challenge,
+        )?;
+        Ok(SignatureRequestProof {
+            proof_elgamal_sk,
+            proof_commitment,
+            proof_ciphertexts,
+        })
+    }
+
+    /// Proofs of each ciphertext in order. Only used by tests with the `parallel` feature.
+    #[cfg_attr(feature = "parallel", allow(dead_code))]
+    fn gen_ciphertext_proofs(
+        pok_vc_ciphertext: Vec<(ProverCommittedSignatureGroup, ProverCommittedSignatureGroup)>,
+        hidden_messages: &FieldElementVector,
+        randomness: &FieldElementVector,
+        challenge: &FieldElement,
+    ) -> Result<Vec<(ProofSignatureGroup, ProofSignatureGroup)>, CoconutError> {
         let mut proof_ciphertexts = vec![];
-        for (i, (pok_vc_1, pok_vc_2)) in self.pok_vc_ciphertext.into_iter().enumerate() {
+        for (i, (pok_vc_1, pok_vc_2)) in pok_vc_ciphertext.into_iter().enumerate() {
             let proof_1 = pok_vc_1.gen_proof(challenge, &[randomness[i + 1].clone()])?;
             let proof_2 = pok_vc_2.gen_proof(
                 challenge,
@@ -312,15 +1573,189 @@ impl SignatureRequestPoK {
             )?;
             proof_ciphertexts.push((proof_1, proof_2));
         }
-        Ok(SignatureRequestProof {
-            proof_elgamal_sk,
-            proof_commitment,
-            proof_ciphertexts,
-        })
+        Ok(proof_ciphertexts)
+    }
+
+    /// Same as `gen_ciphertext_proofs` but each ciphertext's proofs are generated on the rayon
+    /// thread pool. The proofs are in the same order as the ciphertexts.
+    #[cfg(feature = "parallel")]
+    fn gen_ciphertext_proofs_parallel(
+        pok_vc_ciphertext: Vec<(ProverCommittedSignatureGroup, ProverCommittedSignatureGroup)>,
+        hidden_messages: &FieldElementVector,
+        randomness: &FieldElementVector,
+        challenge: &FieldElement,
+    ) -> Result<Vec<(ProofSignatureGroup, ProofSignatureGroup)>, CoconutError> {
+        pok_vc_ciphertext
+            .into_par_iter()
+            .enumerate()
+            .map(|(i, (pok_vc_1, pok_vc_2))| {
+                let proof_1 = pok_vc_1.gen_proof(challenge, &[randomness[i + 1].clone()])?;
+                let proof_2 = pok_vc_2.gen_proof(
+                    challenge,
+                    &[randomness[i + 1].clone(), hidden_messages[i].clone()],
+                )?;
+                Ok((proof_1, proof_2))
+            })
+            .collect()
+    }
+}
+
+/// Schnorr commitment of the commitment phase of a Schnorr protocol, as hashed in challenges. The
+/// committed states of this crate and of the PS sig crate don't expose it but serialize it last,
+/// after the bases.
+pub trait SchnorrCommitment {
+    type Element: GroupElement;
+
+    fn schnorr_commitment(&self) -> Self::Element;
+}
+
+impl SchnorrCommitment for ProverCommittedSignatureGroup {
+    type Element = SignatureGroup;
+
+    fn schnorr_commitment(&self) -> SignatureGroup {
+        let bytes = self.to_bytes();
+        // Just serialized so always decodes
+        SignatureGroup::from_bytes(&bytes[bytes.len() - SIGNATURE_GROUP_SIZE..]).unwrap()
+    }
+}
+
+impl SchnorrCommitment for PSProverCommittedOtherGroup {
+    type Element = OtherGroup;
+
+    fn schnorr_commitment(&self) -> OtherGroup {
+        let bytes = self.to_bytes();
+        // Just serialized so always decodes
+        OtherGroup::from_bytes(&bytes[bytes.len() - OTHER_GROUP_SIZE..]).unwrap()
+    }
+}
+
+/// Size in bytes of `Verkey::fingerprint`
+pub const VERKEY_FINGERPRINT_SIZE: usize = 32;
+
+/// Domain separation tag prefixed to the verkey bytes in `Verkey::fingerprint`
+pub const VERKEY_FINGERPRINT_TAG: &[u8] = b"coconut/verkey/fingerprint";
+
+/// Domain separation tag prefixed to the transcript in `challenge_v1`
+pub const CHALLENGE_V1_TAG: &[u8] = b"coconut/signature-request/challenge/v1";
+
+/// Fiat-Shamir challenge for the proof of knowledge of a signature request, computed over a fixed
+/// and documented byte layout so that independent implementations derive the same challenge.
+/// All counts are 4 byte big-endian integers and all elements are in their `to_bytes` form.
+/// The transcript is the concatenation of:
+/// 1. `CHALLENGE_V1_TAG`
+/// 2. `params.g`, `params.g_tilde`, `params.credential_type`, count of `params.h`, each element
+///    of `params.h`
+/// 3. `elgamal_pk`
+/// 4. `sig_req.commitment`, count of ciphertexts, each ciphertext as `c1 || c2`
+/// 5. count of known messages, each known message
+/// 6. count of Schnorr commitments, each commitment
+/// The commitments are those of `SignatureRequestPoK::commitments` on the user's side and
+/// `SignatureRequestProof::commitments` on the signer's side so that the signer can recompute the
+/// challenge from the proof alone.
+/// The challenge is `FieldElement::from_msg_hash(transcript)`.
+pub fn challenge_v1(
+    params: &Params,
+    elgamal_pk: &SignatureGroup,
+    sig_req: &SignatureRequest,
+    commitments: &[SignatureGroup],
+) -> FieldElement {
+    challenge_v1_with_hasher(
+        params,
+        elgamal_pk,
+        sig_req,
+        commitments,
+        &DefaultChallengeHasher,
+    )
+}
+
+/// Same as `challenge_v1` but the transcript is hashed with `hasher`
+pub fn challenge_v1_with_hasher(
+    params: &Params,
+    elgamal_pk: &SignatureGroup,
+    sig_req: &SignatureRequest,
+    commitments: &[SignatureGroup],
+    hasher: &dyn ChallengeHasher,
+) -> FieldElement {
+    hasher.hash_to_field(&challenge_v1_transcript(
+        params,
+        elgamal_pk,
+        sig_req,
+        commitments,
+    ))
+}
+
+/// The bytes hashed by `challenge_v1`
+pub fn challenge_v1_transcript(
+    params: &Params,
+    elgamal_pk: &SignatureGroup,
+    sig_req: &SignatureRequest,
+    commitments: &[SignatureGroup],
+) -> Vec<u8> {
+    let mut bytes = CHALLENGE_V1_TAG.to_vec();
+    bytes.append(&mut params.g.to_bytes());
+    bytes.append(&mut params.g_tilde.to_bytes());
+    bytes.append(&mut params.credential_type.to_bytes());
+    bytes.extend_from_slice(&(params.h.len() as u32).to_be_bytes());
+    for h in params.h.iter() {
+        bytes.append(&mut h.to_bytes());
+    }
+    bytes.append(&mut elgamal_pk.to_bytes());
+    bytes.append(&mut sig_req.commitment.to_bytes());
+    bytes.extend_from_slice(&(sig_req.ciphertexts.len() as u32).to_be_bytes());
+    for (c1, c2) in &sig_req.ciphertexts {
+        bytes.append(&mut c1.to_bytes());
+        bytes.append(&mut c2.to_bytes());
+    }
+    bytes.extend_from_slice(&(sig_req.known_messages.len() as u32).to_be_bytes());
+    for m in sig_req.known_messages.iter() {
+        bytes.append(&mut m.to_bytes());
+    }
+    bytes.extend_from_slice(&(commitments.len() as u32).to_be_bytes());
+    for t in commitments {
+        bytes.append(&mut t.to_bytes());
+    }
+    bytes
+}
+
+/// Collects the commitments of several proofs of knowledge, like a `SignatureRequestPoK` and proofs
+/// about the hidden messages in other protocols, into 1 challenge used by all of them. Each item
+/// is prefixed with its length as a 4 byte big-endian integer so that different splits of the
+/// same bytes give different challenges.
+#[derive(Clone, Debug, Default)]
+pub struct ChallengeBuilder {
+    transcript: Vec<u8>,
+}
+
+impl ChallengeBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn signature_request_pok(self, pok: &SignatureRequestPoK) -> Self {
+        self.bytes(&pok.to_bytes())
+    }
+
+    /// Bytes of another proof's commitments or of any context to bind the proofs to
+    pub fn bytes(mut self, bytes: &[u8]) -> Self {
+        append_u32(&mut self.transcript, bytes.len());
+        self.transcript.extend_from_slice(bytes);
+        self
+    }
+
+    pub fn finish(self) -> FieldElement {
+        self.finish_with_hasher(&DefaultChallengeHasher)
+    }
+
+    /// Same as `finish` but the transcript is hashed with `hasher`
+    pub fn finish_with_hasher(self, hasher: &dyn ChallengeHasher) -> FieldElement {
+        hasher.hash_to_field(&self.transcript)
     }
 }
 
 impl SignatureRequestProof {
+    /// A verifier that does not derive the challenge itself should verify through
+    /// `ChallengeCache::check_and_verify` to reject a replayed proof. The proofs of all ciphertexts
+    /// are checked together with 2 multi-scalar multiplications.
     pub fn verify(
         &self,
         sig_req: &SignatureRequest,
@@ -328,31 +1763,25 @@ impl SignatureRequestProof {
         challenge: &FieldElement,
         params: &Params,
     ) -> Result<bool, CoconutError> {
-        assert_eq!(self.proof_ciphertexts.len(), sig_req.ciphertexts.len());
-        assert_eq!(
-            self.proof_commitment.responses.len(),
-            self.proof_ciphertexts.len() + 1
-        );
-
-        // Verify proof of knowledge of Elgamal secret key
-        if !self
-            .proof_elgamal_sk
-            .verify(&[params.g.clone()], elgamal_pk, challenge)?
+        if !self.verify_sk_and_commitment(sig_req, elgamal_pk, challenge, params)?
+            || !self.ciphertext_responses_consistent()
         {
             return Ok(false);
         }
+        self.verify_ciphertexts_batched(sig_req, elgamal_pk, challenge, params)
+    }
 
-        // Verify proof of knowledge of hidden messages in the commitment
-        let mut bases = params
-            .h
-            .iter()
-            .take(sig_req.ciphertexts.len())
-            .map(|h| h.clone())
-            .collect::<Vec<SignatureGroup>>();
-        bases.push(params.g.clone());
-        if !self
-            .proof_commitment
-            .verify(&bases, &sig_req.commitment, challenge)?
+    /// Same as `verify` but the proof of each ciphertext is checked on its own, which costs 2
+    /// multi-scalar multiplications for each ciphertext rather than 2 in all
+    pub fn verify_unbatched(
+        &self,
+        sig_req: &SignatureRequest,
+        elgamal_pk: &SignatureGroup,
+        challenge: &FieldElement,
+        params: &Params,
+    ) -> Result<bool, CoconutError> {
+        if !self.verify_sk_and_commitment(sig_req, elgamal_pk, challenge, params)?
+            || !self.ciphertext_responses_consistent()
         {
             return Ok(false);
         }
@@ -361,11 +1790,6 @@ impl SignatureRequestProof {
         let h = SignatureRequest::compute_h(&sig_req.commitment, sig_req.known_messages.as_slice());
         let bases = vec![elgamal_pk.clone(), h];
         for (i, (proof_1, proof_2)) in self.proof_ciphertexts.iter().enumerate() {
-            // The response for the hidden message should be same as that in the commitment.
-            if proof_2.responses[1] != self.proof_commitment.responses[i] {
-                return Ok(false);
-            }
-
             if !proof_1.verify(&[params.g.clone()], &sig_req.ciphertexts[i].0, challenge)? {
                 return Ok(false);
             }
@@ -375,22 +1799,472 @@ impl SignatureRequestProof {
         }
         Ok(true)
     }
-}
-
-impl BlindSignature {
-    /// Signed creates a blinded signature. "BlindSign" from paper.
-    pub fn new(sig_request: &SignatureRequest, sigkey: &Sigkey) -> Self {
-        let hidden_msg_count = sig_request.ciphertexts.len();
 
-        assert_eq!(
-            hidden_msg_count + sig_request.known_messages.len(),
-            sigkey.y.len()
-        );
+    /// Whether each ciphertext proof has 1 response for c_1 and 2 for c_2 and the response for
+    /// the hidden message is the same as that in the proof of the commitment
+    fn ciphertext_responses_consistent(&self) -> bool {
+        self.proof_ciphertexts
+            .iter()
+            .enumerate()
+            .all(|(i, (proof_1, proof_2))| {
+                proof_1.responses.len() == 1
+                    && proof_2.responses.len() == 2
+                    && proof_2.responses[1] == self.proof_commitment.responses[i]
+            })
+    }
 
-        let h = SignatureRequest::compute_h(
-            &sig_request.commitment,
-            sig_request.known_messages.as_slice(),
-        );
+    /// Check the proofs of the Elgamal secret key and of the opening of the commitment
+    fn verify_sk_and_commitment(
+        &self,
+        sig_req: &SignatureRequest,
+        elgamal_pk: &SignatureGroup,
+        challenge: &FieldElement,
+        params: &Params,
+    ) -> Result<bool, CoconutError> {
+        // A proof for another count of hidden messages, like a truncated one, can't be valid
+        if self.proof_ciphertexts.len() != sig_req.ciphertexts.len()
+            || self.proof_commitment.responses.len() != self.proof_ciphertexts.len() + 1
+        {
+            return Ok(false);
+        }
+
+        // Verify proof of knowledge of Elgamal secret key
+        if !self
+            .proof_elgamal_sk
+            .verify(&[params.g.clone()], elgamal_pk, challenge)?
+        {
+            return Ok(false);
+        }
+
+        // Verify proof of knowledge of hidden messages in the commitment
+        if !Commitment::verify_opening(
+            &sig_req.commitment,
+            &sig_req.commitment_bases(params),
+            &self.proof_commitment,
+            challenge,
+        )? {
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    /// Check the proofs of all ciphertexts (c_1, c_2) = (g^k, pk^k.h^m) with 2 multi-scalar
+    /// multiplications. Each Schnorr check bases^responses.target^c == commitment is weighted by
+    /// a random r_i and the checks of all c_1 and of all c_2 are summed, so that g, pk and h, which
+    /// are shared by all ciphertexts, are multiplied once by the sum of their weighted responses.
+    /// Each sum is the identity for valid proofs and otherwise only with negligible probability.
+    /// The proofs must have 1 response for c_1 and 2 for c_2.
+    fn verify_ciphertexts_batched(
+        &self,
+        sig_req: &SignatureRequest,
+        elgamal_pk: &SignatureGroup,
+        challenge: &FieldElement,
+        params: &Params,
+    ) -> Result<bool, CoconutError> {
+        let n = self.proof_ciphertexts.len();
+        let mut bases_1 = SignatureGroupVec::with_capacity(2 * n + 1);
+        let mut exps_1 = FieldElementVector::with_capacity(2 * n + 1);
+        let mut bases_2 = SignatureGroupVec::with_capacity(2 * n + 2);
+        let mut exps_2 = FieldElementVector::with_capacity(2 * n + 2);
+        let mut g_exp = FieldElement::zero();
+        let mut pk_exp = FieldElement::zero();
+        let mut h_exp = FieldElement::zero();
+        for ((proof_1, proof_2), (c_1, c_2)) in self
+            .proof_ciphertexts
+            .iter()
+            .zip(sig_req.ciphertexts.iter())
+        {
+            let r = FieldElement::random();
+            g_exp += &proof_1.responses[0] * &r;
+            bases_1.push(c_1.clone());
+            exps_1.push(challenge * &r);
+            bases_1.push(proof_1.commitment.clone());
+            exps_1.push(r.negation());
+
+            pk_exp += &proof_2.responses[0] * &r;
+            h_exp += &proof_2.responses[1] * &r;
+            bases_2.push(c_2.clone());
+            exps_2.push(challenge * &r);
+            bases_2.push(proof_2.commitment.clone());
+            exps_2.push(r.negation());
+        }
+        bases_1.push(params.g.clone());
+        exps_1.push(g_exp);
+        // XXX: This computation can be avoided if h is persisted`
+        bases_2.push(elgamal_pk.clone());
+        exps_2.push(pk_exp);
+        bases_2.push(SignatureRequest::compute_h(
+            &sig_req.commitment,
+            sig_req.known_messages.as_slice(),
+        ));
+        exps_2.push(h_exp);
+        Ok(bases_1.multi_scalar_mul_var_time(&exps_1)?.is_identity()
+            && bases_2.multi_scalar_mul_var_time(&exps_2)?.is_identity())
+    }
+
+    /// Verify the proofs of many requests, each item being (request, proof, challenge, Elgamal
+    /// public key). Every Schnorr check b_1^s_1...b_n^s_n.Y^c == T of every proof is weighted by
+    /// a random r and all are combined into a single multi-scalar multiplication that must be the
+    /// identity, so the batch fails if any proof is invalid. Use `verify_batch_each` to find which
+    /// one.
+    pub fn verify_batch(
+        items: &[(
+            &SignatureRequest,
+            &SignatureRequestProof,
+            &FieldElement,
+            &SignatureGroup,
+        )],
+        params: &Params,
+    ) -> bool {
+        if items.is_empty() {
+            return false;
+        }
+        let mut bases = SignatureGroupVec::with_capacity(items.len());
+        let mut exps = FieldElementVector::with_capacity(items.len());
+        for (sig_req, proof, challenge, elgamal_pk) in items {
+            if proof.proof_ciphertexts.len() != sig_req.ciphertexts.len() {
+                return false;
+            }
+            let mut checked = push_weighted_check(
+                &mut bases,
+                &mut exps,
+                &[params.g.clone()],
+                elgamal_pk,
+                &proof.proof_elgamal_sk,
+                challenge,
+            ) && push_weighted_check(
+                &mut bases,
+                &mut exps,
+                &sig_req.commitment_bases(params),
+                &sig_req.commitment,
+                &proof.proof_commitment,
+                challenge,
+            );
+            let h =
+                SignatureRequest::compute_h(&sig_req.commitment, sig_req.known_messages.as_slice());
+            let ciphertext_bases = [(*elgamal_pk).clone(), h];
+            for (i, (proof_1, proof_2)) in proof.proof_ciphertexts.iter().enumerate() {
+                checked = checked
+                    && push_weighted_check(
+                        &mut bases,
+                        &mut exps,
+                        &[params.g.clone()],
+                        &sig_req.ciphertexts[i].0,
+                        proof_1,
+                        challenge,
+                    )
+                    && push_weighted_check(
+                        &mut bases,
+                        &mut exps,
+                        &ciphertext_bases,
+                        &sig_req.ciphertexts[i].1,
+                        proof_2,
+                        challenge,
+                    )
+                    // The response for the hidden message should be same as that in the commitment
+                    && proof_2.responses[1] == proof.proof_commitment.responses[i];
+            }
+            if !checked {
+                return false;
+            }
+        }
+        match bases.multi_scalar_mul_var_time(&exps) {
+            Ok(p) => p.is_identity(),
+            Err(_) => false,
+        }
+    }
+
+    /// Verify many proofs, each given as (proof, request, challenge) with the Elgamal public key
+    /// at the same index of `elgamal_pks`, returning whether each proof verified. All proofs are
+    /// first checked together with `verify_batch`. If that fails, the proofs are split in halves
+    /// which are checked again, so a few invalid proofs among many cost a few more batches rather
+    /// than verifying every proof on its own. All false if the number of keys is not the number
+    /// of proofs.
+    pub fn verify_batch_each(
+        proofs: &[(SignatureRequestProof, SignatureRequest, FieldElement)],
+        elgamal_pks: &[SignatureGroup],
+        params: &Params,
+    ) -> Vec<bool> {
+        let mut results = vec![false; proofs.len()];
+        if elgamal_pks.len() != proofs.len() {
+            return results;
+        }
+        let items = proofs
+            .iter()
+            .zip(elgamal_pks.iter())
+            .map(|((proof, sig_req, challenge), pk)| (sig_req, proof, challenge, pk))
+            .collect::<Vec<_>>();
+        Self::verify_bisecting(&items, params, &mut results);
+        results
+    }
+
+    /// Set `results` to true for the proofs of `items` that verify, splitting `items` in halves
+    /// whenever the batch of all fails
+    fn verify_bisecting(
+        items: &[(
+            &SignatureRequest,
+            &SignatureRequestProof,
+            &FieldElement,
+            &SignatureGroup,
+        )],
+        params: &Params,
+        results: &mut [bool],
+    ) {
+        if items.is_empty() {
+            return;
+        }
+        if Self::verify_batch(items, params) {
+            for r in results.iter_mut() {
+                *r = true;
+            }
+            return;
+        }
+        if items.len() == 1 {
+            return;
+        }
+        let mid = items.len() / 2;
+        let (results_1, results_2) = results.split_at_mut(mid);
+        Self::verify_bisecting(&items[..mid], params, results_1);
+        Self::verify_bisecting(&items[mid..], params, results_2);
+    }
+}
+
+/// Add the Schnorr check `bases`^responses.`target`^c == commitment of `proof`, weighted by a
+/// random r, to the bases and exponents of a batch check. False if the responses are not 1 for
+/// each base.
+fn push_weighted_check(
+    batch_bases: &mut SignatureGroupVec,
+    batch_exps: &mut FieldElementVector,
+    bases: &[SignatureGroup],
+    target: &SignatureGroup,
+    proof: &ProofSignatureGroup,
+    challenge: &FieldElement,
+) -> bool {
+    if proof.responses.len() != bases.len() {
+        return false;
+    }
+    let r = FieldElement::random();
+    for (b, s) in bases.iter().zip(proof.responses.iter()) {
+        batch_bases.push(b.clone());
+        batch_exps.push(s * &r);
+    }
+    batch_bases.push(target.clone());
+    batch_exps.push(challenge * &r);
+    batch_bases.push(proof.commitment.clone());
+    batch_exps.push(r.negation());
+    true
+}
+
+impl SignatureRequestProof {
+    /// Byte representation is proof of Elgamal secret key || proof of commitment || count of
+    /// ciphertexts || (proof of c1 || proof of c2) for each ciphertext, where each proof is
+    /// commitment || count of responses || responses. Counts are 4 byte big-endian integers.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        append_schnorr_proof(&mut bytes, &self.proof_elgamal_sk);
+        append_schnorr_proof(&mut bytes, &self.proof_commitment);
+        append_u32(&mut bytes, self.proof_ciphertexts.len());
+        for (proof_1, proof_2) in &self.proof_ciphertexts {
+            append_schnorr_proof(&mut bytes, proof_1);
+            append_schnorr_proof(&mut bytes, proof_2);
+        }
+        bytes
+    }
+
+    /// Schnorr commitments in the same order as `SignatureRequestPoK::commitments`, so a signer
+    /// can recompute `challenge_v1` without the user's `SignatureRequestPoK`.
+    pub fn commitments(&self) -> Vec<SignatureGroup> {
+        let mut commitments = vec![
+            self.proof_elgamal_sk.commitment.clone(),
+            self.proof_commitment.commitment.clone(),
+        ];
+        for (proof_1, proof_2) in &self.proof_ciphertexts {
+            commitments.push(proof_1.commitment.clone());
+            commitments.push(proof_2.commitment.clone());
+        }
+        commitments
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CoconutError> {
+        let mut reader = ByteReader::new(bytes);
+        let proof_elgamal_sk = read_schnorr_proof(&mut reader)?;
+        let proof_commitment = read_schnorr_proof(&mut reader)?;
+        // Each proof is at least a commitment and a count of responses
+        let count = reader.read_count(2 * (SIGNATURE_GROUP_SIZE + 4))?;
+        let mut proof_ciphertexts = Vec::with_capacity(count);
+        for _ in 0..count {
+            let proof_1 = read_schnorr_proof(&mut reader)?;
+            let proof_2 = read_schnorr_proof(&mut reader)?;
+            proof_ciphertexts.push((proof_1, proof_2));
+        }
+        reader.finish()?;
+        Ok(Self {
+            proof_elgamal_sk,
+            proof_commitment,
+            proof_ciphertexts,
+        })
+    }
+
+    /// Verify the proofs created by `SignatureRequestPoK::gen_proof_multi_issuer` for a request
+    /// created with `SignatureRequest::new_multi_issuer`. Each signer's proof is verified against
+    /// its request and Elgamal public key and the responses for the hidden messages must be the
+    /// same in all of them. Errors if the signers of the proofs or keys are not those of the
+    /// request.
+    pub fn verify_multi_issuer(
+        proofs: &BTreeMap<usize, SignatureRequestProof>,
+        sig_req: &SignatureRequest,
+        elgamal_pubkeys: &[(usize, SignatureGroup)],
+        challenge: &FieldElement,
+        params: &Params,
+    ) -> Result<bool, CoconutError> {
+        check_signer_keys(sig_req, elgamal_pubkeys.iter().map(|(id, _)| *id))?;
+        check_signer_keys(sig_req, proofs.keys().cloned())?;
+        let count_hidden = hidden_message_count(sig_req, params)?;
+        let mut hidden_responses: Option<&[FieldElement]> = None;
+        for (id, pk) in elgamal_pubkeys {
+            let proof = &proofs[id];
+            if proof.proof_commitment.responses.len() != count_hidden + 1 {
+                return Ok(false);
+            }
+            let responses = &proof.proof_commitment.responses.as_slice()[..count_hidden];
+            match hidden_responses {
+                Some(r) if r != responses => return Ok(false),
+                Some(_) => (),
+                None => hidden_responses = Some(responses),
+            }
+            if !proof.verify(&sig_req.for_signer(*id)?, pk, challenge, params)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// Number of hidden messages of `sig_req` given the messages of `params`. Errors with
+/// `UnsupportedNoOfMessages` if the request has more known messages than `params`.
+fn hidden_message_count(
+    sig_req: &SignatureRequest,
+    params: &Params,
+) -> Result<usize, CoconutError> {
+    params
+        .h
+        .len()
+        .checked_sub(sig_req.known_messages.len())
+        .ok_or_else(|| {
+            CoconutError::from(CoconutErrorKind::UnsupportedNoOfMessages {
+                expected: params.h.len(),
+                given: sig_req.known_messages.len(),
+            })
+        })
+}
+
+/// Check that `signer_ids` are exactly the signers of a request created with
+/// `SignatureRequest::new_multi_issuer`, each given once
+fn check_signer_keys<I: Iterator<Item = usize>>(
+    sig_req: &SignatureRequest,
+    signer_ids: I,
+) -> Result<(), CoconutError> {
+    let mut seen = HashSet::new();
+    for id in signer_ids {
+        if !seen.insert(id) {
+            return Err(CoconutErrorKind::DuplicateSignerId { id }.into());
+        }
+        if !sig_req.signer_ciphertexts.contains_key(&id) {
+            return Err(CoconutErrorKind::GeneralError {
+                msg: format!("No ciphertexts for signer {}", id),
+            }
+            .into());
+        }
+    }
+    if seen.len() != sig_req.signer_ciphertexts.len() {
+        return Err(CoconutErrorKind::GeneralError {
+            msg: format!(
+                "Request is for {} signers but given {}",
+                sig_req.signer_ciphertexts.len(),
+                seen.len()
+            ),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+impl BlindSignature {
+    /// Signed creates a blinded signature. "BlindSign" from paper. Errors if the commitment or
+    /// any ciphertext of the request is not a valid point.
+    pub fn new(sig_request: &SignatureRequest, sigkey: &Sigkey) -> Result<Self, CoconutError> {
+        Self::new_with_type_tag(sig_request, sigkey, &FieldElement::zero(), false)
+    }
+
+    /// Signer creates a blinded signature on a credential of the type of `params`. Signers issuing
+    /// typed credentials (`Params::new_typed`) must use this rather than `new`.
+    pub fn new_typed(
+        sig_request: &SignatureRequest,
+        sigkey: &Sigkey,
+        params: &Params,
+    ) -> Result<Self, CoconutError> {
+        Self::new_with_type_tag(sig_request, sigkey, &params.credential_type, false)
+    }
+
+    /// Same as `new_typed` but also includes the term a_i^y_i of each ciphertext (a_i, b_i) so
+    /// that the hidden messages can be encrypted under different Elgamal keys and unblinded with
+    /// `unblind_selective`. The terms are the 1st component of the blinded signature of `new` split
+    /// by ciphertext, and as the user knows the randomness k_i of a_i = g^k_i, a term gives it
+    /// g^y_i. This is no more than `new` gives: the user picks the k_i, so it can solve for each
+    /// g^y_i from the blinded signatures of `new` on 2 requests with different randomness. Like
+    /// Y_i in the signature group of PS signatures, g^y_i does not help in forging since x is
+    /// only used in h^x for h from hashing the request.
+    pub fn new_selective(
+        sig_request: &SignatureRequest,
+        sigkey: &Sigkey,
+        params: &Params,
+    ) -> Result<Self, CoconutError> {
+        Self::new_with_type_tag(sig_request, sigkey, &params.credential_type, true)
+    }
+
+    fn new_with_type_tag(
+        sig_request: &SignatureRequest,
+        sigkey: &Sigkey,
+        credential_type: &FieldElement,
+        with_terms: bool,
+    ) -> Result<Self, CoconutError> {
+        if !sig_request.signer_ciphertexts.is_empty() {
+            return Err(CoconutErrorKind::GeneralError {
+                msg: String::from(
+                    "Request has ciphertexts for several signers, sign its part for this signer \
+                     from SignatureRequest::for_signer",
+                ),
+            }
+            .into());
+        }
+        ensure_valid_point(&sig_request.commitment)?;
+        for (c1, c2) in &sig_request.ciphertexts {
+            ensure_valid_point(c1)?;
+            ensure_valid_point(c2)?;
+        }
+        let hidden_msg_count = sig_request.ciphertexts.len();
+
+        if hidden_msg_count + sig_request.known_messages.len() != sigkey.y.len() {
+            return Err(CoconutErrorKind::UnsupportedNoOfMessages {
+                expected: sigkey.y.len(),
+                given: hidden_msg_count + sig_request.known_messages.len(),
+            }
+            .into());
+        }
+
+        // For typed credentials the type attribute, the last message, must be known and be the tag
+        if !credential_type.is_zero() && sig_request.known_messages.last() != Some(credential_type)
+        {
+            return Err(CoconutErrorKind::TypeAttributeMismatch {
+                idx: sigkey.y.len() - 1,
+            }
+            .into());
+        }
+
+        let h = SignatureRequest::compute_h(
+            &sig_request.commitment,
+            sig_request.known_messages.as_slice(),
+        );
 
         // The blinded signature is (h, c_tilde).
         // c_tilde = (a_1^y_1.a_2^y_2...a_hidden_msg_count^y_hidden_msg_count, b_1^y_1.b_2^y_2....b_hidden_msg_count^y_hidden_msg_count . h^(x + y_{hidden_msg_count+1}*m_{hidden_msg_count+1} + y_{hidden_msg_count+2}*m_{hidden_msg_count+2} + .. y_n*m_n))
@@ -420,16 +2294,81 @@ impl BlindSignature {
         }
         c_tilde_2_exps.push(exp);
 
-        let c_tilde_1 = c_tilde_1_bases
-            .multi_scalar_mul_const_time(&c_tilde_1_exps)
-            .unwrap();
-        let c_tilde_2 = c_tilde_2_bases
-            .multi_scalar_mul_const_time(&c_tilde_2_exps)
-            .unwrap();
-        Self {
+        let c_tilde_1 = c_tilde_1_bases.multi_scalar_mul_const_time(&c_tilde_1_exps)?;
+        let c_tilde_2 = c_tilde_2_bases.multi_scalar_mul_const_time(&c_tilde_2_exps)?;
+        let blinded_terms = if with_terms {
+            sig_request
+                .ciphertexts
+                .iter()
+                .enumerate()
+                .map(|(i, (a, _))| a * &sigkey.y[i])
+                .collect::<Vec<SignatureGroup>>()
+        } else {
+            vec![]
+        };
+        Ok(Self {
             h,
             blinded: (c_tilde_1, c_tilde_2),
+            blinded_terms,
+        })
+    }
+
+    /// Check before unblinding that the blinded signature over `sig_req` was created with the
+    /// sigkey of `vk`, the verkey of the signer, rather than with another key. As
+    /// c_tilde_1 = a_1^y_1.a_2^y_2... and c_tilde_2 = b_1^y_1.b_2^y_2... h^(x + y_j*m_j + ...) for
+    /// ciphertexts (a_i, b_i) and known messages m_j, checks
+    /// e(c_tilde_1, g_tilde) == e(a_1, Y_tilde_1).e(a_2, Y_tilde_2)... and
+    /// e(c_tilde_2, g_tilde) == e(b_1, Y_tilde_1).e(b_2, Y_tilde_2)... e(h, K) where
+    /// K = X_tilde.Y_tilde_j^m_j... False if the type attribute of typed `params` is not known with
+    /// the tag as value, as checked by `new_typed`.
+    pub fn verify_against_verkey(
+        &self,
+        sig_req: &SignatureRequest,
+        vk: &Verkey,
+        params: &Params,
+    ) -> bool {
+        let hidden_msg_count = sig_req.ciphertexts.len();
+        if hidden_msg_count + sig_req.known_messages.len() != vk.Y_tilde.len() {
+            return false;
+        }
+        let h = SignatureRequest::compute_h(&sig_req.commitment, sig_req.known_messages.as_slice());
+        if self.h != h || self.h.is_identity() {
+            return false;
+        }
+
+        let mut pairs_1 = Vec::with_capacity(hidden_msg_count + 1);
+        let mut pairs_2 = Vec::with_capacity(hidden_msg_count + 2);
+        for ((a, b), y) in sig_req.ciphertexts.iter().zip(vk.Y_tilde.iter()) {
+            pairs_1.push((a.clone(), y.clone()));
+            pairs_2.push((b.clone(), y.clone()));
+        }
+        if !params.has_type_attribute(sig_req.known_messages.as_slice()) {
+            return false;
+        }
+        let messages = sig_req
+            .known_messages
+            .iter()
+            .enumerate()
+            .map(|(j, m)| (hidden_msg_count + j, m.clone()))
+            .collect::<Vec<(usize, FieldElement)>>();
+        let h_exp_term = match vk.partial_kappa(&messages, params) {
+            Ok(k) => k,
+            Err(_) => return false,
+        };
+        pairs_2.push((h, h_exp_term));
+        pairs_2.push((self.blinded.1.negation(), params.g_tilde.clone()));
+
+        if hidden_msg_count == 0 {
+            if !self.blinded.0.is_identity() {
+                return false;
+            }
+        } else {
+            pairs_1.push((self.blinded.0.negation(), params.g_tilde.clone()));
+            if !ate_multi_pairing(&pairs_1).is_one() {
+                return false;
+            }
         }
+        ate_multi_pairing(&pairs_2).is_one()
     }
 
     /// User unblinds the blinded signature received from a signer. "Unblind" from paper.
@@ -441,12 +2380,174 @@ impl BlindSignature {
             sigma_2,
         }
     }
+
+    /// Same as `unblind` but first checks that h (sigma_1) is the h of `sig_req`, computed from
+    /// its commitment and known messages, and errors with `HMismatch` otherwise. A signer giving
+    /// another h, like one whose discrete log it knows, would otherwise go unnoticed until the
+    /// signature fails to verify.
+    pub fn unblind_with_request(
+        self,
+        elgamal_sk: &FieldElement,
+        sig_req: &SignatureRequest,
+    ) -> Result<Signature, CoconutError> {
+        let h = SignatureRequest::compute_h(&sig_req.commitment, sig_req.known_messages.as_slice());
+        if self.h != h {
+            return Err(CoconutErrorKind::HMismatch.into());
+        }
+        Ok(self.unblind(elgamal_sk))
+    }
+
+    /// Same as `unblind` but with the secret key of `keypair`
+    pub fn unblind_with_keypair(self, keypair: &ElgamalKeypair) -> Signature {
+        self.unblind(&keypair.sk)
+    }
+
+    /// Unblind a signature created with `new_selective` when the hidden messages are encrypted
+    /// under different Elgamal keys. Each item of `keys` is (ciphertext index, Elgamal secret key)
+    /// and only the terms a_i^(y_i*sk_i) of those ciphertexts are subtracted. The signature is
+    /// valid once the terms of all ciphertexts are subtracted. Errors if an index is not of a
+    /// ciphertext or is given more than once.
+    pub fn unblind_selective(
+        self,
+        keys: &[(usize, FieldElement)],
+    ) -> Result<Signature, CoconutError> {
+        let mut seen = HashSet::new();
+        let mut sigma_2 = self.blinded.1;
+        for (idx, sk) in keys {
+            if *idx >= self.blinded_terms.len() {
+                return Err(CoconutErrorKind::InvalidAttributeIndex {
+                    idx: *idx,
+                    msg_count: self.blinded_terms.len(),
+                }
+                .into());
+            }
+            if !seen.insert(*idx) {
+                return Err(CoconutErrorKind::GeneralError {
+                    msg: format!("Ciphertext index {} given more than once", idx),
+                }
+                .into());
+            }
+            sigma_2 = &sigma_2 - &(&self.blinded_terms[*idx] * sk);
+        }
+        Ok(Signature {
+            sigma_1: self.h,
+            sigma_2,
+        })
+    }
+
+    /// Created by a holder of share sk_i of a threshold shared Elgamal secret key. The share
+    /// c_tilde_1^sk_i of decrypting the blinded signature.
+    pub fn decryption_share(&self, elgamal_sk_share: &FieldElement) -> SignatureGroup {
+        &self.blinded.0 * elgamal_sk_share
+    }
+
+    /// Unblind when the Elgamal secret key is threshold shared. `shares` are
+    /// (share id, `decryption_share`) of at least `threshold` holders of the key shares.
+    pub fn unblind_threshold(
+        self,
+        threshold: usize,
+        shares: Vec<(usize, SignatureGroup)>,
+    ) -> Result<Signature, CoconutError> {
+        let sigma_2 = threshold_decrypt(threshold, &self.blinded.1, shares)?;
+        Ok(Signature {
+            sigma_1: self.h,
+            sigma_2,
+        })
+    }
+
+    /// Byte representation is h || blinded.0 || blinded.1 || count of blinded terms || blinded
+    /// terms. The count is a 4 byte big-endian integer.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.h.to_bytes();
+        bytes.append(&mut self.blinded.0.to_bytes());
+        bytes.append(&mut self.blinded.1.to_bytes());
+        append_u32(&mut bytes, self.blinded_terms.len());
+        for t in &self.blinded_terms {
+            bytes.append(&mut t.to_bytes());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CoconutError> {
+        let mut reader = ByteReader::new(bytes);
+        let h = reader.read_group_elem::<SignatureGroup>(SIGNATURE_GROUP_SIZE)?;
+        let c1 = reader.read_group_elem::<SignatureGroup>(SIGNATURE_GROUP_SIZE)?;
+        let c2 = reader.read_group_elem::<SignatureGroup>(SIGNATURE_GROUP_SIZE)?;
+        let count = reader.read_count(SIGNATURE_GROUP_SIZE)?;
+        let mut blinded_terms = Vec::with_capacity(count);
+        for _ in 0..count {
+            blinded_terms.push(reader.read_group_elem::<SignatureGroup>(SIGNATURE_GROUP_SIZE)?);
+        }
+        reader.finish()?;
+        Ok(Self {
+            h,
+            blinded: (c1, c2),
+            blinded_terms,
+        })
+    }
+}
+
+/// Combine the decryption shares c_1^sk_i of an Elgamal ciphertext (c_1, c_2), given as
+/// (share id, c_1^sk_i), into c_1^sk by Lagrange interpolation like in `Signature::aggregate`.
+/// Only the first `threshold` shares are used. Errors if less than `threshold` shares are given,
+/// a share id is repeated or a share is not a valid point.
+pub fn combine_decryption_shares(
+    threshold: usize,
+    shares: Vec<(usize, SignatureGroup)>,
+) -> Result<SignatureGroup, CoconutError> {
+    check_share_ids(threshold, shares.iter().map(|(id, _)| *id))?;
+    let share_ids = shares
+        .iter()
+        .take(threshold)
+        .map(|(id, _)| *id)
+        .collect::<BTreeSet<usize>>();
+    let mut bases = SignatureGroupVec::with_capacity(threshold);
+    let mut exps = FieldElementVector::with_capacity(threshold);
+    let coeffs = LagrangeCoefficients::new(&share_ids);
+    for (id, share) in shares.into_iter().take(threshold) {
+        ensure_valid_point(&share)?;
+        bases.push(share);
+        exps.push(coeffs[id].clone());
+    }
+    Ok(bases.multi_scalar_mul_const_time(&exps)?)
+}
+
+/// Decrypt the Elgamal ciphertext (c_1, `c_2`) with the decryption shares of c_1 from at least
+/// `threshold` holders of the Elgamal secret key shares, as c_2 / c_1^sk.
+pub fn threshold_decrypt(
+    threshold: usize,
+    c_2: &SignatureGroup,
+    shares: Vec<(usize, SignatureGroup)>,
+) -> Result<SignatureGroup, CoconutError> {
+    Ok(c_2 - &combine_decryption_shares(threshold, shares)?)
 }
 
 impl Signature {
     /// Create an aggregated signature from signatures from various signers. "AggCred" from paper.
-    pub fn aggregate(threshold: usize, sigs: Vec<(usize, Signature)>) -> Signature {
-        assert!(sigs.len() >= threshold);
+    /// Errors if less than `threshold` signatures are given, a signature has an invalid point or
+    /// the signatures don't all have the same sigma_1 (h), like when signers sample h themselves.
+    pub fn aggregate(
+        threshold: usize,
+        sigs: Vec<(usize, Signature)>,
+    ) -> Result<Signature, CoconutError> {
+        if sigs.len() < threshold {
+            return Err(CoconutErrorKind::InsufficientShares {
+                expected: threshold,
+                given: sigs.len(),
+            }
+            .into());
+        }
+        for (id, sig) in sigs.iter().take(threshold) {
+            ensure_valid_point(&sig.sigma_1)?;
+            ensure_valid_point(&sig.sigma_2)?;
+            if sig.sigma_1 != sigs[0].1.sigma_1 {
+                return Err(CoconutErrorKind::InconsistentH { id: *id }.into());
+            }
+        }
+        // With threshold 1, like a single issuer, a signature is already the aggregate
+        if threshold == 1 {
+            return Ok(sigs.into_iter().next().unwrap().1);
+        }
         let mut s_bases = SignatureGroupVec::with_capacity(threshold);
         let mut s_exps = FieldElementVector::with_capacity(threshold);
         let sigma_1 = sigs[0].1.sigma_1.clone();
@@ -455,149 +2556,4371 @@ impl Signature {
             .iter()
             .take(threshold)
             .map(|(i, _)| *i)
-            .collect::<HashSet<usize>>();
+            .collect::<BTreeSet<usize>>();
+        let coeffs = LagrangeCoefficients::new(&signer_ids);
         for (id, sig) in sigs.into_iter().take(threshold) {
-            let l = Polynomial::lagrange_basis_at_0(signer_ids.clone(), id);
+            let l = coeffs[id].clone();
             s_bases.push(sig.sigma_2.clone());
             s_exps.push(l);
         }
         // s = sigma_2[i]^l for all i
-        let s = s_bases.multi_scalar_mul_const_time(&s_exps).unwrap();
-        Signature {
+        let s = s_bases.multi_scalar_mul_const_time(&s_exps)?;
+        Ok(Signature {
             sigma_1,
             sigma_2: s,
+        })
+    }
+
+    /// Same as `aggregate` but also returns the ids of the signers whose signatures were used,
+    /// which the aggregated signature does not reveal.
+    pub fn aggregate_with_metadata(
+        threshold: usize,
+        sigs: Vec<(usize, Signature)>,
+    ) -> Result<(Signature, AggregateMetadata), CoconutError> {
+        let signer_ids = sigs
+            .iter()
+            .take(threshold)
+            .map(|(id, _)| *id)
+            .collect::<BTreeSet<usize>>();
+        let sig = Self::aggregate(threshold, sigs)?;
+        Ok((sig, AggregateMetadata { signer_ids }))
+    }
+
+    /// Create an aggregated signature from the signatures of weighted signers. Each item of `sigs`
+    /// has the (share id, signature) pairs of 1 signer, 1 for each of its shares. Errors if the
+    /// shares given are less than `threshold` or a share id is repeated.
+    pub fn aggregate_weighted(
+        threshold: usize,
+        sigs: Vec<Vec<(usize, Signature)>>,
+    ) -> Result<Signature, CoconutError> {
+        let sigs = sigs.into_iter().flatten().collect::<Vec<(usize, Signature)>>();
+        check_share_ids(threshold, sigs.iter().map(|(id, _)| *id))?;
+        Self::aggregate(threshold, sigs)
+    }
+
+    /// Randomize the signature as (sigma_1^r, sigma_2^r) for a random r. The randomized signature
+    /// verifies over the same messages and can't be linked to the original.
+    pub fn randomize(&self) -> Signature {
+        self.randomize_with(&FieldElement::random())
+    }
+
+    /// Same as `randomize` but with the randomizer `r` given by the caller, like for test vectors
+    /// or deterministic wallets. `r` must be secret and not be reused across presentations.
+    pub fn randomize_with(&self, r: &FieldElement) -> Signature {
+        Signature {
+            sigma_1: &self.sigma_1 * r,
+            sigma_2: &self.sigma_2 * r,
         }
     }
 
     /// Verify a signature. Can verify unblinded sig received from a signer and the aggregate sig as well.
-    pub fn verify(&self, messages: &[FieldElement], vk: &Verkey, params: &Params) -> bool {
+    pub fn verify(
+        &self,
+        messages: &[FieldElement],
+        vk: &Verkey,
+        params: &Params,
+    ) -> Result<bool, CoconutError> {
+        if !self.is_valid_structure() || !params.has_type_attribute(messages) {
+            return Ok(false);
+        }
         let p = transform_to_PS_params(params);
         let vk = transform_to_PS_verkey(vk);
-        // TODO: Remove unwrap
-        PSSignature::verify(&transform_to_PS_sig(&self), messages, &vk, &p).unwrap()
+        Ok(PSSignature::verify(
+            &transform_to_PS_sig(&self),
+            messages,
+            &vk,
+            &p,
+        )?)
     }
-}
 
-impl Verkey {
-    /// Create an aggregated verkey.
-    pub fn aggregate(threshold: usize, keys: Vec<(usize, &Verkey)>) -> Verkey {
-        assert!(keys.len() >= threshold);
-        let q = keys[0].1.Y_tilde.len();
-        for i in 1..keys.len() {
-            assert_eq!(q, keys[i].1.Y_tilde.len());
+    /// Verify a signature over a single message, like of a 1 attribute token credential. Computes
+    /// kappa = X_tilde.Y_tilde^m with 1 scalar multiplication and avoids the conversions and
+    /// allocations of `verify`. False if the verkey is not for exactly 1 message or if `params` are
+    /// typed, as typed credentials have the type attribute besides the message.
+    pub fn verify_single(&self, message: &FieldElement, vk: &Verkey, params: &Params) -> bool {
+        if vk.Y_tilde.len() != 1 || params.is_typed() {
+            return false;
         }
+        let kappa = &vk.X_tilde + &(&vk.Y_tilde[0] * message);
+        self.pairing_check(&kappa, params)
+    }
 
-        let mut X_tilde_bases = OtherGroupVec::with_capacity(threshold);
-        let mut X_tilde_exps = FieldElementVector::with_capacity(threshold);
-
-        let mut Y_tilde_bases = vec![OtherGroupVec::with_capacity(threshold); q];
-        let mut Y_tilde_exps = vec![FieldElementVector::with_capacity(threshold); q];
+    /// Verify a signature using a kappa precomputed with `Verkey::partial_kappa` over some of the
+    /// messages. `varying` contains (index, message) pairs for the remaining messages. The caller
+    /// must ensure that the messages used for the partial kappa and `varying` together cover
+    /// every message index exactly once.
+    pub fn verify_with_partial_kappa(
+        &self,
+        partial_kappa: &OtherGroup,
+        varying: &[(usize, FieldElement)],
+        vk: &Verkey,
+        params: &Params,
+    ) -> bool {
+        match vk.kappa_terms(varying, params) {
+            Ok(terms) => self.pairing_check(&(partial_kappa + &terms), params),
+            Err(_) => false,
+        }
+    }
 
-        let signer_ids = keys
-            .iter()
-            .take(threshold)
-            .map(|(i, _)| *i)
-            .collect::<HashSet<usize>>();
-        for (id, vk) in keys.into_iter().take(threshold) {
-            let l = Polynomial::lagrange_basis_at_0(signer_ids.clone(), id);
-            X_tilde_bases.push(vk.X_tilde.clone());
-            X_tilde_exps.push(l.clone());
-            for j in 0..q {
-                Y_tilde_bases[j].push(vk.Y_tilde[j].clone());
-                Y_tilde_exps[j].push(l.clone());
+    /// Verify the signature against each of the given verkeys and return the index of the first
+    /// verkey for which the signature is valid. The exponents for kappa are computed once and
+    /// reused for every verkey.
+    pub fn verify_any(
+        &self,
+        messages: &[FieldElement],
+        vks: &[Verkey],
+        params: &Params,
+    ) -> Option<usize> {
+        if !self.has_valid_points() || !params.has_type_attribute(messages) {
+            return None;
+        }
+        // kappa = X_tilde^1.Y_tilde_1^m_1...Y_tilde_n^m_n
+        let mut exps = FieldElementVector::with_capacity(messages.len() + 1);
+        exps.push(FieldElement::one());
+        for m in messages {
+            exps.push(m.clone());
+        }
+        for (i, vk) in vks.iter().enumerate() {
+            if vk.Y_tilde.len() != messages.len() {
+                continue;
+            }
+            let mut bases = OtherGroupVec::with_capacity(messages.len() + 1);
+            bases.push(vk.X_tilde.clone());
+            for y in &vk.Y_tilde {
+                bases.push(y.clone());
+            }
+            match bases.multi_scalar_mul_var_time(&exps) {
+                Ok(kappa) if self.pairing_check(&kappa, params) => return Some(i),
+                _ => continue,
             }
         }
+        None
+    }
+
+    /// Verify the signature over each of the given message sets and return the result for each set.
+    /// e(sigma_2, g_tilde) is computed once and compared against e(sigma_1, kappa_i) for the kappa
+    /// of each set, so each set costs 1 pairing rather than the 2 of an independent `verify`.
+    pub fn verify_many_messages(
+        &self,
+        message_sets: &[FieldElementVector],
+        vk: &Verkey,
+        params: &Params,
+    ) -> Vec<bool> {
+        if !self.has_valid_points() {
+            return vec![false; message_sets.len()];
+        }
+        let rhs = ate_pairing(&self.sigma_2, &params.g_tilde);
+        message_sets
+            .iter()
+            .map(|messages| {
+                if messages.len() != vk.Y_tilde.len() {
+                    return false;
+                }
+                let msgs = messages
+                    .iter()
+                    .enumerate()
+                    .map(|(i, m)| (i, m.clone()))
+                    .collect::<Vec<(usize, FieldElement)>>();
+                match vk.partial_kappa(&msgs, params) {
+                    Ok(kappa) => ate_pairing(&self.sigma_1, &kappa) == rhs,
+                    Err(_) => false,
+                }
+            })
+            .collect()
+    }
+
+    /// Verify independent signatures, each over its own messages and verkey. The checks
+    /// e(sigma_1_i, kappa_i) == e(sigma_2_i, g_tilde) are combined with random weights r_i into
+    /// e(sigma_1_1^r_1, kappa_1)...e(sigma_1_n^r_n, kappa_n) == e(sigma_2_1^r_1...sigma_2_n^r_n, g_tilde)
+    /// which is checked with a single multi-pairing. Returns false if any signature is invalid.
+    pub fn verify_batch_multi(
+        items: &[(Signature, FieldElementVector, &Verkey)],
+        params: &Params,
+    ) -> bool {
+        if items.is_empty() {
+            return false;
+        }
+        let mut pairs = Vec::with_capacity(items.len() + 1);
+        let mut sigma_2_bases = SignatureGroupVec::with_capacity(items.len());
+        let mut weights = FieldElementVector::with_capacity(items.len());
+        for (sig, messages, vk) in items {
+            if !sig.is_valid_structure() || messages.len() != vk.Y_tilde.len() {
+                return false;
+            }
+            let msgs = messages
+                .iter()
+                .enumerate()
+                .map(|(i, m)| (i, m.clone()))
+                .collect::<Vec<(usize, FieldElement)>>();
+            let kappa = match vk.partial_kappa(&msgs, params) {
+                Ok(kappa) => kappa,
+                Err(_) => return false,
+            };
+            let r = FieldElement::random();
+            pairs.push((&sig.sigma_1 * &r, kappa));
+            sigma_2_bases.push(sig.sigma_2.clone());
+            weights.push(r);
+        }
+        match sigma_2_bases.multi_scalar_mul_var_time(&weights) {
+            Ok(sigma_2) => pairs.push((sigma_2.negation(), params.g_tilde.clone())),
+            Err(_) => return false,
+        }
+        ate_multi_pairing(&pairs).is_one()
+    }
+
+    /// Points of the pairing check of verification over `messages` as limbs of their coordinates,
+    /// to be given as inputs to a circuit verifying the signature.
+    pub fn to_circuit_inputs(
+        &self,
+        messages: &[FieldElement],
+        vk: &Verkey,
+        params: &Params,
+    ) -> Result<CircuitInputs, CoconutError> {
+        if messages.len() != vk.Y_tilde.len() {
+            return Err(CoconutErrorKind::UnsupportedNoOfMessages {
+                expected: vk.Y_tilde.len(),
+                given: messages.len(),
+            }
+            .into());
+        }
+        let msgs = messages
+            .iter()
+            .enumerate()
+            .map(|(i, m)| (i, m.clone()))
+            .collect::<Vec<(usize, FieldElement)>>();
+        let kappa = vk.partial_kappa(&msgs, params)?;
+        Ok(CircuitInputs::new(
+            &self.sigma_1,
+            &self.sigma_2,
+            &kappa,
+            &params.g_tilde,
+        ))
+    }
+
+    /// Open the attribute at `index` of this credential over `messages`, see `OpeningProof`
+    pub fn open_attribute(
+        &self,
+        index: usize,
+        messages: &[FieldElement],
+        vk: &Verkey,
+        params: &Params,
+    ) -> Result<OpeningProof, CoconutError> {
+        OpeningProof::new(self, index, messages, vk, params)
+    }
+
+    /// Whether sigma_1 and sigma_2 pass `ensure_valid_point`
+    fn has_valid_points(&self) -> bool {
+        ensure_valid_point(&self.sigma_1).is_ok() && ensure_valid_point(&self.sigma_2).is_ok()
+    }
+
+    /// Cheap check before the pairings of `verify`. sigma_1 and sigma_2 must not be the identity
+    /// and must be in the prime order subgroup, regardless of the `strict` feature. A sigma_1 with
+    /// a component in a small subgroup could otherwise pass the pairing check with sigma_2.
+    pub fn is_valid_structure(&self) -> bool {
+        self.has_valid_points()
+            && self.sigma_1.has_correct_order()
+            && self.sigma_2.has_correct_order()
+    }
+
+    /// Same as `verify` but says why a signature could not be checked rather than returning false.
+    /// Errors with `UnsupportedNoOfMessages` if the verkey is for a different number of messages,
+    /// `InvalidPoint` if the signature fails `is_valid_structure`, `TypeAttributeMismatch` if the
+    /// type attribute of typed `params` is not the tag and `PairingFailed` if an input of the
+    /// pairing, like g_tilde of the params, is the identity. Ok(false) only when the pairing check
+    /// fails.
+    pub fn verify_detailed(
+        &self,
+        messages: &[FieldElement],
+        vk: &Verkey,
+        params: &Params,
+    ) -> Result<bool, CoconutError> {
+        if messages.len() != vk.Y_tilde.len() {
+            return Err(CoconutErrorKind::UnsupportedNoOfMessages {
+                expected: vk.Y_tilde.len(),
+                given: messages.len(),
+            }
+            .into());
+        }
+        if !self.is_valid_structure() {
+            return Err(CoconutErrorKind::InvalidPoint {
+                msg: String::from("signature not in prime order subgroup or identity"),
+            }
+            .into());
+        }
+        let msgs = messages
+            .iter()
+            .enumerate()
+            .map(|(i, m)| (i, m.clone()))
+            .collect::<Vec<(usize, FieldElement)>>();
+        let kappa = vk.partial_kappa(&msgs, params)?;
+        // e(sigma_1, kappa) * e(-sigma_2, g_tilde) == 1
+        let e = checked_ate_2_pairing(
+            &self.sigma_1,
+            &kappa,
+            &self.sigma_2.negation(),
+            &params.g_tilde,
+        )?;
+        Ok(e.is_one())
+    }
+
+    /// Same as `verify_detailed` but Ok(()) on success and an error for every failure, so that
+    /// callers can use `?` and log why a signature was rejected. Errors with `SigmaIsIdentity` if
+    /// sigma_1 or sigma_2 is the identity, `AttributeCountMismatch` if `vk` is for a different
+    /// number of messages, `PairingCheckFailed` for a signature that is well formed but not valid
+    /// on `messages` under `vk` and otherwise as `verify_detailed`.
+    pub fn verify_or_err(
+        &self,
+        messages: &[FieldElement],
+        vk: &Verkey,
+        params: &Params,
+    ) -> Result<(), CoconutError> {
+        if self.sigma_1.is_identity() || self.sigma_2.is_identity() {
+            return Err(CoconutErrorKind::SigmaIsIdentity.into());
+        }
+        if messages.len() != vk.Y_tilde.len() {
+            return Err(CoconutErrorKind::AttributeCountMismatch {
+                expected: vk.Y_tilde.len(),
+                given: messages.len(),
+            }
+            .into());
+        }
+        if !self.verify_detailed(messages, vk, params)? {
+            return Err(CoconutErrorKind::PairingCheckFailed.into());
+        }
+        Ok(())
+    }
+
+    /// Check e(sigma_1, kappa) == e(sigma_2, g_tilde)
+    pub(crate) fn pairing_check(&self, kappa: &OtherGroup, params: &Params) -> bool {
+        if !self.has_valid_points() {
+            return false;
+        }
+        // e(sigma_1, kappa) * e(-sigma_2, g_tilde) == 1
+        let e = ate_2_pairing(
+            &self.sigma_1,
+            kappa,
+            &self.sigma_2.negation(),
+            &params.g_tilde,
+        );
+        e.is_one()
+    }
+
+    /// Byte representation is sigma_1 || sigma_2
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.sigma_1.to_bytes();
+        bytes.append(&mut self.sigma_2.to_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CoconutError> {
+        if bytes.len() != 2 * SIGNATURE_GROUP_SIZE {
+            return Err(CoconutErrorKind::InvalidByteLength {
+                expected: 2 * SIGNATURE_GROUP_SIZE,
+                given: bytes.len(),
+            }
+            .into());
+        }
+        let sigma_1 = group_elem_from_bytes::<SignatureGroup>(&bytes[0..SIGNATURE_GROUP_SIZE])?;
+        let sigma_2 = group_elem_from_bytes::<SignatureGroup>(&bytes[SIGNATURE_GROUP_SIZE..])?;
+        Ok(Self { sigma_1, sigma_2 })
+    }
+
+    pub fn to_hex(&self) -> String {
+        bytes_to_hex(&self.to_bytes())
+    }
+
+    pub fn from_hex(hex: &str) -> Result<Self, CoconutError> {
+        Self::from_bytes(&hex_to_bytes(hex)?)
+    }
+}
+
+impl PartialSignatureSet {
+    /// Set for the blind signatures of `threshold` or more signers over `sig_req` for credentials
+    /// of `params`
+    pub fn new(threshold: usize, sig_req: &SignatureRequest, params: &Params) -> Self {
+        Self {
+            threshold,
+            sig_req: sig_req.clone(),
+            params: params.clone(),
+            h: SignatureRequest::compute_h(&sig_req.commitment, sig_req.known_messages.as_slice()),
+            sigs: BTreeMap::new(),
+        }
+    }
+
+    /// Add the blind signature of the signer with id `signer_id` and verkey `vk`. Errors with
+    /// `DuplicateSignerId` if the signer's blind signature was already added, with `HMismatch` if
+    /// its h is not the h of the request, if a point of its ciphertext is the identity and with
+    /// `PairingCheckFailed` if it was not created with the sigkey of `vk` over the request (its
+    /// part for the signer for a request created with `SignatureRequest::new_multi_issuer`), see
+    /// `BlindSignature::verify_against_verkey`. The set is unchanged on error.
+    pub fn insert(
+        &mut self,
+        signer_id: usize,
+        blind_sig: BlindSignature,
+        vk: &Verkey,
+    ) -> Result<(), CoconutError> {
+        if self.sigs.contains_key(&signer_id) {
+            return Err(CoconutErrorKind::DuplicateSignerId { id: signer_id }.into());
+        }
+        if blind_sig.h != self.h {
+            return Err(CoconutErrorKind::HMismatch.into());
+        }
+        ensure_valid_point(&blind_sig.blinded.0)?;
+        ensure_valid_point(&blind_sig.blinded.1)?;
+        if !blind_sig.verify_against_verkey(&self.sig_req.for_signer(signer_id)?, vk, &self.params)
+        {
+            return Err(CoconutErrorKind::PairingCheckFailed.into());
+        }
+        self.sigs.insert(signer_id, blind_sig);
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.sigs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sigs.is_empty()
+    }
+
+    /// Whether at least `threshold` blind signatures have been added
+    pub fn is_complete(&self) -> bool {
+        self.sigs.len() >= self.threshold
+    }
+
+    /// Ids of the signers whose blind signatures have been added, in increasing order
+    pub fn signer_ids(&self) -> BTreeSet<usize> {
+        self.sigs.keys().cloned().collect()
+    }
+
+    /// Unblind the blind signatures with `elgamal_sk` and aggregate them, see
+    /// `Signature::aggregate`. The signatures of the `threshold` signers with the smallest ids
+    /// are used. Errors with `InsufficientShares` if the set is not complete.
+    pub fn aggregate(self, elgamal_sk: &FieldElement) -> Result<Signature, CoconutError> {
+        let sigs = self
+            .sigs
+            .into_iter()
+            .map(|(id, blind_sig)| (id, blind_sig.unblind(elgamal_sk)))
+            .collect::<Vec<(usize, Signature)>>();
+        Signature::aggregate(self.threshold, sigs)
+    }
+}
+
+impl SignatureBatch {
+    /// All signatures must have the same sigma_1
+    pub fn new(sigs: &[Signature]) -> Result<Self, CoconutError> {
+        if sigs.is_empty() {
+            return Err(CoconutErrorKind::GeneralError {
+                msg: String::from("Need at least 1 signature for a batch"),
+            }
+            .into());
+        }
+        let sigma_1 = sigs[0].sigma_1.clone();
+        let mut sigma_2s = Vec::with_capacity(sigs.len());
+        for sig in sigs {
+            if sig.sigma_1 != sigma_1 {
+                return Err(CoconutErrorKind::GeneralError {
+                    msg: String::from("All signatures in a batch must have the same sigma_1"),
+                }
+                .into());
+            }
+            sigma_2s.push(sig.sigma_2.clone());
+        }
+        Ok(Self { sigma_1, sigma_2s })
+    }
+
+    pub fn len(&self) -> usize {
+        self.sigma_2s.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sigma_2s.is_empty()
+    }
+
+    pub fn signatures(&self) -> Vec<Signature> {
+        self.sigma_2s
+            .iter()
+            .map(|sigma_2| Signature {
+                sigma_1: self.sigma_1.clone(),
+                sigma_2: sigma_2.clone(),
+            })
+            .collect()
+    }
+
+    /// Verify each signature of the batch over the corresponding messages and verkey in `items`.
+    /// Since sigma_1 is shared, the checks e(sigma_1, kappa_i) == e(sigma_2_i, g_tilde) are combined
+    /// with random weights r_i into e(sigma_1, kappa_1^r_1...kappa_n^r_n) == e(sigma_2_1^r_1...sigma_2_n^r_n, g_tilde)
+    /// so that only 1 pairing check is needed.
+    pub fn verify_batch(&self, items: &[(&[FieldElement], &Verkey)], params: &Params) -> bool {
+        if items.len() != self.sigma_2s.len() || self.sigma_1.is_identity() {
+            return false;
+        }
+        let mut kappa_bases = OtherGroupVec::with_capacity(items.len());
+        let mut sigma_2_bases = SignatureGroupVec::with_capacity(items.len());
+        let mut weights = FieldElementVector::with_capacity(items.len());
+        for (i, (messages, vk)) in items.iter().enumerate() {
+            if self.sigma_2s[i].is_identity() || vk.Y_tilde.len() != messages.len() {
+                return false;
+            }
+            let msgs = messages
+                .iter()
+                .enumerate()
+                .map(|(j, m)| (j, m.clone()))
+                .collect::<Vec<(usize, FieldElement)>>();
+            match vk.partial_kappa(&msgs, params) {
+                Ok(kappa) => kappa_bases.push(kappa),
+                Err(_) => return false,
+            }
+            sigma_2_bases.push(self.sigma_2s[i].clone());
+            weights.push(FieldElement::random());
+        }
+        let (kappa, sigma_2) = match (
+            kappa_bases.multi_scalar_mul_var_time(&weights),
+            sigma_2_bases.multi_scalar_mul_var_time(&weights),
+        ) {
+            (Ok(kappa), Ok(sigma_2)) => (kappa, sigma_2),
+            _ => return false,
+        };
+        Signature {
+            sigma_1: self.sigma_1.clone(),
+            sigma_2,
+        }
+        .pairing_check(&kappa, params)
+    }
+
+    /// Byte representation is sigma_1 || 4 byte big-endian count of sigma_2s || sigma_2s
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.sigma_1.to_bytes();
+        bytes.extend_from_slice(&(self.sigma_2s.len() as u32).to_be_bytes());
+        for s in &self.sigma_2s {
+            bytes.append(&mut s.to_bytes());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CoconutError> {
+        if bytes.len() < SIGNATURE_GROUP_SIZE + 4 {
+            return Err(CoconutErrorKind::InvalidByteLength {
+                expected: SIGNATURE_GROUP_SIZE + 4,
+                given: bytes.len(),
+            }
+            .into());
+        }
+        let sigma_1 = group_elem_from_bytes::<SignatureGroup>(&bytes[0..SIGNATURE_GROUP_SIZE])?;
+        let mut count_bytes = [0u8; 4];
+        count_bytes.copy_from_slice(&bytes[SIGNATURE_GROUP_SIZE..SIGNATURE_GROUP_SIZE + 4]);
+        let count = u32::from_be_bytes(count_bytes) as usize;
+        let rest = &bytes[SIGNATURE_GROUP_SIZE + 4..];
+        if rest.len() / SIGNATURE_GROUP_SIZE != count || rest.len() % SIGNATURE_GROUP_SIZE != 0 {
+            return Err(CoconutErrorKind::InvalidByteLength {
+                expected: SIGNATURE_GROUP_SIZE + 4 + count.saturating_mul(SIGNATURE_GROUP_SIZE),
+                given: bytes.len(),
+            }
+            .into());
+        }
+        let mut sigma_2s = Vec::with_capacity(count);
+        for chunk in rest.chunks(SIGNATURE_GROUP_SIZE) {
+            sigma_2s.push(group_elem_from_bytes::<SignatureGroup>(chunk)?);
+        }
+        Ok(Self { sigma_1, sigma_2s })
+    }
+}
+
+/// Check that there are at least `threshold` share ids and no id is repeated
+fn check_share_ids<I: Iterator<Item = usize>>(
+    threshold: usize,
+    ids: I,
+) -> Result<(), CoconutError> {
+    let mut seen = HashSet::new();
+    for id in ids {
+        if !seen.insert(id) {
+            return Err(CoconutErrorKind::DuplicateSignerId { id }.into());
+        }
+    }
+    if seen.len() < threshold {
+        return Err(CoconutErrorKind::InsufficientShares {
+            expected: threshold,
+            given: seen.len(),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Check that the signers with `ids` can form an aggregate, before asking for or aggregating their
+/// signatures. Errors with `UnknownSignerId` if an id is not among `known_ids`, then with
+/// `DuplicateSignerId` if an id is repeated and then with `InsufficientShares` if there are fewer
+/// than `threshold` ids.
+pub fn can_aggregate(
+    ids: &[usize],
+    threshold: usize,
+    known_ids: &[usize],
+) -> Result<(), CoconutError> {
+    let known = known_ids.iter().collect::<HashSet<&usize>>();
+    if let Some(id) = ids.iter().find(|id| !known.contains(id)) {
+        return Err(CoconutErrorKind::UnknownSignerId { id: *id }.into());
+    }
+    check_share_ids(threshold, ids.iter().cloned())
+}
+
+impl Sigkey {
+    /// Reconstruct the aggregate signing key (x, y_1, y_2, ... y_n) by Lagrange interpolation at 0
+    /// from at least `threshold` shares. Only the first `threshold` shares are used.
+    /// This is a break-glass operation for disaster recovery and testing. Whoever runs it holds
+    /// the key that the threshold scheme is meant to keep distributed.
+    #[cfg(feature = "dangerous_reconstruct")]
+    pub fn reconstruct(
+        threshold: usize,
+        shares: &[(usize, &Sigkey)],
+    ) -> Result<Sigkey, CoconutError> {
+        if shares.len() < threshold {
+            return Err(CoconutErrorKind::InsufficientShares {
+                expected: threshold,
+                given: shares.len(),
+            }
+            .into());
+        }
+        let shares = &shares[0..threshold];
+        let mut signer_ids = BTreeSet::new();
+        for (id, _) in shares {
+            if !signer_ids.insert(*id) {
+                return Err(CoconutErrorKind::DuplicateSignerId { id: *id }.into());
+            }
+        }
+        let q = shares[0].1.y.len();
+        for (_, s) in shares {
+            if s.y.len() != q {
+                return Err(CoconutErrorKind::UnsupportedNoOfMessages {
+                    expected: q,
+                    given: s.y.len(),
+                }
+                .into());
+            }
+        }
+
+        let mut x = FieldElement::zero();
+        let mut y = vec![FieldElement::zero(); q];
+        let coeffs = LagrangeCoefficients::new(&signer_ids);
+        for (id, s) in shares {
+            let l = &coeffs[*id];
+            x += &s.x * l;
+            for j in 0..q {
+                y[j] += &s.y[j] * l;
+            }
+        }
+        Ok(Sigkey { x, y })
+    }
+
+    /// Byte representation is x || y_1 || y_2 || ... y_n
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.x.to_bytes();
+        for y in &self.y {
+            bytes.append(&mut y.to_bytes());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CoconutError> {
+        if bytes.len() < FIELD_ELEMENT_SIZE || bytes.len() % FIELD_ELEMENT_SIZE != 0 {
+            return Err(CoconutErrorKind::InvalidByteLength {
+                expected: FIELD_ELEMENT_SIZE
+                    + (bytes.len().saturating_sub(FIELD_ELEMENT_SIZE) / FIELD_ELEMENT_SIZE)
+                        * FIELD_ELEMENT_SIZE,
+                given: bytes.len(),
+            }
+            .into());
+        }
+        let x = FieldElement::from_bytes(&bytes[0..FIELD_ELEMENT_SIZE])?;
+        let mut y = Vec::with_capacity(bytes.len() / FIELD_ELEMENT_SIZE - 1);
+        for chunk in bytes[FIELD_ELEMENT_SIZE..].chunks(FIELD_ELEMENT_SIZE) {
+            y.push(FieldElement::from_bytes(chunk)?);
+        }
+        Ok(Self { x, y })
+    }
+
+    /// Write `to_bytes` to `writer` framed by its length, see `write_framed`. This is the secret
+    /// signing key so `writer` must be as protected as the key, like a file only the signer can
+    /// read. The bytes are not encrypted.
+    pub fn write_to<W: Write>(&self, writer: W) -> Result<(), CoconutError> {
+        write_framed(writer, &self.to_bytes())
+    }
+
+    /// Read a signing key written by `write_to`
+    pub fn read_from<R: Read>(reader: R) -> Result<Self, CoconutError> {
+        Self::from_bytes(&read_framed(reader)?)
+    }
+}
+
+/// Domain separation tag prefixed to the seed in `seeded_y`
+pub const SEEDED_Y_TAG: &[u8] = b"coconut/sigkey/seeded-y";
+
+/// The y's of a signing key for `msg_count` messages derived from `y_seed`, y_i is the hash of
+/// `SEEDED_Y_TAG || seed length || y_seed || i` with the length and i as 4 byte big-endian
+/// integers. Anyone with the seed has the y's, so it must be kept as secret as the signing key.
+pub fn seeded_y(y_seed: &[u8], msg_count: usize) -> Vec<FieldElement> {
+    let mut bytes = SEEDED_Y_TAG.to_vec();
+    append_u32(&mut bytes, y_seed.len());
+    bytes.extend_from_slice(y_seed);
+    (0..msg_count)
+        .map(|i| {
+            let mut b = bytes.clone();
+            append_u32(&mut b, i);
+            FieldElement::from_msg_hash(&b)
+        })
+        .collect()
+}
+
+/// Verkey whose Y_tilde are regenerated from the seed of its y's, see `Verkey::compress`. Holds
+/// the seed, so it is for storage by the owner of the signing key and not for publishing.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CompressedVerkey {
+    pub X_tilde: OtherGroup,
+    pub y_seed: Vec<u8>,
+}
+
+impl CompressedVerkey {
+    /// Verkey with a Y_tilde for each message of `params`
+    pub fn decompress(&self, params: &Params) -> Verkey {
+        Verkey {
+            X_tilde: self.X_tilde.clone(),
+            Y_tilde: seeded_y(&self.y_seed, params.msg_count())
+                .iter()
+                .map(|y| &params.g_tilde * y)
+                .collect(),
+        }
+    }
+}
+
+impl Verkey {
+    /// Indices of the messages this verkey can verify signatures over, 1 for each Y_tilde
+    pub fn attribute_indices(&self) -> Range<usize> {
+        0..self.Y_tilde.len()
+    }
+
+    /// Verkey for the secrets (x, y_1, y_2, ... y_n), i.e. X_tilde = g_tilde^x and
+    /// Y_tilde_i = g_tilde^y_i. For the secrets shared among the signers this is the aggregated
+    /// verkey, so it can be computed without any signer's verkey.
+    pub fn from_secrets(x: &FieldElement, y: &[FieldElement], params: &Params) -> Verkey {
+        Verkey {
+            X_tilde: &params.g_tilde * x,
+            Y_tilde: y.iter().map(|y_i| &params.g_tilde * y_i).collect(),
+        }
+    }
+
+    /// Verkey with X_tilde and only the Y_tilde at `indices`, in that order, for verifying
+    /// signatures that only involve these attributes. The caller is responsible for the
+    /// ordering: the messages verified under the projected verkey are the attributes at
+    /// `indices` in the order of `indices`, not by their original index. A signature only
+    /// verifies under it if the dropped attributes, with the credential type added, are zero,
+    /// like the unused attributes of a credential issued under untyped params with more
+    /// attributes than needed. Errors if an index is invalid or given more than once.
+    pub fn project(&self, indices: &[usize]) -> Result<Verkey, CoconutError> {
+        let msg_count = self.Y_tilde.len();
+        for (k, idx) in indices.iter().enumerate() {
+            if *idx >= msg_count {
+                return Err(CoconutErrorKind::InvalidAttributeIndex {
+                    idx: *idx,
+                    msg_count,
+                }
+                .into());
+            }
+            if indices[..k].contains(idx) {
+                return Err(CoconutErrorKind::GeneralError {
+                    msg: format!("Attribute {} given more than once", idx),
+                }
+                .into());
+            }
+        }
+        Ok(Verkey {
+            X_tilde: self.X_tilde.clone(),
+            Y_tilde: indices.iter().map(|i| self.Y_tilde[*i].clone()).collect(),
+        })
+    }
+
+    /// Compress a verkey whose y's were derived from `y_seed` with `seeded_y`, like by
+    /// `keygen_with_seeded_y`, by dropping the Y_tilde. Errors if the Y_tilde are not those
+    /// derived from `y_seed`.
+    pub fn compress(
+        &self,
+        y_seed: &[u8],
+        params: &Params,
+    ) -> Result<CompressedVerkey, CoconutError> {
+        let compressed = CompressedVerkey {
+            X_tilde: self.X_tilde.clone(),
+            y_seed: y_seed.to_vec(),
+        };
+        let vk = compressed.decompress(params);
+        if vk.Y_tilde.len() != self.Y_tilde.len() {
+            return Err(CoconutErrorKind::UnsupportedNoOfMessages {
+                expected: vk.Y_tilde.len(),
+                given: self.Y_tilde.len(),
+            }
+            .into());
+        }
+        if vk.Y_tilde != self.Y_tilde {
+            return Err(CoconutErrorKind::GeneralError {
+                msg: String::from("Verkey not derived from the seed"),
+            }
+            .into());
+        }
+        Ok(compressed)
+    }
+
+    /// Create an aggregated verkey by interpolating over all the given verkeys. Since the verkeys
+    /// are evaluations of polynomials of degree `threshold - 1`, any `threshold` or more of them
+    /// give the same aggregated verkey. Errors if less than `threshold` verkeys or none are given,
+    /// an id is repeated or the verkeys are for different numbers of messages.
+    pub fn aggregate(
+        threshold: usize,
+        keys: Vec<(usize, &Verkey)>,
+    ) -> Result<Verkey, CoconutError> {
+        check_share_ids(threshold, keys.iter().map(|(id, _)| *id))?;
+        // Only possible with threshold 0
+        if keys.is_empty() {
+            return Err(CoconutErrorKind::InsufficientShares {
+                expected: 1,
+                given: 0,
+            }
+            .into());
+        }
+        let q = keys[0].1.Y_tilde.len();
+        for i in 1..keys.len() {
+            if keys[i].1.Y_tilde.len() != q {
+                return Err(CoconutErrorKind::UnsupportedNoOfMessages {
+                    expected: q,
+                    given: keys[i].1.Y_tilde.len(),
+                }
+                .into());
+            }
+        }
+        // A single key, like of a single issuer, is already the aggregate
+        if keys.len() == 1 {
+            return Ok(keys[0].1.clone());
+        }
+        if q == 1 {
+            return Ok(Self::aggregate_single(keys));
+        }
+
+        let n = keys.len();
+        let mut X_tilde_bases = OtherGroupVec::with_capacity(n);
+        let mut X_tilde_exps = FieldElementVector::with_capacity(n);
+
+        let mut Y_tilde_bases = vec![OtherGroupVec::with_capacity(n); q];
+        let mut Y_tilde_exps = vec![FieldElementVector::with_capacity(n); q];
+
+        let signer_ids = keys.iter().map(|(i, _)| *i).collect::<BTreeSet<usize>>();
+        let coeffs = LagrangeCoefficients::new(&signer_ids);
+        for (id, vk) in keys {
+            let l = &coeffs[id];
+            X_tilde_bases.push(vk.X_tilde.clone());
+            X_tilde_exps.push(l.clone());
+            for j in 0..q {
+                Y_tilde_bases[j].push(vk.Y_tilde[j].clone());
+                Y_tilde_exps[j].push(l.clone());
+            }
+        }
+
+        // X_tilde = X_tilde_1^l_1 * X_tilde_2^l_2 * ... X_tilde_i^l_i for i in keys
+        let X_tilde = X_tilde_bases.multi_scalar_mul_var_time(&X_tilde_exps)?;
+
+        // Y_tilde = [Y_tilde_1^l_1 * Y_tilde_2^l_2 * ... Y_tilde_i^l_i for i in keys, .. for all q]
+        let mut Y_tilde = vec![];
+        for i in 0..q {
+            Y_tilde.push(Y_tilde_bases[i].multi_scalar_mul_var_time(&Y_tilde_exps[i])?);
+        }
+        Ok(Self { X_tilde, Y_tilde })
+    }
+
+    /// Check that this verkey is the aggregate of the verkeys of `contributors`, given as
+    /// (signer id, verkey) like for `aggregate`, like when the aggregate verkey is given by a
+    /// coordinator. False also if the contributors can't be aggregated.
+    pub fn verify_aggregate(&self, threshold: usize, contributors: &[(usize, &Verkey)]) -> bool {
+        match Self::aggregate(threshold, contributors.to_vec()) {
+            Ok(vk) => vk.X_tilde == self.X_tilde && vk.Y_tilde == self.Y_tilde,
+            Err(_) => false,
+        }
+    }
+
+    /// Aggregation of verkeys for a single message. Sums X_tilde_i^l_i and Y_tilde_i^l_i directly
+    /// rather than building vectors of bases and exponents for each message.
+    fn aggregate_single(keys: Vec<(usize, &Verkey)>) -> Verkey {
+        let signer_ids = keys.iter().map(|(i, _)| *i).collect::<BTreeSet<usize>>();
+        let mut X_tilde = OtherGroup::identity();
+        let mut Y_tilde = OtherGroup::identity();
+        let coeffs = LagrangeCoefficients::new(&signer_ids);
+        for (id, vk) in keys {
+            let l = &coeffs[id];
+            X_tilde = &X_tilde + &(&vk.X_tilde * l);
+            Y_tilde = &Y_tilde + &(&vk.Y_tilde[0] * l);
+        }
+        Self {
+            X_tilde,
+            Y_tilde: vec![Y_tilde],
+        }
+    }
+
+    /// Create an aggregated verkey from the verkeys of the shares of weighted signers. Errors if the
+    /// total weight of the signers is less than `threshold` or a share id is repeated.
+    pub fn aggregate_weighted(
+        threshold: usize,
+        signers: &[&WeightedSigner],
+    ) -> Result<Verkey, CoconutError> {
+        let keys = signers
+            .iter()
+            .flat_map(|s| s.verkeys())
+            .collect::<Vec<(usize, &Verkey)>>();
+        Self::aggregate(threshold, keys)
+    }
+
+    /// Partial aggregate of the verkeys of the members of a sub-committee, to be combined with
+    /// the partial aggregates of other sub-committees by `aggregate_of_aggregates`. The Lagrange
+    /// coefficient of each member is computed over `signer_ids`, the ids of the members of all
+    /// the sub-committees taking part, and not over the sub-committee's own members. Errors if a
+    /// key's id is not in `signer_ids`.
+    pub fn aggregate_partial(
+        signer_ids: &HashSet<usize>,
+        keys: Vec<(usize, &Verkey)>,
+    ) -> Result<Verkey, CoconutError> {
+        if keys.is_empty() {
+            return Err(CoconutErrorKind::InsufficientShares {
+                expected: 1,
+                given: 0,
+            }
+            .into());
+        }
+        let q = keys[0].1.Y_tilde.len();
+        let coeffs =
+            LagrangeCoefficients::new(&signer_ids.iter().cloned().collect::<BTreeSet<usize>>());
+        let mut X_tilde = OtherGroup::identity();
+        let mut Y_tilde = vec![OtherGroup::identity(); q];
+        for (id, vk) in keys {
+            if !signer_ids.contains(&id) {
+                return Err(CoconutErrorKind::GeneralError {
+                    msg: format!("Signer id {} not among the ids of the signers", id),
+                }
+                .into());
+            }
+            if vk.Y_tilde.len() != q {
+                return Err(CoconutErrorKind::UnsupportedNoOfMessages {
+                    expected: q,
+                    given: vk.Y_tilde.len(),
+                }
+                .into());
+            }
+            let l = &coeffs[id];
+            X_tilde = &X_tilde + &(&vk.X_tilde * l);
+            for j in 0..q {
+                Y_tilde[j] = &Y_tilde[j] + &(&vk.Y_tilde[j] * l);
+            }
+        }
+        Ok(Self { X_tilde, Y_tilde })
+    }
+
+    /// Combine the partial aggregate verkeys of sub-committees into the global aggregate verkey.
+    /// Each item of `sub_aggregates` has the ids of the members of a sub-committee and its partial
+    /// aggregate. The global verkey is X_tilde = X_tilde_1^l_1 * ... X_tilde_n^l_n where l_i is the
+    /// Lagrange coefficient of signer i over all the n signers taking part, so it is split by
+    /// sub-committee into partial products and the global verkey is the product of the partials.
+    /// Interpolating each sub-committee over its own members and then interpolating the results
+    /// again does not give the global verkey since the coefficients depend on the whole set of
+    /// signers. Hence the partials must be created with `aggregate_partial` over the union of the
+    /// ids given here. Errors if the sub-committees have fewer than `threshold` members in total,
+    /// a member is in more than 1 sub-committee or the partials are for different numbers of
+    /// messages.
+    pub fn aggregate_of_aggregates(
+        threshold: usize,
+        sub_aggregates: &[(&[usize], &Verkey)],
+    ) -> Result<Verkey, CoconutError> {
+        check_share_ids(
+            threshold,
+            sub_aggregates
+                .iter()
+                .flat_map(|(ids, _)| ids.iter().cloned()),
+        )?;
+        if sub_aggregates.is_empty() {
+            return Err(CoconutErrorKind::InsufficientShares {
+                expected: threshold,
+                given: 0,
+            }
+            .into());
+        }
+        let q = sub_aggregates[0].1.Y_tilde.len();
+        let mut X_tilde = OtherGroup::identity();
+        let mut Y_tilde = vec![OtherGroup::identity(); q];
+        for (_, vk) in sub_aggregates {
+            if vk.Y_tilde.len() != q {
+                return Err(CoconutErrorKind::UnsupportedNoOfMessages {
+                    expected: q,
+                    given: vk.Y_tilde.len(),
+                }
+                .into());
+            }
+            X_tilde = &X_tilde + &vk.X_tilde;
+            for j in 0..q {
+                Y_tilde[j] = &Y_tilde[j] + &vk.Y_tilde[j];
+            }
+        }
+        Ok(Self { X_tilde, Y_tilde })
+    }
+
+    /// Precompute X_tilde.Y_tilde_i^m_i for all (i, m_i) in `fixed`. When verifying many signatures
+    /// sharing these messages, the result is passed to `Signature::verify_with_partial_kappa`
+    /// which only needs to add the terms for the varying messages.
+    pub fn partial_kappa(
+        &self,
+        fixed: &[(usize, FieldElement)],
+        params: &Params,
+    ) -> Result<OtherGroup, CoconutError> {
+        Ok(&self.X_tilde + &self.kappa_terms(fixed, params)?)
+    }
+
+    /// Y_tilde_i^m_i for all (i, m_i) in `messages`. Errors with `InvalidAttributeIndex` if an
+    /// index has no Y_tilde and with `TypeAttributeMismatch` if the type attribute of typed
+    /// `params` is among the messages with a value other than the tag.
+    pub(crate) fn kappa_terms(
+        &self,
+        messages: &[(usize, FieldElement)],
+        params: &Params,
+    ) -> Result<OtherGroup, CoconutError> {
+        if messages.is_empty() {
+            return Ok(OtherGroup::identity());
+        }
+        let mut bases = OtherGroupVec::with_capacity(messages.len());
+        let mut exps = FieldElementVector::with_capacity(messages.len());
+        for (i, m) in messages {
+            if *i >= self.Y_tilde.len() {
+                return Err(CoconutErrorKind::InvalidAttributeIndex {
+                    idx: *i,
+                    msg_count: self.Y_tilde.len(),
+                }
+                .into());
+            }
+            if params.is_wrong_type_attribute(*i, m) {
+                return Err(CoconutErrorKind::TypeAttributeMismatch { idx: *i }.into());
+            }
+            bases.push(self.Y_tilde[*i].clone());
+            exps.push(m.clone());
+        }
+        Ok(bases.multi_scalar_mul_var_time(&exps)?)
+    }
+
+    /// Byte representation is X_tilde || Y_tilde_1 || Y_tilde_2 || ... Y_tilde_n
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.X_tilde.to_bytes();
+        for y in &self.Y_tilde {
+            bytes.append(&mut y.to_bytes());
+        }
+        bytes
+    }
+
+    /// Identifier for looking up the verkey, the SHA-256 hash of
+    /// `VERKEY_FINGERPRINT_TAG || to_bytes()`. Collision resistant so a verifier holding only the
+    /// fingerprint of the expected verkey can't be given another verkey with the same fingerprint.
+    pub fn fingerprint(&self) -> [u8; VERKEY_FINGERPRINT_SIZE] {
+        let mut bytes = VERKEY_FINGERPRINT_TAG.to_vec();
+        bytes.append(&mut self.to_bytes());
+        let mut fingerprint = [0u8; VERKEY_FINGERPRINT_SIZE];
+        fingerprint.copy_from_slice(&Sha256::digest(&bytes));
+        fingerprint
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CoconutError> {
+        if bytes.len() < OTHER_GROUP_SIZE || bytes.len() % OTHER_GROUP_SIZE != 0 {
+            return Err(CoconutErrorKind::InvalidByteLength {
+                expected: OTHER_GROUP_SIZE
+                    + (bytes.len().saturating_sub(OTHER_GROUP_SIZE) / OTHER_GROUP_SIZE)
+                        * OTHER_GROUP_SIZE,
+                given: bytes.len(),
+            }
+            .into());
+        }
+        let X_tilde = group_elem_from_bytes::<OtherGroup>(&bytes[0..OTHER_GROUP_SIZE])?;
+        let mut Y_tilde = Vec::with_capacity(bytes.len() / OTHER_GROUP_SIZE - 1);
+        for chunk in bytes[OTHER_GROUP_SIZE..].chunks(OTHER_GROUP_SIZE) {
+            Y_tilde.push(group_elem_from_bytes::<OtherGroup>(chunk)?);
+        }
+        let vk = Self { X_tilde, Y_tilde };
+        vk.validate()?;
+        Ok(vk)
+    }
+
+    /// Check that X_tilde and each Y_tilde is not the identity and lies in the prime order
+    /// subgroup. A verkey with small order or cofactor points must not be accepted from an issuer.
+    pub fn validate(&self) -> Result<(), CoconutError> {
+        if self.X_tilde.is_identity() || !self.X_tilde.has_correct_order() {
+            return Err(CoconutErrorKind::InvalidPoint {
+                msg: String::from("X_tilde"),
+            }
+            .into());
+        }
+        for (i, y) in self.Y_tilde.iter().enumerate() {
+            if y.is_identity() || !y.has_correct_order() {
+                return Err(CoconutErrorKind::InvalidPoint {
+                    msg: format!("Y_tilde[{}]", i),
+                }
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn to_hex(&self) -> String {
+        bytes_to_hex(&self.to_bytes())
+    }
+
+    pub fn from_hex(hex: &str) -> Result<Self, CoconutError> {
+        Self::from_bytes(&hex_to_bytes(hex)?)
+    }
+
+    /// Write `to_bytes` to `writer` framed by its length, see `write_framed`
+    pub fn write_to<W: Write>(&self, writer: W) -> Result<(), CoconutError> {
+        write_framed(writer, &self.to_bytes())
+    }
+
+    /// Read a verkey written by `write_to`. The verkey is validated as with `from_bytes`.
+    pub fn read_from<R: Read>(reader: R) -> Result<Self, CoconutError> {
+        Self::from_bytes(&read_framed(reader)?)
+    }
+}
+
+/// Keeps Y_tilde_i^m_i for each message of a verkey so that when verifying signatures over
+/// messages that differ from the previous ones in a few attributes, only the terms of the changed
+/// attributes are computed again.
+#[derive(Clone, Debug)]
+pub struct VerkeyEvaluator<'a> {
+    vk: &'a Verkey,
+    /// Index and value of the type attribute of typed params
+    type_attribute: Option<(usize, FieldElement)>,
+    /// Y_tilde_i^m_i for each index i
+    terms: Vec<OtherGroup>,
+    /// X_tilde.Y_tilde_1^m_1.Y_tilde_2^m_2...Y_tilde_n^m_n
+    kappa: OtherGroup,
+}
+
+impl<'a> VerkeyEvaluator<'a> {
+    /// Errors with `TypeAttributeMismatch` if `messages` don't have the type attribute of typed
+    /// `params`.
+    pub fn new(
+        vk: &'a Verkey,
+        messages: &[FieldElement],
+        params: &Params,
+    ) -> Result<Self, CoconutError> {
+        if messages.len() != vk.Y_tilde.len() {
+            return Err(CoconutErrorKind::UnsupportedNoOfMessages {
+                expected: vk.Y_tilde.len(),
+                given: messages.len(),
+            }
+            .into());
+        }
+        if !params.has_type_attribute(messages) {
+            return Err(CoconutErrorKind::TypeAttributeMismatch {
+                idx: params.msg_count() - 1,
+            }
+            .into());
+        }
+        let terms = vk
+            .Y_tilde
+            .iter()
+            .zip(messages)
+            .map(|(y, m)| y * m)
+            .collect::<Vec<OtherGroup>>();
+        let mut kappa = vk.X_tilde.clone();
+        for t in &terms {
+            kappa = &kappa + t;
+        }
+        Ok(Self {
+            vk,
+            type_attribute: params
+                .type_attribute_index()
+                .map(|i| (i, params.credential_type.clone())),
+            terms,
+            kappa,
+        })
+    }
+
+    /// Replace the message at index `idx` and update kappa by removing the old term and adding the
+    /// new one. The type attribute of typed params can't be changed.
+    pub fn update(&mut self, idx: usize, message: &FieldElement) -> Result<(), CoconutError> {
+        if idx >= self.terms.len() {
+            return Err(CoconutErrorKind::InvalidAttributeIndex {
+                idx,
+                msg_count: self.terms.len(),
+            }
+            .into());
+        }
+        if self
+            .type_attribute
+            .as_ref()
+            .map_or(false, |(i, t)| *i == idx && message != t)
+        {
+            return Err(CoconutErrorKind::TypeAttributeMismatch { idx }.into());
+        }
+        let term = &self.vk.Y_tilde[idx] * message;
+        self.kappa = &(&self.kappa - &self.terms[idx]) + &term;
+        self.terms[idx] = term;
+        Ok(())
+    }
+
+    pub fn kappa(&self) -> &OtherGroup {
+        &self.kappa
+    }
+
+    /// Verify a signature over the current messages
+    pub fn verify(&self, sig: &Signature, params: &Params) -> bool {
+        sig.pairing_check(&self.kappa, params)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keygen::{
+        keygen_with_seeded_y, setup_signers_for_test, trusted_party_PVSS_keygen,
+        trusted_party_SSS_keygen, trusted_party_SSS_keygen_weighted,
+        trusted_party_SSS_keygen_with_rng, Signer,
+    };
+    use crate::pok_sig::CredentialProofBuilder;
+    use crate::test_utils::{sign_request, signers_with_verkey};
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+    use secret_sharing::pedersen_vss::PedersenVSS;
+    use secret_sharing::shamir_secret_sharing::get_shared_secret;
+    use std::collections::HashMap;
+    use std::io::Cursor;
+
+    fn check_key_aggregation(
+        threshold: usize,
+        msg_count: usize,
+        secret_x: FieldElement,
+        secret_y: FieldElementVector,
+        signers: &[Signer],
+        params: &Params,
+    ) {
+        let aggr_vk = Verkey::aggregate(
+            threshold,
+            signers
+                .iter()
+                .take(threshold)
+                .map(|s| (s.id, &s.verkey))
+                .collect::<Vec<(usize, &Verkey)>>(),
+        )
+        .unwrap();
+
+        let expected_X_tilde = &params.g_tilde * &secret_x;
+        assert_eq!(expected_X_tilde, aggr_vk.X_tilde);
+
+        for i in 0..msg_count {
+            let expected_Y_tilde_i = &params.g_tilde * &secret_y[i];
+            assert_eq!(expected_Y_tilde_i, aggr_vk.Y_tilde[i]);
+        }
+    }
+
+    fn check_key_aggregation_gaps_in_ids(
+        threshold: usize,
+        msg_count: usize,
+        secret_x: FieldElement,
+        secret_y: FieldElementVector,
+        keys_to_aggr: Vec<(usize, &Verkey)>,
+        params: &Params,
+    ) {
+        let aggr_vk = Verkey::aggregate(threshold, keys_to_aggr).unwrap();
+
+        let expected_X_tilde = &params.g_tilde * &secret_x;
+        assert_eq!(expected_X_tilde, aggr_vk.X_tilde);
+
+        for i in 0..msg_count {
+            let expected_Y_tilde_i = &params.g_tilde * &secret_y[i];
+            assert_eq!(expected_Y_tilde_i, aggr_vk.Y_tilde[i]);
+        }
+    }
+
+    fn check_signing_on_random_msgs(
+        threshold: usize,
+        msg_count: usize,
+        count_hidden: usize,
+        signers: &[Signer],
+        params: &Params,
+    ) {
+        let msgs = FieldElementVector::random(msg_count);
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+
+        let (sig_req, randomness) =
+            SignatureRequest::new(&msgs, count_hidden, &elg_pk, &params).unwrap();
+
+        // Initiate proof of knowledge of various items of Signature request
+        let sig_req_pok = SignatureRequestPoK::init(&sig_req, &elg_pk, &params);
+
+        // The challenge can include other things also (if proving other predicates)
+        let challenge = FieldElement::from_msg_hash(&sig_req_pok.to_bytes());
+
+        // Create proof once the challenge is finalized
+        let hidden_msgs: FieldElementVector = msgs
+            .iter()
+            .take(count_hidden)
+            .map(|m| m.clone())
+            .collect::<Vec<FieldElement>>()
+            .into();
+        let sig_req_proof = sig_req_pok
+            .gen_proof(&hidden_msgs, randomness, &elg_sk, &challenge)
+            .unwrap();
+
+        let mut blinded_sigs = vec![];
+        for i in 0..threshold {
+            // Each signer verifier proof of knowledge of items of signature request before signing
+            assert!(sig_req_proof
+                .verify(&sig_req, &elg_pk, &challenge, &params)
+                .unwrap());
+            blinded_sigs.push(BlindSignature::new(&sig_req, &signers[i].sigkey).unwrap());
+        }
+
+        let mut unblinded_sigs = vec![];
+        for i in 0..threshold {
+            let unblinded_sig = blinded_sigs.remove(0).unblind(&elg_sk);
+            assert!(unblinded_sig
+                .verify(msgs.as_slice(), &signers[i].verkey, &params)
+                .unwrap());
+            unblinded_sigs.push((signers[i].id, unblinded_sig));
+        }
+
+        let aggr_sig = Signature::aggregate(threshold, unblinded_sigs).unwrap();
+
+        let aggr_vk = Verkey::aggregate(
+            threshold,
+            signers
+                .iter()
+                .map(|s| (s.id, &s.verkey))
+                .collect::<Vec<(usize, &Verkey)>>(),
+        )
+        .unwrap();
+
+        assert!(aggr_sig.verify(msgs.as_slice(), &aggr_vk, &params).unwrap());
+    }
+
+    #[test]
+    fn test_verkey_aggregation_shamir_secret_sharing_keygen() {
+        let threshold = 3;
+        let total = 5;
+        let msg_count = 7;
+        let params = Params::new(msg_count, "test".as_bytes());
+
+        let (secret_x, secret_y, signers) = trusted_party_SSS_keygen(threshold, total, &params);
+
+        check_key_aggregation(threshold, msg_count, secret_x, secret_y, &signers, &params)
+    }
+
+    #[test]
+    fn test_verkey_aggregation_verifiable_secret_sharing_keygen() {
+        let threshold = 3;
+        let total = 5;
+        let msg_count = 7;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (g, h) = PedersenVSS::gens("testPVSS".as_bytes());
+
+        let keygen_res = trusted_party_PVSS_keygen(threshold, total, &params, &g, &h);
+        let secret_x = keygen_res.0;
+        let secret_y = keygen_res.1;
+        let signers = keygen_res.2;
+
+        check_key_aggregation(threshold, msg_count, secret_x, secret_y, &signers, &params)
+    }
+
+    #[test]
+    fn test_project_verkey() {
+        let threshold = 2;
+        let msg_count = 3;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (signers, vk) = signers_with_verkey(threshold, 3, &params);
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        let issue = |msgs: &FieldElementVector| {
+            let (sig_req, _) = SignatureRequest::new(msgs, 1, &elg_pk, &params).unwrap();
+            sign_request(&sig_req, &elg_sk, &signers, threshold, &params)
+        };
+
+        // Attribute 1 is unused so the signature is restricted to attributes 0 and 2
+        let mut msgs = FieldElementVector::random(msg_count);
+        msgs[1] = FieldElement::zero();
+        let sig = issue(&msgs);
+        let projected = vk.project(&[0, 2]).unwrap();
+        assert_eq!(projected.X_tilde, vk.X_tilde);
+        assert_eq!(
+            projected.Y_tilde,
+            vec![vk.Y_tilde[0].clone(), vk.Y_tilde[2].clone()]
+        );
+        let restricted = vec![msgs[0].clone(), msgs[2].clone()];
+        assert!(sig.verify(&restricted, &projected, &params).unwrap());
+        assert!(!sig
+            .verify(&[msgs[2].clone(), msgs[0].clone()], &projected, &params)
+            .unwrap());
+        assert!(!sig
+            .verify(
+                &[msgs[0].clone(), FieldElement::random()],
+                &projected,
+                &params
+            )
+            .unwrap());
+        // Ordering follows the given indices
+        let reversed = vk.project(&[2, 0]).unwrap();
+        assert!(sig
+            .verify(&[msgs[2].clone(), msgs[0].clone()], &reversed, &params)
+            .unwrap());
+
+        // A signature with a non-zero dropped attribute doesn't verify
+        let other_msgs = FieldElementVector::random(msg_count);
+        let other = issue(&other_msgs);
+        assert!(!other
+            .verify(
+                &[other_msgs[0].clone(), other_msgs[2].clone()],
+                &projected,
+                &params
+            )
+            .unwrap());
+
+        assert_eq!(vk.project(&[0, 1, 2]).unwrap(), vk);
+        match vk.project(&[0, msg_count]).unwrap_err().kind() {
+            CoconutErrorKind::InvalidAttributeIndex { idx, .. } => assert_eq!(idx, msg_count),
+            k => panic!("unexpected error kind {:?}", k),
+        }
+        assert!(vk.project(&[2, 2]).is_err());
+    }
+
+    #[test]
+    fn test_verkey_fingerprint() {
+        let params = Params::new(3, "test".as_bytes());
+        let (_, _, signers) = trusted_party_SSS_keygen(2, 3, &params);
+        let vk = &signers[0].verkey;
+        let mut bytes = VERKEY_FINGERPRINT_TAG.to_vec();
+        bytes.append(&mut vk.to_bytes());
+        assert_eq!(vk.fingerprint().to_vec(), Sha256::digest(&bytes).to_vec());
+        assert_eq!(vk.fingerprint(), vk.clone().fingerprint());
+        assert_ne!(vk.fingerprint(), signers[1].verkey.fingerprint());
+    }
+
+    #[test]
+    fn test_eq_and_hash() {
+        let threshold = 2;
+        let msg_count = 3;
+        let params = Params::new(msg_count, "test".as_bytes());
+        assert_eq!(params, Params::new(msg_count, "test".as_bytes()));
+        assert_ne!(params, Params::new(msg_count, "other".as_bytes()));
+        assert_ne!(params, Params::new(msg_count + 1, "test".as_bytes()));
+        assert_ne!(
+            params,
+            Params::new_typed(msg_count, "test".as_bytes(), "typed".as_bytes())
+        );
+
+        let (_, _, signers) = trusted_party_SSS_keygen(threshold, 3, &params);
+        let vk = Verkey::from_bytes(&signers[0].verkey.to_bytes()).unwrap();
+        assert_eq!(vk, signers[0].verkey);
+        assert_ne!(vk, signers[1].verkey);
+
+        // Verkeys as keys of a map
+        let mut ids = HashMap::new();
+        for s in &signers {
+            ids.insert(s.verkey.clone(), s.id);
+        }
+        assert_eq!(ids.len(), signers.len());
+        for s in &signers {
+            assert_eq!(ids[&s.verkey], s.id);
+        }
+        assert_eq!(ids.get(&vk), Some(&signers[0].id));
+
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        let msgs = FieldElementVector::random(msg_count);
+        let sign = || {
+            let (sig_req, _) = SignatureRequest::new(&msgs, 1, &elg_pk, &params).unwrap();
+            BlindSignature::new(&sig_req, &signers[0].sigkey)
+                .unwrap()
+                .unblind(&elg_sk)
+        };
+        let sig = sign();
+        assert_eq!(sig, Signature::from_bytes(&sig.to_bytes()).unwrap());
+        // Each request has a new h so the signatures differ
+        let other = sign();
+        assert_ne!(sig, other);
+        let mut same_sigma_1 = sig.clone();
+        same_sigma_1.sigma_2 = other.sigma_2.clone();
+        assert_ne!(sig, same_sigma_1);
+        let mut sigs = HashSet::new();
+        sigs.insert(sig.clone());
+        sigs.insert(sig.clone());
+        sigs.insert(other);
+        assert_eq!(sigs.len(), 2);
+    }
+
+    #[test]
+    fn test_from_ps_sig() {
+        let msg_count = 4;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let ps_params = transform_to_PS_params(&params);
+        let (ps_sk, ps_vk) = ps_sig::keys::keygen(msg_count, &ps_params);
+        let msgs = FieldElementVector::random(msg_count);
+        let ps_sig = PSSignature::new(msgs.as_slice(), &ps_sk, &ps_params).unwrap();
+
+        let vk = Verkey::try_from(ps_vk.clone()).unwrap();
+        let sig = Signature::try_from(ps_sig.clone()).unwrap();
+        assert!(sig.verify(msgs.as_slice(), &vk, &params).unwrap());
+        assert!(!sig
+            .verify(
+                FieldElementVector::random(msg_count).as_slice(),
+                &vk,
+                &params
+            )
+            .unwrap());
+
+        // The messages have no type attribute so the ps_sig signature doesn't verify as typed
+        let typed = Params::new_typed(msg_count, "test".as_bytes(), "typed".as_bytes());
+        assert!(!sig.verify(msgs.as_slice(), &vk, &typed).unwrap());
+
+        // Back to ps_sig
+        assert_eq!(transform_to_PS_sig(&sig).sigma_1, ps_sig.sigma_1);
+        assert_eq!(transform_to_PS_verkey(&vk).Y_tilde, ps_vk.Y_tilde);
+        let ps_vk_back = PSVerkey::from(vk);
+        assert_eq!(ps_vk_back.X_tilde, ps_vk.X_tilde);
+        assert_eq!(ps_vk_back.Y_tilde, ps_vk.Y_tilde);
+        let ps_sig_back = PSSignature::from(sig);
+        assert_eq!(ps_sig_back.sigma_1, ps_sig.sigma_1);
+        assert_eq!(ps_sig_back.sigma_2, ps_sig.sigma_2);
+        assert!(
+            PSSignature::verify(&ps_sig_back, msgs.as_slice(), &ps_vk_back, &ps_params).unwrap()
+        );
+
+        // Identity or points outside the prime order subgroup are rejected
+        let expect_invalid_point = |res: Result<(), CoconutError>| match res.unwrap_err().kind() {
+            CoconutErrorKind::InvalidPoint { .. } => (),
+            k => panic!("unexpected error kind {:?}", k),
+        };
+        let mut identity_sig = ps_sig.clone();
+        identity_sig.sigma_1 = SignatureGroup::identity();
+        expect_invalid_point(Signature::try_from(identity_sig).map(|_| ()));
+        let mut wrong_order_sig = ps_sig.clone();
+        wrong_order_sig.sigma_2 =
+            &wrong_order_sig.sigma_2 + &signature_group_point_outside_subgroup();
+        expect_invalid_point(Signature::try_from(wrong_order_sig).map(|_| ()));
+        let mut identity_vk = ps_vk.clone();
+        identity_vk.Y_tilde[2] = OtherGroup::identity();
+        expect_invalid_point(Verkey::try_from(identity_vk).map(|_| ()));
+    }
+
+    #[test]
+    fn test_sign_verify_shamir_secret_sharing_keygen() {
+        let threshold = 3;
+        let total = 5;
+        let msg_count = 6;
+        let count_hidden = 2;
+        let params = Params::new(msg_count, "test".as_bytes());
+
+        let (_, _, signers) = trusted_party_SSS_keygen(threshold, total, &params);
+
+        check_signing_on_random_msgs(threshold, msg_count, count_hidden, &signers, &params)
+    }
+
+    #[test]
+    fn test_sign_verify_verifiable_secret_sharing_keygen() {
+        let threshold = 3;
+        let total = 5;
+        let msg_count = 6;
+        let count_hidden = 2;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (g, h) = PedersenVSS::gens("testPVSS".as_bytes());
+
+        let keygen_res = trusted_party_PVSS_keygen(threshold, total, &params, &g, &h);
+        let signers = keygen_res.2;
+
+        check_signing_on_random_msgs(threshold, msg_count, count_hidden, &signers, &params)
+    }
+
+    #[test]
+    fn test_sign_verify_decentralized_verifiable_secret_sharing_keygen() {
+        let threshold = 3;
+        let total = 5;
+        let msg_count = 6;
+        let count_hidden = 2;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (g, h) = PedersenVSS::gens("testPVSS".as_bytes());
+
+        let (_, _, signers) = setup_signers_for_test(threshold, total, &params, &g, &h);
+
+        check_signing_on_random_msgs(threshold, msg_count, count_hidden, &signers, &params)
+    }
+
+    #[test]
+    fn test_verkey_aggregation_gaps_in_ids_shamir_secret_sharing_keygen() {
+        let threshold = 3;
+        let total = 5;
+        let msg_count = 7;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (secret_x, secret_y, signers) = trusted_party_SSS_keygen(threshold, total, &params);
+
+        let mut keys_to_aggr = vec![];
+        keys_to_aggr.push((signers[0].id, &signers[0].verkey));
+        keys_to_aggr.push((signers[2].id, &signers[2].verkey));
+        keys_to_aggr.push((signers[4].id, &signers[4].verkey));
+
+        check_key_aggregation_gaps_in_ids(
+            threshold,
+            msg_count,
+            secret_x,
+            secret_y,
+            keys_to_aggr,
+            &params,
+        );
+    }
+
+    #[test]
+    fn test_verkey_aggregation_gaps_in_ids_verifiable_secret_sharing_keygen() {
+        let threshold = 3;
+        let total = 5;
+        let msg_count = 7;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (g, h) = PedersenVSS::gens("testPVSS".as_bytes());
+
+        let keygen_res = trusted_party_PVSS_keygen(threshold, total, &params, &g, &h);
+        let secret_x = keygen_res.0;
+        let secret_y = keygen_res.1;
+        let signers = keygen_res.2;
+
+        let mut keys_to_aggr = vec![];
+        keys_to_aggr.push((signers[0].id, &signers[0].verkey));
+        keys_to_aggr.push((signers[2].id, &signers[2].verkey));
+        keys_to_aggr.push((signers[4].id, &signers[4].verkey));
+
+        check_key_aggregation_gaps_in_ids(
+            threshold,
+            msg_count,
+            secret_x,
+            secret_y,
+            keys_to_aggr,
+            &params,
+        );
+    }
+
+    #[test]
+    fn test_verkey_aggregation_more_than_threshold() {
+        // All 4 of the given verkeys are used, not just the first `threshold`
+        let threshold = 3;
+        let total = 5;
+        let msg_count = 4;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (secret_x, secret_y, signers) = trusted_party_SSS_keygen(threshold, total, &params);
+
+        let keys_to_aggr = signers
+            .iter()
+            .skip(1)
+            .map(|s| (s.id, &s.verkey))
+            .collect::<Vec<(usize, &Verkey)>>();
+        assert_eq!(keys_to_aggr.len(), 4);
+        check_key_aggregation_gaps_in_ids(
+            threshold,
+            msg_count,
+            secret_x,
+            secret_y,
+            keys_to_aggr.clone(),
+            &params,
+        );
+
+        // Signature aggregated from a different threshold subset verifies with it
+        let msgs = FieldElementVector::random(msg_count);
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        let (sig_req, _) = SignatureRequest::new(&msgs, 1, &elg_pk, &params).unwrap();
+        let sigs = signers
+            .iter()
+            .take(threshold)
+            .map(|s| {
+                (
+                    s.id,
+                    BlindSignature::new(&sig_req, &s.sigkey)
+                        .unwrap()
+                        .unblind(&elg_sk),
+                )
+            })
+            .collect::<Vec<(usize, Signature)>>();
+        let sig = Signature::aggregate(threshold, sigs).unwrap();
+        let aggr_vk = Verkey::aggregate(threshold, keys_to_aggr).unwrap();
+        assert!(sig.verify(msgs.as_slice(), &aggr_vk, &params).unwrap());
+
+        // Same with a single message
+        let params = Params::new(1, "test".as_bytes());
+        let (secret_x, secret_y, signers) = trusted_party_SSS_keygen(threshold, total, &params);
+        check_key_aggregation_gaps_in_ids(
+            threshold,
+            1,
+            secret_x,
+            secret_y,
+            signers
+                .iter()
+                .skip(1)
+                .map(|s| (s.id, &s.verkey))
+                .collect::<Vec<(usize, &Verkey)>>(),
+            &params,
+        );
+    }
+
+    #[test]
+    fn test_sign_verify_1() {
+        // Request signature from 1 threshold group of signers and form aggregate verkey from
+        // different threshold group of signers.
+        let threshold = 3;
+        let total = 6;
+        let msg_count = 6;
+        let count_hidden = 2;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (_, _, signers) = trusted_party_SSS_keygen(threshold, total, &params);
+
+        let msgs = FieldElementVector::random(msg_count);
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+
+        let (sig_req, randomness) =
+            SignatureRequest::new(&msgs, count_hidden, &elg_pk, &params).unwrap();
+
+        // Signers from which signature will be requested.
+        let mut signer_ids = HashSet::new();
+        signer_ids.insert(1);
+        signer_ids.insert(3);
+        signer_ids.insert(5);
+
+        let sig_req_pok = SignatureRequestPoK::init(&sig_req, &elg_pk, &params);
+        let challenge = FieldElement::from_msg_hash(&sig_req_pok.to_bytes());
+        let hidden_msgs: FieldElementVector = msgs
+            .iter()
+            .take(count_hidden)
+            .map(|m| m.clone())
+            .collect::<Vec<FieldElement>>()
+            .into();
+        let sig_req_proof = sig_req_pok
+            .gen_proof(&hidden_msgs, randomness, &elg_sk, &challenge)
+            .unwrap();
+
+        let mut blinded_sigs = vec![];
+        for i in &signer_ids {
+            assert!(sig_req_proof
+                .verify(&sig_req, &elg_pk, &challenge, &params)
+                .unwrap());
+            // Keys at index i have id i+1
+            blinded_sigs.push(BlindSignature::new(&sig_req, &signers[*i - 1].sigkey).unwrap());
+        }
+
+        let mut unblinded_sigs = vec![];
+        for i in &signer_ids {
+            let unblinded_sig = blinded_sigs.remove(0).unblind(&elg_sk);
+            // Keys at index i have id i+1
+            assert!(unblinded_sig
+                .verify(msgs.as_slice(), &signers[*i - 1].verkey, &params)
+                .unwrap());
+            unblinded_sigs.push((signers[*i - 1].id, unblinded_sig));
+        }
+
+        let aggr_sig = Signature::aggregate(threshold, unblinded_sigs).unwrap();
+
+        let mut keys_to_aggr = vec![];
+        keys_to_aggr.push((signers[1].id, &signers[1].verkey));     // signer id is 2
+        keys_to_aggr.push((signers[3].id, &signers[3].verkey));     // signer id is 4
+        keys_to_aggr.push((signers[5].id, &signers[5].verkey));     // signer id is 6
+
+        let aggr_vk = Verkey::aggregate(threshold, keys_to_aggr).unwrap();
+
+        assert!(aggr_sig.verify(msgs.as_slice(), &aggr_vk, &params).unwrap());
+    }
+
+    #[test]
+    fn test_hex_conversion() {
+        let threshold = 3;
+        let total = 5;
+        let msg_count = 4;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (_, _, signers) = trusted_party_SSS_keygen(threshold, total, &params);
+
+        let msgs = FieldElementVector::random(msg_count);
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        let (sig_req, _) = SignatureRequest::new(&msgs, 0, &elg_pk, &params).unwrap();
+        let sig = BlindSignature::new(&sig_req, &signers[0].sigkey).unwrap().unblind(&elg_sk);
+        let vk = &signers[0].verkey;
+
+        let params_1 = Params::from_hex(&params.to_hex()).unwrap();
+        assert_eq!(params_1.g, params.g);
+        assert_eq!(params_1.g_tilde, params.g_tilde);
+        assert_eq!(params_1.h.as_slice(), params.h.as_slice());
+
+        let vk_1 = Verkey::from_hex(&vk.to_hex()).unwrap();
+        assert_eq!(vk_1.X_tilde, vk.X_tilde);
+        assert_eq!(vk_1.Y_tilde, vk.Y_tilde);
+
+        let sig_1 = Signature::from_hex(&sig.to_hex()).unwrap();
+        assert_eq!(sig_1.sigma_1, sig.sigma_1);
+        assert_eq!(sig_1.sigma_2, sig.sigma_2);
+
+        assert!(sig_1.verify(msgs.as_slice(), &vk_1, &params_1).unwrap());
+    }
+
+    #[test]
+    fn test_read_write_keys() {
+        let msg_count = 3;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (_, _, signers) = trusted_party_SSS_keygen(2, 3, &params);
+        let vk = &signers[0].verkey;
+        let sk = &signers[0].sigkey;
+
+        // All written one after the other and read back in order
+        let mut cursor = Cursor::new(Vec::new());
+        params.write_to(&mut cursor).unwrap();
+        vk.write_to(&mut cursor).unwrap();
+        sk.write_to(&mut cursor).unwrap();
+        let written = cursor.into_inner();
+
+        let mut cursor = Cursor::new(written.clone());
+        let params_1 = Params::read_from(&mut cursor).unwrap();
+        let vk_1 = Verkey::read_from(&mut cursor).unwrap();
+        let sk_1 = Sigkey::read_from(&mut cursor).unwrap();
+        assert_eq!(params_1.to_bytes(), params.to_bytes());
+        assert_eq!(vk_1.to_bytes(), vk.to_bytes());
+        assert_eq!(sk_1.to_bytes(), sk.to_bytes());
+
+        let msgs = FieldElementVector::random(msg_count);
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        let (sig_req, _) = SignatureRequest::new(&msgs, 1, &elg_pk, &params_1).unwrap();
+        let sig = BlindSignature::new(&sig_req, &sk_1)
+            .unwrap()
+            .unblind(&elg_sk);
+        assert!(sig.verify(msgs.as_slice(), &vk_1, &params_1).unwrap());
+
+        // Nothing left to read
+        match Verkey::read_from(&mut cursor) {
+            Err(e) => match e.kind() {
+                CoconutErrorKind::InvalidByteLength { expected, given } => {
+                    assert_eq!(expected, 4);
+                    assert_eq!(given, 0);
+                }
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(_) => panic!("read a verkey past the end of input"),
+        }
+
+        // Input ends within the frame
+        let truncated = &written[0..written.len() - 1];
+        let mut cursor = Cursor::new(truncated);
+        Params::read_from(&mut cursor).unwrap();
+        Verkey::read_from(&mut cursor).unwrap();
+        match Sigkey::read_from(&mut cursor) {
+            Err(e) => match e.kind() {
+                CoconutErrorKind::InvalidByteLength { expected, given } => {
+                    assert_eq!(expected, (msg_count + 1) * FIELD_ELEMENT_SIZE);
+                    assert_eq!(given, expected - 1);
+                }
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(_) => panic!("read a truncated signing key"),
+        }
+
+        // Failing reader
+        struct FailingReader;
+        impl Read for FailingReader {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::new(
+                    std::io::ErrorKind::Other,
+                    "disk failure",
+                ))
+            }
+        }
+        match Verkey::read_from(FailingReader) {
+            Err(e) => match e.kind() {
+                CoconutErrorKind::IOError { msg } => assert_eq!(msg, "disk failure"),
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(_) => panic!("read a verkey from a failing reader"),
+        }
+    }
+
+    #[test]
+    fn test_invalid_hex() {
+        let params = Params::new(2, "test".as_bytes());
+        let mut hex = params.to_hex();
+
+        // Odd length
+        hex.pop();
+        match Params::from_hex(&hex) {
+            Err(e) => match e.kind() {
+                CoconutErrorKind::InvalidHex { .. } => (),
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(_) => panic!("odd length hex should not parse"),
+        }
+
+        // Non-hex character
+        for s in &["zz", "0g", "é0"] {
+            for res in vec![
+                Verkey::from_hex(s).map(|_| ()),
+                Signature::from_hex(s).map(|_| ()),
+                Params::from_hex(s).map(|_| ()),
+            ] {
+                match res {
+                    Err(e) => match e.kind() {
+                        CoconutErrorKind::InvalidHex { .. } => (),
+                        k => panic!("unexpected error kind {:?}", k),
+                    },
+                    Ok(_) => panic!("non-hex string should not parse"),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_typed_credentials() {
+        // Signature issued for one credential type does not verify as another type under same verkey
+        let threshold = 3;
+        let total = 5;
+        let msg_count = 4;
+        let count_hidden = 1;
+        let params_untyped = Params::new(msg_count + 1, "test".as_bytes());
+        let params_a = Params::new_typed(msg_count, "test".as_bytes(), "passport".as_bytes());
+        let params_b = Params::new_typed(msg_count, "test".as_bytes(), "membership".as_bytes());
+        assert!(!params_untyped.is_typed());
+        assert!(params_a.is_typed());
+        assert_eq!(params_a.g_tilde, params_b.g_tilde);
+        assert_ne!(params_a.h[0], params_b.h[0]);
+        assert_eq!(params_a.msg_count(), msg_count + 1);
+        assert_eq!(params_a.attribute_count(), msg_count);
+        assert_eq!(params_a.type_attribute_index(), Some(msg_count));
+        assert_eq!(params_untyped.type_attribute_index(), None);
+        assert_eq!(
+            Params::from_bytes(&params_a.to_bytes())
+                .unwrap()
+                .credential_type,
+            params_a.credential_type
+        );
+
+        // Same keys for all credential types
+        let (_, _, signers) = trusted_party_SSS_keygen(threshold, total, &params_untyped);
+
+        let attributes = FieldElementVector::random(msg_count);
+        let msgs = params_a.typed_messages(attributes.as_slice());
+        assert_eq!(msgs[msg_count], params_a.credential_type);
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params_a.g);
+        let (sig_req, _) = SignatureRequest::new(&msgs, count_hidden, &elg_pk, &params_a).unwrap();
+
+        let mut unblinded_sigs = vec![];
+        for i in 0..threshold {
+            let blinded_sig =
+                BlindSignature::new_typed(&sig_req, &signers[i].sigkey, &params_a).unwrap();
+            assert!(blinded_sig.verify_against_verkey(&sig_req, &signers[i].verkey, &params_a));
+            assert!(!blinded_sig.verify_against_verkey(&sig_req, &signers[i].verkey, &params_b));
+            let unblinded_sig = blinded_sig.unblind(&elg_sk);
+            assert!(unblinded_sig
+                .verify(msgs.as_slice(), &signers[i].verkey, &params_a)
+                .unwrap());
+            assert!(!unblinded_sig
+                .verify(msgs.as_slice(), &signers[i].verkey, &params_b)
+                .unwrap());
+            unblinded_sigs.push((signers[i].id, unblinded_sig));
+        }
+        let aggr_sig = Signature::aggregate(threshold, unblinded_sigs).unwrap();
+        let aggr_vk = Verkey::aggregate(
+            threshold,
+            signers
+                .iter()
+                .map(|s| (s.id, &s.verkey))
+                .collect::<Vec<(usize, &Verkey)>>(),
+        )
+        .unwrap();
+        assert!(aggr_sig
+            .verify(msgs.as_slice(), &aggr_vk, &params_a)
+            .unwrap());
+        assert!(!aggr_sig
+            .verify(msgs.as_slice(), &aggr_vk, &params_b)
+            .unwrap());
+        // Untyped params see the type attribute as any other message
+        assert!(aggr_sig
+            .verify(msgs.as_slice(), &aggr_vk, &params_untyped)
+            .unwrap());
+
+        // The signer refuses a request whose type attribute is not the type of its params
+        let msgs_b = params_b.typed_messages(attributes.as_slice());
+        let (sig_req_b, _) =
+            SignatureRequest::new(&msgs_b, count_hidden, &elg_pk, &params_a).unwrap();
+        match BlindSignature::new_typed(&sig_req_b, &signers[0].sigkey, &params_a) {
+            Err(e) => match e.kind() {
+                CoconutErrorKind::TypeAttributeMismatch { idx } => assert_eq!(idx, msg_count),
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(_) => panic!("signed a request of another credential type"),
+        }
+        // nor a request hiding the type attribute
+        let (hidden_type_req, _) =
+            SignatureRequest::new(&msgs, msg_count + 1, &elg_pk, &params_a).unwrap();
+        assert!(
+            BlindSignature::new_typed(&hidden_type_req, &signers[0].sigkey, &params_a).is_err()
+        );
+    }
+
+    #[test]
+    fn test_cross_type_forgery() {
+        // With the credential type t added to every message when signing, a type A signature on
+        // m verified as type B on m' = m + t_A - t_B. The type is now a signed attribute so
+        // neither adjusted messages nor a replaced type attribute verify as type B.
+        let msg_count = 3;
+        let params_a = Params::new_typed(msg_count, "test".as_bytes(), "passport".as_bytes());
+        let params_b = Params::new_typed(msg_count, "test".as_bytes(), "membership".as_bytes());
+        let (_, _, signers) = trusted_party_SSS_keygen(2, 3, &params_a);
+        let vk = &signers[0].verkey;
+        let msgs = params_a.typed_messages(FieldElementVector::random(msg_count).as_slice());
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params_a.g);
+        let (sig_req, _) = SignatureRequest::new(&msgs, 1, &elg_pk, &params_a).unwrap();
+        let sig = BlindSignature::new_typed(&sig_req, &signers[0].sigkey, &params_a)
+            .unwrap()
+            .unblind(&elg_sk);
+        assert!(sig.verify(msgs.as_slice(), vk, &params_a).unwrap());
+
+        let shift = &params_a.credential_type - &params_b.credential_type;
+        let shifted = msgs
+            .iter()
+            .map(|m| m + &shift)
+            .collect::<Vec<FieldElement>>();
+        let mut retyped = msgs.clone();
+        retyped[msg_count] = params_b.credential_type.clone();
+        for forged in vec![shifted, retyped.as_slice().to_vec()] {
+            assert!(!sig.verify(&forged, vk, &params_b).unwrap());
+            assert!(sig.verify_any(&forged, &[vk.clone()], &params_b).is_none());
+            assert!(!sig.verify_many_messages(&[forged.clone().into()], vk, &params_b)[0]);
+            assert!(VerkeyEvaluator::new(vk, &forged, &params_b).is_err());
+        }
+        match sig.verify_detailed(retyped.as_slice(), vk, &params_a) {
+            Err(e) => match e.kind() {
+                CoconutErrorKind::TypeAttributeMismatch { idx } => assert_eq!(idx, msg_count),
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(_) => panic!("verified with the type attribute of another type"),
+        }
+        let mut evaluator = VerkeyEvaluator::new(vk, msgs.as_slice(), &params_a).unwrap();
+        assert!(evaluator.verify(&sig, &params_a));
+        assert!(evaluator
+            .update(msg_count, &params_b.credential_type)
+            .is_err());
+
+        // A presentation reveals the type attribute, so one of a type A credential doesn't
+        // verify as type B, even when made under the params of type B
+        let nonce = "verifier nonce".as_bytes();
+        let proof = CredentialProofBuilder::new(&sig, msgs.as_slice(), vk, &params_a)
+            .verifier_nonce(nonce)
+            .finish()
+            .unwrap();
+        assert_eq!(proof.revealed_msgs[&msg_count], params_a.credential_type);
+        assert!(proof.verify(nonce, vk, &params_a).unwrap());
+        assert!(!proof.verify(nonce, vk, &params_b).unwrap());
+        let proof = CredentialProofBuilder::new(&sig, msgs.as_slice(), vk, &params_b)
+            .verifier_nonce(nonce)
+            .finish()
+            .unwrap();
+        assert!(!proof.verify(nonce, vk, &params_b).unwrap());
+        // Stripping the type attribute from the revealed attributes fails too
+        let mut stripped = proof.clone();
+        stripped.revealed_msgs.remove(&msg_count);
+        assert!(!stripped.verify(nonce, vk, &params_b).unwrap());
+    }
+
+    #[test]
+    fn test_challenge_v1() {
+        // Fixed inputs, no randomness involved. Typed params so that the credential type is part
+        // of the transcript.
+        let msg_count = 2;
+        let params = Params::new_typed(msg_count, "test".as_bytes(), "passport".as_bytes());
+        let elgamal_sk = FieldElement::from_msg_hash("elgamal sk".as_bytes());
+        let elgamal_pk = &params.g * &elgamal_sk;
+        let fixed = |s: &str| &params.g * &FieldElement::from_msg_hash(s.as_bytes());
+        let sig_req = SignatureRequest {
+            known_messages: vec![
+                FieldElement::from_msg_hash("m2".as_bytes()),
+                params.credential_type.clone(),
+            ]
+            .into(),
+            commitment: fixed("commitment"),
+            ciphertexts: vec![(fixed("c1"), fixed("c2"))],
+            signer_ciphertexts: BTreeMap::new(),
+        };
+        let pok = {
+            let mut c = ProverCommittingSignatureGroup::new();
+            c.commit(
+                &params.g,
+                Some(&FieldElement::from_msg_hash("b".as_bytes())),
+            );
+            let committed = c.finish();
+            SignatureRequestPoK {
+                pok_vc_elgamal_sk: committed.clone(),
+                pok_vc_commitment: committed.clone(),
+                pok_vc_ciphertext: vec![(committed.clone(), committed)],
+            }
+        };
+        let commitments = pok.commitments();
+        // The Schnorr commitment of each committed state is g^b
+        let t = &params.g * &FieldElement::from_msg_hash("b".as_bytes());
+        assert_eq!(commitments, vec![t; 4]);
+
+        // The layout, with the tag and counts pinned as hex
+        let hex = |h: &str| hex_to_bytes(h).unwrap();
+        let transcript = challenge_v1_transcript(&params, &elgamal_pk, &sig_req, &commitments);
+        let mut expected =
+            hex("636f636f6e75742f7369676e61747572652d726571756573742f6368616c6c656e67652f7631");
+        expected.append(&mut params.g.to_bytes());
+        expected.append(&mut params.g_tilde.to_bytes());
+        expected.append(&mut params.credential_type.to_bytes());
+        expected.append(&mut hex("00000003"));
+        for h in params.h.iter() {
+            expected.append(&mut h.to_bytes());
+        }
+        expected.append(&mut elgamal_pk.to_bytes());
+        expected.append(&mut sig_req.commitment.to_bytes());
+        expected.append(&mut hex("00000001"));
+        expected.append(&mut sig_req.ciphertexts[0].0.to_bytes());
+        expected.append(&mut sig_req.ciphertexts[0].1.to_bytes());
+        expected.append(&mut hex("00000002"));
+        expected.append(&mut sig_req.known_messages[0].to_bytes());
+        expected.append(&mut sig_req.known_messages[1].to_bytes());
+        expected.append(&mut hex("00000004"));
+        let t = &params.g * &FieldElement::from_msg_hash("b".as_bytes());
+        for _ in 0..4 {
+            expected.append(&mut t.to_bytes());
+        }
+        assert_eq!(transcript, expected);
+        // The challenge bytes for a complete request are pinned by the test vectors
+        assert_eq!(
+            challenge_v1(&params, &elgamal_pk, &sig_req, &commitments),
+            FieldElement::from_msg_hash(&expected)
+        );
+
+        // Any change in the inputs changes the challenge, including only the credential type
+        let challenge = challenge_v1(&params, &elgamal_pk, &sig_req, &commitments);
+        let mut other_type = params.clone();
+        other_type.credential_type = FieldElement::from_msg_hash("license".as_bytes());
+        assert_ne!(
+            challenge,
+            challenge_v1(&other_type, &elgamal_pk, &sig_req, &commitments)
+        );
+        assert_ne!(
+            challenge,
+            challenge_v1(&params, &params.g, &sig_req, &commitments)
+        );
+        assert_ne!(
+            challenge,
+            challenge_v1(&params, &elgamal_pk, &sig_req, &commitments[1..])
+        );
+    }
+
+    #[test]
+    fn test_verify_with_partial_kappa() {
+        // Several signatures sharing the first 2 messages
+        let msg_count = 5;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (_, _, signers) = trusted_party_SSS_keygen(3, 5, &params);
+        let vk = &signers[0].verkey;
+
+        let shared = vec![(0, FieldElement::random()), (1, FieldElement::random())];
+        let partial_kappa = vk.partial_kappa(&shared, &params).unwrap();
+
+        for _ in 0..4 {
+            let mut msgs = FieldElementVector::random(msg_count);
+            msgs[0] = shared[0].1.clone();
+            msgs[1] = shared[1].1.clone();
+            let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+            let (sig_req, _) = SignatureRequest::new(&msgs, 1, &elg_pk, &params).unwrap();
+            let sig = BlindSignature::new(&sig_req, &signers[0].sigkey).unwrap().unblind(&elg_sk);
+
+            let varying = (2..msg_count)
+                .map(|i| (i, msgs[i].clone()))
+                .collect::<Vec<(usize, FieldElement)>>();
+            assert!(sig.verify(msgs.as_slice(), vk, &params).unwrap());
+            assert!(sig.verify_with_partial_kappa(&partial_kappa, &varying, vk, &params));
+
+            let mut wrong_varying = varying.clone();
+            wrong_varying[0].1 = FieldElement::random();
+            assert!(!sig.verify_with_partial_kappa(&partial_kappa, &wrong_varying, vk, &params));
+
+            // An index past the messages of the verkey
+            let mut out_of_range = varying.clone();
+            out_of_range[0].0 = msg_count;
+            assert!(!sig.verify_with_partial_kappa(&partial_kappa, &out_of_range, vk, &params));
+        }
+
+        match vk
+            .partial_kappa(&[(msg_count, FieldElement::random())], &params)
+            .unwrap_err()
+            .kind()
+        {
+            CoconutErrorKind::InvalidAttributeIndex { idx, msg_count: n } => {
+                assert_eq!(idx, msg_count);
+                assert_eq!(n, msg_count);
+            }
+            k => panic!("unexpected error kind {:?}", k),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_corrupt_bytes() {
+        let msg_count = 3;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (_, _, signers) = trusted_party_SSS_keygen(3, 5, &params);
+        let msgs = FieldElementVector::random(msg_count);
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        let (sig_req, _) = SignatureRequest::new(&msgs, 0, &elg_pk, &params).unwrap();
+        let sig = BlindSignature::new(&sig_req, &signers[0].sigkey).unwrap().unblind(&elg_sk);
+
+        // Serde round trip
+        let sig_json = serde_json::to_string(&sig).unwrap();
+        let sig_1: Signature = serde_json::from_str(&sig_json).unwrap();
+        assert!(sig_1
+            .verify(msgs.as_slice(), &signers[0].verkey, &params)
+            .unwrap());
+        let vk_json = serde_json::to_string(&signers[0].verkey).unwrap();
+        let vk_1: Verkey = serde_json::from_str(&vk_json).unwrap();
+        assert!(sig_1.verify(msgs.as_slice(), &vk_1, &params).unwrap());
+
+        // Random bytes of correct and incorrect lengths result in errors, not panics
+        for len in vec![
+            2 * SIGNATURE_GROUP_SIZE,
+            2 * SIGNATURE_GROUP_SIZE - 1,
+            10,
+            0,
+        ] {
+            for _ in 0..10 {
+                let bytes = (0..len).map(|_| rand::random::<u8>()).collect::<Vec<u8>>();
+                assert!(Signature::from_bytes(&bytes).is_err());
+                let json = serde_json::to_string(&bytes).unwrap();
+                assert!(serde_json::from_str::<Signature>(&json).is_err());
+            }
+        }
+
+        let bytes = (0..2 * SIGNATURE_GROUP_SIZE)
+            .map(|_| rand::random::<u8>())
+            .collect::<Vec<u8>>();
+        match Signature::from_bytes(&bytes).unwrap_err().kind() {
+            CoconutErrorKind::PointDecodeFailed { .. } => (),
+            k => panic!("unexpected error kind {:?}", k),
+        }
+    }
+
+    #[test]
+    fn test_from_bytes_with_huge_count() {
+        // A count of u32::MAX items is rejected from the remaining length before allocating
+        let huge_count = u32::max_value().to_be_bytes();
+        let expect_invalid_length = |res: Result<(), CoconutError>| match res {
+            Err(e) => match e.kind() {
+                CoconutErrorKind::InvalidByteLength { .. } => (),
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(_) => panic!("parsed bytes with huge count"),
+        };
+
+        // commitment || ciphertext count
+        let mut bytes = SignatureGroup::random().to_bytes();
+        bytes.extend_from_slice(&huge_count);
+        expect_invalid_length(SignatureRequest::from_bytes(&bytes).map(|_| ()));
+
+        // commitment || no ciphertexts || known message count
+        let mut bytes = SignatureGroup::random().to_bytes();
+        append_u32(&mut bytes, 0);
+        bytes.extend_from_slice(&huge_count);
+        bytes.append(&mut FieldElement::random().to_bytes());
+        expect_invalid_length(SignatureRequest::from_bytes(&bytes).map(|_| ()));
+
+        // Response count of the proof of the Elgamal secret key
+        let mut bytes = SignatureGroup::random().to_bytes();
+        bytes.extend_from_slice(&huge_count);
+        expect_invalid_length(SignatureRequestProof::from_bytes(&bytes).map(|_| ()));
+
+        // h || c1 || c2 || count of blinded terms
+        let mut bytes = vec![];
+        for _ in 0..3 {
+            bytes.append(&mut SignatureGroup::random().to_bytes());
+        }
+        bytes.extend_from_slice(&huge_count);
+        expect_invalid_length(BlindSignature::from_bytes(&bytes).map(|_| ()));
+    }
+
+    #[test]
+    fn test_partial_signature_set() {
+        let threshold = 3;
+        let total = 5;
+        let msg_count = 4;
+        let count_hidden = 2;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (signers, vk) = signers_with_verkey(threshold, total, &params);
+        let msgs = FieldElementVector::random(msg_count);
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        let (sig_req, _) = SignatureRequest::new(&msgs, count_hidden, &elg_pk, &params).unwrap();
+
+        // Each signer signs independently and the blind signatures arrive in reverse id order
+        let blind_sigs = signers
+            .iter()
+            .map(|s| (s.id, BlindSignature::new(&sig_req, &s.sigkey).unwrap()))
+            .collect::<Vec<(usize, BlindSignature)>>();
+        let vk_of = |id: usize| &signers.iter().find(|s| s.id == id).unwrap().verkey;
+        let mut set = PartialSignatureSet::new(threshold, &sig_req, &params);
+        assert!(set.is_empty());
+        for (id, blind_sig) in blind_sigs.iter().rev().take(threshold) {
+            assert!(!set.is_complete());
+            set.insert(*id, blind_sig.clone(), vk_of(*id)).unwrap();
+        }
+        assert!(set.is_complete());
+        assert_eq!(set.len(), threshold);
+        assert_eq!(
+            set.signer_ids(),
+            vec![3, 4, 5].into_iter().collect::<BTreeSet<usize>>()
+        );
+
+        // Repeated signer and a blind signature over another request are rejected
+        match set
+            .insert(5, blind_sigs[4].1.clone(), vk_of(5))
+            .unwrap_err()
+            .kind()
+        {
+            CoconutErrorKind::DuplicateSignerId { id } => assert_eq!(id, 5),
+            k => panic!("unexpected error kind {:?}", k),
+        }
+        let (other_req, _) = SignatureRequest::new(&msgs, count_hidden, &elg_pk, &params).unwrap();
+        let other_sig = BlindSignature::new(&other_req, &signers[0].sigkey).unwrap();
+        match set.insert(1, other_sig, vk_of(1)).unwrap_err().kind() {
+            CoconutErrorKind::HMismatch => (),
+            k => panic!("unexpected error kind {:?}", k),
+        }
+        // Blind signature of signer 1 claimed to be of signer 2 or made with a key of no signer
+        match set
+            .insert(2, blind_sigs[0].1.clone(), vk_of(2))
+            .unwrap_err()
+            .kind()
+        {
+            CoconutErrorKind::PairingCheckFailed => (),
+            k => panic!("unexpected error kind {:?}", k),
+        }
+        let (_, _, other_signers) = trusted_party_SSS_keygen(threshold, total, &params);
+        let rogue_sig = BlindSignature::new(&sig_req, &other_signers[1].sigkey).unwrap();
+        match set.insert(2, rogue_sig, vk_of(2)).unwrap_err().kind() {
+            CoconutErrorKind::PairingCheckFailed => (),
+            k => panic!("unexpected error kind {:?}", k),
+        }
+        assert_eq!(set.len(), threshold);
+
+        let sig = set.aggregate(&elg_sk).unwrap();
+        assert!(sig.verify(msgs.as_slice(), &vk, &params).unwrap());
+
+        // Too few blind signatures
+        let mut set = PartialSignatureSet::new(threshold, &sig_req, &params);
+        set.insert(
+            blind_sigs[1].0,
+            blind_sigs[1].1.clone(),
+            vk_of(blind_sigs[1].0),
+        )
+        .unwrap();
+        match set.aggregate(&elg_sk).unwrap_err().kind() {
+            CoconutErrorKind::InsufficientShares { expected, given } => {
+                assert_eq!((expected, given), (threshold, 1))
+            }
+            k => panic!("unexpected error kind {:?}", k),
+        }
+    }
+
+    #[test]
+    fn test_can_aggregate() {
+        let known_ids = [1, 2, 3, 4, 5];
+        can_aggregate(&[5, 1, 3], 3, &known_ids).unwrap();
+        can_aggregate(&known_ids, 3, &known_ids).unwrap();
+
+        match can_aggregate(&[1, 3], 3, &known_ids).unwrap_err().kind() {
+            CoconutErrorKind::InsufficientShares { expected, given } => {
+                assert_eq!((expected, given), (3, 2))
+            }
+            k => panic!("unexpected error kind {:?}", k),
+        }
+        match can_aggregate(&[1, 3, 3], 2, &known_ids).unwrap_err().kind() {
+            CoconutErrorKind::DuplicateSignerId { id } => assert_eq!(id, 3),
+            k => panic!("unexpected error kind {:?}", k),
+        }
+        match can_aggregate(&[1, 6, 3], 3, &known_ids).unwrap_err().kind() {
+            CoconutErrorKind::UnknownSignerId { id } => assert_eq!(id, 6),
+            k => panic!("unexpected error kind {:?}", k),
+        }
+        match can_aggregate(&[0, 1], 3, &known_ids).unwrap_err().kind() {
+            CoconutErrorKind::UnknownSignerId { id } => assert_eq!(id, 0),
+            k => panic!("unexpected error kind {:?}", k),
+        }
+    }
+
+    #[test]
+    fn test_validate_structure() {
+        let msg_count = 4;
+        let count_hidden = 2;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let msgs = FieldElementVector::random(msg_count);
+        let (_, elg_pk) = elgamal_keygen!(&params.g);
+        let (sig_req, _) = SignatureRequest::new(&msgs, count_hidden, &elg_pk, &params).unwrap();
+        sig_req.validate_structure(&params).unwrap();
+        let (all_known, _) = SignatureRequest::new(&msgs, 0, &elg_pk, &params).unwrap();
+        all_known.validate_structure(&params).unwrap();
+        let pks = vec![(1, elg_pk.clone()), (2, elgamal_keygen!(&params.g).1)];
+        let (multi_req, _) =
+            SignatureRequest::new_multi_issuer(&msgs, count_hidden, &pks, &params).unwrap();
+        multi_req.validate_structure(&params).unwrap();
+
+        let expect_err = |req: &SignatureRequest, params: &Params, point: bool| match req
+            .validate_structure(params)
+            .unwrap_err()
+            .kind()
+        {
+            CoconutErrorKind::InvalidPoint { .. } if point => (),
+            CoconutErrorKind::UnsupportedNoOfMessages { .. } if !point => (),
+            k => panic!("unexpected error kind {:?}", k),
+        };
+
+        // Request for other number of messages
+        expect_err(
+            &sig_req,
+            &Params::new(msg_count + 1, "test".as_bytes()),
+            false,
+        );
+        let mut missing_ciphertext = sig_req.clone();
+        missing_ciphertext.ciphertexts.pop();
+        expect_err(&missing_ciphertext, &params, false);
+        let mut too_many_known = sig_req.clone();
+        for _ in 0..3 {
+            too_many_known.known_messages.push(FieldElement::random());
+        }
+        expect_err(&too_many_known, &params, false);
+        let mut missing_signer_ciphertext = multi_req.clone();
+        missing_signer_ciphertext
+            .signer_ciphertexts
+            .get_mut(&2)
+            .unwrap()
+            .pop();
+        expect_err(&missing_signer_ciphertext, &params, false);
+
+        // Identity points
+        let mut identity_commitment = sig_req.clone();
+        identity_commitment.commitment = SignatureGroup::identity();
+        expect_err(&identity_commitment, &params, true);
+        let mut identity_ciphertext = sig_req.clone();
+        identity_ciphertext.ciphertexts[1].0 = SignatureGroup::identity();
+        expect_err(&identity_ciphertext, &params, true);
+        let mut identity_signer_ciphertext = multi_req.clone();
+        identity_signer_ciphertext
+            .signer_ciphertexts
+            .get_mut(&1)
+            .unwrap()[0]
+            .1 = SignatureGroup::identity();
+        expect_err(&identity_signer_ciphertext, &params, true);
+
+        // Both kinds of ciphertexts
+        let mut both = multi_req.clone();
+        both.ciphertexts = sig_req.ciphertexts.clone();
+        match both.validate_structure(&params).unwrap_err().kind() {
+            CoconutErrorKind::GeneralError { .. } => (),
+            k => panic!("unexpected error kind {:?}", k),
+        }
+    }
+
+    #[cfg(feature = "dangerous_reconstruct")]
+    #[test]
+    fn test_sigkey_reconstruction() {
+        let threshold = 3;
+        let total = 5;
+        let msg_count = 4;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (secret_x, secret_y, signers) = trusted_party_SSS_keygen(threshold, total, &params);
+
+        let shares = vec![
+            (signers[4].id, &signers[4].sigkey),
+            (signers[1].id, &signers[1].sigkey),
+            (signers[2].id, &signers[2].sigkey),
+        ];
+        let sigkey = Sigkey::reconstruct(threshold, &shares).unwrap();
+        assert_eq!(sigkey.x, secret_x);
+        assert_eq!(sigkey.y.len(), msg_count);
+        for i in 0..msg_count {
+            assert_eq!(sigkey.y[i], secret_y[i]);
+        }
+
+        // Too few shares
+        match Sigkey::reconstruct(threshold, &shares[0..2])
+            .unwrap_err()
+            .kind()
+        {
+            CoconutErrorKind::InsufficientShares { .. } => (),
+            k => panic!("unexpected error kind {:?}", k),
+        }
+
+        // Duplicate ids
+        let dup = vec![shares[0], shares[1], shares[0]];
+        match Sigkey::reconstruct(threshold, &dup).unwrap_err().kind() {
+            CoconutErrorKind::DuplicateSignerId { .. } => (),
+            k => panic!("unexpected error kind {:?}", k),
+        }
+    }
+
+    #[test]
+    fn test_verify_any() {
+        let threshold = 3;
+        let total = 5;
+        let msg_count = 4;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let mut vks = vec![];
+        let mut sigkeys = vec![];
+        for _ in 0..3 {
+            let (_, _, signers) = trusted_party_SSS_keygen(threshold, total, &params);
+            vks.push(
+                Verkey::aggregate(
+                    threshold,
+                    signers
+                        .iter()
+                        .map(|s| (s.id, &s.verkey))
+                        .collect::<Vec<(usize, &Verkey)>>(),
+                )
+                .unwrap(),
+            );
+            sigkeys.push(signers);
+        }
+
+        // Signed by the 2nd group of signers
+        let msgs = FieldElementVector::random(msg_count);
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        let (sig_req, _) = SignatureRequest::new(&msgs, 2, &elg_pk, &params).unwrap();
+        let sigs = (0..threshold)
+            .map(|i| {
+                (
+                    sigkeys[1][i].id,
+                    BlindSignature::new(&sig_req, &sigkeys[1][i].sigkey).unwrap().unblind(&elg_sk),
+                )
+            })
+            .collect::<Vec<(usize, Signature)>>();
+        let sig = Signature::aggregate(threshold, sigs).unwrap();
+
+        assert_eq!(sig.verify_any(msgs.as_slice(), &vks, &params), Some(1));
+        assert_eq!(sig.verify_any(msgs.as_slice(), &vks[0..1], &params), None);
+        let wrong_msgs = FieldElementVector::random(msg_count);
+        assert_eq!(sig.verify_any(wrong_msgs.as_slice(), &vks, &params), None);
+    }
+
+    #[test]
+    fn test_verkey_validation() {
+        let params = Params::new(3, "test".as_bytes());
+        let (_, _, signers) = trusted_party_SSS_keygen(3, 5, &params);
+        let vk = signers[0].verkey.clone();
+        assert!(vk.validate().is_ok());
+
+        let mut invalid_vk = vk.clone();
+        invalid_vk.Y_tilde[1] = OtherGroup::identity();
+        match invalid_vk.validate().unwrap_err().kind() {
+            CoconutErrorKind::InvalidPoint { .. } => (),
+            k => panic!("unexpected error kind {:?}", k),
+        }
+    }
+
+    #[cfg(feature = "SignatureG2")]
+    #[test]
+    fn test_verkey_from_bytes_with_point_outside_subgroup() {
+        // (4, y) lies on the BLS12-381 G1 curve but not in the prime order subgroup. Encoded as
+        // 0x04 || x || y
+        let x_hex = "04";
+        let y_hex = "0a989badd40d6212b33cffc3f3763e9bc760f988c9926b26da9dd85e928483446346b8ed00e1de5d5ea93e354abe706c";
+        let point_hex = format!("04{:0>96}{}", x_hex, y_hex);
+        let point_bytes = hex_to_bytes(&point_hex).unwrap();
+        assert_eq!(point_bytes.len(), OTHER_GROUP_SIZE);
+
+        let params = Params::new(3, "test".as_bytes());
+        let (_, _, signers) = trusted_party_SSS_keygen(3, 5, &params);
+        let mut bytes = signers[0].verkey.to_bytes();
+        assert!(Verkey::from_bytes(&bytes).is_ok());
+
+        bytes[0..OTHER_GROUP_SIZE].copy_from_slice(&point_bytes);
+        match Verkey::from_bytes(&bytes).unwrap_err().kind() {
+            CoconutErrorKind::InvalidPoint { .. } => (),
+            k => panic!("unexpected error kind {:?}", k),
+        }
+    }
+
+    /// (4, y) lies on the BLS12-381 G1 curve but not in the prime order subgroup. Encoded as
+    /// 0x04 || x || y
+    #[cfg(feature = "SignatureG1")]
+    fn signature_group_point_outside_subgroup() -> SignatureGroup {
+        let y_hex = "0a989badd40d6212b33cffc3f3763e9bc760f988c9926b26da9dd85e928483446346b8ed00e1de5d5ea93e354abe706c";
+        let point_hex = format!("04{:0>96}{}", "04", y_hex);
+        group_elem_from_bytes::<SignatureGroup>(&hex_to_bytes(&point_hex).unwrap()).unwrap()
+    }
+
+    /// (2, y) with x, y in Fp2 lies on the BLS12-381 G2 twist y^2 = x^3 + 4(1 + i) but not in the
+    /// prime order subgroup. Encoded as x.a || x.b || y.a || y.b for x = x.a + x.b*i
+    #[cfg(feature = "SignatureG2")]
+    fn signature_group_point_outside_subgroup() -> SignatureGroup {
+        let y_a_hex = "18c6b864ae17dc9da64203ffefb966306425a7bc6aeb7c75247438372716284a4173830420cd476ba1a365b95bfcec38";
+        let y_b_hex = "172e93db764a8400a7d5071b6b6f5de0da2f0f4a063119abca014006b7c40a2cfe291a1924e65db0d6d0fcfbf3bf3d5c";
+        let point_hex = format!("{:0>96}{:0>96}{}{}", "02", "00", y_a_hex, y_b_hex);
+        group_elem_from_bytes::<SignatureGroup>(&hex_to_bytes(&point_hex).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_signature_with_small_subgroup_sigma_1() {
+        let msg_count = 2;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (_, _, signers) = trusted_party_SSS_keygen(2, 3, &params);
+        let msgs = FieldElementVector::random(msg_count);
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        let (sig_req, _) = SignatureRequest::new(&msgs, 1, &elg_pk, &params).unwrap();
+        let sig = BlindSignature::new(&sig_req, &signers[0].sigkey)
+            .unwrap()
+            .unblind(&elg_sk);
+        assert!(sig.is_valid_structure());
+        assert!(sig
+            .verify(msgs.as_slice(), &signers[0].verkey, &params)
+            .unwrap());
+
+        // Multiplying a point outside the prime order subgroup by the group order r, as
+        // (r-1).P + P, leaves a point of the small subgroup.
+        let p = signature_group_point_outside_subgroup();
+        assert!(!p.has_correct_order());
+        let small = &(&p * &FieldElement::one().negation()) + &p;
+        assert!(!small.is_identity());
+        assert!(!small.has_correct_order());
+
+        for sigma_1 in vec![small.clone(), &sig.sigma_1 + &small] {
+            let crafted = Signature {
+                sigma_1,
+                sigma_2: sig.sigma_2.clone(),
+            };
+            assert!(!crafted.is_valid_structure());
+            assert!(!crafted
+                .verify(msgs.as_slice(), &signers[0].verkey, &params)
+                .unwrap());
+        }
+    }
+
+    #[test]
+    fn test_signature_batch() {
+        // 10 signers with independent keys sign the same request, so all signatures have the
+        // same sigma_1
+        let msg_count = 4;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let mut signers = vec![];
+        for _ in 0..2 {
+            let (_, _, s) = trusted_party_SSS_keygen(3, 5, &params);
+            signers.extend(s);
+        }
+
+        let msgs = FieldElementVector::random(msg_count);
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        let (sig_req, _) = SignatureRequest::new(&msgs, 2, &elg_pk, &params).unwrap();
+        let sigs = signers
+            .iter()
+            .map(|s| BlindSignature::new(&sig_req, &s.sigkey).unwrap().unblind(&elg_sk))
+            .collect::<Vec<Signature>>();
+        assert_eq!(sigs.len(), 10);
+
+        let batch = SignatureBatch::new(&sigs).unwrap();
+        let bytes = batch.to_bytes();
+        assert_eq!(bytes.len(), 11 * SIGNATURE_GROUP_SIZE + 4);
+        let batch = SignatureBatch::from_bytes(&bytes).unwrap();
+        assert_eq!(batch.len(), 10);
+
+        let items = signers
+            .iter()
+            .map(|s| (msgs.as_slice(), &s.verkey))
+            .collect::<Vec<(&[FieldElement], &Verkey)>>();
+        assert!(batch.verify_batch(&items, &params));
+        for (sig, s) in batch.signatures().iter().zip(signers.iter()) {
+            assert!(sig.verify(msgs.as_slice(), &s.verkey, &params).unwrap());
+        }
+
+        // Swapping verkeys of 2 signatures fails the batch
+        let mut wrong_items = items.clone();
+        wrong_items.swap(0, 1);
+        assert!(!batch.verify_batch(&wrong_items, &params));
+
+        // Signatures with different sigma_1 can't be batched
+        let (sig_req_1, _) = SignatureRequest::new(&msgs, 2, &elg_pk, &params).unwrap();
+        let other_sig = BlindSignature::new(&sig_req_1, &signers[0].sigkey)
+            .unwrap()
+            .unblind(&elg_sk);
+        assert!(SignatureBatch::new(&[sigs[0].clone(), other_sig]).is_err());
+
+        // Truncated bytes
+        assert!(SignatureBatch::from_bytes(&bytes[0..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_verify_many_messages() {
+        let msg_count = 4;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (_, _, signers) = trusted_party_SSS_keygen(3, 5, &params);
+        let vk = &signers[0].verkey;
+
+        let msgs = FieldElementVector::random(msg_count);
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        let (sig_req, _) = SignatureRequest::new(&msgs, 1, &elg_pk, &params).unwrap();
+        let sig = BlindSignature::new(&sig_req, &signers[0].sigkey).unwrap().unblind(&elg_sk);
+
+        let mut wrong_msgs = msgs.clone();
+        wrong_msgs[2] = FieldElement::random();
+        let message_sets = vec![
+            FieldElementVector::random(msg_count),
+            msgs.clone(),
+            wrong_msgs,
+        ];
+        assert_eq!(
+            sig.verify_many_messages(&message_sets, vk, &params),
+            vec![false, true, false]
+        );
+        for (messages, valid) in message_sets
+            .iter()
+            .zip(sig.verify_many_messages(&message_sets, vk, &params))
+        {
+            assert_eq!(sig.verify(messages.as_slice(), vk, &params).unwrap(), valid);
+        }
+
+        // Message set of wrong size
+        let short = vec![FieldElementVector::random(msg_count - 1)];
+        assert_eq!(sig.verify_many_messages(&short, vk, &params), vec![false]);
+    }
+
+    #[test]
+    fn test_signature_request_pok_with_external_commitment() {
+        // The hidden message at index 0 is also committed in an external Pedersen commitment and
+        // both proofs of knowledge use the same blinding for it.
+        let msg_count = 4;
+        let count_hidden = 2;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let msgs = FieldElementVector::random(msg_count);
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        let (sig_req, randomness) =
+            SignatureRequest::new(&msgs, count_hidden, &elg_pk, &params).unwrap();
+
+        let ext_g = SignatureGroup::from_msg_hash("external g".as_bytes());
+        let ext_h = SignatureGroup::from_msg_hash("external h".as_bytes());
+        let ext_r = FieldElement::random();
+        let ext_comm = &(&ext_g * &msgs[0]) + &(&ext_h * &ext_r);
+
+        let blindings = FieldElementVector::random(count_hidden);
+        assert!(SignatureRequestPoK::init_with_blindings(
+            &sig_req,
+            &elg_pk,
+            &params,
+            &blindings.as_slice()[0..1]
+        )
+        .is_err());
+        let sig_req_pok = SignatureRequestPoK::init_with_blindings(
+            &sig_req,
+            &elg_pk,
+            &params,
+            blindings.as_slice(),
+        )
+        .unwrap();
+        let mut ext_committing = ProverCommittingSignatureGroup::new();
+        ext_committing.commit(&ext_g, Some(&blindings[0]));
+        ext_committing.commit(&ext_h, None);
+        let ext_committed = ext_committing.finish();
+
+        let mut chal_bytes = sig_req_pok.to_bytes();
+        chal_bytes.append(&mut ext_committed.to_bytes());
+        let challenge = FieldElement::from_msg_hash(&chal_bytes);
+
+        let hidden_msgs: FieldElementVector = msgs
+            .iter()
+            .take(count_hidden)
+            .map(|m| m.clone())
+            .collect::<Vec<FieldElement>>()
+            .into();
+        let sig_req_proof = sig_req_pok
+            .gen_proof(&hidden_msgs, randomness, &elg_sk, &challenge)
+            .unwrap();
+        let ext_proof = ext_committed
+            .gen_proof(&challenge, &[msgs[0].clone(), ext_r])
+            .unwrap();
+
+        assert!(sig_req_proof
+            .verify(&sig_req, &elg_pk, &challenge, &params)
+            .unwrap());
+        assert!(ext_proof
+            .verify(&[ext_g, ext_h], &ext_comm, &challenge)
+            .unwrap());
+        // Same hidden message in both
+        assert_eq!(
+            sig_req_proof.proof_commitment.responses[0],
+            ext_proof.responses[0]
+        );
+        assert_ne!(
+            sig_req_proof.proof_commitment.responses[1],
+            ext_proof.responses[0]
+        );
+    }
+
+    #[test]
+    fn test_params_in_subgroup() {
+        for params in vec![
+            Params::new(5, "test".as_bytes()),
+            Params::new_typed(5, "test".as_bytes(), "passport".as_bytes()),
+        ] {
+            assert!(!params.g.is_identity() && params.g.has_correct_order());
+            assert!(!params.g_tilde.is_identity() && params.g_tilde.has_correct_order());
+            for h in params.h.iter() {
+                assert!(!h.is_identity() && h.has_correct_order());
+            }
+        }
+    }
+
+    #[test]
+    fn test_params_from_points() {
+        // Points from a ceremony
+        let msg_count = 3;
+        let threshold = 2;
+        let g = SignatureGroup::random();
+        let g_tilde = OtherGroup::random();
+        let h = (0..msg_count)
+            .map(|_| SignatureGroup::random())
+            .collect::<Vec<SignatureGroup>>();
+        let params = Params::from_points(g.clone(), g_tilde.clone(), h.clone()).unwrap();
+        assert_eq!(params.msg_count(), msg_count);
+        assert!(!params.is_typed());
+
+        let (signers, vk) = signers_with_verkey(threshold, 3, &params);
+        let msgs = FieldElementVector::random(msg_count);
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        let (sig_req, randomness) = SignatureRequest::new(&msgs, 2, &elg_pk, &params).unwrap();
+        let sig_req_pok = SignatureRequestPoK::init(&sig_req, &elg_pk, &params);
+        let challenge = FieldElement::from_msg_hash(&sig_req_pok.to_bytes());
+        let hidden_msgs: FieldElementVector = msgs.as_slice()[..2].to_vec().into();
+        let proof = sig_req_pok
+            .gen_proof(&hidden_msgs, randomness, &elg_sk, &challenge)
+            .unwrap();
+        assert!(proof
+            .verify(&sig_req, &elg_pk, &challenge, &params)
+            .unwrap());
+        let sig = sign_request(&sig_req, &elg_sk, &signers, threshold, &params);
+        sig.verify_or_err(msgs.as_slice(), &vk, &params).unwrap();
+
+        // Identity points and repeated points
+        let mut with_identity = h.clone();
+        with_identity[1] = SignatureGroup::identity();
+        let mut repeated = h.clone();
+        repeated[2] = h[0].clone();
+        let mut with_g = h.clone();
+        with_g[0] = g.clone();
+        for res in vec![
+            Params::from_points(SignatureGroup::identity(), g_tilde.clone(), h.clone()),
+            Params::from_points(g.clone(), OtherGroup::identity(), h.clone()),
+            Params::from_points(g.clone(), g_tilde.clone(), with_identity),
+            Params::from_points(g.clone(), g_tilde.clone(), repeated),
+            Params::from_points(g.clone(), g_tilde.clone(), with_g),
+        ] {
+            match res.unwrap_err().kind() {
+                CoconutErrorKind::InvalidPoint { .. } => (),
+                k => panic!("unexpected error kind {:?}", k),
+            }
+        }
+    }
+
+    #[test]
+    fn test_params_commit() {
+        let msg_count = 5;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let msgs = FieldElementVector::random(msg_count);
+        let (_, elg_pk) = elgamal_keygen!(&params.g);
+        for count_hidden in 0..=msg_count {
+            let (sig_req, randomness) =
+                SignatureRequest::new(&msgs, count_hidden, &elg_pk, &params).unwrap();
+            let hidden_msgs = &msgs.as_slice()[..count_hidden];
+            let commitment = params.commit(hidden_msgs, &randomness[0]).unwrap();
+            assert_eq!(commitment, sig_req.commitment);
+            assert_eq!(
+                sig_req.commitment_bases(&params),
+                params.commitment_bases(count_hidden)
+            );
+            assert_ne!(
+                params.commit(hidden_msgs, &FieldElement::random()).unwrap(),
+                sig_req.commitment
+            );
+        }
+
+        let too_many = FieldElementVector::random(msg_count + 1);
+        match params
+            .commit(too_many.as_slice(), &FieldElement::random())
+            .unwrap_err()
+            .kind()
+        {
+            CoconutErrorKind::UnsupportedNoOfMessages { expected, given } => {
+                assert_eq!((expected, given), (msg_count, msg_count + 1))
+            }
+            k => panic!("unexpected error kind {:?}", k),
+        }
+    }
+
+    #[test]
+    fn test_reencrypt_signature_request() {
+        let threshold = 3;
+        let msg_count = 5;
+        let count_hidden = 3;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (_, _, signers) = trusted_party_SSS_keygen(threshold, 5, &params);
+        let msgs = FieldElementVector::random(msg_count);
+        let (old_sk, old_pk) = elgamal_keygen!(&params.g);
+        let (new_sk, new_pk) = elgamal_keygen!(&params.g);
+        let (sig_req, randomness) =
+            SignatureRequest::new(&msgs, count_hidden, &old_pk, &params).unwrap();
+
+        let (new_req, new_k) = sig_req.reencrypt(&old_sk, &new_pk, &params);
+        assert_eq!(new_k.len(), count_hidden);
+        assert_eq!(new_req.commitment, sig_req.commitment);
+        for ((c1, c2), (new_c1, new_c2)) in
+            sig_req.ciphertexts.iter().zip(new_req.ciphertexts.iter())
+        {
+            assert_ne!(c1, new_c1);
+            let m = elgamal_decrypt!(c1, c2, &old_sk);
+            let new_m = elgamal_decrypt!(new_c1, new_c2, &new_sk);
+            assert_eq!(m, new_m);
+            assert_ne!(elgamal_decrypt!(new_c1, new_c2, &old_sk), m);
+        }
+
+        // Proof of knowledge created again for the new request
+        let mut new_randomness = FieldElementVector::with_capacity(count_hidden + 1);
+        new_randomness.push(randomness[0].clone());
+        for k in new_k.iter() {
+            new_randomness.push(k.clone());
+        }
+        let pok = SignatureRequestPoK::init(&new_req, &new_pk, &params);
+        let challenge = FieldElement::from_msg_hash(&pok.to_bytes());
+        let hidden_msgs: FieldElementVector = msgs
+            .iter()
+            .take(count_hidden)
+            .map(|m| m.clone())
+            .collect::<Vec<FieldElement>>()
+            .into();
+        let proof = pok
+            .gen_proof(&hidden_msgs, new_randomness, &new_sk, &challenge)
+            .unwrap();
+        assert!(proof
+            .verify(&new_req, &new_pk, &challenge, &params)
+            .unwrap());
+
+        // Signature unblinded with the new key verifies
+        let sig = BlindSignature::new(&new_req, &signers[0].sigkey)
+            .unwrap()
+            .unblind(&new_sk);
+        assert!(sig
+            .verify(msgs.as_slice(), &signers[0].verkey, &params)
+            .unwrap());
+    }
+
+    #[test]
+    fn test_reencrypt_multi_issuer_request() {
+        let threshold = 2;
+        let msg_count = 3;
+        let count_hidden = 2;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (_, _, signers) = trusted_party_SSS_keygen(threshold, 3, &params);
+        let keys = signers
+            .iter()
+            .take(threshold)
+            .map(|s| (s.id, elgamal_keygen!(&params.g)))
+            .collect::<Vec<(usize, (FieldElement, SignatureGroup))>>();
+        let elgamal_pks = keys
+            .iter()
+            .map(|(id, (_, pk))| (*id, pk.clone()))
+            .collect::<Vec<(usize, SignatureGroup)>>();
+        let msgs = FieldElementVector::random(msg_count);
+        let (sig_req, _) =
+            SignatureRequest::new_multi_issuer(&msgs, count_hidden, &elgamal_pks, &params).unwrap();
+
+        // The signers' ciphertexts are kept and survive serialization
+        let (old_sk, _) = elgamal_keygen!(&params.g);
+        let (_, new_pk) = elgamal_keygen!(&params.g);
+        let (new_req, new_k) = sig_req.reencrypt(&old_sk, &new_pk, &params);
+        assert!(new_k.is_empty());
+        assert!(new_req.ciphertexts.is_empty());
+        assert_eq!(new_req.signer_ciphertexts, sig_req.signer_ciphertexts);
+        let decoded = SignatureRequest::from_bytes(&new_req.to_bytes()).unwrap();
+        assert_eq!(decoded.signer_ciphertexts, sig_req.signer_ciphertexts);
+        assert_eq!(decoded.commitment, sig_req.commitment);
+
+        // Each signer still signs its request and the signature unblinds with that signer's key
+        for (s, (_, (sk, _))) in signers.iter().zip(keys.iter()) {
+            let signer_req = decoded.for_signer(s.id).unwrap();
+            let sig = BlindSignature::new(&signer_req, &s.sigkey)
+                .unwrap()
+                .unblind(sk);
+            assert!(sig.verify(msgs.as_slice(), &s.verkey, &params).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_weighted_threshold() {
+        // Signer 1 has weight 2 and signers 2 and 3 have weight 1
+        let threshold = 3;
+        let msg_count = 4;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (secret_x, _, signers) =
+            trusted_party_SSS_keygen_weighted(threshold, &[2, 1, 1], &params).unwrap();
+        assert_eq!(signers[0].weight(), 2);
+        assert_eq!(signers[0].verkeys()[1].0, 2);
+        assert_eq!(signers[1].verkeys()[0].0, 3);
+
+        let msgs = FieldElementVector::random(msg_count);
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        let (sig_req, _) = SignatureRequest::new(&msgs, 2, &elg_pk, &params).unwrap();
+
+        // Weight-2 signer and a weight-1 signer meet the threshold of 3
+        let sigs = [&signers[0], &signers[2]]
+            .iter()
+            .map(|s| {
+                s.blind_sign(&sig_req, &params)
+                    .unwrap()
+                    .into_iter()
+                    .map(|(id, b)| (id, b.unblind(&elg_sk)))
+                    .collect::<Vec<(usize, Signature)>>()
+            })
+            .collect::<Vec<Vec<(usize, Signature)>>>();
+        let aggr_sig = Signature::aggregate_weighted(threshold, sigs.clone()).unwrap();
+        let aggr_vk = Verkey::aggregate_weighted(threshold, &[&signers[0], &signers[2]]).unwrap();
+        assert_eq!(aggr_vk.X_tilde, &params.g_tilde * &secret_x);
+        assert!(aggr_sig.verify(msgs.as_slice(), &aggr_vk, &params).unwrap());
+
+        // Same aggregate verkey from a different set of signers with enough weight
+        let other_vk = Verkey::aggregate_weighted(threshold, &[&signers[0], &signers[1]]).unwrap();
+        assert!(aggr_sig
+            .verify(msgs.as_slice(), &other_vk, &params)
+            .unwrap());
+
+        // Weight-1 signers alone don't meet the threshold
+        match Verkey::aggregate_weighted(threshold, &[&signers[1], &signers[2]])
+            .unwrap_err()
+            .kind()
+        {
+            CoconutErrorKind::InsufficientShares { .. } => (),
+            k => panic!("unexpected error kind {:?}", k),
+        }
+        match Signature::aggregate_weighted(threshold, sigs[1..].to_vec())
+            .unwrap_err()
+            .kind()
+        {
+            CoconutErrorKind::InsufficientShares { .. } => (),
+            k => panic!("unexpected error kind {:?}", k),
+        }
+
+        // Total weight less than threshold
+        match trusted_party_SSS_keygen_weighted(5, &[2, 1, 1], &params) {
+            Err(e) => match e.kind() {
+                CoconutErrorKind::InsufficientShares { .. } => (),
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(_) => panic!("keygen should fail when total weight is less than threshold"),
+        }
+    }
+
+    #[test]
+    fn test_check_elgamal_randomness() {
+        let params = Params::new(4, "test".as_bytes());
+        let msgs = FieldElementVector::random(4);
+        let (_, elg_pk) = elgamal_keygen!(&params.g);
+        let (_, randomness) = SignatureRequest::new(&msgs, 3, &elg_pk, &params).unwrap();
+        assert!(check_elgamal_randomness(&randomness).is_ok());
+
+        let mut reused = randomness.clone();
+        reused[3] = reused[1].clone();
+        match check_elgamal_randomness(&reused).unwrap_err().kind() {
+            CoconutErrorKind::RandomnessReuse { first, second } => {
+                assert_eq!((first, second), (1, 3))
+            }
+            k => panic!("unexpected error kind {:?}", k),
+        }
+
+        // Commitment randomness is not Elgamal randomness
+        let mut r = randomness.clone();
+        r[0] = r[1].clone();
+        assert!(check_elgamal_randomness(&r).is_ok());
+    }
+
+    /// Broken RNG giving the same bytes every time
+    struct ConstantRng;
+
+    impl RngCore for ConstantRng {
+        fn next_u32(&mut self) -> u32 {
+            7
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            7
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            for b in dest.iter_mut() {
+                *b = 7;
+            }
+        }
+
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+            self.fill_bytes(dest);
+            Ok(())
+        }
+    }
+
+    impl CryptoRng for ConstantRng {}
+
+    #[test]
+    fn test_signature_request_new_checked() {
+        let params = Params::new(4, "test".as_bytes());
+        let msgs = FieldElementVector::random(4);
+        let (_, elg_pk) = elgamal_keygen!(&params.g);
+
+        let (_, randomness) =
+            SignatureRequest::new_checked(&msgs, 3, &elg_pk, &params, &mut thread_rng()).unwrap();
+        assert_eq!(randomness.len(), 4);
+
+        // Elgamal randomness repeated
+        match SignatureRequest::new_checked(&msgs, 3, &elg_pk, &params, &mut ConstantRng) {
+            Err(e) => match e.kind() {
+                CoconutErrorKind::RandomnessReuse { first, second } => {
+                    assert_eq!((first, second), (1, 2))
+                }
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(_) => panic!("request created with reused randomness"),
+        }
+
+        // Only 1 ciphertext but its randomness is the commitment randomness
+        match SignatureRequest::new_checked(&msgs, 1, &elg_pk, &params, &mut ConstantRng) {
+            Err(e) => match e.kind() {
+                CoconutErrorKind::RandomnessReuse { first, second } => {
+                    assert_eq!((first, second), (0, 1))
+                }
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(_) => panic!("request created with reused randomness"),
+        }
+
+        // Nothing hidden, no Elgamal randomness to reuse
+        assert!(
+            SignatureRequest::new_checked(&msgs, 0, &elg_pk, &params, &mut ConstantRng).is_ok()
+        );
+    }
+
+    #[cfg(feature = "strict")]
+    #[test]
+    fn test_randomness_reuse_detected_in_strict_mode() {
+        let count_hidden = 2;
+        let params = Params::new(4, "test".as_bytes());
+        let msgs = FieldElementVector::random(4);
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        let (sig_req, mut randomness) =
+            SignatureRequest::new(&msgs, count_hidden, &elg_pk, &params).unwrap();
+        randomness[2] = randomness[1].clone();
+
+        let pok = SignatureRequestPoK::init(&sig_req, &elg_pk, &params);
+        let challenge = FieldElement::from_msg_hash(&pok.to_bytes());
+        let hidden_msgs: FieldElementVector = msgs
+            .iter()
+            .take(count_hidden)
+            .map(|m| m.clone())
+            .collect::<Vec<FieldElement>>()
+            .into();
+        match pok
+            .gen_proof(&hidden_msgs, randomness, &elg_sk, &challenge)
+            .unwrap_err()
+            .kind()
+        {
+            CoconutErrorKind::RandomnessReuse { .. } => (),
+            k => panic!("unexpected error kind {:?}", k),
+        }
+    }
+
+    #[test]
+    fn test_identity_points_rejected() {
+        let msg_count = 3;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (_, _, signers) = trusted_party_SSS_keygen(3, 5, &params);
+        let msgs = FieldElementVector::random(msg_count);
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        let (sig_req, _) = SignatureRequest::new(&msgs, 1, &elg_pk, &params).unwrap();
+
+        let expect_invalid_point = |res: Result<(), CoconutError>| match res.unwrap_err().kind() {
+            CoconutErrorKind::InvalidPoint { .. } => (),
+            k => panic!("unexpected error kind {:?}", k),
+        };
+
+        expect_invalid_point(ensure_valid_point(&SignatureGroup::identity()));
+        expect_invalid_point(ensure_valid_point(&OtherGroup::identity()));
+
+        // Blind signing
+        let mut bad_req = sig_req.clone();
+        bad_req.commitment = SignatureGroup::identity();
+        expect_invalid_point(BlindSignature::new(&bad_req, &signers[0].sigkey).map(|_| ()));
+        let mut bad_req = sig_req.clone();
+        bad_req.ciphertexts[0].1 = SignatureGroup::identity();
+        expect_invalid_point(
+            BlindSignature::new_typed(&bad_req, &signers[0].sigkey, &params).map(|_| ()),
+        );
+
+        // Aggregation
+        let mut sigs = (0..3)
+            .map(|i| {
+                (
+                    signers[i].id,
+                    BlindSignature::new(&sig_req, &signers[i].sigkey)
+                        .unwrap()
+                        .unblind(&elg_sk),
+                )
+            })
+            .collect::<Vec<(usize, Signature)>>();
+        assert!(Signature::aggregate(3, sigs.clone()).is_ok());
+        match Signature::aggregate(3, sigs[0..2].to_vec()).unwrap_err().kind() {
+            CoconutErrorKind::InsufficientShares { .. } => (),
+            k => panic!("unexpected error kind {:?}", k),
+        }
+        sigs[1].1.sigma_2 = SignatureGroup::identity();
+        expect_invalid_point(Signature::aggregate(3, sigs.clone()).map(|_| ()));
+
+        // Verification
+        let mut sig = BlindSignature::new(&sig_req, &signers[0].sigkey)
+            .unwrap()
+            .unblind(&elg_sk);
+        assert!(sig
+            .verify(msgs.as_slice(), &signers[0].verkey, &params)
+            .unwrap());
+        sig.sigma_1 = SignatureGroup::identity();
+        assert!(!sig
+            .verify(msgs.as_slice(), &signers[0].verkey, &params)
+            .unwrap());
+    }
+
+    #[test]
+    fn test_randomize_signature() {
+        let msg_count = 3;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (_, _, signers) = trusted_party_SSS_keygen(3, 5, &params);
+        let vk = &signers[0].verkey;
+        let msgs = FieldElementVector::random(msg_count);
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        let (sig_req, _) = SignatureRequest::new(&msgs, 1, &elg_pk, &params).unwrap();
+        let sig = BlindSignature::new(&sig_req, &signers[0].sigkey)
+            .unwrap()
+            .unblind(&elg_sk);
+
+        let r = FieldElement::from_msg_hash("randomizer".as_bytes());
+        let sig_1 = sig.randomize_with(&r);
+        let sig_2 = sig.randomize_with(&r);
+        assert_eq!(sig_1.to_bytes(), sig_2.to_bytes());
+        assert_ne!(sig_1.sigma_1, sig.sigma_1);
+        assert!(sig_1.verify(msgs.as_slice(), vk, &params).unwrap());
+        assert!(sig_2.verify(msgs.as_slice(), vk, &params).unwrap());
+
+        let sig_3 = sig.randomize();
+        assert_ne!(sig_3.to_bytes(), sig_1.to_bytes());
+        assert!(sig_3.verify(msgs.as_slice(), vk, &params).unwrap());
+    }
+
+    #[test]
+    fn test_single_attribute_fast_paths() {
+        let threshold = 3;
+        let total = 5;
+        let params = Params::new(1, "test".as_bytes());
+        let (secret_x, secret_y, signers) = trusted_party_SSS_keygen(threshold, total, &params);
+        let keys = signers
+            .iter()
+            .skip(1)
+            .map(|s| (s.id, &s.verkey))
+            .collect::<Vec<(usize, &Verkey)>>();
+        let aggr_vk = Verkey::aggregate(threshold, keys).unwrap();
+        assert_eq!(aggr_vk.X_tilde, &params.g_tilde * &secret_x);
+        assert_eq!(aggr_vk.Y_tilde.len(), 1);
+        assert_eq!(aggr_vk.Y_tilde[0], &params.g_tilde * &secret_y[0]);
+
+        let msgs = FieldElementVector::random(1);
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        let (sig_req, _) = SignatureRequest::new(&msgs, 1, &elg_pk, &params).unwrap();
+        let sigs = (1..=threshold)
+            .map(|i| {
+                (
+                    signers[i].id,
+                    BlindSignature::new(&sig_req, &signers[i].sigkey)
+                        .unwrap()
+                        .unblind(&elg_sk),
+                )
+            })
+            .collect::<Vec<(usize, Signature)>>();
+        let sig = Signature::aggregate(threshold, sigs).unwrap();
+
+        assert!(sig.verify(msgs.as_slice(), &aggr_vk, &params).unwrap());
+        assert!(sig.verify_single(&msgs[0], &aggr_vk, &params));
+        let wrong = FieldElement::random();
+        assert!(!sig.verify(&[wrong.clone()], &aggr_vk, &params).unwrap());
+        assert!(!sig.verify_single(&wrong, &aggr_vk, &params));
+
+        // Not a single message verkey
+        let params = Params::new(2, "test".as_bytes());
+        let (_, _, signers) = trusted_party_SSS_keygen(threshold, total, &params);
+        let sig = Signature {
+            sigma_1: SignatureGroup::random(),
+            sigma_2: SignatureGroup::random(),
+        };
+        assert!(!sig.verify_single(&FieldElement::random(), &signers[0].verkey, &params));
+    }
+
+    #[test]
+    fn test_aggregate_verkey_errors() {
+        let threshold = 2;
+        let params = Params::new(2, "test".as_bytes());
+        let (_, _, signers) = trusted_party_SSS_keygen(threshold, 3, &params);
+
+        match Verkey::aggregate(threshold, vec![(signers[0].id, &signers[0].verkey)]) {
+            Err(e) => match e.kind() {
+                CoconutErrorKind::InsufficientShares { expected, given } => {
+                    assert_eq!(expected, 2);
+                    assert_eq!(given, 1);
+                }
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(_) => panic!("aggregated with insufficient verkeys"),
+        }
+
+        let keys = vec![
+            (signers[0].id, &signers[0].verkey),
+            (signers[1].id, &signers[1].verkey),
+            (signers[0].id, &signers[0].verkey),
+        ];
+        match Verkey::aggregate(threshold, keys) {
+            Err(e) => match e.kind() {
+                CoconutErrorKind::DuplicateSignerId { id } => assert_eq!(id, signers[0].id),
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(_) => panic!("aggregated with a repeated verkey"),
+        }
+
+        // No verkeys, even with threshold 0
+        for t in 0..=threshold {
+            match Verkey::aggregate(t, vec![]) {
+                Err(e) => match e.kind() {
+                    CoconutErrorKind::InsufficientShares { expected, given } => {
+                        assert_eq!(expected, t.max(1));
+                        assert_eq!(given, 0);
+                    }
+                    k => panic!("unexpected error kind {:?}", k),
+                },
+                Ok(_) => panic!("aggregated no verkeys"),
+            }
+        }
+
+        // Verkey for a different number of messages
+        let other_params = Params::new(3, "test".as_bytes());
+        let (_, _, other_signers) = trusted_party_SSS_keygen(threshold, 3, &other_params);
+        let keys = vec![
+            (signers[0].id, &signers[0].verkey),
+            (other_signers[1].id, &other_signers[1].verkey),
+        ];
+        match Verkey::aggregate(threshold, keys) {
+            Err(e) => match e.kind() {
+                CoconutErrorKind::UnsupportedNoOfMessages { expected, given } => {
+                    assert_eq!(expected, 2);
+                    assert_eq!(given, 3);
+                }
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(_) => panic!("aggregated verkeys for different message counts"),
+        }
+
+        // Mismatched bases and exponents surface as an error rather than a panic
+        let bases = OtherGroupVec::random(2);
+        let exps = FieldElementVector::random(3);
+        let e: CoconutError = bases.multi_scalar_mul_var_time(&exps).unwrap_err().into();
+        match e.kind() {
+            CoconutErrorKind::ScalarMulMismatch { bases, exponents } => {
+                assert_eq!(bases, 2);
+                assert_eq!(exponents, 3);
+            }
+            k => panic!("unexpected error kind {:?}", k),
+        }
+    }
+
+    #[test]
+    fn test_aggregate_of_aggregates() {
+        let threshold = 3;
+        let total = 6;
+        let msg_count = 3;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (secret_x, secret_y, signers) = trusted_party_SSS_keygen(threshold, total, &params);
+
+        // 2 sub-committees with 2 members each, neither meets the threshold on its own
+        let committee_1: &[usize] = &[signers[0].id, signers[1].id];
+        let committee_2: &[usize] = &[signers[3].id, signers[4].id];
+        let signer_ids = committee_1
+            .iter()
+            .chain(committee_2.iter())
+            .cloned()
+            .collect::<HashSet<usize>>();
+        let keys_of = |ids: &[usize]| {
+            signers
+                .iter()
+                .filter(|s| ids.contains(&s.id))
+                .map(|s| (s.id, &s.verkey))
+                .collect::<Vec<(usize, &Verkey)>>()
+        };
+        let partial_1 = Verkey::aggregate_partial(&signer_ids, keys_of(committee_1)).unwrap();
+        let partial_2 = Verkey::aggregate_partial(&signer_ids, keys_of(committee_2)).unwrap();
+
+        let vk = Verkey::aggregate_of_aggregates(
+            threshold,
+            &[(committee_1, &partial_1), (committee_2, &partial_2)],
+        )
+        .unwrap();
+        assert_eq!(vk.X_tilde, &params.g_tilde * &secret_x);
+        for i in 0..msg_count {
+            assert_eq!(vk.Y_tilde[i], &params.g_tilde * &secret_y[i]);
+        }
+
+        // Interpolating each sub-committee over its own members does not give the global verkey
+        let naive_1 = Verkey::aggregate(2, keys_of(committee_1)).unwrap();
+        let naive_2 = Verkey::aggregate(2, keys_of(committee_2)).unwrap();
+        let naive = Verkey::aggregate_of_aggregates(
+            threshold,
+            &[(committee_1, &naive_1), (committee_2, &naive_2)],
+        )
+        .unwrap();
+        assert_ne!(naive.X_tilde, vk.X_tilde);
+
+        // Signature from members of both sub-committees verifies with the global verkey
+        let msgs = FieldElementVector::random(msg_count);
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        let (sig_req, _) = SignatureRequest::new(&msgs, 1, &elg_pk, &params).unwrap();
+        let sigs = signers
+            .iter()
+            .filter(|s| signer_ids.contains(&s.id))
+            .take(threshold)
+            .map(|s| {
+                (
+                    s.id,
+                    BlindSignature::new(&sig_req, &s.sigkey)
+                        .unwrap()
+                        .unblind(&elg_sk),
+                )
+            })
+            .collect::<Vec<(usize, Signature)>>();
+        let sig = Signature::aggregate(threshold, sigs).unwrap();
+        assert!(sig.verify(msgs.as_slice(), &vk, &params).unwrap());
+
+        // Member in both sub-committees
+        match Verkey::aggregate_of_aggregates(
+            threshold,
+            &[(committee_1, &partial_1), (committee_1, &partial_2)],
+        ) {
+            Err(e) => match e.kind() {
+                CoconutErrorKind::DuplicateSignerId { id } => assert_eq!(id, committee_1[0]),
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(_) => panic!("aggregated overlapping sub-committees"),
+        }
+
+        // Too few signers in total
+        match Verkey::aggregate_of_aggregates(threshold, &[(committee_1, &partial_1)]) {
+            Err(e) => match e.kind() {
+                CoconutErrorKind::InsufficientShares { expected, given } => {
+                    assert_eq!(expected, threshold);
+                    assert_eq!(given, 2);
+                }
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(_) => panic!("aggregated with insufficient signers"),
+        }
+
+        // Member not among the signers
+        assert!(Verkey::aggregate_partial(&signer_ids, keys_of(&[signers[2].id][..])).is_err());
+    }
+
+    #[test]
+    fn test_unblind_selective() {
+        let threshold = 2;
+        let total = 3;
+        let msg_count = 3;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (_, _, signers) = trusted_party_SSS_keygen(threshold, total, &params);
+        let vk = Verkey::aggregate(
+            threshold,
+            signers
+                .iter()
+                .map(|s| (s.id, &s.verkey))
+                .collect::<Vec<(usize, &Verkey)>>(),
+        )
+        .unwrap();
+
+        // 2 hidden messages, the 1st encrypted under key 1 and the 2nd under key 2
+        let msgs = FieldElementVector::random(msg_count);
+        let (sk_1, pk_1) = elgamal_keygen!(&params.g);
+        let (sk_2, pk_2) = elgamal_keygen!(&params.g);
+        let (mut sig_req, _) = SignatureRequest::new(&msgs, 2, &pk_1, &params).unwrap();
+        let h = SignatureRequest::compute_h(&sig_req.commitment, sig_req.known_messages.as_slice());
+        let (c1, c2, _) = elgamal_encrypt!(&params.g, &pk_2, &(&h * &msgs[1]));
+        sig_req.ciphertexts[1] = (c1, c2);
+
+        let keys = vec![(0, sk_1.clone()), (1, sk_2.clone())];
+        let sigs = signers
+            .iter()
+            .take(threshold)
+            .map(|s| {
+                let blinded = BlindSignature::new_selective(&sig_req, &s.sigkey, &params).unwrap();
+                assert_eq!(blinded.blinded_terms.len(), 2);
+                let sig = blinded.unblind_selective(&keys).unwrap();
+                assert!(sig.verify(msgs.as_slice(), &s.verkey, &params).unwrap());
+                (s.id, sig)
+            })
+            .collect::<Vec<(usize, Signature)>>();
+        let sig = Signature::aggregate(threshold, sigs).unwrap();
+        assert!(sig.verify(msgs.as_slice(), &vk, &params).unwrap());
+
+        // Unblinding with a single key does not give a valid signature
+        let blinded = BlindSignature::new_selective(&sig_req, &signers[0].sigkey, &params).unwrap();
+        let sig = blinded.clone().unblind(&sk_1);
+        assert!(!sig
+            .verify(msgs.as_slice(), &signers[0].verkey, &params)
+            .unwrap());
+
+        // Subtracting the term of only 1 ciphertext does not give a valid signature
+        let sig = blinded
+            .clone()
+            .unblind_selective(&[(0, sk_1.clone())])
+            .unwrap();
+        assert!(!sig
+            .verify(msgs.as_slice(), &signers[0].verkey, &params)
+            .unwrap());
+
+        match blinded.clone().unblind_selective(&[(2, sk_1.clone())]) {
+            Err(e) => match e.kind() {
+                CoconutErrorKind::InvalidAttributeIndex { idx, msg_count } => {
+                    assert_eq!(idx, 2);
+                    assert_eq!(msg_count, 2);
+                }
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(_) => panic!("unblinded with invalid ciphertext index"),
+        }
+        assert!(blinded
+            .unblind_selective(&[(0, sk_1.clone()), (0, sk_2.clone())])
+            .is_err());
+
+        // Blind signature from `new` has no terms
+        let blinded = BlindSignature::new(&sig_req, &signers[0].sigkey).unwrap();
+        assert!(blinded.blinded_terms.is_empty());
+        assert!(blinded.unblind_selective(&keys).is_err());
+    }
+
+    #[test]
+    fn test_selective_terms_reveal_no_more_than_new() {
+        let msg_count = 3;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (_, _, signers) = trusted_party_SSS_keygen(2, 3, &params);
+        let sigkey = &signers[0].sigkey;
+        let msgs = FieldElementVector::random(msg_count);
+        let (_, pk) = elgamal_keygen!(&params.g);
+        let (base_req, _) = SignatureRequest::new(&msgs, 2, &pk, &params).unwrap();
+        let h =
+            SignatureRequest::compute_h(&base_req.commitment, base_req.known_messages.as_slice());
+
+        // Requests for the same messages with Elgamal randomness known to the user
+        let request = || {
+            let mut sig_req = base_req.clone();
+            let mut ks = vec![];
+            for i in 0..2 {
+                let (c1, c2, k) = elgamal_encrypt!(&params.g, &pk, &(&h * &msgs[i]));
+                sig_req.ciphertexts[i] = (c1, c2);
+                ks.push(k);
+            }
+            (sig_req, ks)
+        };
+        let (req_1, k) = request();
+        let (req_2, k_) = request();
 
-        // X_tilde = X_tilde_1^l_1 * X_tilde_2^l_2 * ... X_tilde_i^l_i for i in threshold
-        let X_tilde = X_tilde_bases
-            .multi_scalar_mul_var_time(&X_tilde_exps)
-            .unwrap();
+        // c = g^(k_0*y_0 + k_1*y_1) and c_ = g^(k_0'*y_0 + k_1'*y_1) from `new`
+        let c = BlindSignature::new(&req_1, sigkey).unwrap().blinded.0;
+        let c_ = BlindSignature::new(&req_2, sigkey).unwrap().blinded.0;
+        let det = &(&k[0] * &k_[1]) - &(&k_[0] * &k[1]);
+        let g_y_0 = &(&(&c * &k_[1]) - &(&c_ * &k[1])) * &det.inverse();
+        let g_y_1 = &(&(&c_ * &k[0]) - &(&c * &k_[0])) * &det.inverse();
+        assert_eq!(g_y_0, &params.g * &sigkey.y[0]);
+        assert_eq!(g_y_1, &params.g * &sigkey.y[1]);
 
-        // Y_tilde = [Y_tilde_1^l_1 * Y_tilde_2^l_2 * ... Y_tilde_i^l_i for i in threshold, .. for all q]
-        let mut Y_tilde = vec![];
-        for i in 0..q {
-            Y_tilde.push(
-                Y_tilde_bases[i]
-                    .multi_scalar_mul_var_time(&Y_tilde_exps[i])
-                    .unwrap(),
+        // The terms of `new_selective` give the same g^y_i and split c
+        let blinded = BlindSignature::new_selective(&req_1, sigkey, &params).unwrap();
+        assert_eq!(&blinded.blinded_terms[0] * &k[0].inverse(), g_y_0);
+        assert_eq!(&blinded.blinded_terms[1] * &k[1].inverse(), g_y_1);
+        assert_eq!(&blinded.blinded_terms[0] + &blinded.blinded_terms[1], c);
+    }
+
+    #[test]
+    fn test_public_only_request_commitment() {
+        let msg_count = 3;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let msgs = FieldElementVector::random(msg_count);
+        let (_, elg_pk) = elgamal_keygen!(&params.g);
+        let (sig_req, randomness) = SignatureRequest::new(&msgs, 0, &elg_pk, &params).unwrap();
+        assert!(sig_req.ciphertexts.is_empty());
+
+        // Variable time commitment of the request is the same as the constant time one
+        let mut bases = SignatureGroupVec::with_capacity(1);
+        bases.push(params.g.clone());
+        let mut exps = FieldElementVector::with_capacity(1);
+        exps.push(randomness[0].clone());
+        assert_eq!(
+            sig_req.commitment,
+            bases.multi_scalar_mul_const_time(&exps).unwrap()
+        );
+        assert_eq!(
+            sig_req.commitment,
+            bases.multi_scalar_mul_var_time(&exps).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_seeded_rng_reproducible() {
+        let threshold = 2;
+        let total = 3;
+        let msg_count = 3;
+        let count_hidden = 2;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let msgs = FieldElementVector::random(msg_count);
+
+        let run = |seed: u64| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let (secret_x, _, signers) =
+                trusted_party_SSS_keygen_with_rng(threshold, total, &params, &mut rng).unwrap();
+            let (elg_sk, elg_pk) = elgamal_keygen!(&params.g, &mut rng);
+            let (sig_req, randomness) =
+                SignatureRequest::new_with_rng(&msgs, count_hidden, &elg_pk, &params, &mut rng)
+                    .unwrap();
+            let sig_req_pok =
+                SignatureRequestPoK::init_with_rng(&sig_req, &elg_pk, &params, &mut rng);
+            (
+                secret_x,
+                signers,
+                elg_sk,
+                sig_req,
+                randomness,
+                sig_req_pok.to_bytes(),
+            )
+        };
+
+        let (secret_x, signers_1, elg_sk, sig_req_1, randomness_1, pok_bytes_1) = run(7);
+        let (_, signers_2, _, sig_req_2, randomness_2, pok_bytes_2) = run(7);
+        for (s_1, s_2) in signers_1.iter().zip(signers_2.iter()) {
+            assert_eq!(s_1.verkey.to_bytes(), s_2.verkey.to_bytes());
+        }
+        assert_eq!(
+            serde_json::to_vec(&sig_req_1).unwrap(),
+            serde_json::to_vec(&sig_req_2).unwrap()
+        );
+        assert_eq!(randomness_1.as_slice(), randomness_2.as_slice());
+        assert_eq!(pok_bytes_1, pok_bytes_2);
+
+        let (_, signers_3, _, sig_req_3, _, pok_bytes_3) = run(8);
+        assert_ne!(
+            signers_1[0].verkey.to_bytes(),
+            signers_3[0].verkey.to_bytes()
+        );
+        assert_ne!(
+            serde_json::to_vec(&sig_req_1).unwrap(),
+            serde_json::to_vec(&sig_req_3).unwrap()
+        );
+        assert_ne!(pok_bytes_1, pok_bytes_3);
+
+        // Keys from the seeded keygen work as usual
+        let vk = Verkey::aggregate(
+            threshold,
+            signers_1
+                .iter()
+                .map(|s| (s.id, &s.verkey))
+                .collect::<Vec<(usize, &Verkey)>>(),
+        )
+        .unwrap();
+        assert_eq!(vk.X_tilde, &params.g_tilde * &secret_x);
+        let sigs = signers_1
+            .iter()
+            .take(threshold)
+            .map(|s| {
+                (
+                    s.id,
+                    BlindSignature::new(&sig_req_1, &s.sigkey)
+                        .unwrap()
+                        .unblind(&elg_sk),
+                )
+            })
+            .collect::<Vec<(usize, Signature)>>();
+        let sig = Signature::aggregate(threshold, sigs).unwrap();
+        assert!(sig.verify(msgs.as_slice(), &vk, &params).unwrap());
+    }
+
+    #[test]
+    fn test_verify_detailed() {
+        let msg_count = 3;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (_, _, signers) = trusted_party_SSS_keygen(2, 3, &params);
+        let vk = &signers[0].verkey;
+        let msgs = FieldElementVector::random(msg_count);
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        let (sig_req, _) = SignatureRequest::new(&msgs, 1, &elg_pk, &params).unwrap();
+        let sig = BlindSignature::new(&sig_req, &signers[0].sigkey)
+            .unwrap()
+            .unblind(&elg_sk);
+        assert!(sig.verify_detailed(msgs.as_slice(), vk, &params).unwrap());
+        let wrong_msgs = FieldElementVector::random(msg_count);
+        assert!(!sig
+            .verify_detailed(wrong_msgs.as_slice(), vk, &params)
+            .unwrap());
+
+        match sig.verify_detailed(&msgs.as_slice()[..2], vk, &params) {
+            Err(e) => match e.kind() {
+                CoconutErrorKind::UnsupportedNoOfMessages { expected, given } => {
+                    assert_eq!(expected, 3);
+                    assert_eq!(given, 2);
+                }
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(_) => panic!("verified with fewer messages"),
+        }
+
+        // Maliciously constructed params with the identity as g_tilde
+        let mut bad_params = params.clone();
+        bad_params.g_tilde = OtherGroup::identity();
+        match sig.verify_detailed(msgs.as_slice(), vk, &bad_params) {
+            Err(e) => match e.kind() {
+                CoconutErrorKind::PairingFailed { .. } => (),
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(_) => panic!("verified with identity g_tilde"),
+        }
+
+        let bad_sig = Signature {
+            sigma_1: SignatureGroup::identity(),
+            sigma_2: sig.sigma_2.clone(),
+        };
+        match bad_sig.verify_detailed(msgs.as_slice(), vk, &params) {
+            Err(e) => match e.kind() {
+                CoconutErrorKind::InvalidPoint { .. } => (),
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(_) => panic!("verified signature with identity sigma_1"),
+        }
+    }
+
+    #[test]
+    fn test_verify_or_err() {
+        let msg_count = 3;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (_, _, signers) = trusted_party_SSS_keygen(2, 3, &params);
+        let vk = &signers[0].verkey;
+        let msgs = FieldElementVector::random(msg_count);
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        let (sig_req, _) = SignatureRequest::new(&msgs, 1, &elg_pk, &params).unwrap();
+        let sig = BlindSignature::new(&sig_req, &signers[0].sigkey)
+            .unwrap()
+            .unblind(&elg_sk);
+        sig.verify_or_err(msgs.as_slice(), vk, &params).unwrap();
+
+        let wrong_msgs = FieldElementVector::random(msg_count);
+        match sig
+            .verify_or_err(wrong_msgs.as_slice(), vk, &params)
+            .unwrap_err()
+            .kind()
+        {
+            CoconutErrorKind::PairingCheckFailed => (),
+            k => panic!("unexpected error kind {:?}", k),
+        }
+        match sig
+            .verify_or_err(&msgs.as_slice()[..2], vk, &params)
+            .unwrap_err()
+            .kind()
+        {
+            CoconutErrorKind::AttributeCountMismatch { expected, given } => {
+                assert_eq!((expected, given), (3, 2))
+            }
+            k => panic!("unexpected error kind {:?}", k),
+        }
+        for bad_sig in vec![
+            Signature {
+                sigma_1: SignatureGroup::identity(),
+                sigma_2: sig.sigma_2.clone(),
+            },
+            Signature {
+                sigma_1: sig.sigma_1.clone(),
+                sigma_2: SignatureGroup::identity(),
+            },
+        ] {
+            match bad_sig
+                .verify_or_err(msgs.as_slice(), vk, &params)
+                .unwrap_err()
+                .kind()
+            {
+                CoconutErrorKind::SigmaIsIdentity => (),
+                k => panic!("unexpected error kind {:?}", k),
+            }
+        }
+    }
+
+    #[test]
+    fn test_aggregate_with_inconsistent_h() {
+        let threshold = 2;
+        let msg_count = 2;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (_, _, signers) = trusted_party_SSS_keygen(threshold, 3, &params);
+        let msgs = FieldElementVector::random(msg_count);
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+
+        // Each signer signs a different request so the h (sigma_1) of the signatures differ
+        let sigs = signers
+            .iter()
+            .take(threshold)
+            .map(|s| {
+                let (sig_req, _) = SignatureRequest::new(&msgs, 1, &elg_pk, &params).unwrap();
+                let sig = BlindSignature::new(&sig_req, &s.sigkey)
+                    .unwrap()
+                    .unblind(&elg_sk);
+                assert!(sig.verify(msgs.as_slice(), &s.verkey, &params).unwrap());
+                (s.id, sig)
+            })
+            .collect::<Vec<(usize, Signature)>>();
+        assert_ne!(sigs[0].1.sigma_1, sigs[1].1.sigma_1);
+        match Signature::aggregate(threshold, sigs) {
+            Err(e) => match e.kind() {
+                CoconutErrorKind::InconsistentH { id } => assert_eq!(id, signers[1].id),
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(_) => panic!("aggregated signatures with different h"),
+        }
+    }
+
+    #[test]
+    fn test_blinding_context() {
+        let threshold = 2;
+        let msg_count = 4;
+        let count_hidden = 2;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (signers, vk) = signers_with_verkey(threshold, 3, &params);
+        let msgs = FieldElementVector::random(msg_count);
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        let (sig_req, randomness) =
+            SignatureRequest::new(&msgs, count_hidden, &elg_pk, &params).unwrap();
+        let sig = sign_request(&sig_req, &elg_sk, &signers, threshold, &params);
+
+        let (req_pok, ctx) = SignatureRequestPoK::init_with_context(&sig_req, &elg_pk, &params);
+        assert!(ctx.blinding(0).is_some() && ctx.blinding(1).is_some());
+        assert!(ctx.blinding(2).is_none());
+        let mut revealed_msg_indices = HashSet::new();
+        revealed_msg_indices.insert(3);
+        let blindings = ctx.presentation_blindings(msg_count, &revealed_msg_indices);
+        assert_eq!(blindings.len(), 3);
+        assert_eq!(&blindings[0], ctx.blinding(0).unwrap());
+
+        // A presentation needing the request's proof can't be finished without it
+        assert!(
+            CredentialProofBuilder::new(&sig, msgs.as_slice(), &vk, &params)
+                .with_blinding_context(&ctx)
+                .finish()
+                .is_err()
+        );
+
+        // The issuance-time commitment of the request is linked to the presentation by the equal
+        // responses of the hidden messages under the presentation's challenge
+        let nonce = b"verifier nonce";
+        let hidden_msgs: FieldElementVector = msgs
+            .iter()
+            .take(count_hidden)
+            .cloned()
+            .collect::<Vec<FieldElement>>()
+            .into();
+        let (proof, req_proof) = CredentialProofBuilder::new(&sig, msgs.as_slice(), &vk, &params)
+            .verifier_nonce(nonce)
+            .reveal(3)
+            .with_blinding_context(&ctx)
+            .finish_linked(req_pok, &hidden_msgs, randomness, &elg_sk)
+            .unwrap();
+        for i in 0..count_hidden {
+            assert_eq!(
+                proof.hidden_msg_response(i).unwrap(),
+                &req_proof.proof_commitment.responses[i]
             );
         }
-        Self { X_tilde, Y_tilde }
+        assert_ne!(
+            proof.hidden_msg_response(2).unwrap(),
+            &req_proof.proof_commitment.responses[0]
+        );
+        assert!(proof
+            .verify_linked(nonce, &sig_req, &req_proof, &elg_pk, &vk, &params)
+            .unwrap());
+        assert!(!proof
+            .verify_linked(b"other nonce", &sig_req, &req_proof, &elg_pk, &vk, &params)
+            .unwrap());
+
+        // The challenge covers the request's commitments so the presentation alone doesn't
+        // verify
+        assert!(!proof.verify(nonce, &vk, &params).unwrap());
+
+        // A presentation of a credential on other hidden messages doesn't link to the request
+        let other_msgs = FieldElementVector::random(msg_count);
+        let (other_sig_req, other_randomness) =
+            SignatureRequest::new(&other_msgs, count_hidden, &elg_pk, &params).unwrap();
+        let other_sig = sign_request(&other_sig_req, &elg_sk, &signers, threshold, &params);
+        let (other_req_pok, other_ctx) =
+            SignatureRequestPoK::init_with_context(&other_sig_req, &elg_pk, &params);
+        let other_hidden_msgs: FieldElementVector = other_msgs
+            .iter()
+            .take(count_hidden)
+            .cloned()
+            .collect::<Vec<FieldElement>>()
+            .into();
+        let (other_proof, other_req_proof) =
+            CredentialProofBuilder::new(&other_sig, other_msgs.as_slice(), &vk, &params)
+                .verifier_nonce(nonce)
+                .with_blinding_context(&other_ctx)
+                .finish_linked(other_req_pok, &other_hidden_msgs, other_randomness, &elg_sk)
+                .unwrap();
+        assert!(other_proof
+            .verify_linked(
+                nonce,
+                &other_sig_req,
+                &other_req_proof,
+                &elg_pk,
+                &vk,
+                &params
+            )
+            .unwrap());
+        assert!(!other_proof
+            .verify_linked(nonce, &sig_req, &req_proof, &elg_pk, &vk, &params)
+            .unwrap());
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::keygen::{
-        setup_signers_for_test, trusted_party_PVSS_keygen, trusted_party_SSS_keygen, Signer,
-    };
-    use secret_sharing::pedersen_vss::PedersenVSS;
+    #[test]
+    fn test_signature_request_builder() {
+        let threshold = 2;
+        let msg_count = 4;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (_, _, signers) = trusted_party_SSS_keygen(threshold, 3, &params);
+        let vk = Verkey::aggregate(
+            threshold,
+            signers
+                .iter()
+                .map(|s| (s.id, &s.verkey))
+                .collect::<Vec<(usize, &Verkey)>>(),
+        )
+        .unwrap();
+        let msgs = FieldElementVector::random(msg_count);
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
 
-    fn check_key_aggregation(
-        threshold: usize,
-        msg_count: usize,
-        secret_x: FieldElement,
-        secret_y: FieldElementVector,
-        signers: &[Signer],
-        params: &Params,
-    ) {
+        // 2 hidden and 2 public attributes
+        let (sig_req, _) = SignatureRequestBuilder::new()
+            .hidden_attribute(msgs[0].clone())
+            .hidden_attribute(msgs[1].clone())
+            .public_attribute(msgs[2].clone())
+            .public_attribute(msgs[3].clone())
+            .elgamal_pubkey(&elg_pk)
+            .build(&params)
+            .unwrap();
+        assert_eq!(sig_req.ciphertexts.len(), 2);
+        assert_eq!(sig_req.known_messages.as_slice(), &msgs.as_slice()[2..]);
+        let sigs = signers
+            .iter()
+            .take(threshold)
+            .map(|s| {
+                (
+                    s.id,
+                    BlindSignature::new(&sig_req, &s.sigkey)
+                        .unwrap()
+                        .unblind(&elg_sk),
+                )
+            })
+            .collect::<Vec<(usize, Signature)>>();
+        let sig = Signature::aggregate(threshold, sigs).unwrap();
+        assert!(sig.verify(msgs.as_slice(), &vk, &params).unwrap());
+
+        // All public, no Elgamal key needed
+        let (sig_req, _) = msgs
+            .iter()
+            .fold(SignatureRequestBuilder::new(), |b, m| {
+                b.public_attribute(m.clone())
+            })
+            .build(&params)
+            .unwrap();
+        assert!(sig_req.ciphertexts.is_empty());
+
+        // Hidden attribute after a public one
+        assert!(SignatureRequestBuilder::new()
+            .hidden_attribute(msgs[0].clone())
+            .public_attribute(msgs[1].clone())
+            .hidden_attribute(msgs[2].clone())
+            .public_attribute(msgs[3].clone())
+            .elgamal_pubkey(&elg_pk)
+            .build(&params)
+            .is_err());
+
+        // Hidden attributes without Elgamal key
+        assert!(SignatureRequestBuilder::new()
+            .hidden_attribute(msgs[0].clone())
+            .public_attribute(msgs[1].clone())
+            .public_attribute(msgs[2].clone())
+            .public_attribute(msgs[3].clone())
+            .build(&params)
+            .is_err());
+
+        // Wrong number of attributes
+        match SignatureRequestBuilder::new()
+            .hidden_attribute(msgs[0].clone())
+            .public_attribute(msgs[1].clone())
+            .elgamal_pubkey(&elg_pk)
+            .build(&params)
+        {
+            Err(e) => match e.kind() {
+                CoconutErrorKind::UnsupportedNoOfMessages { expected, given } => {
+                    assert_eq!(expected, 4);
+                    assert_eq!(given, 2);
+                }
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(_) => panic!("built request with too few attributes"),
+        }
+    }
+
+    #[test]
+    fn test_verify_batch_multi() {
+        let threshold = 2;
+        let msg_count = 3;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let issue = |signers: &[Signer], msgs: &FieldElementVector| {
+            let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+            let (sig_req, _) = SignatureRequest::new(msgs, 1, &elg_pk, &params).unwrap();
+            let sigs = signers
+                .iter()
+                .take(threshold)
+                .map(|s| {
+                    (
+                        s.id,
+                        BlindSignature::new(&sig_req, &s.sigkey)
+                            .unwrap()
+                            .unblind(&elg_sk),
+                    )
+                })
+                .collect::<Vec<(usize, Signature)>>();
+            let vk = Verkey::aggregate(
+                threshold,
+                signers
+                    .iter()
+                    .map(|s| (s.id, &s.verkey))
+                    .collect::<Vec<(usize, &Verkey)>>(),
+            )
+            .unwrap();
+            (Signature::aggregate(threshold, sigs).unwrap(), vk)
+        };
+
+        // 2 credentials under each of 2 verkeys
+        let (_, _, signers_1) = trusted_party_SSS_keygen(threshold, 3, &params);
+        let (_, _, signers_2) = trusted_party_SSS_keygen(threshold, 3, &params);
+        let msgs = (0..4)
+            .map(|_| FieldElementVector::random(msg_count))
+            .collect::<Vec<FieldElementVector>>();
+        let (sig_1, vk_1) = issue(&signers_1, &msgs[0]);
+        let (sig_2, _) = issue(&signers_1, &msgs[1]);
+        let (sig_3, vk_2) = issue(&signers_2, &msgs[2]);
+        let (sig_4, _) = issue(&signers_2, &msgs[3]);
+        let mut items = vec![
+            (sig_1, msgs[0].clone(), &vk_1),
+            (sig_2, msgs[1].clone(), &vk_1),
+            (sig_3, msgs[2].clone(), &vk_2),
+            (sig_4, msgs[3].clone(), &vk_2),
+        ];
+        assert!(Signature::verify_batch_multi(&items, &params));
+        assert!(!Signature::verify_batch_multi(&[], &params));
+
+        // Tampered message
+        let original = items[1].1.clone();
+        items[1].1 = FieldElementVector::random(msg_count);
+        assert!(!Signature::verify_batch_multi(&items, &params));
+        items[1].1 = original;
+
+        // Credential shown against the wrong verkey
+        items[2].2 = &vk_1;
+        assert!(!Signature::verify_batch_multi(&items, &params));
+        items[2].2 = &vk_2;
+
+        // Tampered signature
+        let original = items[3].0.clone();
+        items[3].0.sigma_2 = &items[3].0.sigma_2 + &params.g;
+        assert!(!Signature::verify_batch_multi(&items, &params));
+        items[3].0 = original;
+        assert!(Signature::verify_batch_multi(&items, &params));
+    }
+
+    #[test]
+    fn test_verkey_from_secrets() {
+        let threshold = 3;
+        let total = 5;
+        let msg_count = 4;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (secret_x, secret_y, signers) = trusted_party_SSS_keygen(threshold, total, &params);
+
+        let vk = Verkey::from_secrets(&secret_x, secret_y.as_slice(), &params);
         let aggr_vk = Verkey::aggregate(
             threshold,
             signers
                 .iter()
-                .take(threshold)
                 .map(|s| (s.id, &s.verkey))
                 .collect::<Vec<(usize, &Verkey)>>(),
+        )
+        .unwrap();
+        assert_eq!(vk.X_tilde, aggr_vk.X_tilde);
+        assert_eq!(vk.Y_tilde, aggr_vk.Y_tilde);
+        assert_eq!(vk.attribute_indices(), params.attribute_indices());
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel_params() {
+        let msg_count = 128;
+        let label = "test".as_bytes();
+        let sequential = Params::gen_h_sequential(msg_count, label);
+        let parallel = Params::gen_h_parallel(msg_count, label);
+        assert_eq!(sequential.as_slice(), parallel.as_slice());
+        assert_eq!(
+            Params::new(msg_count, label).h.as_slice(),
+            sequential.as_slice()
+        );
+        let typed = Params::new_typed(msg_count, label, "passport".as_bytes());
+        let typed_label = [
+            label,
+            " : credential_type : ".as_bytes(),
+            "passport".as_bytes(),
+        ]
+        .concat();
+        assert_eq!(
+            typed.h.as_slice(),
+            Params::gen_h_sequential(msg_count + 1, &typed_label).as_slice()
         );
+    }
 
-        let expected_X_tilde = &params.g_tilde * &secret_x;
-        assert_eq!(expected_X_tilde, aggr_vk.X_tilde);
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel_ciphertext_proofs() {
+        let msg_count = 8;
+        let count_hidden = 6;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let msgs = FieldElementVector::random(msg_count);
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        let (sig_req, randomness) =
+            SignatureRequest::new(&msgs, count_hidden, &elg_pk, &params).unwrap();
+        let sig_req_pok = SignatureRequestPoK::init(&sig_req, &elg_pk, &params);
+        let challenge = FieldElement::from_msg_hash(&sig_req_pok.to_bytes());
+        let hidden_msgs: FieldElementVector = msgs
+            .iter()
+            .take(count_hidden)
+            .map(|m| m.clone())
+            .collect::<Vec<FieldElement>>()
+            .into();
 
-        for i in 0..msg_count {
-            let expected_Y_tilde_i = &params.g_tilde * &secret_y[i];
-            assert_eq!(expected_Y_tilde_i, aggr_vk.Y_tilde[i]);
-        }
+        let sequential = SignatureRequestPoK::gen_ciphertext_proofs(
+            sig_req_pok.pok_vc_ciphertext.clone(),
+            &hidden_msgs,
+            &randomness,
+            &challenge,
+        )
+        .unwrap();
+        let parallel = SignatureRequestPoK::gen_ciphertext_proofs_parallel(
+            sig_req_pok.pok_vc_ciphertext.clone(),
+            &hidden_msgs,
+            &randomness,
+            &challenge,
+        )
+        .unwrap();
+        assert_eq!(
+            serde_json::to_string(&sequential).unwrap(),
+            serde_json::to_string(&parallel).unwrap()
+        );
+
+        let proof = sig_req_pok
+            .gen_proof(&hidden_msgs, randomness, &elg_sk, &challenge)
+            .unwrap();
+        assert_eq!(
+            serde_json::to_string(&proof.proof_ciphertexts).unwrap(),
+            serde_json::to_string(&parallel).unwrap()
+        );
+        assert!(proof
+            .verify(&sig_req, &elg_pk, &challenge, &params)
+            .unwrap());
     }
 
-    fn check_key_aggregation_gaps_in_ids(
-        threshold: usize,
-        msg_count: usize,
-        secret_x: FieldElement,
-        secret_y: FieldElementVector,
-        keys_to_aggr: Vec<(usize, &Verkey)>,
-        params: &Params,
-    ) {
-        let aggr_vk = Verkey::aggregate(threshold, keys_to_aggr);
+    #[test]
+    fn test_challenge_builder() {
+        // Signature request composed with a proof of knowledge of the discrete log of an unrelated
+        // point under 1 challenge
+        let msg_count = 3;
+        let count_hidden = 1;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let msgs = FieldElementVector::random(msg_count);
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        let (sig_req, randomness) =
+            SignatureRequest::new(&msgs, count_hidden, &elg_pk, &params).unwrap();
+        let sig_req_pok = SignatureRequestPoK::init(&sig_req, &elg_pk, &params);
 
-        let expected_X_tilde = &params.g_tilde * &secret_x;
-        assert_eq!(expected_X_tilde, aggr_vk.X_tilde);
+        let base = SignatureGroup::from_msg_hash("other base".as_bytes());
+        let secret = FieldElement::random();
+        let point = &base * &secret;
+        let mut committing = ProverCommittingSignatureGroup::new();
+        committing.commit(&base, None);
+        let committed = committing.finish();
 
-        for i in 0..msg_count {
-            let expected_Y_tilde_i = &params.g_tilde * &secret_y[i];
-            assert_eq!(expected_Y_tilde_i, aggr_vk.Y_tilde[i]);
-        }
+        let challenge = ChallengeBuilder::new()
+            .signature_request_pok(&sig_req_pok)
+            .bytes(&committed.to_bytes())
+            .finish();
+        // Order and split of the items matter
+        assert_ne!(
+            challenge,
+            ChallengeBuilder::new()
+                .bytes(&committed.to_bytes())
+                .signature_request_pok(&sig_req_pok)
+                .finish()
+        );
+        let mut joined = sig_req_pok.to_bytes();
+        joined.append(&mut committed.to_bytes());
+        assert_ne!(challenge, ChallengeBuilder::new().bytes(&joined).finish());
+
+        let hidden_msgs: FieldElementVector = vec![msgs[0].clone()].into();
+        let sig_req_proof = sig_req_pok
+            .gen_proof(&hidden_msgs, randomness, &elg_sk, &challenge)
+            .unwrap();
+        let other_proof = committed.gen_proof(&challenge, &[secret]).unwrap();
+        assert!(sig_req_proof
+            .verify(&sig_req, &elg_pk, &challenge, &params)
+            .unwrap());
+        assert!(other_proof.verify(&[base], &point, &challenge).unwrap());
     }
 
-    fn check_signing_on_random_msgs(
-        threshold: usize,
-        msg_count: usize,
-        count_hidden: usize,
-        signers: &[Signer],
-        params: &Params,
-    ) {
+    #[test]
+    fn test_verkey_evaluator() {
+        let msg_count = 10;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (_, _, signers) = trusted_party_SSS_keygen(3, 5, &params);
+        let vk = &signers[0].verkey;
+        let sign = |msgs: &FieldElementVector| {
+            let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+            let (sig_req, _) = SignatureRequest::new(msgs, 0, &elg_pk, &params).unwrap();
+            BlindSignature::new(&sig_req, &signers[0].sigkey)
+                .unwrap()
+                .unblind(&elg_sk)
+        };
+
+        let mut msgs = FieldElementVector::random(msg_count);
+        let mut evaluator = VerkeyEvaluator::new(vk, msgs.as_slice(), &params).unwrap();
+        assert!(evaluator.verify(&sign(&msgs), &params));
+
+        // Change 1 attribute out of 10
+        let old_sig = sign(&msgs);
+        msgs[7] = FieldElement::random();
+        evaluator.update(7, &msgs[7]).unwrap();
+        let full = VerkeyEvaluator::new(vk, msgs.as_slice(), &params).unwrap();
+        assert_eq!(evaluator.kappa(), full.kappa());
+        assert_eq!(
+            *evaluator.kappa(),
+            vk.partial_kappa(
+                &msgs.iter().cloned().enumerate().collect::<Vec<_>>(),
+                &params
+            )
+            .unwrap()
+        );
+        assert!(evaluator.verify(&sign(&msgs), &params));
+        assert!(!evaluator.verify(&old_sig, &params));
+
+        assert!(evaluator.update(msg_count, &msgs[0]).is_err());
+        assert!(VerkeyEvaluator::new(vk, &msgs.as_slice()[1..], &params).is_err());
+    }
+
+    #[test]
+    fn test_threshold_decryption() {
+        // Elgamal secret key shared 3-of-5 among the decrypting authorities
+        let elg_threshold = 3;
+        let msg_count = 4;
+        let threshold = 2;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (_, _, signers) = trusted_party_SSS_keygen(threshold, 3, &params);
+        let (elg_sk, elg_sk_shares) = get_shared_secret(elg_threshold, 5);
+        let elg_pk = &params.g * &elg_sk;
+
         let msgs = FieldElementVector::random(msg_count);
-        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        let (sig_req, _) = SignatureRequest::new(&msgs, 2, &elg_pk, &params).unwrap();
+
+        let mut sigs = vec![];
+        for s in signers.iter().take(threshold) {
+            let blind_sig = BlindSignature::new(&sig_req, &s.sigkey).unwrap();
+            // Authorities 2, 4 and 5 decrypt
+            let dec_shares = [2, 4, 5]
+                .iter()
+                .map(|id| (*id, blind_sig.decryption_share(&elg_sk_shares[id])))
+                .collect::<Vec<(usize, SignatureGroup)>>();
+            assert_eq!(
+                combine_decryption_shares(elg_threshold, dec_shares.clone()).unwrap(),
+                &blind_sig.blinded.0 * &elg_sk
+            );
+            let sig = blind_sig
+                .unblind_threshold(elg_threshold, dec_shares)
+                .unwrap();
+            assert!(sig.verify(msgs.as_slice(), &s.verkey, &params).unwrap());
+            sigs.push((s.id, sig));
+        }
+        let sig = Signature::aggregate(threshold, sigs).unwrap();
+        let vk = Verkey::aggregate(
+            threshold,
+            signers
+                .iter()
+                .map(|s| (s.id, &s.verkey))
+                .collect::<Vec<(usize, &Verkey)>>(),
+        )
+        .unwrap();
+        assert!(sig.verify(msgs.as_slice(), &vk, &params).unwrap());
+
+        // Plain Elgamal ciphertext
+        let plaintext = SignatureGroup::random();
+        let (c_1, c_2, _) = elgamal_encrypt!(&params.g, &elg_pk, &plaintext);
+        let dec_shares = (1..=elg_threshold)
+            .map(|id| (id, &c_1 * &elg_sk_shares[&id]))
+            .collect::<Vec<(usize, SignatureGroup)>>();
+        assert_eq!(
+            threshold_decrypt(elg_threshold, &c_2, dec_shares.clone()).unwrap(),
+            plaintext
+        );
 
-        let (sig_req, randomness) = SignatureRequest::new(&msgs, count_hidden, &elg_pk, &params);
+        // Too few shares
+        match threshold_decrypt(elg_threshold, &c_2, dec_shares[0..2].to_vec()) {
+            Err(e) => match e.kind() {
+                CoconutErrorKind::InsufficientShares { expected, given } => {
+                    assert_eq!(expected, elg_threshold);
+                    assert_eq!(given, 2);
+                }
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(_) => panic!("decrypted with too few shares"),
+        }
+        // Repeated share
+        let mut repeated = dec_shares.clone();
+        repeated[2] = dec_shares[0].clone();
+        match threshold_decrypt(elg_threshold, &c_2, repeated) {
+            Err(e) => match e.kind() {
+                CoconutErrorKind::DuplicateSignerId { id } => assert_eq!(id, 1),
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(_) => panic!("decrypted with a repeated share"),
+        }
+    }
 
-        // Initiate proof of knowledge of various items of Signature request
-        let sig_req_pok = SignatureRequestPoK::init(&sig_req, &elg_pk, &params);
+    #[test]
+    fn test_request_with_elgamal_keypair() {
+        let msg_count = 4;
+        let count_hidden = 2;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (_, _, signers) = trusted_party_SSS_keygen(2, 3, &params);
+        let keypair = ElgamalKeypair::generate(&params.g);
 
-        // The challenge can include other things also (if proving other predicates)
-        let challenge = FieldElement::from_msg_hash(&sig_req_pok.to_bytes());
+        let msgs = FieldElementVector::random(msg_count);
+        let (sig_req, randomness) =
+            SignatureRequest::new_with_keypair(&msgs, count_hidden, &keypair, &params).unwrap();
+        // Hidden messages are encrypted under the key pair
+        let h = SignatureRequest::compute_h(&sig_req.commitment, &sig_req.known_messages);
+        for i in 0..count_hidden {
+            let (c1, c2) = &sig_req.ciphertexts[i];
+            assert_eq!(keypair.decrypt(c1, c2), &h * &msgs[i]);
+        }
 
-        // Create proof once the challenge is finalized
+        let sig_req_pok = SignatureRequestPoK::init(&sig_req, &keypair.pk, &params);
+        let challenge = FieldElement::from_msg_hash(&sig_req_pok.to_bytes());
         let hidden_msgs: FieldElementVector = msgs
             .iter()
             .take(count_hidden)
@@ -605,219 +6928,648 @@ mod tests {
             .collect::<Vec<FieldElement>>()
             .into();
         let sig_req_proof = sig_req_pok
-            .gen_proof(&hidden_msgs, randomness, &elg_sk, &challenge)
+            .gen_proof(&hidden_msgs, randomness, &keypair.sk, &challenge)
             .unwrap();
+        assert!(sig_req_proof
+            .verify(&sig_req, &keypair.pk, &challenge, &params)
+            .unwrap());
 
-        let mut blinded_sigs = vec![];
-        for i in 0..threshold {
-            // Each signer verifier proof of knowledge of items of signature request before signing
-            assert!(sig_req_proof
-                .verify(&sig_req, &elg_pk, &challenge, &params)
-                .unwrap());
-            blinded_sigs.push(BlindSignature::new(&sig_req, &signers[i].sigkey));
-        }
+        let sig = BlindSignature::new(&sig_req, &signers[0].sigkey)
+            .unwrap()
+            .unblind_with_keypair(&keypair);
+        assert!(sig
+            .verify(msgs.as_slice(), &signers[0].verkey, &params)
+            .unwrap());
+    }
 
-        let mut unblinded_sigs = vec![];
-        for i in 0..threshold {
-            let unblinded_sig = blinded_sigs.remove(0).unblind(&elg_sk);
-            assert!(unblinded_sig.verify(msgs.as_slice(), &signers[i].verkey, &params));
-            unblinded_sigs.push((signers[i].id, unblinded_sig));
-        }
+    #[test]
+    fn test_verify_aggregate_verkey() {
+        let threshold = 3;
+        let msg_count = 4;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (_, _, signers) = trusted_party_SSS_keygen(threshold, 5, &params);
+        let contributors = signers
+            .iter()
+            .map(|s| (s.id, &s.verkey))
+            .collect::<Vec<(usize, &Verkey)>>();
+        let vk = Verkey::aggregate(threshold, contributors.clone()).unwrap();
+        assert!(vk.verify_aggregate(threshold, &contributors));
+        // Any threshold of the contributors give the same aggregate
+        assert!(vk.verify_aggregate(threshold, &contributors[2..]));
 
-        let aggr_sig = Signature::aggregate(threshold, unblinded_sigs);
+        // Tampered aggregate
+        let mut tampered = vk.clone();
+        tampered.Y_tilde[2] = &tampered.Y_tilde[2] + &params.g_tilde;
+        assert!(!tampered.verify_aggregate(threshold, &contributors));
+        let mut tampered = vk.clone();
+        tampered.X_tilde = OtherGroup::random();
+        assert!(!tampered.verify_aggregate(threshold, &contributors));
 
-        let aggr_vk = Verkey::aggregate(
+        // Tampered contributor
+        let forged = Verkey {
+            X_tilde: OtherGroup::random(),
+            Y_tilde: signers[1].verkey.Y_tilde.clone(),
+        };
+        let mut with_forged = contributors.clone();
+        with_forged[1] = (signers[1].id, &forged);
+        assert!(!vk.verify_aggregate(threshold, &with_forged));
+
+        // Too few contributors
+        assert!(!vk.verify_aggregate(threshold, &contributors[..2]));
+    }
+
+    #[test]
+    fn test_multi_issuer_request() {
+        // 2 signers, each with its own Elgamal key
+        let threshold = 2;
+        let msg_count = 4;
+        let count_hidden = 2;
+        let params = Params::new(msg_count, "test".as_bytes());
+        let (_, _, signers) = trusted_party_SSS_keygen(threshold, 3, &params);
+        let keys = signers
+            .iter()
+            .take(threshold)
+            .map(|s| (s.id, elgamal_keygen!(&params.g)))
+            .collect::<Vec<(usize, (FieldElement, SignatureGroup))>>();
+        let elgamal_sks = keys
+            .iter()
+            .map(|(id, (sk, _))| (*id, sk.clone()))
+            .collect::<Vec<(usize, FieldElement)>>();
+        let elgamal_pks = keys
+            .iter()
+            .map(|(id, (_, pk))| (*id, pk.clone()))
+            .collect::<Vec<(usize, SignatureGroup)>>();
+
+        let msgs = FieldElementVector::random(msg_count);
+        let (sig_req, randomness) =
+            SignatureRequest::new_multi_issuer(&msgs, count_hidden, &elgamal_pks, &params).unwrap();
+        assert!(sig_req.ciphertexts.is_empty());
+        assert_eq!(sig_req.signer_ciphertexts.len(), threshold);
+        assert!(sig_req.for_signer(signers[2].id).is_err());
+
+        let poks = SignatureRequestPoK::init_multi_issuer(&sig_req, &elgamal_pks, &params).unwrap();
+        let challenge = poks
+            .values()
+            .fold(ChallengeBuilder::new(), |b, pok| {
+                b.signature_request_pok(pok)
+            })
+            .finish();
+        let hidden_msgs: FieldElementVector = msgs
+            .iter()
+            .take(count_hidden)
+            .map(|m| m.clone())
+            .collect::<Vec<FieldElement>>()
+            .into();
+        let proofs = SignatureRequestPoK::gen_proof_multi_issuer(
+            poks,
+            &hidden_msgs,
+            randomness,
+            &elgamal_sks,
+            &challenge,
+        )
+        .unwrap();
+        assert!(SignatureRequestProof::verify_multi_issuer(
+            &proofs,
+            &sig_req,
+            &elgamal_pks,
+            &challenge,
+            &params
+        )
+        .unwrap());
+
+        // Each signer signs its request and the signature is unblinded with that signer's key
+        let mut sigs = vec![];
+        for (s, (_, sk)) in signers.iter().zip(elgamal_sks.iter()) {
+            let blind_sig = BlindSignature::new(&sig_req.for_signer(s.id).unwrap(), &s.sigkey);
+            let sig = blind_sig.unwrap().unblind(sk);
+            assert!(sig.verify(msgs.as_slice(), &s.verkey, &params).unwrap());
+            sigs.push((s.id, sig));
+        }
+        let sig = Signature::aggregate(threshold, sigs).unwrap();
+        let vk = Verkey::aggregate(
             threshold,
             signers
                 .iter()
                 .map(|s| (s.id, &s.verkey))
                 .collect::<Vec<(usize, &Verkey)>>(),
+        )
+        .unwrap();
+        assert!(sig.verify(msgs.as_slice(), &vk, &params).unwrap());
+
+        // Ciphertext for the 2nd signer of a different message
+        let id_2 = elgamal_pks[1].0;
+        let h = SignatureRequest::compute_h(&sig_req.commitment, sig_req.known_messages.as_slice());
+        let (c1, c2, _) = elgamal_encrypt!(
+            &params.g,
+            &elgamal_pks[1].1,
+            &(&h * &FieldElement::random())
         );
+        let mut tampered_req = sig_req.clone();
+        tampered_req.signer_ciphertexts.get_mut(&id_2).unwrap()[0] = (c1, c2);
+        assert!(!SignatureRequestProof::verify_multi_issuer(
+            &proofs,
+            &tampered_req,
+            &elgamal_pks,
+            &challenge,
+            &params
+        )
+        .unwrap());
 
-        assert!(aggr_sig.verify(msgs.as_slice(), &aggr_vk, &params));
-    }
+        // Responses for the hidden messages differ between the signers' proofs
+        let mut mismatched = proofs.clone();
+        let r = FieldElement::random();
+        let proof_2 = mismatched.get_mut(&id_2).unwrap();
+        proof_2.proof_commitment.responses[0] = r.clone();
+        proof_2.proof_ciphertexts[0].1.responses[1] = r;
+        assert!(!SignatureRequestProof::verify_multi_issuer(
+            &mismatched,
+            &sig_req,
+            &elgamal_pks,
+            &challenge,
+            &params
+        )
+        .unwrap());
 
-    #[test]
-    fn test_verkey_aggregation_shamir_secret_sharing_keygen() {
-        let threshold = 3;
-        let total = 5;
-        let msg_count = 7;
-        let params = Params::new(msg_count, "test".as_bytes());
+        // Key of a signer missing
+        assert!(SignatureRequestProof::verify_multi_issuer(
+            &proofs,
+            &sig_req,
+            &elgamal_pks[..1],
+            &challenge,
+            &params
+        )
+        .is_err());
 
-        let (secret_x, secret_y, signers) = trusted_party_SSS_keygen(threshold, total, &params);
+        // The whole request rather than the signer's part is rejected by the signer
+        assert!(BlindSignature::new(&sig_req, &signers[0].sigkey).is_err());
 
-        check_key_aggregation(threshold, msg_count, secret_x, secret_y, &signers, &params)
+        // Params with fewer messages than the request's known messages
+        let small_params = Params::new(1, "test".as_bytes());
+        for res in vec![
+            SignatureRequestPoK::init_multi_issuer(&sig_req, &elgamal_pks, &small_params)
+                .map(|_| ()),
+            SignatureRequestProof::verify_multi_issuer(
+                &proofs,
+                &sig_req,
+                &elgamal_pks,
+                &challenge,
+                &small_params,
+            )
+            .map(|_| ()),
+        ] {
+            match res.unwrap_err().kind() {
+                CoconutErrorKind::UnsupportedNoOfMessages { expected, given } => {
+                    assert_eq!(expected, 1);
+                    assert_eq!(given, msg_count - count_hidden);
+                }
+                k => panic!("unexpected error kind {:?}", k),
+            }
+        }
+
+        // Signer's key over a different number of messages
+        let (_, _, other_signers) =
+            trusted_party_SSS_keygen(threshold, 3, &Params::new(msg_count + 1, "test".as_bytes()));
+        match BlindSignature::new(
+            &sig_req.for_signer(signers[0].id).unwrap(),
+            &other_signers[0].sigkey,
+        )
+        .unwrap_err()
+        .kind()
+        {
+            CoconutErrorKind::UnsupportedNoOfMessages { expected, given } => {
+                assert_eq!(expected, msg_count + 1);
+                assert_eq!(given, msg_count);
+            }
+            k => panic!("unexpected error kind {:?}", k),
+        }
     }
 
     #[test]
-    fn test_verkey_aggregation_verifiable_secret_sharing_keygen() {
+    fn test_aggregate_with_metadata() {
         let threshold = 3;
-        let total = 5;
-        let msg_count = 7;
+        let msg_count = 2;
         let params = Params::new(msg_count, "test".as_bytes());
-        let (g, h) = PedersenVSS::gens("testPVSS".as_bytes());
+        let (_, _, signers) = trusted_party_SSS_keygen(threshold, 5, &params);
+        let aggregate = |ids: &[usize]| {
+            let msgs = FieldElementVector::random(msg_count);
+            let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+            let (sig_req, _) = SignatureRequest::new(&msgs, 1, &elg_pk, &params).unwrap();
+            let sigs = ids
+                .iter()
+                .map(|id| {
+                    let s = signers.iter().find(|s| s.id == *id).unwrap();
+                    let blind_sig = BlindSignature::new(&sig_req, &s.sigkey).unwrap();
+                    (s.id, blind_sig.unblind(&elg_sk))
+                })
+                .collect::<Vec<(usize, Signature)>>();
+            Signature::aggregate_with_metadata(threshold, sigs).unwrap()
+        };
 
-        let keygen_res = trusted_party_PVSS_keygen(threshold, total, &params, &g, &h);
-        let secret_x = keygen_res.0;
-        let secret_y = keygen_res.1;
-        let signers = keygen_res.2;
+        let (_, meta_1) = aggregate(&[1, 2, 3]);
+        let (_, meta_2) = aggregate(&[3, 1, 2]);
+        let (_, meta_3) = aggregate(&[1, 2, 4]);
+        assert_eq!(
+            meta_1.signer_ids.iter().cloned().collect::<Vec<usize>>(),
+            vec![1, 2, 3]
+        );
+        assert!(same_signer_set(&meta_1, &meta_2));
+        assert!(!same_signer_set(&meta_1, &meta_3));
 
-        check_key_aggregation(threshold, msg_count, secret_x, secret_y, &signers, &params)
+        // Only the signers used count
+        let (_, meta_4) = aggregate(&[1, 2, 3, 5]);
+        assert!(same_signer_set(&meta_1, &meta_4));
     }
 
     #[test]
-    fn test_sign_verify_shamir_secret_sharing_keygen() {
+    fn test_aggregation_independent_of_order() {
         let threshold = 3;
-        let total = 5;
-        let msg_count = 6;
-        let count_hidden = 2;
+        let msg_count = 2;
         let params = Params::new(msg_count, "test".as_bytes());
+        let (_, _, signers) = trusted_party_SSS_keygen(threshold, 5, &params);
+        let msgs = FieldElementVector::random(msg_count);
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        let (sig_req, _) = SignatureRequest::new(&msgs, 1, &elg_pk, &params).unwrap();
+        let mut sigs = signers
+            .iter()
+            .take(threshold)
+            .map(|s| {
+                let blind_sig = BlindSignature::new(&sig_req, &s.sigkey).unwrap();
+                (s.id, blind_sig.unblind(&elg_sk))
+            })
+            .collect::<Vec<(usize, Signature)>>();
+        let mut keys = signers
+            .iter()
+            .map(|s| (s.id, &s.verkey))
+            .collect::<Vec<(usize, &Verkey)>>();
+        // Verkeys for a single message are aggregated separately
+        let mut single_keys = signers
+            .iter()
+            .map(|s| {
+                let vk = Verkey {
+                    X_tilde: s.verkey.X_tilde.clone(),
+                    Y_tilde: vec![s.verkey.Y_tilde[0].clone()],
+                };
+                (s.id, vk)
+            })
+            .collect::<Vec<(usize, Verkey)>>();
+        let aggregate_single = |keys: &[(usize, Verkey)]| {
+            Verkey::aggregate(threshold, keys.iter().map(|(i, k)| (*i, k)).collect())
+                .unwrap()
+                .to_bytes()
+        };
 
-        let (_, _, signers) = trusted_party_SSS_keygen(threshold, total, &params);
-
-        check_signing_on_random_msgs(threshold, msg_count, count_hidden, &signers, &params)
+        let sig_bytes = Signature::aggregate(threshold, sigs.clone())
+            .unwrap()
+            .to_bytes();
+        let vk_bytes = Verkey::aggregate(threshold, keys.clone())
+            .unwrap()
+            .to_bytes();
+        let single_vk_bytes = aggregate_single(&single_keys);
+        let mut rng = thread_rng();
+        for _ in 0..5 {
+            sigs.shuffle(&mut rng);
+            keys.shuffle(&mut rng);
+            single_keys.shuffle(&mut rng);
+            assert_eq!(
+                Signature::aggregate(threshold, sigs.clone())
+                    .unwrap()
+                    .to_bytes(),
+                sig_bytes
+            );
+            assert_eq!(
+                Verkey::aggregate(threshold, keys.clone())
+                    .unwrap()
+                    .to_bytes(),
+                vk_bytes
+            );
+            assert_eq!(aggregate_single(&single_keys), single_vk_bytes);
+        }
     }
 
     #[test]
-    fn test_sign_verify_verifiable_secret_sharing_keygen() {
-        let threshold = 3;
-        let total = 5;
+    fn test_compressed_verkey() {
         let msg_count = 6;
-        let count_hidden = 2;
         let params = Params::new(msg_count, "test".as_bytes());
-        let (g, h) = PedersenVSS::gens("testPVSS".as_bytes());
+        let y_seed = "signer y seed".as_bytes();
+        let (sigkey, vk) = keygen_with_seeded_y(y_seed, &params);
+        assert_eq!(sigkey.y, seeded_y(y_seed, msg_count));
 
-        let keygen_res = trusted_party_PVSS_keygen(threshold, total, &params, &g, &h);
-        let signers = keygen_res.2;
+        let compressed = vk.compress(y_seed, &params).unwrap();
+        let decompressed = compressed.decompress(&params);
+        assert_eq!(decompressed.X_tilde, vk.X_tilde);
+        assert_eq!(decompressed.Y_tilde, vk.Y_tilde);
+        assert!(vk.to_bytes().len() > compressed.X_tilde.to_bytes().len() + y_seed.len());
 
-        check_signing_on_random_msgs(threshold, msg_count, count_hidden, &signers, &params)
+        // Signature verifies with the decompressed verkey
+        let msgs = FieldElementVector::random(msg_count);
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        let (sig_req, _) = SignatureRequest::new(&msgs, 2, &elg_pk, &params).unwrap();
+        let sig = BlindSignature::new(&sig_req, &sigkey)
+            .unwrap()
+            .unblind(&elg_sk);
+        assert!(sig.verify(msgs.as_slice(), &decompressed, &params).unwrap());
+
+        // Wrong seed or a verkey not derived from a seed
+        assert!(vk.compress("other seed".as_bytes(), &params).is_err());
+        let (_, _, signers) = trusted_party_SSS_keygen(2, 3, &params);
+        assert!(signers[0].verkey.compress(y_seed, &params).is_err());
     }
 
     #[test]
-    fn test_sign_verify_decentralized_verifiable_secret_sharing_keygen() {
-        let threshold = 3;
-        let total = 5;
-        let msg_count = 6;
-        let count_hidden = 2;
+    fn test_blind_signature_verify_against_verkey() {
+        let msg_count = 4;
         let params = Params::new(msg_count, "test".as_bytes());
-        let (g, h) = PedersenVSS::gens("testPVSS".as_bytes());
+        let (_, _, signers) = trusted_party_SSS_keygen(2, 3, &params);
+        let msgs = FieldElementVector::random(msg_count);
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        for count_hidden in 0..=msg_count {
+            let (sig_req, _) =
+                SignatureRequest::new(&msgs, count_hidden, &elg_pk, &params).unwrap();
+            let blind_sig = BlindSignature::new(&sig_req, &signers[0].sigkey).unwrap();
+            assert!(blind_sig.verify_against_verkey(&sig_req, &signers[0].verkey, &params));
 
-        let (_, _, signers) = setup_signers_for_test(threshold, total, &params, &g, &h);
+            // Signer used a sigkey other than that of its verkey, caught before unblinding
+            let rogue = BlindSignature::new(&sig_req, &signers[1].sigkey).unwrap();
+            assert!(!rogue.verify_against_verkey(&sig_req, &signers[0].verkey, &params));
+            assert!(!rogue
+                .clone()
+                .unblind(&elg_sk)
+                .verify(msgs.as_slice(), &signers[0].verkey, &params)
+                .unwrap());
 
-        check_signing_on_random_msgs(threshold, msg_count, count_hidden, &signers, &params)
+            // Only x is different
+            let mut sigkey = signers[0].sigkey.clone();
+            sigkey.x = FieldElement::random();
+            let rogue = BlindSignature::new(&sig_req, &sigkey).unwrap();
+            assert!(!rogue.verify_against_verkey(&sig_req, &signers[0].verkey, &params));
+        }
+
+        // Typed credential
+        let params = Params::new_typed(msg_count, "test".as_bytes(), "passport".as_bytes());
+        let other = Params::new_typed(msg_count, "test".as_bytes(), "membership".as_bytes());
+        let (_, _, signers) = trusted_party_SSS_keygen(2, 3, &params);
+        let typed_msgs = params.typed_messages(msgs.as_slice());
+        let (sig_req, _) = SignatureRequest::new(&typed_msgs, 2, &elg_pk, &params).unwrap();
+        let blind_sig = BlindSignature::new_typed(&sig_req, &signers[0].sigkey, &params).unwrap();
+        assert!(blind_sig.verify_against_verkey(&sig_req, &signers[0].verkey, &params));
+        assert!(!blind_sig.verify_against_verkey(&sig_req, &signers[0].verkey, &other));
+        // Signed without checking the type attribute, which is of another type
+        let other_msgs = other.typed_messages(msgs.as_slice());
+        let (other_req, _) = SignatureRequest::new(&other_msgs, 2, &elg_pk, &params).unwrap();
+        let untyped = BlindSignature::new(&other_req, &signers[0].sigkey).unwrap();
+        assert!(!untyped.verify_against_verkey(&other_req, &signers[0].verkey, &params));
     }
 
     #[test]
-    fn test_verkey_aggregation_gaps_in_ids_shamir_secret_sharing_keygen() {
-        let threshold = 3;
-        let total = 5;
-        let msg_count = 7;
+    fn test_unblind_with_request() {
+        let msg_count = 3;
         let params = Params::new(msg_count, "test".as_bytes());
-        let (secret_x, secret_y, signers) = trusted_party_SSS_keygen(threshold, total, &params);
+        let (_, _, signers) = trusted_party_SSS_keygen(2, 3, &params);
+        let msgs = FieldElementVector::random(msg_count);
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        let (sig_req, _) = SignatureRequest::new(&msgs, 2, &elg_pk, &params).unwrap();
+        let blind_sig = BlindSignature::new(&sig_req, &signers[0].sigkey).unwrap();
+        let sig = blind_sig
+            .clone()
+            .unblind_with_request(&elg_sk, &sig_req)
+            .unwrap();
+        assert!(sig
+            .verify(msgs.as_slice(), &signers[0].verkey, &params)
+            .unwrap());
 
-        let mut keys_to_aggr = vec![];
-        keys_to_aggr.push((signers[0].id, &signers[0].verkey));
-        keys_to_aggr.push((signers[2].id, &signers[2].verkey));
-        keys_to_aggr.push((signers[4].id, &signers[4].verkey));
+        // Signer replaced h
+        let mut tampered = blind_sig.clone();
+        tampered.h = SignatureGroup::random();
+        match tampered.unblind_with_request(&elg_sk, &sig_req) {
+            Err(e) => match e.kind() {
+                CoconutErrorKind::HMismatch => (),
+                k => panic!("unexpected error kind {:?}", k),
+            },
+            Ok(_) => panic!("unblinded a blind signature with a tampered h"),
+        }
 
-        check_key_aggregation_gaps_in_ids(
-            threshold,
-            msg_count,
-            secret_x,
-            secret_y,
-            keys_to_aggr,
-            &params,
-        );
+        // Blind signature over another request
+        let (other_req, _) = SignatureRequest::new(&msgs, 2, &elg_pk, &params).unwrap();
+        assert!(blind_sig.unblind_with_request(&elg_sk, &other_req).is_err());
     }
 
     #[test]
-    fn test_verkey_aggregation_gaps_in_ids_verifiable_secret_sharing_keygen() {
-        let threshold = 3;
-        let total = 5;
-        let msg_count = 7;
+    fn test_single_signer() {
+        // threshold 1 of 1, a centralized issuer
+        let msg_count = 3;
         let params = Params::new(msg_count, "test".as_bytes());
-        let (g, h) = PedersenVSS::gens("testPVSS".as_bytes());
+        let (secret_x, secret_y, signers) = trusted_party_SSS_keygen(1, 1, &params);
+        assert_eq!(signers.len(), 1);
+        assert_eq!(signers[0].sigkey.x, secret_x);
+        assert_eq!(signers[0].sigkey.y, secret_y.as_slice().to_vec());
+        for id in 1..4 {
+            let ids = vec![id].into_iter().collect::<BTreeSet<usize>>();
+            assert_eq!(LagrangeCoefficients::new(&ids)[id], FieldElement::one());
+        }
 
-        let keygen_res = trusted_party_PVSS_keygen(threshold, total, &params, &g, &h);
-        let secret_x = keygen_res.0;
-        let secret_y = keygen_res.1;
-        let signers = keygen_res.2;
+        let vk = Verkey::aggregate(1, vec![(signers[0].id, &signers[0].verkey)]).unwrap();
+        assert_eq!(vk.to_bytes(), signers[0].verkey.to_bytes());
 
-        let mut keys_to_aggr = vec![];
-        keys_to_aggr.push((signers[0].id, &signers[0].verkey));
-        keys_to_aggr.push((signers[2].id, &signers[2].verkey));
-        keys_to_aggr.push((signers[4].id, &signers[4].verkey));
+        let msgs = FieldElementVector::random(msg_count);
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        let (sig_req, _) = SignatureRequest::new(&msgs, 1, &elg_pk, &params).unwrap();
+        let sig = BlindSignature::new(&sig_req, &signers[0].sigkey)
+            .unwrap()
+            .unblind(&elg_sk);
+        let aggr = Signature::aggregate(1, vec![(signers[0].id, sig.clone())]).unwrap();
+        assert_eq!(aggr.to_bytes(), sig.to_bytes());
+        assert!(aggr.verify(msgs.as_slice(), &vk, &params).unwrap());
 
-        check_key_aggregation_gaps_in_ids(
-            threshold,
-            msg_count,
-            secret_x,
-            secret_y,
-            keys_to_aggr,
-            &params,
-        );
+        // threshold 1 of 3, each signer holds the aggregate key
+        let (_, _, signers) = trusted_party_SSS_keygen(1, 3, &params);
+        let vk = Verkey::aggregate(
+            1,
+            signers
+                .iter()
+                .map(|s| (s.id, &s.verkey))
+                .collect::<Vec<(usize, &Verkey)>>(),
+        )
+        .unwrap();
+        for s in &signers {
+            assert_eq!(s.verkey.to_bytes(), vk.to_bytes());
+            let sig = BlindSignature::new(&sig_req, &s.sigkey)
+                .unwrap()
+                .unblind(&elg_sk);
+            let aggr = Signature::aggregate(1, vec![(s.id, sig.clone())]).unwrap();
+            assert_eq!(aggr.to_bytes(), sig.to_bytes());
+            assert!(aggr.verify(msgs.as_slice(), &vk, &params).unwrap());
+        }
+    }
+
+    /// `count` requests over the messages of `params` with a varying number of hidden messages,
+    /// each with its proof, challenge and Elgamal public key
+    fn request_proofs(
+        count: usize,
+        params: &Params,
+    ) -> Vec<(
+        SignatureRequest,
+        SignatureRequestProof,
+        FieldElement,
+        SignatureGroup,
+    )> {
+        let msg_count = params.h.len();
+        let mut requests = vec![];
+        for i in 0..count {
+            let count_hidden = 1 + i % msg_count;
+            let msgs = FieldElementVector::random(msg_count);
+            let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+            let (sig_req, randomness) =
+                SignatureRequest::new(&msgs, count_hidden, &elg_pk, params).unwrap();
+            let pok = SignatureRequestPoK::init(&sig_req, &elg_pk, params);
+            let challenge = FieldElement::from_msg_hash(&pok.to_bytes());
+            let hidden_msgs: FieldElementVector = msgs
+                .iter()
+                .take(count_hidden)
+                .cloned()
+                .collect::<Vec<FieldElement>>()
+                .into();
+            let proof = pok
+                .gen_proof(&hidden_msgs, randomness, &elg_sk, &challenge)
+                .unwrap();
+            requests.push((sig_req, proof, challenge, elg_pk));
+        }
+        requests
     }
 
     #[test]
-    fn test_sign_verify_1() {
-        // Request signature from 1 threshold group of signers and form aggregate verkey from
-        // different threshold group of signers.
-        let threshold = 3;
-        let total = 6;
-        let msg_count = 6;
-        let count_hidden = 2;
-        let params = Params::new(msg_count, "test".as_bytes());
-        let (_, _, signers) = trusted_party_SSS_keygen(threshold, total, &params);
+    fn test_signature_request_proof_verify_batch() {
+        let params = Params::new(4, "test".as_bytes());
+        let requests = request_proofs(20, &params);
+        let items = |requests: &[(
+            SignatureRequest,
+            SignatureRequestProof,
+            FieldElement,
+            SignatureGroup,
+        )]| {
+            requests
+                .iter()
+                .map(|(r, p, c, pk)| (r, p, c, pk))
+                .collect::<Vec<_>>()
+        };
+        assert!(SignatureRequestProof::verify_batch(
+            &items(&requests),
+            &params
+        ));
+        assert!(!SignatureRequestProof::verify_batch(&[], &params));
 
-        let msgs = FieldElementVector::random(msg_count);
-        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        // Corrupted response in 1 proof
+        let mut corrupted = requests.clone();
+        corrupted[7].1.proof_ciphertexts[0].0.responses[0] = FieldElement::random();
+        assert!(!SignatureRequestProof::verify_batch(
+            &items(&corrupted),
+            &params
+        ));
 
-        let (sig_req, randomness) = SignatureRequest::new(&msgs, count_hidden, &elg_pk, &params);
+        // Proof of 1 request given with another's challenge
+        let mut corrupted = requests.clone();
+        corrupted[3].2 = corrupted[4].2.clone();
+        assert!(!SignatureRequestProof::verify_batch(
+            &items(&corrupted),
+            &params
+        ));
+    }
 
-        // Signers from which signature will be requested.
-        let mut signer_ids = HashSet::new();
-        signer_ids.insert(1);
-        signer_ids.insert(3);
-        signer_ids.insert(5);
+    #[test]
+    fn test_verify_ciphertexts_batched() {
+        // Up to 4 hidden messages
+        let params = Params::new(4, "test".as_bytes());
+        for (sig_req, proof, challenge, elg_pk) in request_proofs(8, &params) {
+            let check = |sig_req: &SignatureRequest, proof: &SignatureRequestProof| {
+                let batched = proof.verify(sig_req, &elg_pk, &challenge, &params).unwrap();
+                let each = proof
+                    .verify_unbatched(sig_req, &elg_pk, &challenge, &params)
+                    .unwrap();
+                assert_eq!(batched, each);
+                batched
+            };
+            assert!(check(&sig_req, &proof));
 
-        let sig_req_pok = SignatureRequestPoK::init(&sig_req, &elg_pk, &params);
-        let challenge = FieldElement::from_msg_hash(&sig_req_pok.to_bytes());
-        let hidden_msgs: FieldElementVector = msgs
-            .iter()
-            .take(count_hidden)
-            .map(|m| m.clone())
-            .collect::<Vec<FieldElement>>()
-            .into();
-        let sig_req_proof = sig_req_pok
-            .gen_proof(&hidden_msgs, randomness, &elg_sk, &challenge)
-            .unwrap();
+            let last = proof.proof_ciphertexts.len() - 1;
+            let mut corrupted = proof.clone();
+            corrupted.proof_ciphertexts[last].0.responses[0] = FieldElement::random();
+            assert!(!check(&sig_req, &corrupted));
+            let mut corrupted = proof.clone();
+            corrupted.proof_ciphertexts[last].1.responses[0] = FieldElement::random();
+            assert!(!check(&sig_req, &corrupted));
+            let mut corrupted = proof.clone();
+            corrupted.proof_ciphertexts[0].1.commitment = SignatureGroup::random();
+            assert!(!check(&sig_req, &corrupted));
+            let mut corrupted_req = sig_req.clone();
+            corrupted_req.ciphertexts[last].0 = SignatureGroup::random();
+            assert!(!check(&corrupted_req, &proof));
 
-        let mut blinded_sigs = vec![];
-        for i in &signer_ids {
-            assert!(sig_req_proof
-                .verify(&sig_req, &elg_pk, &challenge, &params)
-                .unwrap());
-            // Keys at index i have id i+1
-            blinded_sigs.push(BlindSignature::new(&sig_req, &signers[*i - 1].sigkey));
+            // Missing responses are rejected by both rather than panicking
+            let mut truncated = proof.clone();
+            truncated.proof_ciphertexts[last].1.responses = FieldElementVector::new(0);
+            assert!(!check(&sig_req, &truncated));
+            let mut truncated = proof.clone();
+            truncated.proof_ciphertexts[last].0.responses = FieldElementVector::new(0);
+            assert!(!check(&sig_req, &truncated));
         }
+    }
 
-        let mut unblinded_sigs = vec![];
-        for i in &signer_ids {
-            let unblinded_sig = blinded_sigs.remove(0).unblind(&elg_sk);
-            // Keys at index i have id i+1
-            assert!(unblinded_sig.verify(msgs.as_slice(), &signers[*i - 1].verkey, &params));
-            unblinded_sigs.push((signers[*i - 1].id, unblinded_sig));
+    #[test]
+    fn test_signature_request_proof_verify_batch_each() {
+        let params = Params::new(4, "test".as_bytes());
+        let mut proofs = vec![];
+        let mut elgamal_pks = vec![];
+        for (sig_req, proof, challenge, pk) in request_proofs(20, &params) {
+            proofs.push((proof, sig_req, challenge));
+            elgamal_pks.push(pk);
         }
+        assert_eq!(
+            SignatureRequestProof::verify_batch_each(&proofs, &elgamal_pks, &params),
+            vec![true; 20]
+        );
 
-        let aggr_sig = Signature::aggregate(threshold, unblinded_sigs);
+        let invalid = 13;
+        proofs[invalid].0.proof_commitment.responses[0] = FieldElement::random();
+        let results = SignatureRequestProof::verify_batch_each(&proofs, &elgamal_pks, &params);
+        for (i, res) in results.into_iter().enumerate() {
+            assert_eq!(res, i != invalid);
+        }
 
-        let mut keys_to_aggr = vec![];
-        keys_to_aggr.push((signers[1].id, &signers[1].verkey));     // signer id is 2
-        keys_to_aggr.push((signers[3].id, &signers[3].verkey));     // signer id is 4
-        keys_to_aggr.push((signers[5].id, &signers[5].verkey));     // signer id is 6
+        // Keys not matching the proofs
+        assert_eq!(
+            SignatureRequestProof::verify_batch_each(&proofs, &elgamal_pks[1..], &params),
+            vec![false; 20]
+        );
+    }
 
-        let aggr_vk = Verkey::aggregate(threshold, keys_to_aggr);
+    #[test]
+    fn test_signature_request_size() {
+        let (_, elg_pk) = elgamal_keygen!(&SignatureGroup::random());
+        for &(msg_count, hidden_count) in &[(1, 0), (1, 1), (3, 1), (5, 5), (6, 2)] {
+            let params = Params::new(msg_count, "test".as_bytes());
+            let msgs = FieldElementVector::random(msg_count);
+            let (sig_req, _) =
+                SignatureRequest::new(&msgs, hidden_count, &elg_pk, &params).unwrap();
+            let bytes = sig_req.to_bytes();
+            assert_eq!(
+                bytes.len(),
+                signature_request_size(msg_count, hidden_count).unwrap()
+            );
+
+            let decoded = SignatureRequest::from_bytes(&bytes).unwrap();
+            assert_eq!(decoded.commitment, sig_req.commitment);
+            assert_eq!(decoded.ciphertexts, sig_req.ciphertexts);
+            assert_eq!(decoded.known_messages, sig_req.known_messages);
+            assert!(SignatureRequest::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        }
 
-        assert!(aggr_sig.verify(msgs.as_slice(), &aggr_vk, &params));
+        // Request for signers with different Elgamal keys
+        let params = Params::new(3, "test".as_bytes());
+        let msgs = FieldElementVector::random(3);
+        let pks = vec![(1, elg_pk.clone()), (4, SignatureGroup::random())];
+        let (sig_req, _) = SignatureRequest::new_multi_issuer(&msgs, 2, &pks, &params).unwrap();
+        let decoded = SignatureRequest::from_bytes(&sig_req.to_bytes()).unwrap();
+        assert!(decoded.ciphertexts.is_empty());
+        assert_eq!(decoded.signer_ciphertexts, sig_req.signer_ciphertexts);
+        assert!(signature_request_size(2, 3).is_err());
     }
 }
@@ -0,0 +1,112 @@
+// Verification of a stream of presentations against a single verkey. The verkey is laid out once as
+// bases for computing kappa and the kappa of each message set is cached so that presentations
+// repeating a message set only need the pairing check.
+
+use crate::signature::{Params, Signature, Verkey};
+use crate::{OtherGroup, OtherGroupVec, FIELD_ELEMENT_SIZE};
+use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
+use amcl_wrapper::group_elem::GroupElementVector;
+use std::collections::HashMap;
+
+/// Max number of kappas cached by a `StreamVerifier`. The cache is cleared when full.
+pub const STREAM_VERIFIER_CACHE_SIZE: usize = 1024;
+
+pub struct StreamVerifier {
+    params: Params,
+    /// X_tilde, Y_tilde_1, Y_tilde_2, ... Y_tilde_n
+    kappa_bases: OtherGroupVec,
+    /// kappa for message sets seen before, keyed by the bytes of the messages
+    kappa_cache: HashMap<Vec<u8>, OtherGroup>,
+}
+
+impl StreamVerifier {
+    pub fn new(vk: &Verkey, params: &Params) -> Self {
+        let mut kappa_bases = OtherGroupVec::with_capacity(vk.Y_tilde.len() + 1);
+        kappa_bases.push(vk.X_tilde.clone());
+        for y in &vk.Y_tilde {
+            kappa_bases.push(y.clone());
+        }
+        Self {
+            params: params.clone(),
+            kappa_bases,
+            kappa_cache: HashMap::new(),
+        }
+    }
+
+    /// Verify the signature of a presentation over `messages`
+    pub fn verify(&mut self, sig: &Signature, messages: &[FieldElement]) -> bool {
+        if messages.len() + 1 != self.kappa_bases.len() || !self.params.has_type_attribute(messages)
+        {
+            return false;
+        }
+        let mut key = Vec::with_capacity(messages.len() * FIELD_ELEMENT_SIZE);
+        for m in messages {
+            key.append(&mut m.to_bytes());
+        }
+        if !self.kappa_cache.contains_key(&key) {
+            // kappa = X_tilde^1.Y_tilde_1^m_1...Y_tilde_n^m_n
+            let mut exps = FieldElementVector::with_capacity(messages.len() + 1);
+            exps.push(FieldElement::one());
+            for m in messages {
+                exps.push(m.clone());
+            }
+            let kappa = self.kappa_bases.multi_scalar_mul_var_time(&exps).unwrap();
+            if self.kappa_cache.len() >= STREAM_VERIFIER_CACHE_SIZE {
+                self.kappa_cache.clear();
+            }
+            self.kappa_cache.insert(key.clone(), kappa);
+        }
+        sig.pairing_check(&self.kappa_cache[&key], &self.params)
+    }
+
+    /// Number of message sets whose kappa is cached
+    pub fn cached_message_sets(&self) -> usize {
+        self.kappa_cache.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::issue_credentials;
+
+    #[test]
+    fn test_stream_verifier() {
+        let msg_count = 4;
+        let params = Params::new(msg_count, "test".as_bytes());
+
+        // 3 credentials, each presented several times with a randomized signature
+        let msgs_list = (0..3)
+            .map(|_| FieldElementVector::random(msg_count))
+            .collect::<Vec<FieldElementVector>>();
+        let (sigs, vk) = issue_credentials(
+            &msgs_list.iter().collect::<Vec<&FieldElementVector>>(),
+            &params,
+        );
+        let creds = sigs
+            .into_iter()
+            .zip(msgs_list.into_iter())
+            .collect::<Vec<(Signature, FieldElementVector)>>();
+
+        let mut verifier = StreamVerifier::new(&vk, &params);
+        let mut valid_count = 0;
+        for i in 0..100 {
+            let (sig, msgs) = &creds[i % 3];
+            let sig = sig.randomize();
+            // Every 4th presentation is over messages of another credential
+            let valid = i % 4 != 0;
+            let msgs = if valid { msgs } else { &creds[(i + 1) % 3].1 };
+            assert_eq!(verifier.verify(&sig, msgs.as_slice()), valid);
+            assert_eq!(sig.verify(msgs.as_slice(), &vk, &params).unwrap(), valid);
+            if valid {
+                valid_count += 1;
+            }
+        }
+        assert_eq!(valid_count, 75);
+        assert_eq!(verifier.cached_message_sets(), 3);
+
+        // Wrong number of messages
+        let (sig, msgs) = &creds[0];
+        assert!(!verifier.verify(sig, &msgs.as_slice()[1..]));
+    }
+}
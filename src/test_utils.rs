@@ -0,0 +1,71 @@
+// Fixtures shared by the tests of several modules: signers with their aggregate verkey and
+// issuance of credentials by them.
+
+use crate::keygen::{trusted_party_SSS_keygen, Signer};
+use crate::signature::{BlindSignature, Params, Signature, SignatureRequest, Verkey};
+use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
+
+/// `total` signers with threshold `threshold` from `trusted_party_SSS_keygen` and their aggregate
+/// verkey
+pub fn signers_with_verkey(
+    threshold: usize,
+    total: usize,
+    params: &Params,
+) -> (Vec<Signer>, Verkey) {
+    let (_, _, signers) = trusted_party_SSS_keygen(threshold, total, params);
+    let vk = Verkey::aggregate(
+        threshold,
+        signers
+            .iter()
+            .map(|s| (s.id, &s.verkey))
+            .collect::<Vec<(usize, &Verkey)>>(),
+    )
+    .unwrap();
+    (signers, vk)
+}
+
+/// The aggregate of the blind signatures of the 1st `threshold` of `signers` on `sig_req`,
+/// unblinded with `elgamal_sk`. Signed with `BlindSignature::new_typed` so that it works for
+/// typed and untyped params.
+pub fn sign_request(
+    sig_req: &SignatureRequest,
+    elgamal_sk: &FieldElement,
+    signers: &[Signer],
+    threshold: usize,
+    params: &Params,
+) -> Signature {
+    let sigs = signers
+        .iter()
+        .take(threshold)
+        .map(|s| {
+            let blind_sig = BlindSignature::new_typed(sig_req, &s.sigkey, params).unwrap();
+            (s.id, blind_sig.unblind(elgamal_sk))
+        })
+        .collect::<Vec<(usize, Signature)>>();
+    Signature::aggregate(threshold, sigs).unwrap()
+}
+
+/// Issue a credential over `msgs` from a 3 out of 5 signers and return the aggregate signature
+/// and aggregate verkey. The 1st message is hidden from the signers.
+pub fn issue_credential(msgs: &FieldElementVector, params: &Params) -> (Signature, Verkey) {
+    let (mut sigs, vk) = issue_credentials(&[msgs], params);
+    (sigs.remove(0), vk)
+}
+
+/// Same as `issue_credential` but for several credentials from the same signers
+pub fn issue_credentials(
+    msgs_list: &[&FieldElementVector],
+    params: &Params,
+) -> (Vec<Signature>, Verkey) {
+    let threshold = 3;
+    let (signers, vk) = signers_with_verkey(threshold, 5, params);
+    let mut creds = vec![];
+    for msgs in msgs_list {
+        let (elg_sk, elg_pk) = elgamal_keygen!(&params.g);
+        let (sig_req, _) = SignatureRequest::new(msgs, 1, &elg_pk, params).unwrap();
+        let sig = sign_request(&sig_req, &elg_sk, &signers, threshold, params);
+        assert!(sig.verify(msgs.as_slice(), &vk, params).unwrap());
+        creds.push(sig);
+    }
+    (creds, vk)
+}
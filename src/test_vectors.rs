@@ -0,0 +1,203 @@
+// Test vectors of a complete issuance transcript. Keys and all randomness come from an rng seeded
+// with `SEED`, so the same transcript is generated every time: keygen of `TOTAL` signers with
+// threshold `THRESHOLD`, the Elgamal keypair, a request over `MSG_COUNT` fixed messages of which
+// `COUNT_HIDDEN` are hidden, its proof of knowledge with the `challenge_v1` challenge, the blind
+// signatures of the 1st `THRESHOLD` signers, their unblinding and the aggregate signature, with
+// every step verified. The transcript is written as 1 line of "name hex" for each value and
+// compared with the expected transcript in `VECTORS_FILE`, which is generated by the
+// `test_vectors` example and never by the tests. Implementations in other languages can start from
+// the keys and randomness-free steps in the file, or reproduce the rng: ChaCha20 of the
+// `rand_chacha` crate, whose output for a seed is fixed unlike that of `StdRng`.
+
+use crate::errors::{CoconutError, CoconutErrorKind};
+use crate::keygen::trusted_party_SSS_keygen_with_rng;
+use crate::signature::{
+    challenge_v1, BlindSignature, Params, Signature, SignatureRequest, SignatureRequestPoK, Verkey,
+};
+use crate::utils::bytes_to_hex;
+use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
+use rand::SeedableRng;
+use rand_chacha::ChaChaRng;
+
+pub const SEED: u64 = 0x636f636f6e7574;
+pub const PARAMS_LABEL: &[u8] = b"coconut test vectors";
+pub const MSG_COUNT: usize = 3;
+pub const COUNT_HIDDEN: usize = 2;
+pub const THRESHOLD: usize = 2;
+pub const TOTAL: usize = 3;
+
+/// File with the expected transcript, relative to the crate root
+pub const VECTORS_FILE: &str = "test_vectors/transcript.txt";
+
+/// The fixed messages 1, 2, ..., `MSG_COUNT`
+pub fn messages() -> FieldElementVector {
+    (1..=MSG_COUNT)
+        .map(|i| FieldElement::from(i as u64))
+        .collect::<Vec<FieldElement>>()
+        .into()
+}
+
+/// The transcript as pairs of name and bytes, in the order they are produced. Errors if any step
+/// fails verification.
+pub fn transcript() -> Result<Vec<(String, Vec<u8>)>, CoconutError> {
+    let mut rng = ChaChaRng::seed_from_u64(SEED);
+    let mut entries = vec![];
+    let params = Params::new(MSG_COUNT, PARAMS_LABEL);
+    entries.push((String::from("params"), params.to_bytes()));
+
+    let (_, _, signers) = trusted_party_SSS_keygen_with_rng(THRESHOLD, TOTAL, &params, &mut rng)?;
+    for s in &signers {
+        entries.push((format!("sigkey_{}", s.id), s.sigkey.to_bytes()));
+        entries.push((format!("verkey_{}", s.id), s.verkey.to_bytes()));
+    }
+    let vk = Verkey::aggregate(
+        THRESHOLD,
+        signers
+            .iter()
+            .map(|s| (s.id, &s.verkey))
+            .collect::<Vec<(usize, &Verkey)>>(),
+    )?;
+    entries.push((String::from("aggregate_verkey"), vk.to_bytes()));
+
+    let elgamal_sk = FieldElement::random_using_rng(&mut rng);
+    let elgamal_pk = &params.g * &elgamal_sk;
+    entries.push((String::from("elgamal_sk"), elgamal_sk.to_bytes()));
+    entries.push((String::from("elgamal_pk"), elgamal_pk.to_bytes()));
+
+    let msgs = messages();
+    let (sig_req, randomness) =
+        SignatureRequest::new_with_rng(&msgs, COUNT_HIDDEN, &elgamal_pk, &params, &mut rng)?;
+    entries.push((String::from("signature_request"), sig_req.to_bytes()));
+
+    let pok = SignatureRequestPoK::init_with_rng(&sig_req, &elgamal_pk, &params, &mut rng);
+    let challenge = challenge_v1(&params, &elgamal_pk, &sig_req, &pok.commitments());
+    entries.push((String::from("challenge"), challenge.to_bytes()));
+    let hidden_msgs: FieldElementVector = msgs
+        .iter()
+        .take(COUNT_HIDDEN)
+        .cloned()
+        .collect::<Vec<FieldElement>>()
+        .into();
+    let proof = pok.gen_proof(&hidden_msgs, randomness, &elgamal_sk, &challenge)?;
+    if !proof.verify(&sig_req, &elgamal_pk, &challenge, &params)? {
+        return Err(CoconutErrorKind::InvalidProof {
+            msg: String::from("proof of knowledge of the signature request"),
+        }
+        .into());
+    }
+    entries.push((String::from("request_proof"), proof.to_bytes()));
+
+    let mut sigs = Vec::with_capacity(THRESHOLD);
+    for s in signers.iter().take(THRESHOLD) {
+        let blind_sig = BlindSignature::new(&sig_req, &s.sigkey)?;
+        entries.push((format!("blind_signature_{}", s.id), blind_sig.to_bytes()));
+        let sig = blind_sig.unblind(&elgamal_sk);
+        sig.verify_or_err(msgs.as_slice(), &s.verkey, &params)?;
+        entries.push((format!("signature_{}", s.id), sig.to_bytes()));
+        sigs.push((s.id, sig));
+    }
+    let sig = Signature::aggregate(THRESHOLD, sigs)?;
+    sig.verify_or_err(msgs.as_slice(), &vk, &params)?;
+    entries.push((String::from("aggregate_signature"), sig.to_bytes()));
+    Ok(entries)
+}
+
+/// The transcript as 1 line of "name hex" for each entry, the format of `VECTORS_FILE`
+pub fn transcript_text() -> Result<String, CoconutError> {
+    let mut text = String::new();
+    for (name, bytes) in transcript()? {
+        text.push_str(&format!("{} {}\n", name, bytes_to_hex(&bytes)));
+    }
+    Ok(text)
+}
+
+/// Compare the transcript with `expected`, in the format of `VECTORS_FILE`. Errors naming the 1st
+/// entry that differs.
+pub fn check(expected: &str) -> Result<(), CoconutError> {
+    let actual = transcript_text()?;
+    let mut expected_lines = expected.lines().filter(|l| !l.trim().is_empty());
+    for line in actual.lines() {
+        let name = line.split(' ').next().unwrap_or("");
+        if expected_lines.next().map(str::trim) != Some(line) {
+            return Err(CoconutErrorKind::GeneralError {
+                msg: format!("Test vector {} differs from the expected", name),
+            }
+            .into());
+        }
+    }
+    if let Some(extra) = expected_lines.next() {
+        return Err(CoconutErrorKind::GeneralError {
+            msg: format!("Unexpected test vector {}", extra),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FIELD_ELEMENT_SIZE, SIGNATURE_GROUP_SIZE};
+    use std::fs;
+    use std::path::Path;
+
+    #[test]
+    fn test_transcript_layout() {
+        // The entries in order, and the sizes of those that do not depend on the serialization of
+        // the variable length types
+        let mut expected_names = vec![String::from("params")];
+        for id in 1..=TOTAL {
+            expected_names.push(format!("sigkey_{}", id));
+            expected_names.push(format!("verkey_{}", id));
+        }
+        for name in &[
+            "aggregate_verkey",
+            "elgamal_sk",
+            "elgamal_pk",
+            "signature_request",
+            "challenge",
+            "request_proof",
+        ] {
+            expected_names.push(name.to_string());
+        }
+        for id in 1..=THRESHOLD {
+            expected_names.push(format!("blind_signature_{}", id));
+            expected_names.push(format!("signature_{}", id));
+        }
+        expected_names.push(String::from("aggregate_signature"));
+
+        let entries = transcript().unwrap();
+        let names = entries
+            .iter()
+            .map(|(n, _)| n.clone())
+            .collect::<Vec<String>>();
+        assert_eq!(names, expected_names);
+        for (name, bytes) in &entries {
+            match name.as_str() {
+                "elgamal_sk" | "challenge" => assert_eq!(bytes.len(), FIELD_ELEMENT_SIZE),
+                "elgamal_pk" => assert_eq!(bytes.len(), SIGNATURE_GROUP_SIZE),
+                _ => (),
+            }
+        }
+
+        // A changed entry and a missing entry
+        let text = transcript_text().unwrap();
+        let changed = text.replacen("challenge ", "challenge 00", 1);
+        assert!(check(&changed).is_err());
+        let missing = text.lines().skip(1).collect::<Vec<&str>>().join("\n");
+        assert!(check(&missing).is_err());
+    }
+
+    #[test]
+    fn test_transcript_matches_vectors() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(VECTORS_FILE);
+        let expected = fs::read_to_string(&path).unwrap_or_else(|_| {
+            panic!(
+                "No test vectors at {:?}, generate them with `cargo run --example test_vectors \
+                 --features test_vectors > {}`",
+                path, VECTORS_FILE
+            )
+        });
+        check(&expected).unwrap();
+    }
+}
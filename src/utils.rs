@@ -0,0 +1,302 @@
+// Helpers shared across modules for converting between bytes and their string representations
+// and for hashing to group elements.
+
+use crate::errors::{CoconutError, CoconutErrorKind};
+use amcl_wrapper::field_elem::FieldElement;
+use amcl_wrapper::group_elem::GroupElement;
+use serde::de::{self, SeqAccess, Visitor};
+use std::fmt;
+use std::io::{Read, Write};
+use std::panic;
+
+/// Lowercase hex encoding of the given bytes
+pub fn bytes_to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Decode a hex string (lower or upper case) to bytes. Returns an error for odd length strings
+/// or strings containing non-hex characters.
+pub fn hex_to_bytes(hex: &str) -> Result<Vec<u8>, CoconutError> {
+    let hex = hex.as_bytes();
+    if hex.len() % 2 != 0 {
+        return Err(CoconutErrorKind::InvalidHex {
+            msg: format!("odd length {}", hex.len()),
+        }
+        .into());
+    }
+    let mut bytes = Vec::with_capacity(hex.len() / 2);
+    for pair in hex.chunks(2) {
+        let hi = hex_digit(pair[0])?;
+        let lo = hex_digit(pair[1])?;
+        bytes.push((hi << 4) | lo);
+    }
+    Ok(bytes)
+}
+
+fn hex_digit(c: u8) -> Result<u8, CoconutError> {
+    match c {
+        b'0'..=b'9' => Ok(c - b'0'),
+        b'a'..=b'f' => Ok(c - b'a' + 10),
+        b'A'..=b'F' => Ok(c - b'A' + 10),
+        _ => Err(CoconutErrorKind::InvalidHex {
+            msg: format!("non-hex character {:?}", c as char),
+        }
+        .into()),
+    }
+}
+
+/// Decode a group element from bytes. Never panics, a failure to decode or a point not on the
+/// curve (which amcl decodes as the identity) results in `PointDecodeFailed`.
+pub fn group_elem_from_bytes<G: GroupElement>(bytes: &[u8]) -> Result<G, CoconutError> {
+    match panic::catch_unwind(|| G::from_bytes(bytes)) {
+        Ok(Ok(p)) => {
+            if p.is_identity() {
+                Err(CoconutErrorKind::PointDecodeFailed {
+                    msg: String::from("identity or point not on curve"),
+                }
+                .into())
+            } else {
+                Ok(p)
+            }
+        }
+        Ok(Err(e)) => Err(CoconutErrorKind::PointDecodeFailed {
+            msg: format!("{:?}", e),
+        }
+        .into()),
+        Err(_) => Err(CoconutErrorKind::PointDecodeFailed {
+            msg: String::from("panic while decoding"),
+        }
+        .into()),
+    }
+}
+
+/// Reject a point entering from outside, like from a signature request or a signature. The
+/// identity is always rejected and with the `strict` feature, points outside the prime order
+/// subgroup are rejected as well.
+pub fn ensure_valid_point<G: GroupElement>(p: &G) -> Result<(), CoconutError> {
+    if p.is_identity() {
+        return Err(CoconutErrorKind::InvalidPoint {
+            msg: String::from("identity"),
+        }
+        .into());
+    }
+    #[cfg(feature = "strict")]
+    {
+        if !p.has_correct_order() {
+            return Err(CoconutErrorKind::InvalidPoint {
+                msg: String::from("not in prime order subgroup"),
+            }
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// Multiply a point by a public scalar given as big-endian bytes. For scalars like the G2 cofactor
+/// that are larger than the group order and so can't be a `FieldElement`.
+pub fn scalar_mul_bytes<G: GroupElement>(point: &G, scalar: &[u8]) -> G {
+    let mut acc = G::identity();
+    for byte in scalar {
+        for i in (0..8).rev() {
+            acc = acc.double();
+            if (byte >> i) & 1 == 1 {
+                acc = acc.plus(point);
+            }
+        }
+    }
+    acc
+}
+
+/// Hash to a point in the prime order subgroup by multiplying the output of `from_msg_hash` with
+/// `cofactor`, one of `SIGNATURE_GROUP_COFACTOR` or `OTHER_GROUP_COFACTOR`. Does not rely on the
+/// amcl mapping clearing the cofactor.
+pub(crate) fn hash_to_subgroup<G: GroupElement>(msg: &[u8], cofactor: &[u8]) -> G {
+    scalar_mul_bytes(&G::from_msg_hash(msg), cofactor)
+}
+
+/// Reads items sequentially from bytes. Running out of bytes results in `InvalidByteLength`.
+pub struct ByteReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, offset: 0 }
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.bytes.len() - self.offset
+    }
+
+    pub fn take(&mut self, n: usize) -> Result<&'a [u8], CoconutError> {
+        if n > self.remaining() {
+            return Err(CoconutErrorKind::InvalidByteLength {
+                expected: self.offset.saturating_add(n),
+                given: self.bytes.len(),
+            }
+            .into());
+        }
+        let b = &self.bytes[self.offset..self.offset + n];
+        self.offset += n;
+        Ok(b)
+    }
+
+    /// Read a 4 byte big-endian integer
+    pub fn read_u32(&mut self) -> Result<usize, CoconutError> {
+        let mut b = [0u8; 4];
+        b.copy_from_slice(self.take(4)?);
+        Ok(u32::from_be_bytes(b) as usize)
+    }
+
+    /// Read a count of items written by `append_u32` where each item takes at least
+    /// `min_item_size` bytes. Errors with `InvalidByteLength` if the remaining bytes can't hold
+    /// that many items, so a forged count doesn't make the caller allocate for it.
+    pub fn read_count(&mut self, min_item_size: usize) -> Result<usize, CoconutError> {
+        let count = self.read_u32()?;
+        let needed = count.saturating_mul(min_item_size);
+        if needed > self.remaining() {
+            return Err(CoconutErrorKind::InvalidByteLength {
+                expected: self.offset.saturating_add(needed),
+                given: self.bytes.len(),
+            }
+            .into());
+        }
+        Ok(count)
+    }
+
+    /// Read bytes written by `append_with_len`
+    pub fn read_with_len(&mut self) -> Result<&'a [u8], CoconutError> {
+        let len = self.read_u32()?;
+        self.take(len)
+    }
+
+    pub fn read_field_element(&mut self) -> Result<FieldElement, CoconutError> {
+        let b = self.take(crate::FIELD_ELEMENT_SIZE)?;
+        Ok(FieldElement::from_bytes(b)?)
+    }
+
+    pub fn read_group_elem<G: GroupElement>(&mut self, size: usize) -> Result<G, CoconutError> {
+        group_elem_from_bytes(self.take(size)?)
+    }
+
+    /// Fails if any bytes are left unread
+    pub fn finish(self) -> Result<(), CoconutError> {
+        if self.remaining() != 0 {
+            return Err(CoconutErrorKind::InvalidByteLength {
+                expected: self.offset,
+                given: self.bytes.len(),
+            }
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// Append a 4 byte big-endian integer
+pub fn append_u32(bytes: &mut Vec<u8>, n: usize) {
+    bytes.extend_from_slice(&(n as u32).to_be_bytes());
+}
+
+/// Append `data` prefixed with its length as a 4 byte big-endian integer
+pub fn append_with_len(bytes: &mut Vec<u8>, data: &[u8]) {
+    append_u32(bytes, data.len());
+    bytes.extend_from_slice(data);
+}
+
+/// Write `bytes` to `writer` prefixed with their length as a 4 byte big-endian integer
+pub fn write_framed<W: Write>(mut writer: W, bytes: &[u8]) -> Result<(), CoconutError> {
+    writer.write_all(&(bytes.len() as u32).to_be_bytes())?;
+    writer.write_all(bytes)?;
+    Ok(())
+}
+
+/// Read bytes written by `write_framed` from `reader`, consuming only the frame. The bytes are read
+/// as they arrive rather than allocating the length in the frame up front. Reaching the end of the
+/// input before the end of the frame results in `InvalidByteLength`, other IO failures in
+/// `IOError`.
+pub fn read_framed<R: Read>(mut reader: R) -> Result<Vec<u8>, CoconutError> {
+    let len_bytes = read_exactly(&mut reader, 4)?;
+    let mut b = [0u8; 4];
+    b.copy_from_slice(&len_bytes);
+    read_exactly(&mut reader, u32::from_be_bytes(b) as usize)
+}
+
+/// Read exactly `n` bytes, `InvalidByteLength` if the input ends before
+fn read_exactly<R: Read>(reader: &mut R, n: usize) -> Result<Vec<u8>, CoconutError> {
+    let mut bytes = vec![];
+    reader.by_ref().take(n as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != n {
+        return Err(CoconutErrorKind::InvalidByteLength {
+            expected: n,
+            given: bytes.len(),
+        }
+        .into());
+    }
+    Ok(bytes)
+}
+
+/// Serde visitor for types serialized as bytes. Accepts byte strings as well as sequences of
+/// bytes since some formats like JSON serialize bytes as sequence.
+pub struct BytesVisitor;
+
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("bytes")
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        Ok(v.to_vec())
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+        Ok(v)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+        while let Some(b) = seq.next_element::<u8>()? {
+            bytes.push(b);
+        }
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{OtherGroup, SignatureGroup, OTHER_GROUP_COFACTOR, SIGNATURE_GROUP_COFACTOR};
+
+    #[test]
+    fn test_hex_roundtrip() {
+        let bytes = vec![0u8, 1, 15, 16, 127, 128, 255];
+        let hex = bytes_to_hex(&bytes);
+        assert_eq!(hex, "00010f107f80ff");
+        assert_eq!(hex_to_bytes(&hex).unwrap(), bytes);
+        assert_eq!(hex_to_bytes("00010F107F80FF").unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_scalar_mul_bytes() {
+        let p = SignatureGroup::random();
+        let s = FieldElement::random();
+        assert_eq!(scalar_mul_bytes(&p, &s.to_bytes()), &p * &s);
+        assert_eq!(scalar_mul_bytes(&p, &[0, 1]), p);
+        assert!(scalar_mul_bytes(&p, &[0]).is_identity());
+    }
+
+    #[test]
+    fn test_hash_to_subgroup() {
+        for i in 0..5 {
+            let msg = format!("test {}", i);
+            let p = hash_to_subgroup::<SignatureGroup>(msg.as_bytes(), SIGNATURE_GROUP_COFACTOR);
+            assert!(!p.is_identity());
+            assert!(p.has_correct_order());
+            let q = hash_to_subgroup::<OtherGroup>(msg.as_bytes(), OTHER_GROUP_COFACTOR);
+            assert!(!q.is_identity());
+            assert!(q.has_correct_order());
+        }
+    }
+}